@@ -0,0 +1,50 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use fvm::gas::{Gas, GasTracker};
+
+const CHARGES: u64 = 10_000;
+
+/// Simulates a tight syscall loop (e.g., reading a block in 32-byte chunks), where every syscall
+/// makes a small gas charge.
+fn small_charges(tracker: &GasTracker) {
+    for _ in 0..CHARGES {
+        let _ = black_box(tracker.charge_gas("OnBlockRead", black_box(Gas::new(13))));
+    }
+    black_box(tracker.gas_used());
+}
+
+/// Simulates a more typical actor: mostly small charges, interleaved with larger ones (e.g.,
+/// sends and block creation) that can't be batched.
+fn mixed_charges(tracker: &GasTracker) {
+    for i in 0..CHARGES {
+        let gas = if i % 8 == 0 {
+            Gas::new(5_000)
+        } else {
+            Gas::new(13)
+        };
+        let _ = black_box(tracker.charge_gas("OnBlockRead", black_box(gas)));
+    }
+    black_box(tracker.gas_used());
+}
+
+fn charge_gas(c: &mut Criterion) {
+    let limit = Gas::new(CHARGES * 100);
+    c.bench_function("small charges (batched)", |b| {
+        b.iter(|| small_charges(&GasTracker::new(limit, Gas::new(0), false)))
+    });
+    c.bench_function("small charges (unbatched)", |b| {
+        b.iter(|| small_charges(&GasTracker::new(limit, Gas::new(0), false).without_batching()))
+    });
+    let limit = Gas::new(CHARGES * 1_000);
+    c.bench_function("mixed charges (batched)", |b| {
+        b.iter(|| mixed_charges(&GasTracker::new(limit, Gas::new(0), false)))
+    });
+    c.bench_function("mixed charges (unbatched)", |b| {
+        b.iter(|| mixed_charges(&GasTracker::new(limit, Gas::new(0), false).without_batching()))
+    });
+}
+
+criterion_group!(benches, charge_gas);
+criterion_main!(benches);
@@ -3,7 +3,7 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::Read;
 
 use anyhow::{anyhow, Result};
@@ -12,12 +12,18 @@ use fvm_ipld_blockstore::{Blockstore, Buffered};
 use fvm_ipld_encoding::{CBOR, DAG_CBOR, IPLD_RAW};
 use fvm_shared::commcid::{FIL_COMMITMENT_SEALED, FIL_COMMITMENT_UNSEALED};
 
+use crate::kernel::SupportedHashes;
+
 /// Wrapper around `Blockstore` to limit and have control over when values are written.
 /// This type is not threadsafe and can only be used in synchronous contexts.
 #[derive(Debug)]
 pub struct BufferedBlockstore<BS> {
     base: BS,
     write: RefCell<HashMap<Cid, Vec<u8>>>,
+    /// Keys of blocks written while recording, see [`BufferedBlockstore::start_recording`].
+    recording: RefCell<Option<HashSet<Cid>>>,
+    /// Hash functions (other than identity) that flushed CIDs may use.
+    cid_hashes: Vec<SupportedHashes>,
 }
 
 impl<BS> BufferedBlockstore<BS>
@@ -28,12 +34,86 @@ where
         Self {
             base,
             write: Default::default(),
+            recording: Default::default(),
+            cid_hashes: vec![SupportedHashes::Blake2b256],
         }
     }
 
+    /// Sets the hash functions flushed CIDs may use, in addition to the identity hash. Defaults to
+    /// blake2b-256.
+    pub fn with_cid_hashes(mut self, hashes: Vec<SupportedHashes>) -> Self {
+        self.cid_hashes = hashes;
+        self
+    }
+
     pub fn into_inner(self) -> BS {
         self.base
     }
+
+    /// Starts recording the keys of all blocks written to the buffer, discarding any previous
+    /// recording.
+    pub fn start_recording(&self) {
+        *self.recording.borrow_mut() = Some(HashSet::new());
+    }
+
+    /// Stops recording and returns the keys of all blocks written since the last call to
+    /// [`BufferedBlockstore::start_recording`].
+    pub fn stop_recording(&self) -> HashSet<Cid> {
+        self.recording.borrow_mut().take().unwrap_or_default()
+    }
+
+    /// Like [`Buffered::flush`], but calls `on_block` with the key and size of every block moved
+    /// from the write buffer to the backing store.
+    pub fn flush_with(&self, root: &Cid, mut on_block: impl FnMut(&Cid, usize)) -> Result<()> {
+        let blocks = take_reachable(&mut self.write.borrow_mut(), root, &self.cid_hashes)?;
+        for (k, v) in &blocks {
+            on_block(k, v.len());
+        }
+        self.base.put_many_keyed(blocks)
+    }
+
+    /// Like [`Buffered::flush`], but hands the reachable blocks to `sink` in batches of at most
+    /// [`BlockSink::batch_size`] blocks, in DAG order (every block after the blocks it links to).
+    /// The sink may block to apply backpressure.
+    ///
+    /// If the sink fails, the blocks not yet written are dropped from the buffer along with the
+    /// rest of the DAG.
+    pub fn flush_into(&self, root: &Cid, sink: &mut dyn BlockSink) -> Result<()> {
+        let blocks = take_reachable(&mut self.write.borrow_mut(), root, &self.cid_hashes)?;
+        let batch_size = sink.batch_size().max(1);
+        let mut batch = Vec::with_capacity(batch_size.min(blocks.len()));
+        for chunk in blocks.chunks(batch_size) {
+            batch.clear();
+            batch.extend(chunk.iter().map(|(k, v)| (*k, &v[..])));
+            sink.put_many(&batch)?;
+        }
+        Ok(())
+    }
+
+    /// Moves the blocks reachable from `root` out of the write buffer, in DAG order (every block
+    /// after the blocks it links to), without writing them to the backing store. This lets the
+    /// client persist them at its own pace.
+    pub fn drain_writes(&self, root: &Cid) -> Result<std::vec::IntoIter<(Cid, Vec<u8>)>> {
+        Ok(take_reachable(&mut self.write.borrow_mut(), root, &self.cid_hashes)?.into_iter())
+    }
+
+    fn record(&self, k: &Cid) {
+        if let Some(recording) = self.recording.borrow_mut().as_mut() {
+            recording.insert(*k);
+        }
+    }
+}
+
+/// A destination for blocks flushed from a [`BufferedBlockstore`], see
+/// [`BufferedBlockstore::flush_into`].
+pub trait BlockSink {
+    /// Writes a batch of blocks. This may block to apply backpressure.
+    fn put_many(&mut self, blocks: &[(Cid, &[u8])]) -> Result<()>;
+
+    /// The maximum number of blocks passed to each call to [`BlockSink::put_many`].
+    fn batch_size(&self) -> usize {
+        1024
+    }
 }
 
 impl<BS> Buffered for BufferedBlockstore<BS>
@@ -44,8 +124,7 @@ where
     /// This will recursively traverse the cache and write all data connected by links to this
     /// root Cid, moving the reachable blocks from the write buffer to the backing store.
     fn flush(&self, root: &Cid) -> Result<()> {
-        self.base
-            .put_many_keyed(take_reachable(&mut self.write.borrow_mut(), root)?)
+        self.flush_with(root, |_, _| ())
     }
 }
 
@@ -135,10 +214,13 @@ fn scan_for_links(mut buf: &[u8], out: &mut Vec<Cid>) -> Result<()> {
     Ok(())
 }
 
-/// Moves the IPLD DAG under `root` from the cache to the base store.
-fn take_reachable(cache: &mut HashMap<Cid, Vec<u8>>, root: &Cid) -> Result<Vec<(Cid, Vec<u8>)>> {
-    const BLAKE2B_256: u64 = 0xb220;
-    const BLAKE2B_LEN: u8 = 32;
+/// Moves the IPLD DAG under `root` out of the cache, in DAG order: every block comes after all the
+/// blocks it links to.
+fn take_reachable(
+    cache: &mut HashMap<Cid, Vec<u8>>,
+    root: &Cid,
+    cid_hashes: &[SupportedHashes],
+) -> Result<Vec<(Cid, Vec<u8>)>> {
     const IDENTITY: u64 = 0x0;
 
     // Differences from lotus (vm.Copy):
@@ -147,10 +229,26 @@ fn take_reachable(cache: &mut HashMap<Cid, Vec<u8>>, root: &Cid) -> Result<Vec<(
     // 2. We always write-back new blocks, even if the client already has them. We haven't noticed a
     //    perf impact.
 
-    let mut stack = vec![*root];
+    enum Visit {
+        /// Visit a block's children.
+        Enter(Cid),
+        /// All of a block's children have been visited.
+        Exit(Cid, Vec<u8>),
+    }
+
+    let mut stack = vec![Visit::Enter(*root)];
+    let mut links = Vec::new();
     let mut result = Vec::new();
 
-    while let Some(k) = stack.pop() {
+    while let Some(visit) = stack.pop() {
+        let k = match visit {
+            Visit::Enter(k) => k,
+            Visit::Exit(k, block) => {
+                // Record the block so we can write it back.
+                result.push((k, block));
+                continue;
+            }
+        };
         // Check the codec.
         match k.codec() {
             // We ignore piece commitment CIDs.
@@ -162,8 +260,10 @@ fn take_reachable(cache: &mut HashMap<Cid, Vec<u8>>, root: &Cid) -> Result<Vec<(
         }
         // Check the hash construction.
         match (k.hash().code(), k.hash().size()) {
-            // Allow non-truncated blake2b-256 and identity hashes.
-            (BLAKE2B_256, BLAKE2B_LEN) | (IDENTITY, _) => (),
+            // Allow identity hashes, and non-truncated hashes from the allowed set.
+            (IDENTITY, _) => (),
+            (hash, length)
+                if SupportedHashes::find_cid_hash(cid_hashes, hash, length.into()).is_some() => {}
             // Reject everything else.
             (hash, length) => {
                 return Err(anyhow!(
@@ -173,7 +273,7 @@ fn take_reachable(cache: &mut HashMap<Cid, Vec<u8>>, root: &Cid) -> Result<Vec<(
         }
         if k.hash().code() == IDENTITY {
             if k.codec() == DAG_CBOR {
-                scan_for_links(k.hash().digest(), &mut stack)?;
+                scan_for_links(k.hash().digest(), &mut links)?;
             }
         } else {
             // If we don't have the block, we assume it and it's children are already in the
@@ -185,12 +285,12 @@ fn take_reachable(cache: &mut HashMap<Cid, Vec<u8>>, root: &Cid) -> Result<Vec<(
 
             // At the moment, only DAG_CBOR can link to other blocks.
             if k.codec() == DAG_CBOR {
-                scan_for_links(&block, &mut stack)?;
+                scan_for_links(&block, &mut links)?;
             }
 
-            // Record the block so we can write it back.
-            result.push((k, block));
+            stack.push(Visit::Exit(k, block));
         };
+        stack.extend(links.drain(..).map(Visit::Enter));
     }
 
     Ok(result)
@@ -209,6 +309,7 @@ where
     }
 
     fn put_keyed(&self, cid: &Cid, buf: &[u8]) -> Result<()> {
+        self.record(cid);
         self.write.borrow_mut().insert(*cid, Vec::from(buf));
         Ok(())
     }
@@ -229,7 +330,10 @@ where
     {
         self.write
             .borrow_mut()
-            .extend(blocks.into_iter().map(|(k, v)| (k, v.as_ref().into())));
+            .extend(blocks.into_iter().map(|(k, v)| {
+                self.record(&k);
+                (k, v.as_ref().into())
+            }));
         Ok(())
     }
 }
@@ -327,4 +431,142 @@ mod tests {
         assert_eq!(buf_store.get(&sealed_comm_cid).unwrap(), None);
         assert_eq!(mem.get_cbor::<u8>(&unconnected).unwrap(), None);
     }
+
+    #[test]
+    fn flush_allowed_hashes() {
+        let mem = MemoryBlockstore::default();
+        let buf_store = BufferedBlockstore::new(&mem);
+        let cid = buf_store.put_cbor(&8u8, Code::Sha2_256).unwrap();
+        buf_store.flush(&cid).unwrap_err();
+
+        let buf_store = BufferedBlockstore::new(&mem)
+            .with_cid_hashes(vec![SupportedHashes::Blake2b256, SupportedHashes::Sha2_256]);
+        let cid = buf_store.put_cbor(&8u8, Code::Sha2_256).unwrap();
+        buf_store.flush(&cid).unwrap();
+        assert_eq!(mem.get_cbor::<u8>(&cid).unwrap(), Some(8));
+    }
+
+    #[test]
+    fn recorded_flush() {
+        let mem = MemoryBlockstore::default();
+        let buf_store = BufferedBlockstore::new(&mem);
+
+        let before = buf_store.put_cbor(&1u8, Code::Blake2b256).unwrap();
+        buf_store.start_recording();
+        let during = buf_store.put_cbor(&2u8, Code::Blake2b256).unwrap();
+        let recorded = buf_store.stop_recording();
+        let root = buf_store
+            .put_cbor(&(before, during), Code::Blake2b256)
+            .unwrap();
+        assert_eq!(recorded, HashSet::from([during]));
+
+        let mut flushed = Vec::new();
+        buf_store
+            .flush_with(&root, |k, size| flushed.push((*k, size)))
+            .unwrap();
+        flushed.sort();
+        let mut expected: Vec<_> = [root, before, during]
+            .iter()
+            .map(|k| (*k, mem.get(k).unwrap().unwrap().len()))
+            .collect();
+        expected.sort();
+        assert_eq!(flushed, expected);
+        assert!(buf_store.stop_recording().is_empty());
+    }
+
+    /// Records the batches it's given, sleeping after each to simulate a slow database.
+    struct ThrottlingSink {
+        batches: Vec<Vec<(Cid, Vec<u8>)>>,
+    }
+
+    impl BlockSink for ThrottlingSink {
+        fn put_many(&mut self, blocks: &[(Cid, &[u8])]) -> Result<()> {
+            self.batches
+                .push(blocks.iter().map(|(k, v)| (*k, v.to_vec())).collect());
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            Ok(())
+        }
+
+        fn batch_size(&self) -> usize {
+            2
+        }
+    }
+
+    /// Builds a DAG where two blocks share a child, which the root also links to directly.
+    /// Returns the root and all blocks reachable from it, plus a block that isn't.
+    fn build_dag(buf_store: &BufferedBlockstore<&MemoryBlockstore>) -> (Cid, HashSet<Cid>, Cid) {
+        let a = buf_store.put_cbor(&1u8, Code::Blake2b256).unwrap();
+        let b = buf_store.put_cbor(&2u8, Code::Blake2b256).unwrap();
+        let shared = buf_store.put_cbor(&3u8, Code::Blake2b256).unwrap();
+        let left = buf_store.put_cbor(&(a, shared), Code::Blake2b256).unwrap();
+        let right = buf_store.put_cbor(&(b, shared), Code::Blake2b256).unwrap();
+        let root = buf_store
+            .put_cbor(&(left, right, shared), Code::Blake2b256)
+            .unwrap();
+        let unreachable = buf_store.put_cbor(&4u8, Code::Blake2b256).unwrap();
+        (
+            root,
+            HashSet::from([a, b, shared, left, right, root]),
+            unreachable,
+        )
+    }
+
+    /// Asserts that every block comes after the blocks it links to.
+    fn assert_dag_order(blocks: &[(Cid, Vec<u8>)]) {
+        let mut seen = HashSet::new();
+        for (k, v) in blocks {
+            let mut links = Vec::new();
+            scan_for_links(v, &mut links).unwrap();
+            for link in links {
+                assert!(seen.contains(&link), "{k} written before its child {link}");
+            }
+            seen.insert(*k);
+        }
+    }
+
+    #[test]
+    fn flush_into_sink() {
+        let mem = MemoryBlockstore::default();
+        let buf_store = BufferedBlockstore::new(&mem);
+        let (root, reachable, unreachable) = build_dag(&buf_store);
+
+        let mut sink = ThrottlingSink {
+            batches: Vec::new(),
+        };
+        buf_store.flush_into(&root, &mut sink).unwrap();
+
+        assert!(sink.batches.iter().all(|b| !b.is_empty() && b.len() <= 2));
+        let written: Vec<_> = sink.batches.into_iter().flatten().collect();
+        assert_eq!(written.len(), reachable.len());
+        assert_eq!(
+            written.iter().map(|(k, _)| *k).collect::<HashSet<_>>(),
+            reachable
+        );
+        assert_eq!(written.last().unwrap().0, root);
+        assert_dag_order(&written);
+
+        // The sink is responsible for persisting the blocks.
+        assert!(!mem.has(&root).unwrap());
+        assert!(buf_store.get(&root).unwrap().is_none());
+        assert!(buf_store.get(&unreachable).unwrap().is_some());
+    }
+
+    #[test]
+    fn drain_writes() {
+        let mem = MemoryBlockstore::default();
+        let buf_store = BufferedBlockstore::new(&mem);
+        let (root, reachable, unreachable) = build_dag(&buf_store);
+
+        let drained: Vec<_> = buf_store.drain_writes(&root).unwrap().collect();
+        assert_eq!(
+            drained.iter().map(|(k, _)| *k).collect::<HashSet<_>>(),
+            reachable
+        );
+        assert_dag_order(&drained);
+        assert!(drained.iter().all(|(k, _)| !mem.has(k).unwrap()));
+
+        // Draining again yields nothing, as the blocks were moved out of the buffer.
+        assert_eq!(buf_store.drain_writes(&root).unwrap().count(), 0);
+        assert!(buf_store.get(&unreachable).unwrap().is_some());
+    }
 }
@@ -5,5 +5,5 @@
 mod buffered;
 mod discard;
 
-pub use buffered::BufferedBlockstore;
+pub use buffered::{BlockSink, BufferedBlockstore};
 pub(crate) use discard::DiscardBlockstore;
@@ -2,12 +2,12 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 use std::rc::Rc;
 
-use anyhow::{anyhow, Context};
+use anyhow::anyhow;
 use cid::Cid;
 use derive_more::{Deref, DerefMut};
-use fvm_ipld_amt::Amt;
 use fvm_ipld_encoding::{to_vec, CBOR};
-use fvm_shared::address::{Address, Payload};
+use fvm_shared::address::{derive_actor_address, Address, Payload};
+use fvm_shared::clock::ChainEpoch;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::{ErrorNumber, ExitCode};
 use fvm_shared::event::StampedEvent;
@@ -16,13 +16,16 @@ use fvm_shared::{ActorID, MethodNum, METHOD_SEND};
 use num_traits::Zero;
 
 use super::state_access_tracker::{ActorAccessState, StateAccessTracker};
+use super::tipset_cache::TipsetCidCache;
+use super::tombstones::Tombstones;
 use super::{Backtrace, CallManager, InvocationResult, NO_DATA_BLOCK_ID};
-use crate::blockstore::DiscardBlockstore;
 use crate::call_manager::backtrace::Frame;
 use crate::call_manager::FinishRet;
 use crate::eam_actor::EAM_ACTOR_ID;
 use crate::engine::Engine;
-use crate::gas::{Gas, GasTracker};
+use crate::executor::EventChunks;
+use crate::externs::Chain;
+use crate::gas::{Gas, GasClock, GasTracker};
 use crate::kernel::{
     Block, BlockRegistry, ClassifyResult, ExecutionError, Kernel, Result, SyscallError,
 };
@@ -59,22 +62,37 @@ pub struct InnerDefaultCallManager<M: Machine> {
     origin: ActorID,
     /// The origin address as specified in the message (used to derive new f2 addresses).
     origin_address: Address,
+    /// The origin's delegated address (if any), resolved when the message was set up.
+    origin_delegated_address: Option<Address>,
     /// The nonce of the chain message that initiated this call stack.
     nonce: u64,
+    /// Whether the chain message that initiated this call stack is implicit.
+    implicit: bool,
     /// Number of actors created in this call stack.
     num_actors_created: u64,
     /// Current call-stack depth.
     call_stack_depth: u32,
+    /// The call-stack depth at which execution became read-only, if it is.
+    read_only_depth: Option<u32>,
+    /// The actors currently being invoked on this call stack, along with the invoked methods
+    /// (outermost first).
+    call_stack: Vec<(ActorID, MethodNum)>,
     /// The current chain of errors, if any.
     backtrace: Backtrace,
     /// The current execution trace.
     exec_trace: ExecutionTrace,
     /// Number of actors that have been invoked in this message execution.
     invocation_count: u64,
+    /// Total size of the debug artifacts stored in this message execution.
+    artifact_bytes: u64,
     /// Limits on memory throughout the execution.
     limits: M::Limiter,
     /// Accumulator for events emitted in this call stack.
     events: EventsAccumulator,
+    /// Recently looked-up tipset CIDs.
+    tipset_cids: TipsetCidCache,
+    /// Actors deleted earlier in this call stack.
+    tombstones: Tombstones,
 }
 
 #[doc(hidden)]
@@ -105,14 +123,20 @@ where
         gas_limit: u64,
         origin: ActorID,
         origin_address: Address,
+        origin_delegated_address: Option<Address>,
         receiver: Option<ActorID>,
         receiver_address: Address,
         nonce: u64,
         gas_premium: TokenAmount,
+        implicit: bool,
     ) -> Self {
         let limits = machine.new_limiter();
-        let gas_tracker =
-            GasTracker::new(Gas::new(gas_limit), Gas::zero(), machine.context().tracing);
+        let mut gas_tracker =
+            GasTracker::new(Gas::new(gas_limit), Gas::zero(), machine.context().tracing)
+                .with_refundable_charges(machine.context().price_list.refundable_charges());
+        if machine.context().deterministic_only {
+            gas_tracker = gas_tracker.with_clock(GasClock::logical());
+        }
 
         let state_access_tracker =
             StateAccessTracker::new(&machine.context().price_list.preloaded_actors);
@@ -150,15 +174,22 @@ where
             gas_premium,
             origin,
             origin_address,
+            origin_delegated_address,
             nonce,
+            implicit,
             num_actors_created: 0,
             call_stack_depth: 0,
+            read_only_depth: None,
+            call_stack: Vec::new(),
             backtrace: Backtrace::default(),
             exec_trace: vec![],
             invocation_count: 0,
+            artifact_bytes: 0,
             limits,
             events: Default::default(),
             state_access_tracker,
+            tipset_cids: Default::default(),
+            tombstones: Default::default(),
         })))
     }
 
@@ -166,6 +197,10 @@ where
         &mut self.limits
     }
 
+    fn limiter(&self) -> &<Self::Machine as Machine>::Limiter {
+        &self.limits
+    }
+
     fn send<K>(
         &mut self,
         from: ActorID,
@@ -209,8 +244,15 @@ where
 
         self.state_tree_mut().begin_transaction();
         self.events.begin_transaction();
+        self.tombstones.begin_transaction();
         self.state_access_tracker.begin_transaction();
         self.call_stack_depth += 1;
+        // Remember where read-only mode was entered, so callees can tell why they're read-only.
+        let enters_read_only = read_only && self.read_only_depth.is_none();
+        if enters_read_only {
+            self.read_only_depth = Some(self.call_stack_depth);
+        }
+        self.gas_tracker.enter_call(|| format!("{to}#{method}"));
 
         let (revert, mut result) = match <<Self::Machine as Machine>::Limiter>::with_stack_frame(
             self,
@@ -221,6 +263,10 @@ where
             Err(e) => (true, Err(e)),
         };
 
+        self.gas_tracker.exit_call();
+        if enters_read_only {
+            self.read_only_depth = None;
+        }
         self.call_stack_depth -= 1;
         // Return the _first_ error (if any). We don't expect any errors here anyways as all error
         // cases are fatal.
@@ -228,6 +274,7 @@ where
             // End all transactions
             self.state_access_tracker.end_transaction(revert).err(),
             self.events.end_transaction(revert).err(),
+            self.tombstones.end_transaction(revert).err(),
             self.state_tree_mut().end_transaction(revert).err(),
             // If we pushed a gas limit, pop it.
             gas_limit.and_then(|_| self.gas_tracker.pop_limit().err()),
@@ -282,7 +329,7 @@ where
 
         // Finalize any trace events, if we're tracing.
         if machine.context().tracing {
-            exec_trace.extend(gas_tracker.drain_trace().map(ExecutionEvent::GasCharge));
+            exec_trace.extend(gas_tracker.drain_trace());
         }
 
         let res = events.finish();
@@ -334,6 +381,14 @@ where
         self.origin
     }
 
+    fn origin_delegated_address(&self) -> Option<Address> {
+        self.origin_delegated_address
+    }
+
+    fn implicit(&self) -> bool {
+        self.implicit
+    }
+
     fn nonce(&self) -> u64 {
         self.nonce
     }
@@ -349,10 +404,11 @@ where
         // address? They shouldn't be. The sender can always _replace_ a message with a new message,
         // and completely change how f2 addresses are assigned. Only the message sender can rely on
         // an f2 address (before finality).
-        let mut b = to_vec(&self.origin_address).expect("failed to serialize address");
-        b.extend_from_slice(&self.nonce.to_be_bytes());
-        b.extend_from_slice(&self.num_actors_created.to_be_bytes());
-        Address::new_actor(&b)
+        derive_actor_address(&self.origin_address, self.nonce, self.num_actors_created)
+    }
+
+    fn actors_created(&self) -> u64 {
+        self.num_actors_created
     }
 
     fn create_actor(
@@ -369,6 +425,15 @@ where
             .into());
         }
 
+        // Don't re-bind the ID (and delegated address) of an actor deleted earlier in this message.
+        if self.tombstones.contains(actor_id) {
+            return Err(syscall_error!(
+                Forbidden,
+                "actor {actor_id} was deleted earlier in this message"
+            )
+            .into());
+        }
+
         // Check to make sure the actor doesn't exist, or is a placeholder.
         let actor = match self.get_actor(actor_id)? {
             // Replace the placeholder
@@ -413,14 +478,54 @@ where
     }
 
     fn append_event(&mut self, evt: StampedEvent) {
+        if self.machine.context().tracing {
+            self.trace(ExecutionEvent::Event {
+                depth: self.call_stack_depth,
+                event: evt.clone(),
+            });
+        }
         self.events.append_event(evt)
     }
 
+    fn events_emitted(&self) -> usize {
+        self.events.events.len()
+    }
+
+    fn events(&self) -> &EventChunks {
+        &self.events.events
+    }
+
     // Helper for creating actors. This really doesn't belong on this trait.
     fn invocation_count(&self) -> u64 {
         self.invocation_count
     }
 
+    fn call_stack_depth(&self) -> u32 {
+        self.call_stack_depth
+    }
+
+    fn read_only_depth(&self) -> Option<u32> {
+        self.read_only_depth
+    }
+
+    fn call_stack(&self) -> &[(ActorID, MethodNum)] {
+        &self.call_stack
+    }
+
+    fn artifact_bytes(&self) -> u64 {
+        self.artifact_bytes
+    }
+
+    fn record_artifact_bytes(&mut self, size: u64) {
+        self.artifact_bytes = self.artifact_bytes.saturating_add(size);
+    }
+
+    fn get_tipset_cid(&self, epoch: ChainEpoch) -> Result<Cid> {
+        self.tipset_cids
+            .get_or_load(epoch, |epoch| self.externs().get_tipset_cid(epoch))
+            .or_fatal()
+    }
+
     /// Resolve an address and charge for it.
     fn resolve_address(&self, address: &Address) -> Result<Option<ActorID>> {
         if let Ok(id) = address.id() {
@@ -475,6 +580,9 @@ where
         }
         self.state_tree_mut().delete_actor(id);
         self.state_access_tracker.record_actor_update(id);
+        if self.machine.context().tombstones_deleted_actors() {
+            self.tombstones.record(id);
+        }
         Ok(())
     }
 
@@ -525,8 +633,7 @@ where
         // fine.
         let s = &mut **self;
 
-        s.exec_trace
-            .extend(s.gas_tracker.drain_trace().map(ExecutionEvent::GasCharge));
+        s.exec_trace.extend(s.gas_tracker.drain_trace());
 
         s.exec_trace.push(trace);
     }
@@ -654,6 +761,15 @@ where
     where
         K: Kernel<CallManager = Self>,
     {
+        // Refuse plain value sends to actors deleted earlier in this message.
+        if method == METHOD_SEND && self.tombstones.contains(to) {
+            log::trace!("rejected send {} -> {}: recipient was deleted", from, to);
+            return Ok(InvocationResult {
+                exit_code: ExitCode::SYS_RECIPIENT_DELETED,
+                value: None,
+            });
+        }
+
         // Lookup the actor.
         let state = self
             .get_actor(to)?
@@ -663,6 +779,15 @@ where
             self.trace(ExecutionEvent::InvokeActor(state.code));
         }
 
+        // Refuse to burn funds by sending them to singletons that can't use them.
+        if self.machine.context().rejects_value_send(to, method, value) {
+            log::trace!("rejected value send {} -> {}: {}", from, to, &value);
+            return Ok(InvocationResult {
+                exit_code: ExitCode::SYS_VALUE_REJECTED,
+                value: None,
+            });
+        }
+
         // Transfer, if necessary.
         if !value.is_zero() {
             let t = self.charge_gas(self.price_list().on_value_transfer())?;
@@ -687,7 +812,7 @@ where
         )?;
 
         // Store the parametrs, and initialize the block registry for the target actor.
-        let mut block_registry = BlockRegistry::new();
+        let mut block_registry = BlockRegistry::with_limit(self.context().max_block_handles);
         let params_id = if let Some(blk) = params {
             block_registry.put_reachable(blk)?
         } else {
@@ -710,6 +835,7 @@ where
         log::trace!("calling {} -> {}::{}", from, to, method);
         self.map_mut(|cm| {
             let engine = cm.engine.clone(); // reference the RC.
+            cm.call_stack.push((to, method));
 
             // Make the kernel.
             let kernel = K::new(
@@ -770,6 +896,7 @@ where
             let invocation_data = store.into_data();
             let last_error = invocation_data.last_error;
             let (mut cm, block_registry) = invocation_data.kernel.into_inner();
+            cm.call_stack.pop();
 
             // Resolve the return block's ID into an actual block, converting to an abort if it
             // doesn't exist.
@@ -902,15 +1029,15 @@ where
 /// throw away any events collected from subcalls (and previously merged, as those subcalls returned
 /// normally).
 pub struct EventsAccumulator {
-    events: Vec<StampedEvent>,
+    events: EventChunks,
     idxs: Vec<usize>,
 }
 impl Default for EventsAccumulator {
     fn default() -> Self {
         // Pre-allocate some space here for more consistent performance. We only do this once per
-        // message so the overhead is minimal.
+        // message so the overhead is minimal. Events are chunked, so they don't need to be.
         Self {
-            events: Vec::with_capacity(128),
+            events: EventChunks::new(),
             idxs: Vec::with_capacity(8),
         }
     }
@@ -918,7 +1045,7 @@ impl Default for EventsAccumulator {
 
 pub(crate) struct Events {
     root: Option<Cid>,
-    events: Vec<StampedEvent>,
+    events: EventChunks,
 }
 
 impl EventsAccumulator {
@@ -950,19 +1077,7 @@ impl EventsAccumulator {
             )));
         }
 
-        let root = if !self.events.is_empty() {
-            const EVENTS_AMT_BITWIDTH: u32 = 5;
-            let root = Amt::new_from_iter_with_bit_width(
-                DiscardBlockstore,
-                EVENTS_AMT_BITWIDTH,
-                self.events.iter(),
-            )
-            .context("failed to construct events AMT")
-            .or_fatal()?;
-            Some(root)
-        } else {
-            None
-        };
+        let root = super::events_root(&self.events).or_fatal()?;
 
         Ok(Events {
             root,
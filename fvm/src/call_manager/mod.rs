@@ -1,20 +1,28 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use anyhow::Context as _;
 use cid::Cid;
+use fvm_ipld_amt::Amt;
 use fvm_shared::address::Address;
+use fvm_shared::clock::ChainEpoch;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ExitCode;
 use fvm_shared::{ActorID, MethodNum};
 
+use crate::blockstore::DiscardBlockstore;
 use crate::engine::Engine;
+use crate::executor::EventChunks;
+use crate::externs::Chain;
 use crate::gas::{Gas, GasCharge, GasTimer, GasTracker, PriceList};
-use crate::kernel::{self, Result};
+use crate::kernel::{self, ClassifyResult, Result};
 use crate::machine::{Machine, MachineContext};
 use crate::state_tree::ActorState;
 use crate::Kernel;
 
 pub mod backtrace;
 mod state_access_tracker;
+mod tipset_cache;
+mod tombstones;
 pub use backtrace::Backtrace;
 
 mod default;
@@ -27,6 +35,21 @@ use crate::trace::ExecutionTrace;
 /// BlockID representing nil parameters or return data.
 pub const NO_DATA_BLOCK_ID: u32 = 0;
 
+/// The bitwidth of the events AMT committed in message receipts.
+const EVENTS_AMT_BITWIDTH: u32 = 5;
+
+/// Computes the root of the events AMT over the given events, without writing anything to the
+/// blockstore. Returns `None` if there are no events.
+pub(crate) fn events_root(events: &EventChunks) -> anyhow::Result<Option<Cid>> {
+    if events.is_empty() {
+        return Ok(None);
+    }
+    let root =
+        Amt::new_from_iter_with_bit_width(DiscardBlockstore, EVENTS_AMT_BITWIDTH, events.iter())
+            .context("failed to construct events AMT")?;
+    Ok(Some(root))
+}
+
 /// The `CallManager` manages a single call stack.
 ///
 /// When a top-level message is executed:
@@ -54,10 +77,12 @@ pub trait CallManager: 'static {
         gas_limit: u64,
         origin: ActorID,
         origin_address: Address,
+        origin_delegated_address: Option<Address>,
         receiver: Option<ActorID>,
         receiver_address: Address,
         nonce: u64,
         gas_premium: TokenAmount,
+        implicit: bool,
     ) -> Self;
 
     /// Send a message. The type parameter `K` specifies the the _kernel_ on top of which the target
@@ -94,12 +119,22 @@ pub trait CallManager: 'static {
     /// Getter for origin actor.
     fn origin(&self) -> ActorID;
 
+    /// Returns the origin's delegated (f4) address, if any, as of the start of the message.
+    fn origin_delegated_address(&self) -> Option<Address>;
+
+    /// Returns true if the message being executed is an implicit (system) message.
+    fn implicit(&self) -> bool;
+
     /// Get the actor address (f2) that will should be assigned to the next actor created.
     ///
     /// This method doesn't have any side-effects and will continue to return the same address until
     /// `create_actor` is called next.
     fn next_actor_address(&self) -> Address;
 
+    /// Returns the number of actors created so far in this message. Together with the origin
+    /// address and nonce, this determines the [next actor address](Self::next_actor_address).
+    fn actors_created(&self) -> u64;
+
     /// Create a new actor with the given code CID, actor ID, and delegated address. This method
     /// does not register the actor with the init actor. It just creates it in the state-tree.
     ///
@@ -133,6 +168,25 @@ pub trait CallManager: 'static {
     /// Gets the total invocations done on this call stack.
     fn invocation_count(&self) -> u64;
 
+    /// Gets the depth of the current call stack, where the top-level message is at depth 1.
+    fn call_stack_depth(&self) -> u32;
+
+    /// Gets the call-stack depth at which execution became read-only (either because the message
+    /// was read-only, or because a caller sent with the read-only flag), or `None` if execution
+    /// isn't read-only.
+    fn read_only_depth(&self) -> Option<u32>;
+
+    /// Returns the actors currently being invoked on this call stack along with the invoked
+    /// methods, outermost first. Plain value sends don't invoke an actor, so don't appear here.
+    fn call_stack(&self) -> &[(ActorID, MethodNum)];
+
+    /// Returns the total size (in bytes) of the debug artifacts stored while executing this
+    /// message.
+    fn artifact_bytes(&self) -> u64;
+
+    /// Records that `size` bytes of debug artifacts have been stored while executing this message.
+    fn record_artifact_bytes(&mut self, size: u64);
+
     /// Returns the current price list.
     fn price_list(&self) -> &PriceList {
         self.machine().context().price_list
@@ -153,6 +207,12 @@ pub trait CallManager: 'static {
         self.machine().externs()
     }
 
+    /// Looks up the tipset CID for the given epoch via the externs. This method does _not_ charge
+    /// gas, and implementations may cache the result.
+    fn get_tipset_cid(&self, epoch: ChainEpoch) -> Result<Cid> {
+        self.externs().get_tipset_cid(epoch).or_fatal()
+    }
+
     /// Charge gas.
     fn charge_gas(&self, charge: GasCharge) -> Result<GasTimer> {
         self.gas_tracker().apply_charge(charge)
@@ -161,8 +221,19 @@ pub trait CallManager: 'static {
     /// Limit memory usage throughout a message execution.
     fn limiter_mut(&mut self) -> &mut <Self::Machine as Machine>::Limiter;
 
+    /// Returns the memory limiter, for inspecting memory usage.
+    fn limiter(&self) -> &<Self::Machine as Machine>::Limiter;
+
     /// Appends an event to the event accumulator.
     fn append_event(&mut self, evt: StampedEvent);
+
+    /// Returns the number of events emitted so far in this message, excluding events discarded
+    /// when the calls that emitted them were reverted.
+    fn events_emitted(&self) -> usize;
+
+    /// Returns the events emitted so far in this message, excluding events discarded when the
+    /// calls that emitted them were reverted.
+    fn events(&self) -> &EventChunks;
 }
 
 /// The result of a method invocation.
@@ -188,6 +259,6 @@ pub struct FinishRet {
     pub gas_used: u64,
     pub backtrace: Backtrace,
     pub exec_trace: ExecutionTrace,
-    pub events: Vec<StampedEvent>,
+    pub events: EventChunks,
     pub events_root: Option<Cid>,
 }
@@ -0,0 +1,121 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use cid::Cid;
+use fvm_shared::clock::ChainEpoch;
+
+/// A small LRU cache of tipset CIDs, used to avoid repeatedly crossing the extern boundary when
+/// actors look up the same epochs.
+#[derive(Default)]
+pub struct TipsetCidCache {
+    /// Cached tipset CIDs, along with the "time" they were last used.
+    entries: RefCell<HashMap<ChainEpoch, (Cid, u64)>>,
+    /// A logical clock, incremented on every lookup.
+    clock: Cell<u64>,
+}
+
+impl TipsetCidCache {
+    /// The maximum number of tipset CIDs to cache.
+    pub const CAPACITY: usize = 16;
+
+    /// Returns the tipset CID for the given epoch, calling `load` on a cache miss. Errors are not
+    /// cached.
+    pub fn get_or_load(
+        &self,
+        epoch: ChainEpoch,
+        load: impl FnOnce(ChainEpoch) -> anyhow::Result<Cid>,
+    ) -> anyhow::Result<Cid> {
+        let now = self.clock.get() + 1;
+        self.clock.set(now);
+
+        if let Some((cid, last_used)) = self.entries.borrow_mut().get_mut(&epoch) {
+            *last_used = now;
+            return Ok(*cid);
+        }
+
+        let cid = load(epoch)?;
+
+        let mut entries = self.entries.borrow_mut();
+        if entries.len() >= Self::CAPACITY {
+            // The cache is tiny, so a linear scan for the least recently used entry is fine.
+            if let Some(&lru) = entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(epoch, _)| epoch)
+            {
+                entries.remove(&lru);
+            }
+        }
+        entries.insert(epoch, (cid, now));
+        Ok(cid)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::anyhow;
+    use cid::multihash::{Code, MultihashDigest};
+    use fvm_ipld_encoding::DAG_CBOR;
+
+    use super::*;
+
+    fn tipset_cid(epoch: ChainEpoch) -> Cid {
+        Cid::new_v1(DAG_CBOR, Code::Blake2b256.digest(&epoch.to_be_bytes()))
+    }
+
+    #[test]
+    fn test_cache_hits() {
+        let cache = TipsetCidCache::default();
+        let loads = Cell::new(0);
+        let load = |epoch| {
+            loads.set(loads.get() + 1);
+            Ok(tipset_cid(epoch))
+        };
+
+        for _ in 0..10 {
+            assert_eq!(cache.get_or_load(1, load).unwrap(), tipset_cid(1));
+        }
+        assert_eq!(loads.get(), 1);
+    }
+
+    #[test]
+    fn test_errors_not_cached() {
+        let cache = TipsetCidCache::default();
+        assert!(cache.get_or_load(1, |_| Err(anyhow!("failed"))).is_err());
+        assert_eq!(
+            cache.get_or_load(1, |e| Ok(tipset_cid(e))).unwrap(),
+            tipset_cid(1)
+        );
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let cache = TipsetCidCache::default();
+        let capacity = TipsetCidCache::CAPACITY as ChainEpoch;
+        let loads = Cell::new(0);
+        let load = |epoch| {
+            loads.set(loads.get() + 1);
+            Ok(tipset_cid(epoch))
+        };
+
+        // Fill the cache, then touch epoch 0 so that epoch 1 is the least recently used.
+        for epoch in 0..capacity {
+            cache.get_or_load(epoch, load).unwrap();
+        }
+        cache.get_or_load(0, load).unwrap();
+        assert_eq!(loads.get(), capacity);
+
+        // Loading a new epoch evicts epoch 1.
+        cache.get_or_load(capacity, load).unwrap();
+        assert_eq!(cache.entries.borrow().len(), TipsetCidCache::CAPACITY);
+        assert!(!cache.entries.borrow().contains_key(&1));
+
+        // Epoch 0 is still cached, epoch 1 must be reloaded.
+        cache.get_or_load(0, load).unwrap();
+        assert_eq!(loads.get(), capacity + 1);
+        cache.get_or_load(1, load).unwrap();
+        assert_eq!(loads.get(), capacity + 2);
+    }
+}
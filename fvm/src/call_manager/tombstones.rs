@@ -0,0 +1,84 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use anyhow::anyhow;
+use fvm_shared::ActorID;
+
+use crate::kernel::{ExecutionError, Result};
+
+/// Actors deleted earlier in the current message. For the remainder of the message, plain value
+/// sends to a tombstoned actor fail with
+/// [`SYS_RECIPIENT_DELETED`](fvm_shared::error::ExitCode::SYS_RECIPIENT_DELETED), and its ID can't
+/// be re-bound to a new actor.
+///
+/// Tombstones are layered like the state-tree: if the deleting actor aborts, the deletion is
+/// rolled back and so is the tombstone.
+#[derive(Default)]
+pub(crate) struct Tombstones {
+    deleted: Vec<ActorID>,
+    idxs: Vec<usize>,
+}
+
+impl Tombstones {
+    pub(crate) fn record(&mut self, id: ActorID) {
+        if !self.contains(id) {
+            self.deleted.push(id);
+        }
+    }
+
+    pub(crate) fn contains(&self, id: ActorID) -> bool {
+        self.deleted.contains(&id)
+    }
+
+    pub(crate) fn begin_transaction(&mut self) {
+        self.idxs.push(self.deleted.len());
+    }
+
+    pub(crate) fn end_transaction(&mut self, revert: bool) -> Result<()> {
+        let idx = self.idxs.pop().ok_or_else(|| {
+            ExecutionError::Fatal(anyhow!(
+                "no index in the tombstone set when ending a transaction"
+            ))
+        })?;
+        if revert {
+            self.deleted.truncate(idx);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tombstones;
+
+    #[test]
+    fn transactions() {
+        let mut tombstones = Tombstones::default();
+
+        // Deleted, then the deleting call aborts.
+        tombstones.begin_transaction();
+        tombstones.record(100);
+        assert!(tombstones.contains(100));
+        tombstones.end_transaction(true).unwrap();
+        assert!(!tombstones.contains(100));
+
+        // Deleted in a nested call that returns, then the outer call aborts.
+        tombstones.begin_transaction();
+        tombstones.begin_transaction();
+        tombstones.record(100);
+        tombstones.end_transaction(false).unwrap();
+        assert!(tombstones.contains(100));
+        tombstones.end_transaction(true).unwrap();
+        assert!(!tombstones.contains(100));
+
+        // Deleted in a call that returns, then deleted again (no-op) by a call that aborts.
+        tombstones.begin_transaction();
+        tombstones.record(101);
+        tombstones.end_transaction(false).unwrap();
+        tombstones.begin_transaction();
+        tombstones.record(101);
+        tombstones.end_transaction(true).unwrap();
+        assert!(tombstones.contains(101));
+
+        assert!(tombstones.end_transaction(false).is_err());
+    }
+}
@@ -8,6 +8,7 @@ use std::any::{Any, TypeId};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::collections::HashMap;
 use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
 use anyhow::{anyhow, Context};
@@ -19,9 +20,10 @@ use num_traits::Zero;
 use wasmtime::OptLevel::Speed;
 use wasmtime::{
     Global, GlobalType, InstanceAllocationStrategy, Linker, Memory, MemoryType, Module, Mutability,
-    Val, ValType,
+    UpdateDeadline, Val, ValType,
 };
 
+use crate::executor::Cancelled;
 use crate::gas::{Gas, GasTimer, WasmGasPrices};
 use crate::machine::limiter::MemoryLimiter;
 use crate::machine::{Machine, NetworkConfig};
@@ -54,6 +56,7 @@ pub struct EngineConfig {
     pub concurrency: u32,
     pub wasm_prices: &'static WasmGasPrices,
     pub actor_redirect: Vec<(Cid, Cid)>,
+    pub interruptible: bool,
 }
 
 impl EngineConfig {
@@ -78,6 +81,7 @@ impl From<&NetworkConfig> for EngineConfig {
             wasm_prices: &nc.price_list.wasm_rules,
             actor_redirect: nc.actor_redirect.clone(),
             concurrency: 1,
+            interruptible: nc.interruptible,
         }
     }
 }
@@ -196,7 +200,11 @@ fn wasmtime_config(ec: &EngineConfig) -> anyhow::Result<wasmtime::Config> {
 
     // Execution cost accouting is done through wasm instrumentation,
     c.consume_fuel(false);
-    c.epoch_interruption(false);
+
+    // wasmtime default: false
+    // Used to interrupt actors when execution is cancelled. The epoch is only ever incremented on
+    // cancellation, but enabling this adds a check to every loop header and function entry.
+    c.epoch_interruption(ec.interruptible);
 
     // Disable debug-related things, wasm-instrument doesn't fix debug info
     // yet, so those aren't useful, just add overhead
@@ -246,6 +254,9 @@ struct EngineInner {
     instance_cache: Mutex<HashMap<TypeId, Box<dyn Any + Send>>>,
     config: EngineConfig,
 
+    /// The number of times an actor has been instantiated (successfully or not).
+    instantiations: AtomicU64,
+
     actor_redirect: HashMap<Cid, Cid>,
 }
 
@@ -291,6 +302,7 @@ impl EnginePool {
             instance_cache: Mutex::new(HashMap::new()),
             config: ec,
             actor_redirect,
+            instantiations: AtomicU64::new(0),
         })))
     }
 }
@@ -552,6 +564,7 @@ impl Engine {
 
             // Update the gas _just_ in case.
             update_gas_available(store)?;
+            self.inner.instantiations.fetch_add(1, Ordering::Relaxed);
             let res = pre_instance.instantiate(&mut *store);
             charge_for_exec(store)?;
 
@@ -596,6 +609,12 @@ impl Engine {
         }
     }
 
+    /// Returns the number of times actors have been instantiated by this engine (and any other
+    /// engines from the same pool), including failed instantiations.
+    pub fn instantiations(&self) -> u64 {
+        self.inner.instantiations.load(Ordering::Relaxed)
+    }
+
     /// Construct a new wasmtime "store" from the given kernel.
     pub fn new_store<K: Kernel>(&self, mut kernel: K) -> wasmtime::Store<InvocationData<K>> {
         // Take a new instance and put it into a drop-guard that removes the reservation when
@@ -613,6 +632,7 @@ impl Engine {
         let reservation = InstanceReservation(self.inner.clone());
 
         let memory_bytes = kernel.limiter_mut().memory_used();
+        let cancellation = kernel.machine().context().cancellation.clone();
 
         let id = InvocationData {
             kernel,
@@ -630,10 +650,30 @@ impl Engine {
             .expect("failed to create available_gas global");
         store.data_mut().avail_gas_global = gg;
 
+        // Actors check for cancellation whenever the engine's epoch advances, which only happens
+        // when a token is cancelled. If we've already been cancelled, check immediately. The
+        // token stops bumping our epoch once the store (and therefore the watch) is dropped.
+        let watch = match cancellation {
+            Some(token) if self.inner.config.interruptible => {
+                let watch = token.watch(&self.inner.engine);
+                store.set_epoch_deadline(if token.is_cancelled() { 0 } else { 1 });
+                store.epoch_deadline_callback(move |_| {
+                    if token.is_cancelled() {
+                        Err(Cancelled.into())
+                    } else {
+                        Ok(UpdateDeadline::Continue(1))
+                    }
+                });
+                Some(watch)
+            }
+            _ => None,
+        };
+
         store.limiter(move |data| {
             // Keep the reservation alive as long as the limiter is alive. The limiter limits the
-            // store to one instance and one memory, which is covered by the reservation.
-            let _ = &reservation;
+            // store to one instance and one memory, which is covered by the reservation. Likewise,
+            // keep watching for cancellation until the store is dropped.
+            let _ = (&reservation, &watch);
 
             // SAFETY: This is safe because WasmtimeLimiter is `repr(transparent)`.
             // Unfortunately, we can't simply wrap the limiter as we need to return a reference.
@@ -0,0 +1,105 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Cooperatively cancels message execution, e.g., when replaying a long batch of messages off-chain.
+/// See [`MachineContext::cancellation`](crate::machine::MachineContext::cancellation).
+///
+/// Cancelling aborts the message currently being applied (and any later messages, until the token
+/// is [reset](Self::reset)). The token is checked at every syscall and, if the engine is
+/// [interruptible](crate::machine::NetworkConfig::interruptible), within long-running actor code.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<TokenInner>);
+
+#[derive(Default)]
+struct TokenInner {
+    cancelled: AtomicBool,
+    /// The engines currently running code on behalf of this token, with the number of live
+    /// stores watching each. Cancelling bumps their epochs so that running actors notice promptly.
+    engines: Mutex<Vec<(wasmtime::Engine, usize)>>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels execution.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        for (engine, _) in self.0.engines.lock().unwrap().iter() {
+            engine.increment_epoch();
+        }
+    }
+
+    /// Returns true if execution has been cancelled.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Returns true if an actor is currently running on behalf of this token.
+    pub fn is_running(&self) -> bool {
+        !self.0.engines.lock().unwrap().is_empty()
+    }
+
+    /// Clears the cancellation, allowing further messages to be applied.
+    pub fn reset(&self) {
+        self.0.cancelled.store(false, Ordering::SeqCst);
+    }
+
+    /// Registers an engine that runs code on behalf of this token until the returned watch is
+    /// dropped. This must be called before checking whether the token has been cancelled, so a
+    /// concurrent cancellation can't be missed.
+    pub(crate) fn watch(&self, engine: &wasmtime::Engine) -> Watch {
+        let mut engines = self.0.engines.lock().unwrap();
+        match engines
+            .iter_mut()
+            .find(|(e, _)| wasmtime::Engine::same(e, engine))
+        {
+            Some((_, count)) => *count += 1,
+            None => engines.push((engine.clone(), 1)),
+        }
+        Watch {
+            token: self.clone(),
+            engine: engine.clone(),
+        }
+    }
+}
+
+/// Unregisters an engine from a [`CancellationToken`] when dropped, so the token doesn't keep the
+/// engine alive.
+pub(crate) struct Watch {
+    token: CancellationToken,
+    engine: wasmtime::Engine,
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        let mut engines = self.token.0.engines.lock().unwrap();
+        if let Some(idx) = engines
+            .iter()
+            .position(|(e, _)| wasmtime::Engine::same(e, &self.engine))
+        {
+            engines[idx].1 -= 1;
+            if engines[idx].1 == 0 {
+                engines.swap_remove(idx);
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// The (fatal) error with which execution is aborted when cancelled. The executor turns this into
+/// a [cancelled](super::ApplyFailure::Cancelled) [`ApplyRet`](super::ApplyRet).
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+#[error("execution cancelled")]
+pub struct Cancelled;
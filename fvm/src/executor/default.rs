@@ -1,28 +1,31 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use std::collections::HashMap;
 use std::ops::{Deref, DerefMut};
 use std::result::Result as StdResult;
 
 use anyhow::{anyhow, Result};
 use cid::Cid;
 use fvm_ipld_encoding::{RawBytes, CBOR};
-use fvm_shared::address::Payload;
+use fvm_shared::address::{Address, Payload};
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::{ErrorNumber, ExitCode};
-use fvm_shared::event::StampedEvent;
 use fvm_shared::message::Message;
 use fvm_shared::receipt::Receipt;
 use fvm_shared::{ActorID, IPLD_RAW, METHOD_SEND};
 use num_traits::Zero;
 
-use super::{ApplyFailure, ApplyKind, ApplyRet, Executor};
+use super::{
+    ApplyFailure, ApplyKind, ApplyRet, Cancelled, EventChunks, Executor, PreApplyError,
+    PreApplyFailure, PreApplyResult, ValidatedMessage,
+};
 use crate::call_manager::{backtrace, Backtrace, CallManager, InvocationResult};
 use crate::eam_actor::EAM_ACTOR_ID;
 use crate::engine::EnginePool;
 use crate::gas::{Gas, GasCharge, GasOutputs};
 use crate::kernel::{Block, ClassifyResult, Context as _, ExecutionError, Kernel};
 use crate::machine::{Machine, BURNT_FUNDS_ACTOR_ID, REWARD_ACTOR_ID};
-use crate::trace::ExecutionTrace;
+use crate::trace::{ExecutionEvent, ExecutionTrace};
 
 /// The default [`Executor`].
 ///
@@ -35,6 +38,10 @@ pub struct DefaultExecutor<K: Kernel> {
     engine_pool: EnginePool,
     // If the inner value is `None` it means the machine got poisoned and is unusable.
     machine: Option<<K::CallManager as CallManager>::Machine>,
+    /// Test-only: extra tokens credited to senders on every gas refund. See
+    /// [`DefaultExecutor::skew_refunds`].
+    #[cfg(feature = "testing")]
+    refund_skew: TokenAmount,
 }
 
 impl<K: Kernel> Deref for DefaultExecutor<K> {
@@ -63,13 +70,73 @@ where
         msg: Message,
         apply_kind: ApplyKind,
         raw_length: usize,
+    ) -> anyhow::Result<ApplyRet> {
+        let Some(token) = self.context().cancellation.clone() else {
+            return self.apply_checked_message(msg, apply_kind, raw_length);
+        };
+        if token.is_cancelled() {
+            return Ok(ApplyRet::cancelled(Vec::new()));
+        }
+
+        // Apply cancellable messages in a transaction so we can discard their state changes
+        // (including the sender's gas payment and sequence) if they're cancelled.
+        self.state_tree_mut().begin_transaction();
+        let ret = self.apply_checked_message(msg, apply_kind, raw_length);
+        let cancelled = matches!(&ret, Ok(ret) if ret.is_cancelled());
+        self.state_tree_mut().end_transaction(cancelled)?;
+        ret
+    }
+
+    /// Flush the state-tree to the underlying blockstore.
+    fn flush(&mut self) -> anyhow::Result<Cid> {
+        Ok((**self).flush()?.root)
+    }
+}
+
+impl<K> DefaultExecutor<K>
+where
+    K: Kernel,
+{
+    /// Applies a message, checking invariants if enabled. This is [`Executor::execute_message`],
+    /// minus cancellation.
+    fn apply_checked_message(
+        &mut self,
+        msg: Message,
+        apply_kind: ApplyKind,
+        raw_length: usize,
+    ) -> anyhow::Result<ApplyRet> {
+        if !self.context().check_invariants {
+            return self.apply_message(msg, apply_kind, raw_length);
+        }
+
+        self.state_tree_mut().start_balance_tracking();
+        let ret = self.apply_message(msg, apply_kind, raw_length)?;
+        let balances = self.state_tree_mut().stop_balance_tracking()?;
+        // Cancelled messages are reverted wholesale, there's nothing to check.
+        if !ret.is_cancelled() {
+            self.check_balance_invariants(&balances, &ret)?;
+        }
+        Ok(ret)
+    }
+
+    /// Applies a message. This is [`Executor::execute_message`], minus the invariant checks.
+    fn apply_message(
+        &mut self,
+        msg: Message,
+        apply_kind: ApplyKind,
+        raw_length: usize,
     ) -> anyhow::Result<ApplyRet> {
         // Validate if the message was correct, charge for it, and extract some preliminary data.
-        let (sender_id, gas_cost, inclusion_cost) =
-            match self.preflight_message(&msg, apply_kind, raw_length)? {
-                Ok(res) => res,
-                Err(apply_ret) => return Ok(apply_ret),
-            };
+        let ValidatedMessage {
+            sender_id,
+            receiver_id,
+            gas_cost,
+            inclusion_cost,
+            ..
+        } = match self.preflight_message(&msg, apply_kind, raw_length)? {
+            Ok(res) => res,
+            Err(apply_ret) => return Ok(apply_ret),
+        };
 
         struct MachineExecRet {
             result: crate::kernel::error::Result<InvocationResult>,
@@ -77,14 +144,16 @@ where
             backtrace: Backtrace,
             exec_trace: ExecutionTrace,
             events_root: Option<Cid>,
-            events: Vec<StampedEvent>, // TODO consider removing if nothing in the client ends up using it.
+            events: EventChunks, // TODO consider removing if nothing in the client ends up using it.
         }
 
-        // Pre-resolve the message receiver's address, if known.
-        let receiver_id = self
+        // Resolve the origin's delegated address up-front so actors see a consistent snapshot,
+        // even if the origin's state changes mid-message.
+        let origin_delegated_address = self
             .state_tree()
-            .lookup_id(&msg.to)
-            .context("failure when looking up message receiver")?;
+            .get_actor(sender_id)
+            .context("failure when looking up message origin")?
+            .and_then(|act| act.delegated_address);
 
         // Filecoin caps the premium plus the base-fee at the fee-cap.
         // We expose the _effective_ premium to the user.
@@ -107,10 +176,12 @@ where
                 msg.gas_limit,
                 sender_id,
                 msg.from,
+                origin_delegated_address,
                 receiver_id,
                 msg.to,
                 msg.sequence,
                 effective_premium,
+                apply_kind == ApplyKind::Implicit,
             );
             // This error is fatal because it should have already been accounted for inside
             // preflight_message.
@@ -180,6 +251,12 @@ where
             events,
         } = ret;
 
+        // Cancellation aborts the entire call stack with a fatal error. The caller reverts the
+        // message's state changes.
+        if matches!(&res, Err(ExecutionError::Fatal(err)) if err.is::<Cancelled>()) {
+            return Ok(ApplyRet::cancelled(exec_trace));
+        }
+
         // Extract the exit code and build the result of the message application.
         let receipt = match res {
             Ok(InvocationResult { exit_code, value }) => {
@@ -256,8 +333,9 @@ where
             Some(ApplyFailure::MessageBacktrace(backtrace))
         };
 
-        match apply_kind {
-            ApplyKind::Explicit => self.finish_message(
+        let mut ret = match apply_kind {
+            ApplyKind::Explicit | ApplyKind::Simulated => self.finish_message(
+                apply_kind,
                 sender_id,
                 msg,
                 receipt,
@@ -278,21 +356,70 @@ where
                 failure_info,
                 exec_trace,
                 events,
+                suppressed_events: 0,
             }),
+        }?;
+
+        // The events root has already been computed over all events, so filtering only affects
+        // the events returned to the client.
+        if let Some(filter) = &self.context().event_filter {
+            ret.suppressed_events = filter.apply(&mut ret.events);
         }
+        Ok(ret)
     }
 
-    /// Flush the state-tree to the underlying blockstore.
-    fn flush(&mut self) -> anyhow::Result<Cid> {
-        let k = (**self).flush()?;
-        Ok(k)
+    /// Checks that applying a message conserved tokens: the balances of the actors it modified must
+    /// sum to the same total as before, and none may be negative. Gas burns and miner tips are
+    /// credited to the burnt-funds and reward actors respectively, so they're included in the
+    /// balance changes.
+    ///
+    /// `balances` are the balances of the modified actors from before the message was applied, as
+    /// recorded by [`StateTree::start_balance_tracking`](crate::state_tree::StateTree::start_balance_tracking).
+    fn check_balance_invariants(
+        &self,
+        balances: &HashMap<ActorID, TokenAmount>,
+        ret: &ApplyRet,
+    ) -> Result<()> {
+        let mut delta = TokenAmount::zero();
+        for (&id, before) in balances {
+            let after = self
+                .state_tree()
+                .get_actor(id)?
+                .map(|act| act.balance)
+                .unwrap_or_default();
+            if after.is_negative() {
+                return Err(anyhow!(
+                    "invariant violated: actor {} has a negative balance ({})",
+                    id,
+                    after
+                ));
+            }
+            delta += &after - before;
+        }
+
+        if !delta.is_zero() {
+            return Err(anyhow!(
+                "invariant violated: token conservation: balances of {} modified actors changed by \
+                 {} in total (base fee burn: {}, over-estimation burn: {}, miner tip: {}, \
+                 refund: {})",
+                balances.len(),
+                delta,
+                ret.base_fee_burn,
+                ret.over_estimation_burn,
+                ret.miner_tip,
+                ret.refund,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Test-only: credits senders with an extra `amount` on every gas refund, minting tokens out of
+    /// thin air. This exists to test the invariant checks.
+    #[cfg(feature = "testing")]
+    pub fn skew_refunds(&mut self, amount: TokenAmount) {
+        self.refund_skew = amount;
     }
-}
 
-impl<K> DefaultExecutor<K>
-where
-    K: Kernel,
-{
     /// Create a new [`DefaultExecutor`] for executing messages on the [`Machine`].
     pub fn new(
         engine_pool: EnginePool,
@@ -313,6 +440,8 @@ where
         Ok(Self {
             engine_pool,
             machine: Some(machine),
+            #[cfg(feature = "testing")]
+            refund_skew: TokenAmount::zero(),
         })
     }
 
@@ -322,45 +451,58 @@ where
         self.machine
     }
 
-    // TODO: The return type here is very strange because we have three cases:
-    //  1. Continue: Return sender ID, & gas.
-    //  2. Short-circuit: Return ApplyRet.
-    //  3. Fail: Return an error.
-    //  We could use custom types, but that would be even more annoying.
-    fn preflight_message(
-        &mut self,
+    /// Validates a message against the current state before applying it: checks the message's
+    /// gas limit covers its inclusion cost, the sender exists and may send messages, and (for
+    /// explicit messages) the sender's sequence and balance. Also resolves the receiver, if it
+    /// exists.
+    ///
+    /// This doesn't mutate any state or execute anything, so it can be used to check whether a
+    /// message _would_ be applied. On success, the returned [`ValidatedMessage`] describes the
+    /// changes [`Executor::execute_message`] will make to the sender before executing it.
+    ///
+    /// Returns an error only if the message is malformed or the state can't be read.
+    pub fn validate_for_application(
+        &self,
         msg: &Message,
         apply_kind: ApplyKind,
         raw_length: usize,
-    ) -> Result<StdResult<(ActorID, TokenAmount, GasCharge), ApplyRet>> {
+    ) -> Result<PreApplyResult> {
         msg.check().or_fatal()?;
 
         // TODO We don't like having price lists _inside_ the FVM, but passing
         //  these across the boundary is also a no-go.
         let pl = &self.context().price_list;
 
-        let (inclusion_cost, miner_penalty_amount) = match apply_kind {
+        let (inclusion_cost, miner_penalty) = match apply_kind {
             ApplyKind::Implicit => (
                 GasCharge::new("none", Gas::zero(), Gas::zero()),
                 Default::default(),
             ),
-            ApplyKind::Explicit => {
+            ApplyKind::Explicit | ApplyKind::Simulated => {
                 let inclusion_cost = pl.on_chain_message(raw_length);
                 let inclusion_total = inclusion_cost.total().round_up();
 
                 // Verify the cost of the message is not over the message gas limit.
                 if inclusion_total > msg.gas_limit {
-                    return Ok(Err(ApplyRet::prevalidation_fail(
-                        ExitCode::SYS_OUT_OF_GAS,
-                        format!("Out of gas ({} > {})", inclusion_total, msg.gas_limit),
-                        &self.context().base_fee * inclusion_total,
-                    )));
+                    return Ok(Err(PreApplyFailure {
+                        error: PreApplyError::OutOfGas {
+                            inclusion_gas: inclusion_total,
+                            gas_limit: msg.gas_limit,
+                        },
+                        miner_penalty: &self.context().base_fee * inclusion_total,
+                    }));
                 }
 
-                let miner_penalty_amount = &self.context().base_fee * msg.gas_limit;
-                (inclusion_cost, miner_penalty_amount)
+                let miner_penalty = &self.context().base_fee * msg.gas_limit;
+                (inclusion_cost, miner_penalty)
             }
         };
+        let fail = |error| -> Result<PreApplyResult> {
+            Ok(Err(PreApplyFailure {
+                error,
+                miner_penalty: miner_penalty.clone(),
+            }))
+        };
 
         // Load sender actor state.
         let sender_id = match self
@@ -369,17 +511,24 @@ where
             .with_context(|| format!("failed to lookup actor {}", &msg.from))?
         {
             Some(id) => id,
-            None => {
-                return Ok(Err(ApplyRet::prevalidation_fail(
-                    ExitCode::SYS_SENDER_INVALID,
-                    "Sender invalid",
-                    miner_penalty_amount,
-                )));
-            }
+            None => return fail(PreApplyError::SenderNotFound),
+        };
+
+        // Pre-resolve the message receiver's address, if known.
+        let resolve_receiver = || {
+            self.state_tree()
+                .lookup_id(&msg.to)
+                .context("failure when looking up message receiver")
         };
 
         if apply_kind == ApplyKind::Implicit {
-            return Ok(Ok((sender_id, TokenAmount::zero(), inclusion_cost)));
+            return Ok(Ok(ValidatedMessage {
+                sender_id,
+                receiver_id: resolve_receiver()?,
+                new_sender_state: None,
+                gas_cost: TokenAmount::zero(),
+                inclusion_cost,
+            }));
         }
 
         let mut sender_state = match self
@@ -388,13 +537,7 @@ where
             .with_context(|| format!("failed to lookup actor {}", &msg.from))?
         {
             Some(act) => act,
-            None => {
-                return Ok(Err(ApplyRet::prevalidation_fail(
-                    ExitCode::SYS_SENDER_INVALID,
-                    "Sender invalid",
-                    miner_penalty_amount,
-                )));
-            }
+            None => return fail(PreApplyError::SenderNotFound),
         };
 
         // Sender is valid if it is:
@@ -418,23 +561,15 @@ where
         }
 
         if !sender_is_valid {
-            return Ok(Err(ApplyRet::prevalidation_fail(
-                ExitCode::SYS_SENDER_INVALID,
-                "Send not from valid sender",
-                miner_penalty_amount,
-            )));
+            return fail(PreApplyError::InvalidSender);
         };
 
         // Check sequence is correct
         if msg.sequence != sender_state.sequence {
-            return Ok(Err(ApplyRet::prevalidation_fail(
-                ExitCode::SYS_SENDER_STATE_INVALID,
-                format!(
-                    "Actor sequence invalid: {} != {}",
-                    msg.sequence, sender_state.sequence
-                ),
-                miner_penalty_amount,
-            )));
+            return fail(PreApplyError::SequenceMismatch {
+                expected: sender_state.sequence,
+                actual: msg.sequence,
+            });
         };
 
         sender_state.sequence += 1;
@@ -442,34 +577,56 @@ where
         // Ensure from actor has enough balance to cover the gas cost of the message.
         let gas_cost: TokenAmount = msg.gas_fee_cap.clone() * msg.gas_limit;
         if sender_state.balance < gas_cost {
-            return Ok(Err(ApplyRet::prevalidation_fail(
-                ExitCode::SYS_SENDER_STATE_INVALID,
-                format!(
-                    "Actor balance less than needed: {} < {}",
-                    sender_state.balance, gas_cost
-                ),
-                miner_penalty_amount,
-            )));
+            return fail(PreApplyError::InsufficientBalance {
+                balance: sender_state.balance,
+                required: gas_cost,
+            });
         }
 
         sender_state.deduct_funds(&gas_cost)?;
 
+        Ok(Ok(ValidatedMessage {
+            sender_id,
+            receiver_id: resolve_receiver()?,
+            new_sender_state: Some(sender_state),
+            gas_cost,
+            inclusion_cost,
+        }))
+    }
+
+    /// Validates the message and, if valid, charges the sender for gas and increments its
+    /// sequence. Invalid messages are short-circuited with an [`ApplyRet`].
+    fn preflight_message(
+        &mut self,
+        msg: &Message,
+        apply_kind: ApplyKind,
+        raw_length: usize,
+    ) -> Result<StdResult<ValidatedMessage, ApplyRet>> {
+        let validated = match self.validate_for_application(msg, apply_kind, raw_length)? {
+            Ok(validated) => validated,
+            Err(failure) => return Ok(Err(failure.into())),
+        };
+
         // Update the actor in the state tree
-        self.state_tree_mut().set_actor(sender_id, sender_state);
+        if let Some(sender_state) = &validated.new_sender_state {
+            self.state_tree_mut()
+                .set_actor(validated.sender_id, sender_state.clone());
+        }
 
-        Ok(Ok((sender_id, gas_cost, inclusion_cost)))
+        Ok(Ok(validated))
     }
 
     #[allow(clippy::too_many_arguments)]
     fn finish_message(
         &mut self,
+        apply_kind: ApplyKind,
         sender_id: ActorID,
         msg: Message,
         receipt: Receipt,
         failure_info: Option<ApplyFailure>,
         gas_cost: TokenAmount,
-        exec_trace: ExecutionTrace,
-        events: Vec<StampedEvent>,
+        mut exec_trace: ExecutionTrace,
+        events: EventChunks,
     ) -> anyhow::Result<ApplyRet> {
         // NOTE: we don't support old network versions in the FVM, so we always burn.
         let GasOutputs {
@@ -488,6 +645,32 @@ where
             &msg.gas_premium,
         );
 
+        let tip_recipient = match self.context().miner_tip_recipient {
+            Some(id) if !apply_kind.is_consensus() => id,
+            Some(id) => {
+                log::warn!("ignoring miner tip recipient {id} when applying a consensus message");
+                REWARD_ACTOR_ID
+            }
+            None => REWARD_ACTOR_ID,
+        };
+
+        // Record redirected tips as a transfer from the sender, so the payout shows up in traces.
+        if tip_recipient != REWARD_ACTOR_ID && self.context().tracing {
+            exec_trace.push(ExecutionEvent::Call {
+                from: sender_id,
+                to: Address::new_id(tip_recipient),
+                method: METHOD_SEND,
+                params: None,
+                value: miner_tip.clone(),
+                gas_limit: 0,
+                read_only: false,
+            });
+            exec_trace.push(ExecutionEvent::CallReturn(ExitCode::OK, None));
+        }
+
+        #[cfg(feature = "testing")]
+        let refund_skew = self.refund_skew.clone();
+
         let mut transfer_to_actor = |addr: ActorID, amt: &TokenAmount| -> anyhow::Result<()> {
             if amt.is_negative() {
                 return Err(anyhow!("attempted to transfer negative value into actor"));
@@ -507,13 +690,18 @@ where
 
         transfer_to_actor(BURNT_FUNDS_ACTOR_ID, &base_fee_burn)?;
 
-        transfer_to_actor(REWARD_ACTOR_ID, &miner_tip)?;
+        transfer_to_actor(tip_recipient, &miner_tip)?;
 
         transfer_to_actor(BURNT_FUNDS_ACTOR_ID, &over_estimation_burn)?;
 
         // refund unused gas
         transfer_to_actor(sender_id, &refund)?;
 
+        // Test-only: mint the configured skew on top of the refund. This is not part of the gas
+        // accounting below.
+        #[cfg(feature = "testing")]
+        transfer_to_actor(sender_id, &refund_skew)?;
+
         if (&base_fee_burn + &over_estimation_burn + &refund + &miner_tip) != gas_cost {
             // Sanity check. This could be a fatal error.
             return Err(anyhow!("Gas handling math is wrong"));
@@ -530,6 +718,7 @@ where
             failure_info,
             exec_trace,
             events,
+            suppressed_events: 0,
         })
     }
 
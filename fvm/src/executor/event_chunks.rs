@@ -0,0 +1,252 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::ops::Index;
+
+use fvm_shared::event::StampedEvent;
+
+/// The events emitted by a message, in emission order.
+///
+/// Events are stored in fixed-size chunks so that accumulating thousands of events never requires
+/// reallocating (and copying) one giant buffer, and so that clients can hand the chunks off (see
+/// [`EventChunks::into_chunks`]) without copying them either. Chunk boundaries are otherwise
+/// invisible: every chunk but the last always holds exactly [`EventChunks::CHUNK_SIZE`] events.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventChunks {
+    chunks: Vec<Vec<StampedEvent>>,
+    len: usize,
+}
+
+impl EventChunks {
+    /// The number of events per chunk.
+    pub const CHUNK_SIZE: usize = 256;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of events.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the event at the given index, if any.
+    pub fn get(&self, idx: usize) -> Option<&StampedEvent> {
+        self.chunks
+            .get(idx / Self::CHUNK_SIZE)
+            .and_then(|c| c.get(idx % Self::CHUNK_SIZE))
+    }
+
+    /// Appends an event.
+    pub fn push(&mut self, evt: StampedEvent) {
+        match self.chunks.last_mut() {
+            Some(chunk) if chunk.len() < Self::CHUNK_SIZE => chunk.push(evt),
+            _ => {
+                let mut chunk = Vec::with_capacity(Self::CHUNK_SIZE);
+                chunk.push(evt);
+                self.chunks.push(chunk);
+            }
+        }
+        self.len += 1;
+    }
+
+    /// Removes and returns the last event, if any.
+    pub fn pop(&mut self) -> Option<StampedEvent> {
+        let evt = self.chunks.last_mut()?.pop()?;
+        if self.chunks.last().map_or(false, Vec::is_empty) {
+            self.chunks.pop();
+        }
+        self.len -= 1;
+        Some(evt)
+    }
+
+    /// Drops all events after the first `len` events.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        let chunks = (len + Self::CHUNK_SIZE - 1) / Self::CHUNK_SIZE;
+        self.chunks.truncate(chunks);
+        if let Some(chunk) = self.chunks.last_mut() {
+            chunk.truncate(len - (chunks - 1) * Self::CHUNK_SIZE);
+        }
+        self.len = len;
+    }
+
+    /// Retains only the events matching the predicate, preserving their order.
+    pub fn retain(&mut self, mut f: impl FnMut(&StampedEvent) -> bool) {
+        *self = std::mem::take(self)
+            .into_iter()
+            .filter(|evt| f(evt))
+            .collect();
+    }
+
+    /// Iterates over the events in order.
+    pub fn iter(&self) -> impl Iterator<Item = &StampedEvent> + '_ {
+        self.chunks.iter().flatten()
+    }
+
+    /// Iterates over the chunks in order.
+    pub fn chunks(&self) -> impl Iterator<Item = &[StampedEvent]> + '_ {
+        self.chunks.iter().map(Vec::as_slice)
+    }
+
+    /// Consumes the events, returning the underlying chunks in order.
+    pub fn into_chunks(self) -> Vec<Vec<StampedEvent>> {
+        self.chunks
+    }
+
+    /// Copies the events into a single vector.
+    pub fn to_vec(&self) -> Vec<StampedEvent> {
+        let mut events = Vec::with_capacity(self.len);
+        for chunk in &self.chunks {
+            events.extend_from_slice(chunk);
+        }
+        events
+    }
+}
+
+impl Index<usize> for EventChunks {
+    type Output = StampedEvent;
+
+    fn index(&self, idx: usize) -> &StampedEvent {
+        self.get(idx)
+            .unwrap_or_else(|| panic!("event index {idx} out of bounds ({} events)", self.len))
+    }
+}
+
+impl FromIterator<StampedEvent> for EventChunks {
+    fn from_iter<T: IntoIterator<Item = StampedEvent>>(iter: T) -> Self {
+        let mut events = EventChunks::new();
+        for evt in iter {
+            events.push(evt);
+        }
+        events
+    }
+}
+
+impl From<Vec<StampedEvent>> for EventChunks {
+    fn from(events: Vec<StampedEvent>) -> Self {
+        events.into_iter().collect()
+    }
+}
+
+impl IntoIterator for EventChunks {
+    type Item = StampedEvent;
+    type IntoIter = std::iter::Flatten<std::vec::IntoIter<Vec<StampedEvent>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.chunks.into_iter().flatten()
+    }
+}
+
+impl<'a> IntoIterator for &'a EventChunks {
+    type Item = &'a StampedEvent;
+    type IntoIter = std::iter::Flatten<std::slice::Iter<'a, Vec<StampedEvent>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.chunks.iter().flatten()
+    }
+}
+
+impl PartialEq<[StampedEvent]> for EventChunks {
+    fn eq(&self, other: &[StampedEvent]) -> bool {
+        self.len == other.len() && self.iter().eq(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_shared::event::StampedEvent;
+
+    use super::EventChunks;
+
+    const CHUNK: usize = EventChunks::CHUNK_SIZE;
+
+    fn event(emitter: u64) -> StampedEvent {
+        StampedEvent::new(emitter, vec![].into())
+    }
+
+    fn events(n: usize) -> EventChunks {
+        (0..n as u64).map(event).collect()
+    }
+
+    fn emitters(events: &EventChunks) -> Vec<u64> {
+        events.iter().map(|e| e.emitter).collect()
+    }
+
+    #[test]
+    fn push_and_iterate() {
+        let events = events(10_000);
+        assert_eq!(events.len(), 10_000);
+        assert_eq!(emitters(&events), (0..10_000).collect::<Vec<_>>());
+        assert_eq!(
+            events
+                .to_vec()
+                .iter()
+                .map(|e| e.emitter)
+                .collect::<Vec<_>>(),
+            emitters(&events)
+        );
+
+        // Every chunk but the last is full.
+        let sizes: Vec<_> = events.chunks().map(<[_]>::len).collect();
+        assert_eq!(sizes.len(), (10_000 + CHUNK - 1) / CHUNK);
+        assert!(sizes[..sizes.len() - 1].iter().all(|&s| s == CHUNK));
+        assert_eq!(sizes.iter().sum::<usize>(), 10_000);
+
+        // Indexing works across chunk boundaries.
+        for i in [0, CHUNK - 1, CHUNK, CHUNK + 1, 9_999] {
+            assert_eq!(events[i].emitter, i as u64);
+        }
+        assert!(events.get(10_000).is_none());
+
+        // Handing off the chunks preserves the order.
+        let flat: Vec<_> = events
+            .into_chunks()
+            .into_iter()
+            .flatten()
+            .map(|e| e.emitter)
+            .collect();
+        assert_eq!(flat, (0..10_000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn truncate_and_pop() {
+        for len in [0, 1, CHUNK - 1, CHUNK, CHUNK + 1, 2 * CHUNK, 3 * CHUNK - 1] {
+            let mut events = events(3 * CHUNK);
+            events.truncate(len);
+            assert_eq!(events.len(), len);
+            assert_eq!(emitters(&events), (0..len as u64).collect::<Vec<_>>());
+
+            // Pushing after truncating keeps chunks full.
+            events.push(event(1000));
+            assert_eq!(events[len].emitter, 1000);
+            assert_eq!(events, events.to_vec()[..]);
+            assert!(events.chunks().take(len / CHUNK).all(|c| c.len() == CHUNK));
+        }
+
+        let mut events = events(CHUNK + 1);
+        assert_eq!(events.pop().map(|e| e.emitter), Some(CHUNK as u64));
+        assert_eq!(events.chunks().count(), 1);
+        assert_eq!(events.len(), CHUNK);
+
+        // Truncating to a longer length is a no-op.
+        events.truncate(CHUNK + 10);
+        assert_eq!(events.len(), CHUNK);
+    }
+
+    #[test]
+    fn retain() {
+        let mut events = events(3 * CHUNK);
+        events.retain(|e| e.emitter % 2 == 0);
+        assert_eq!(
+            emitters(&events),
+            (0..3 * CHUNK as u64).step_by(2).collect::<Vec<_>>()
+        );
+        assert_eq!(events[CHUNK].emitter, 2 * CHUNK as u64);
+    }
+}
@@ -0,0 +1,181 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::collections::HashSet;
+
+use fvm_shared::event::{Entry, EventKey, Flags, StampedEvent};
+use fvm_shared::{ActorID, MAX_CID_LEN};
+
+use super::EventChunks;
+
+/// Selects which events are returned in [`ApplyRet::events`](super::ApplyRet::events). See
+/// [`MachineContext::event_filter`](crate::machine::MachineContext::event_filter).
+///
+/// Filtering only affects what's returned to the client: events that don't match are still
+/// committed to the receipt's events root, and are counted in
+/// [`ApplyRet::suppressed_events`](super::ApplyRet::suppressed_events).
+///
+/// An event matches if it was emitted by one of the `emitters`, and at least one of its entries
+/// has a key starting with one of the `key_prefixes` and all the `flags` set. Empty criteria match
+/// anything.
+#[derive(Clone, Debug, Default)]
+pub struct EventFilter {
+    /// The actors whose events are returned, or any actor if empty.
+    pub emitters: HashSet<ActorID>,
+    /// Entry key prefixes, matched against the raw key bytes (the UTF-8 string, or the binary
+    /// CID). Any key matches if empty.
+    pub key_prefixes: Vec<Vec<u8>>,
+    /// Flags that a matching entry must have set.
+    pub flags: Flags,
+}
+
+impl EventFilter {
+    /// Returns true if the event should be returned to the client.
+    pub fn matches(&self, evt: &StampedEvent) -> bool {
+        if !self.emitters.is_empty() && !self.emitters.contains(&evt.emitter) {
+            return false;
+        }
+        if self.key_prefixes.is_empty() && self.flags.is_empty() {
+            return true;
+        }
+        evt.event.entries.iter().any(|e| self.matches_entry(e))
+    }
+
+    fn matches_entry(&self, entry: &Entry) -> bool {
+        if !entry.flags.contains(self.flags) {
+            return false;
+        }
+        if self.key_prefixes.is_empty() {
+            return true;
+        }
+        // Encode CID keys on the stack instead of allocating.
+        let mut buf = [0u8; MAX_CID_LEN];
+        let key = match &entry.key {
+            EventKey::String(k) => k.as_bytes(),
+            EventKey::Cid(c) => match c.write_bytes(&mut buf[..]) {
+                Ok(len) => &buf[..len],
+                Err(_) => return false,
+            },
+        };
+        self.key_prefixes.iter().any(|p| key.starts_with(p))
+    }
+
+    /// Removes the events that don't match the filter, returning how many were removed.
+    pub fn apply(&self, events: &mut EventChunks) -> u64 {
+        let before = events.len();
+        events.retain(|evt| self.matches(evt));
+        (before - events.len()) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cid::Cid;
+    use fvm_shared::event::{Entry, Flags, StampedEvent};
+    use fvm_shared::IPLD_RAW;
+    use multihash::{Code, MultihashDigest};
+
+    use super::EventFilter;
+    use crate::executor::EventChunks;
+
+    fn event(emitter: u64, entries: &[(Flags, &str)]) -> StampedEvent {
+        StampedEvent::new(
+            emitter,
+            entries
+                .iter()
+                .map(|(flags, key)| Entry {
+                    flags: *flags,
+                    key: (*key).into(),
+                    codec: IPLD_RAW,
+                    value: vec![],
+                })
+                .collect::<Vec<_>>()
+                .into(),
+        )
+    }
+
+    #[test]
+    fn matches() {
+        let foo = event(100, &[(Flags::FLAG_INDEXED_ALL, "foo")]);
+        let bar = event(101, &[(Flags::FLAG_INDEXED_KEY, "bar")]);
+        let both = event(
+            101,
+            &[(Flags::empty(), "foobar"), (Flags::FLAG_INDEXED_ALL, "baz")],
+        );
+
+        // The default filter matches everything.
+        let filter = EventFilter::default();
+        assert!([&foo, &bar, &both].iter().all(|e| filter.matches(e)));
+
+        let filter = EventFilter {
+            emitters: [101].into(),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&foo));
+        assert!(filter.matches(&bar));
+
+        // Criteria on entries must be met by the same entry.
+        let filter = EventFilter {
+            key_prefixes: vec![b"foo".to_vec()],
+            flags: Flags::FLAG_INDEXED_VALUE,
+            ..Default::default()
+        };
+        assert!(filter.matches(&foo));
+        assert!(!filter.matches(&bar));
+        assert!(!filter.matches(&both));
+
+        let filter = EventFilter {
+            key_prefixes: vec![b"ba".to_vec()],
+            ..Default::default()
+        };
+        assert!(!filter.matches(&foo));
+        assert!(filter.matches(&bar));
+        assert!(filter.matches(&both));
+    }
+
+    #[test]
+    fn cid_keys() {
+        let cid = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(b"key"));
+        let evt = StampedEvent::new(
+            100,
+            vec![Entry {
+                flags: Flags::FLAG_CID_KEY,
+                key: cid.into(),
+                codec: IPLD_RAW,
+                value: vec![],
+            }]
+            .into(),
+        );
+
+        let bytes = cid.to_bytes();
+        let filter = EventFilter {
+            key_prefixes: vec![bytes[..4].to_vec()],
+            ..Default::default()
+        };
+        assert!(filter.matches(&evt));
+
+        let filter = EventFilter {
+            key_prefixes: vec![b"key".to_vec()],
+            ..Default::default()
+        };
+        assert!(!filter.matches(&evt));
+    }
+
+    #[test]
+    fn apply() {
+        let mut events: EventChunks = vec![
+            event(100, &[(Flags::FLAG_INDEXED_ALL, "foo")]),
+            event(101, &[(Flags::FLAG_INDEXED_ALL, "bar")]),
+            event(100, &[(Flags::FLAG_INDEXED_ALL, "baz")]),
+        ]
+        .into();
+        let filter = EventFilter {
+            emitters: [100].into(),
+            ..Default::default()
+        };
+        assert_eq!(filter.apply(&mut events), 1);
+        assert_eq!(
+            events.iter().map(|e| e.emitter).collect::<Vec<_>>(),
+            vec![100, 100]
+        );
+    }
+}
@@ -1,22 +1,31 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+mod cancel;
 mod default;
+mod event_chunks;
+mod event_filter;
 mod threaded;
 
 use std::fmt::Display;
 
+pub use cancel::{CancellationToken, Cancelled};
 use cid::Cid;
 pub use default::DefaultExecutor;
+pub use event_chunks::EventChunks;
+pub use event_filter::EventFilter;
 use fvm_ipld_encoding::RawBytes;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ExitCode;
 use fvm_shared::event::StampedEvent;
 use fvm_shared::message::Message;
 use fvm_shared::receipt::Receipt;
+use fvm_shared::ActorID;
 use num_traits::Zero;
 pub use threaded::ThreadedExecutor;
 
 use crate::call_manager::Backtrace;
+use crate::gas::GasCharge;
+use crate::state_tree::ActorState;
 use crate::trace::ExecutionTrace;
 use crate::Kernel;
 
@@ -53,6 +62,9 @@ pub enum ApplyFailure {
     MessageBacktrace(Backtrace),
     /// A message describing a pre-validation failure.
     PreValidation(String),
+    /// Execution was cancelled with a [`CancellationToken`]. The message's state changes were
+    /// discarded and its receipt must not be included on-chain.
+    Cancelled,
 }
 
 impl Display for ApplyFailure {
@@ -65,6 +77,9 @@ impl Display for ApplyFailure {
             ApplyFailure::PreValidation(msg) => {
                 writeln!(f, "pre-validation failed: {}", msg)?;
             }
+            ApplyFailure::Cancelled => {
+                writeln!(f, "execution cancelled")?;
+            }
         }
         Ok(())
     }
@@ -91,8 +106,12 @@ pub struct ApplyRet {
     pub failure_info: Option<ApplyFailure>,
     /// Execution trace information, for debugging.
     pub exec_trace: ExecutionTrace,
-    /// Events generated while applying the message.
-    pub events: Vec<StampedEvent>,
+    /// Events generated while applying the message, minus any excluded by the
+    /// [`EventFilter`].
+    pub events: EventChunks,
+    /// The number of events excluded from `events` by the [`EventFilter`]. These events are still
+    /// committed to the receipt's events root.
+    pub suppressed_events: u64,
 }
 
 impl ApplyRet {
@@ -118,19 +137,156 @@ impl ApplyRet {
             gas_burned: 0,
             failure_info: Some(ApplyFailure::PreValidation(message.into())),
             exec_trace: vec![],
-            events: vec![],
+            events: EventChunks::new(),
+            suppressed_events: 0,
+        }
+    }
+
+    /// The result of a cancelled message. This isn't a consensus outcome: the message had no
+    /// effect, and the receipt is a placeholder.
+    pub fn cancelled(exec_trace: ExecutionTrace) -> ApplyRet {
+        ApplyRet {
+            msg_receipt: Receipt {
+                exit_code: ExitCode::SYS_ASSERTION_FAILED,
+                return_data: RawBytes::default(),
+                gas_used: 0,
+                events_root: None,
+            },
+            penalty: TokenAmount::zero(),
+            miner_tip: TokenAmount::zero(),
+            base_fee_burn: TokenAmount::zero(),
+            over_estimation_burn: TokenAmount::zero(),
+            refund: TokenAmount::zero(),
+            gas_refund: 0,
+            gas_burned: 0,
+            failure_info: Some(ApplyFailure::Cancelled),
+            exec_trace,
+            events: EventChunks::new(),
+            suppressed_events: 0,
+        }
+    }
+
+    /// Returns true if the message was cancelled, see [`ApplyRet::cancelled`].
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.failure_info, Some(ApplyFailure::Cancelled))
+    }
+
+    /// Iterates over the returned events, in emission order.
+    pub fn events_iter(&self) -> impl Iterator<Item = &StampedEvent> + '_ {
+        self.events.iter()
+    }
+
+    /// Returns the number of returned events.
+    pub fn events_len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Consumes the result, returning the events in chunks of at most
+    /// [`EventChunks::CHUNK_SIZE`] without copying them.
+    pub fn into_events_chunks(self) -> Vec<Vec<StampedEvent>> {
+        self.events.into_chunks()
+    }
+}
+
+/// The result of validating a message against the current state, before applying it. See
+/// [`DefaultExecutor::validate_for_application`].
+pub type PreApplyResult = std::result::Result<ValidatedMessage, PreApplyFailure>;
+
+/// A message that passed pre-application validation, along with everything needed to apply it.
+#[derive(Clone, Debug)]
+pub struct ValidatedMessage {
+    /// The ID of the message's sender.
+    pub sender_id: ActorID,
+    /// The ID of the message's receiver, if it exists.
+    pub receiver_id: Option<ActorID>,
+    /// The sender's state after paying for gas and incrementing its sequence, or `None` for
+    /// implicit messages (which don't touch the sender).
+    pub new_sender_state: Option<ActorState>,
+    /// The maximum cost of the message (gas fee cap times gas limit), paid up-front by the sender.
+    pub gas_cost: TokenAmount,
+    /// The gas charged for including the message on-chain.
+    pub inclusion_cost: GasCharge,
+}
+
+/// A message that failed pre-application validation.
+#[derive(Clone, Debug)]
+pub struct PreApplyFailure {
+    /// Why the message failed validation.
+    pub error: PreApplyError,
+    /// The penalty the miner must pay for including the invalid message.
+    pub miner_penalty: TokenAmount,
+}
+
+/// The reasons a message may fail pre-application validation.
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+pub enum PreApplyError {
+    /// The message's gas limit doesn't cover the gas for including it on-chain.
+    #[error("Out of gas ({inclusion_gas} > {gas_limit})")]
+    OutOfGas { inclusion_gas: u64, gas_limit: u64 },
+    /// The sender doesn't exist.
+    #[error("Sender invalid")]
+    SenderNotFound,
+    /// The sender isn't allowed to send messages (e.g., it isn't an account).
+    #[error("Send not from valid sender")]
+    InvalidSender,
+    /// The message's sequence doesn't match the sender's.
+    #[error("Actor sequence invalid: {actual} != {expected}")]
+    SequenceMismatch { expected: u64, actual: u64 },
+    /// The sender can't afford the message's maximum gas cost.
+    #[error("Actor balance less than needed: {balance} < {required}")]
+    InsufficientBalance {
+        balance: TokenAmount,
+        required: TokenAmount,
+    },
+}
+
+impl PreApplyError {
+    /// The exit code recorded in the message's receipt.
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            PreApplyError::OutOfGas { .. } => ExitCode::SYS_OUT_OF_GAS,
+            PreApplyError::SenderNotFound | PreApplyError::InvalidSender => {
+                ExitCode::SYS_SENDER_INVALID
+            }
+            PreApplyError::SequenceMismatch { .. } | PreApplyError::InsufficientBalance { .. } => {
+                ExitCode::SYS_SENDER_STATE_INVALID
+            }
         }
     }
 }
 
+impl From<PreApplyFailure> for ApplyRet {
+    fn from(failure: PreApplyFailure) -> Self {
+        ApplyRet::prevalidation_fail(
+            failure.error.exit_code(),
+            failure.error.to_string(),
+            failure.miner_penalty,
+        )
+    }
+}
+
 /// The kind of message being applied:
 ///
 /// 1. Explicit messages may only come from account actors and charge the sending account for gas
 /// consumed.
 /// 2. Implicit messages may come from any actor, ignore the nonce, and charge no gas (but still
 /// account for it).
+/// 3. Simulated messages are applied like explicit messages, but never for consensus (e.g., when
+/// estimating gas or modeling alternative fee markets). Only simulated messages honor
+/// simulation-only options like [`MachineContext::miner_tip_recipient`](crate::machine::MachineContext::miner_tip_recipient).
 #[derive(Eq, PartialEq, Copy, Clone, Debug)]
 pub enum ApplyKind {
     Explicit,
     Implicit,
+    Simulated,
+}
+
+impl ApplyKind {
+    /// Returns true if messages of this kind may be applied for consensus.
+    pub fn is_consensus(self) -> bool {
+        match self {
+            ApplyKind::Explicit | ApplyKind::Implicit => true,
+            ApplyKind::Simulated => false,
+        }
+    }
 }
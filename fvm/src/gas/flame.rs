@@ -0,0 +1,71 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::fmt::Write;
+
+/// A node in a hierarchical gas profile. Call frames contain the gas charges (and nested calls)
+/// made while they were executing; gas charges are leaves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlameFrame {
+    pub name: String,
+    /// Total milligas charged in this frame, including all children.
+    pub gas: u64,
+    pub children: Vec<FlameFrame>,
+}
+
+impl FlameFrame {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            gas: 0,
+            children: Vec::new(),
+        }
+    }
+
+    /// Records a gas charge made directly in this frame. Charges with the same name are merged.
+    pub(super) fn record_charge(&mut self, name: &str, gas: u64) {
+        self.gas = self.gas.saturating_add(gas);
+        match self
+            .children
+            .iter_mut()
+            .find(|c| c.children.is_empty() && c.name == name)
+        {
+            Some(leaf) => leaf.gas = leaf.gas.saturating_add(gas),
+            None => self.children.push(FlameFrame {
+                name: name.to_owned(),
+                gas,
+                children: Vec::new(),
+            }),
+        }
+    }
+
+    /// Adds a completed child frame, accounting for its gas in this frame.
+    pub(super) fn push_child(&mut self, child: FlameFrame) {
+        self.gas = self.gas.saturating_add(child.gas);
+        self.children.push(child);
+    }
+
+    /// Serializes this frame in Brendan Gregg's collapsed stack format: one line per stack,
+    /// consisting of the `;`-separated frame names followed by the gas charged directly in the
+    /// innermost frame.
+    pub fn to_collapsed_stacks(&self) -> String {
+        let mut out = String::new();
+        self.write_collapsed(&mut Vec::new(), &mut out);
+        out
+    }
+
+    fn write_collapsed<'a>(&'a self, stack: &mut Vec<&'a str>, out: &mut String) {
+        stack.push(&self.name);
+        let children_gas = self
+            .children
+            .iter()
+            .fold(0u64, |acc, c| acc.saturating_add(c.gas));
+        let self_gas = self.gas.saturating_sub(children_gas);
+        if self_gas > 0 {
+            let _ = writeln!(out, "{} {}", stack.join(";"), self_gas);
+        }
+        for child in &self.children {
+            child.write_collapsed(stack, out);
+        }
+        stack.pop();
+    }
+}
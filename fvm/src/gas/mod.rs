@@ -2,7 +2,9 @@
 // Copyright 2019-2022 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use std::borrow::Cow;
 use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::fmt::{Debug, Display};
 use std::ops::{Add, AddAssign, Mul, Sub, SubAssign};
 
@@ -10,12 +12,16 @@ use anyhow::Context;
 use num_traits::Zero;
 
 pub use self::charge::GasCharge;
+pub use self::flame::FlameFrame;
 pub(crate) use self::outputs::GasOutputs;
 pub use self::price_list::{price_list_by_network_version, PriceList, WasmGasPrices};
-pub use self::timer::{GasDuration, GasInstant, GasTimer};
+pub use self::timer::{GasClock, GasDuration, GasInstant, GasTimer};
 use crate::kernel::{ClassifyResult, ExecutionError, Result};
+use crate::syscall_error;
+use crate::trace::ExecutionEvent;
 
 mod charge;
+mod flame;
 mod outputs;
 mod price_list;
 mod timer;
@@ -24,7 +30,8 @@ pub const MILLIGAS_PRECISION: u64 = 1000;
 
 /// A typesafe representation of gas (internally stored as milligas).
 ///
-/// - All math operations are _saturating_ and never overflow.
+/// - All math operations are _saturating_ and never overflow: sums and products saturate at
+///   `u64::MAX` milligas, and differences at zero. They never panic or wrap, even in debug builds.
 /// - Enforces correct units by making it impossible to, e.g., get gas squared (by multiplying gas
 ///   by gas).
 /// - Makes it harder to confuse gas and milligas.
@@ -164,11 +171,43 @@ struct GasSnapshot {
     used: Gas,
 }
 
+/// Charges strictly below this amount are eligible for batching.
+const BATCH_CHARGE_THRESHOLD: Gas = Gas::new(1000);
+
+/// The maximum number of charges accumulated before the batch is reconciled.
+const BATCH_MAX_CHARGES: u32 = 64;
+
+/// Small charges that have been accepted but not yet added to the tracked gas used.
+#[derive(Default)]
+struct GasBatch {
+    gas: Cell<Gas>,
+    charges: Cell<u32>,
+}
+
+/// Identifies a sub-meter created with [`GasTracker::create_submeter`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MeterId(u32);
+
 pub struct GasTracker {
     gas_limit: Gas,
     gas_used: Cell<Gas>,
     gas_snapshots: Vec<GasSnapshot>,
-    trace: Option<RefCell<Vec<GasCharge>>>,
+    trace: Option<RefCell<Vec<ExecutionEvent>>>,
+    /// Batched small charges, or `None` if batching is disabled.
+    batch: Option<GasBatch>,
+    /// The stack of open call frames for the flamegraph profile, if tracing. The first frame is
+    /// the root and is never popped.
+    flame: Option<RefCell<Vec<FlameFrame>>>,
+    /// Remaining budgets of named sub-meters, indexed by [`MeterId`].
+    submeters: RefCell<Vec<Gas>>,
+    /// The clock used to time traced gas charges.
+    clock: GasClock,
+    /// Gas charged (less refunds) with [`GasTracker::apply_refundable_charge`] under each refund
+    /// row declared with [`GasTracker::with_refundable_charges`].
+    refundable: RefCell<HashMap<Cow<'static, str>, Gas>>,
+    /// Whether charges use gas. See [`GasTracker::disable_metering`].
+    #[cfg(feature = "benchmarking")]
+    metered: Cell<bool>,
 }
 
 impl GasTracker {
@@ -186,10 +225,83 @@ impl GasTracker {
             gas_used: Cell::new(gas_used),
             gas_snapshots: Vec::new(),
             trace: enable_tracing.then_some(Default::default()),
+            batch: Some(GasBatch::default()),
+            flame: enable_tracing.then(|| RefCell::new(vec![FlameFrame::new("root")])),
+            submeters: Default::default(),
+            clock: GasClock::Wall,
+            refundable: Default::default(),
+            #[cfg(feature = "benchmarking")]
+            metered: Cell::new(true),
+        }
+    }
+
+    /// Stops metering gas: from now on, all charges succeed without using any gas. Used when
+    /// benchmarking, so that gas doesn't interfere with the measurements.
+    #[cfg(feature = "benchmarking")]
+    pub fn disable_metering(&self) {
+        self.reconcile();
+        self.metered.set(false);
+    }
+
+    /// Times traced gas charges with the given clock instead of the wall clock.
+    pub fn with_clock(mut self, clock: GasClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Declares the names of the charges that may be refunded (see
+    /// [`PriceList::refundable_charges`]). Refunds of any other charges are ignored.
+    pub fn with_refundable_charges(self, names: &[&'static str]) -> Self {
+        self.refundable
+            .borrow_mut()
+            .extend(names.iter().map(|&name| (Cow::Borrowed(name), Gas::zero())));
+        self
+    }
+
+    /// Disables batching of small gas charges. Gas accounting is identical either way; this only
+    /// exists to compare the two paths.
+    pub fn without_batching(mut self) -> Self {
+        self.reconcile();
+        self.batch = None;
+        self
+    }
+
+    /// Returns the gas charged in the current batch, not yet added to the gas used.
+    fn pending(&self) -> Gas {
+        self.batch.as_ref().map(|b| b.gas.get()).unwrap_or_default()
+    }
+
+    /// Folds any batched charges into the gas used.
+    fn reconcile(&self) {
+        if let Some(batch) = &self.batch {
+            batch.charges.set(0);
+            let pending = batch.gas.replace(Gas::zero());
+            self.gas_used.set(self.gas_used.get() + pending);
         }
     }
 
     fn charge_gas_inner(&self, to_use: Gas) -> Result<()> {
+        #[cfg(feature = "benchmarking")]
+        if !self.metered.get() {
+            return Ok(());
+        }
+
+        if let Some(batch) = &self.batch {
+            if to_use < BATCH_CHARGE_THRESHOLD {
+                // Preflight: only defer the charge if it (and all other batched charges) would
+                // succeed. Otherwise, reconcile and take the exact path below so that out of gas
+                // errors are raised on exactly the same charge as without batching.
+                let pending = batch.gas.get() + to_use;
+                let charges = batch.charges.get() + 1;
+                if charges < BATCH_MAX_CHARGES && self.gas_used.get() + pending <= self.gas_limit {
+                    batch.gas.set(pending);
+                    batch.charges.set(charges);
+                    return Ok(());
+                }
+            }
+            self.reconcile();
+        }
+
         // The gas type uses saturating math.
         let gas_used = self.gas_used.get() + to_use;
         if gas_used > self.gas_limit {
@@ -207,10 +319,11 @@ impl GasTracker {
     pub fn charge_gas(&self, name: &str, to_use: Gas) -> Result<GasTimer> {
         log::trace!("charging gas: {} {}", name, to_use);
         let res = self.charge_gas_inner(to_use);
+        self.record_flame_charge(name, to_use, &res);
         if let Some(trace) = &self.trace {
             let mut charge = GasCharge::new(name.to_owned(), to_use, Gas::zero());
-            let timer = GasTimer::new(&mut charge.elapsed);
-            trace.borrow_mut().push(charge);
+            let timer = GasTimer::new_with_clock(&mut charge.elapsed, &self.clock);
+            trace.borrow_mut().push(ExecutionEvent::GasCharge(charge));
             res.map(|_| timer)
         } else {
             res.map(|_| GasTimer::empty())
@@ -222,17 +335,96 @@ impl GasTracker {
         let to_use = charge.total();
         log::trace!("charging gas: {} {}", &charge.name, to_use);
         let res = self.charge_gas_inner(to_use);
+        self.record_flame_charge(&charge.name, to_use, &res);
         if let Some(trace) = &self.trace {
-            let timer = GasTimer::new(&mut charge.elapsed);
-            trace.borrow_mut().push(charge);
+            let timer = GasTimer::new_with_clock(&mut charge.elapsed, &self.clock);
+            trace.borrow_mut().push(ExecutionEvent::GasCharge(charge));
             res.map(|_| timer)
         } else {
             res.map(|_| GasTimer::empty())
         }
     }
 
+    /// Applies the specified gas charge like [`GasTracker::apply_charge`], allowing it to be
+    /// refunded later with [`GasTracker::refund_gas`] if the charge's name is a declared refund
+    /// row (see [`GasTracker::with_refundable_charges`]).
+    pub fn apply_refundable_charge(&self, charge: GasCharge) -> Result<GasTimer> {
+        let name = charge.name.clone();
+        let to_use = charge.total();
+        let timer = self.apply_charge(charge)?;
+        match self.refundable.borrow_mut().get_mut(&name) {
+            Some(available) => *available += to_use,
+            None => log::trace!("gas charged under {} can't be refunded", name),
+        }
+        Ok(timer)
+    }
+
+    /// Applies a refund returned by [`PriceList::on_refund`], returning the gas actually refunded.
+    /// See [`GasTracker::refund_gas`].
+    pub fn apply_refund(&self, refund: GasCharge) -> Gas {
+        self.refund_gas(&refund.name, refund.total())
+    }
+
+    /// Refunds up to `amount` of gas previously charged under the given name, returning the gas
+    /// actually refunded. Only declared refund rows can be refunded, and the refund is capped at
+    /// the gas charged (and not yet refunded) under that row with
+    /// [`GasTracker::apply_refundable_charge`], and at the gas used within the
+    /// current gas limit, so the gas used never drops below zero. Refunds are traced as
+    /// [`ExecutionEvent::GasRefund`], separately from charges.
+    pub fn refund_gas(&self, name: &str, amount: Gas) -> Gas {
+        self.reconcile();
+        let refunded = match self.refundable.borrow_mut().get_mut(name) {
+            Some(available) => {
+                let refunded = amount.min(*available).min(self.gas_used.get());
+                *available -= refunded;
+                refunded
+            }
+            None => Gas::zero(),
+        };
+        log::trace!("refunding gas: {} {}", name, refunded);
+        self.gas_used.set(self.gas_used.get() - refunded);
+        if let Some(trace) = &self.trace {
+            let refund = GasCharge::new(name.to_owned(), Gas::zero(), refunded);
+            trace.borrow_mut().push(ExecutionEvent::GasRefund(refund));
+        }
+        refunded
+    }
+
+    /// Creates a sub-meter with the given budget. Charges to the sub-meter (see
+    /// [`GasTracker::charge_to`]) also draw from the global gas budget.
+    pub fn create_submeter(&self, budget: Gas) -> MeterId {
+        let mut submeters = self.submeters.borrow_mut();
+        submeters.push(budget);
+        MeterId((submeters.len() - 1) as u32)
+    }
+
+    /// Charges gas to a sub-meter, and to the global gas budget. Unlike running out of global
+    /// gas, exhausting a sub-meter is a recoverable `LimitExceeded` syscall error, and nothing is
+    /// charged.
+    pub fn charge_to(&self, meter: MeterId, to_use: Gas) -> Result<GasTimer> {
+        let remaining = self.submeter_remaining(meter)?;
+        if to_use > remaining {
+            return Err(syscall_error!(LimitExceeded;
+                "sub-meter {} exhausted: {to_use} > {remaining}", meter.0)
+            .into());
+        }
+        let timer = self.charge_gas("OnSubmeterCharge", to_use)?;
+        self.submeters.borrow_mut()[meter.0 as usize] = remaining - to_use;
+        Ok(timer)
+    }
+
+    /// Returns the gas remaining in a sub-meter.
+    pub fn submeter_remaining(&self, meter: MeterId) -> Result<Gas> {
+        self.submeters
+            .borrow()
+            .get(meter.0 as usize)
+            .copied()
+            .ok_or_else(|| syscall_error!(NotFound; "no such sub-meter {}", meter.0).into())
+    }
+
     /// Push a new gas limit.
     pub fn push_limit(&mut self, new_limit: Gas) {
+        self.reconcile();
         self.gas_snapshots.push(GasSnapshot {
             limit: self.gas_limit,
             used: self.gas_used.get(),
@@ -244,6 +436,7 @@ impl GasTracker {
     /// Pop a gas limit, restoring the previous one, and adding the newly used gas to the old gas
     /// limit.
     pub fn pop_limit(&mut self) -> Result<()> {
+        self.reconcile();
         let snap = self
             .gas_snapshots
             .pop()
@@ -261,15 +454,63 @@ impl GasTracker {
 
     /// Getter for gas used.
     pub fn gas_used(&self) -> Gas {
-        self.gas_used.get()
+        self.gas_used.get() + self.pending()
     }
 
     /// Getter for gas available.
     pub fn gas_available(&self) -> Gas {
-        self.gas_limit - self.gas_used.get()
+        self.gas_limit - self.gas_used()
+    }
+
+    fn record_flame_charge(&self, name: &str, to_use: Gas, res: &Result<()>) {
+        if let (Some(flame), Ok(())) = (&self.flame, res) {
+            if let Some(frame) = flame.borrow_mut().last_mut() {
+                frame.record_charge(name, to_use.as_milligas());
+            }
+        }
+    }
+
+    /// Opens a new call frame in the flamegraph profile. Subsequent charges are attributed to
+    /// this frame until the matching [`GasTracker::exit_call`]. Does nothing unless tracing.
+    pub fn enter_call(&self, name: impl FnOnce() -> String) {
+        if let Some(flame) = &self.flame {
+            flame.borrow_mut().push(FlameFrame::new(name()));
+        }
+    }
+
+    /// Closes the current call frame in the flamegraph profile, attributing its gas to the
+    /// parent frame.
+    pub fn exit_call(&self) {
+        if let Some(flame) = &self.flame {
+            let mut stack = flame.borrow_mut();
+            if stack.len() > 1 {
+                let frame = stack.pop().unwrap();
+                stack.last_mut().unwrap().push_child(frame);
+            }
+        }
     }
 
-    pub fn drain_trace(&self) -> impl Iterator<Item = GasCharge> + '_ {
+    /// Returns the gas charged so far as a tree of call frames, suitable for rendering as a
+    /// flamegraph. Calls that haven't returned yet are included as if they had. Returns an empty
+    /// list unless tracing is enabled.
+    pub fn export_flamegraph_data(&self) -> Vec<FlameFrame> {
+        let Some(flame) = &self.flame else {
+            return Vec::new();
+        };
+        let mut stack = flame.borrow().clone();
+        while stack.len() > 1 {
+            let frame = stack.pop().unwrap();
+            stack.last_mut().unwrap().push_child(frame);
+        }
+        stack.pop().map(|root| root.children).unwrap_or_default()
+    }
+
+    /// Returns true if gas charges are being traced.
+    pub fn tracing_enabled(&self) -> bool {
+        self.trace.is_some()
+    }
+
+    pub fn drain_trace(&self) -> impl Iterator<Item = ExecutionEvent> + '_ {
         self.trace
             .as_ref()
             .map(|v| v.take().into_iter())
@@ -308,6 +549,317 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn storage_duration_price() {
+        let prices = price_list_by_network_version(fvm_shared::version::NetworkVersion::V21);
+        assert_eq!(
+            prices.on_storage_duration(10),
+            prices.on_storage_duration(1) * 10u64
+        );
+        assert_eq!(prices.on_storage_duration(-10), Gas::zero());
+    }
+
+    #[test]
+    fn batched_charges_match_unbatched() -> Result<()> {
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        let limit = Gas::new(1_000_000_000);
+        let mut batched =
+            GasTracker::new(limit, Gas::zero(), false).with_refundable_charges(&["OnBlockCreate"]);
+        let mut unbatched = GasTracker::new(limit, Gas::zero(), false)
+            .with_refundable_charges(&["OnBlockCreate"])
+            .without_batching();
+        let meter = batched.create_submeter(Gas::new(50_000_000));
+        assert_eq!(meter, unbatched.create_submeter(Gas::new(50_000_000)));
+
+        for _ in 0..100_000 {
+            // Mostly small charges, with the occasional large one.
+            let to_use = if rng.gen_bool(0.9) {
+                Gas::from_milligas(rng.gen_range(0..2_000_000))
+            } else {
+                Gas::new(rng.gen_range(0..20_000))
+            };
+            let (a, b) = match rng.gen_range(0..100) {
+                0 => {
+                    let new_limit = Gas::new(rng.gen_range(0..100_000));
+                    batched.push_limit(new_limit);
+                    unbatched.push_limit(new_limit);
+                    (Ok(()), Ok(()))
+                }
+                1 if !batched.gas_snapshots.is_empty() => {
+                    (batched.pop_limit(), unbatched.pop_limit())
+                }
+                2..=9 => (
+                    batched.charge_to(meter, to_use).map(GasTimer::stop),
+                    unbatched.charge_to(meter, to_use).map(GasTimer::stop),
+                ),
+                10..=19 => {
+                    let charge = GasCharge::new("OnBlockCreate", to_use, Gas::zero());
+                    (
+                        batched
+                            .apply_refundable_charge(charge.clone())
+                            .map(GasTimer::stop),
+                        unbatched
+                            .apply_refundable_charge(charge)
+                            .map(GasTimer::stop),
+                    )
+                }
+                20..=24 => {
+                    assert_eq!(
+                        batched.refund_gas("OnBlockCreate", to_use),
+                        unbatched.refund_gas("OnBlockCreate", to_use)
+                    );
+                    (Ok(()), Ok(()))
+                }
+                25..=49 => {
+                    let charge = GasCharge::new("OnBlockRead", to_use, Gas::zero());
+                    (
+                        batched.apply_charge(charge.clone()).map(GasTimer::stop),
+                        unbatched.apply_charge(charge).map(GasTimer::stop),
+                    )
+                }
+                _ => (
+                    batched
+                        .charge_gas("OnBlockRead", to_use)
+                        .map(GasTimer::stop),
+                    unbatched
+                        .charge_gas("OnBlockRead", to_use)
+                        .map(GasTimer::stop),
+                ),
+            };
+            // Same outcome, failing on exactly the same charge, and identical totals at every
+            // step. Reading the totals doesn't reconcile the batch.
+            assert_eq!(a.is_ok(), b.is_ok());
+            assert_eq!(
+                matches!(a, Err(ExecutionError::OutOfGas)),
+                matches!(b, Err(ExecutionError::OutOfGas))
+            );
+            assert_eq!(batched.gas_used(), unbatched.gas_used());
+            assert_eq!(batched.gas_available(), unbatched.gas_available());
+            assert_eq!(
+                batched.submeter_remaining(meter)?,
+                unbatched.submeter_remaining(meter)?
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn flamegraph_data() -> Result<()> {
+        let t = GasTracker::new(Gas::new(1000), Gas::zero(), true);
+        t.charge_gas("OnChainMessage", Gas::new(10))?;
+        t.enter_call(|| "f0100#2".into());
+        t.charge_gas("OnBlockRead", Gas::new(3))?;
+        t.charge_gas("OnBlockRead", Gas::new(4))?;
+        t.enter_call(|| "f0101#0".into());
+        t.charge_gas("OnValueTransfer", Gas::new(5))?;
+        t.exit_call();
+        t.exit_call();
+        // Failed charges aren't recorded.
+        assert!(t.charge_gas("OnBlockCreate", Gas::new(10_000)).is_err());
+
+        let frames = t.export_flamegraph_data();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].name, "OnChainMessage");
+        assert_eq!(frames[1].name, "f0100#2");
+        assert_eq!(frames[1].gas, Gas::new(12).as_milligas());
+        assert_eq!(frames[1].children.len(), 2);
+        assert_eq!(frames[1].children[0].gas, Gas::new(7).as_milligas());
+
+        assert_eq!(
+            frames[1].to_collapsed_stacks(),
+            "f0100#2;OnBlockRead 7000\nf0100#2;f0101#0;OnValueTransfer 5000\n"
+        );
+
+        // Disabled without tracing.
+        let t = GasTracker::new(Gas::new(1000), Gas::zero(), false);
+        t.enter_call(|| unreachable!());
+        t.charge_gas("OnChainMessage", Gas::new(10))?;
+        assert!(t.export_flamegraph_data().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn submeters() -> Result<()> {
+        let t = GasTracker::new(Gas::new(100), Gas::zero(), false);
+        let meter = t.create_submeter(Gas::new(10));
+        let other = t.create_submeter(Gas::new(50));
+
+        t.charge_to(meter, Gas::new(6))?;
+        assert_eq!(t.submeter_remaining(meter)?, Gas::new(4));
+        assert_eq!(t.gas_used(), Gas::new(6));
+
+        // Exhausting the sub-meter is recoverable and charges nothing.
+        assert!(matches!(
+            t.charge_to(meter, Gas::new(5)),
+            Err(ExecutionError::Syscall(_))
+        ));
+        assert_eq!(t.submeter_remaining(meter)?, Gas::new(4));
+        assert_eq!(t.gas_used(), Gas::new(6));
+
+        // Other meters and the global budget are unaffected.
+        t.charge_to(other, Gas::new(50))?;
+        assert_eq!(t.submeter_remaining(other)?, Gas::zero());
+        assert_eq!(t.gas_available(), Gas::new(44));
+
+        assert!(t.submeter_remaining(MeterId(2)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn refunds() -> Result<()> {
+        let t = GasTracker::new(Gas::new(100), Gas::zero(), true)
+            .with_refundable_charges(&["OnBlockCreate"]);
+        t.charge_gas("OnBlockCreate", Gas::new(10))?;
+        t.apply_refundable_charge(GasCharge::new("OnBlockCreate", Gas::new(5), Gas::new(15)))?;
+        // Not a declared refund row, so never refundable.
+        t.apply_refundable_charge(GasCharge::new("OnBlockRead", Gas::new(5), Gas::zero()))?;
+
+        // Only gas charged as refundable under a declared row can be refunded, and only once.
+        assert_eq!(t.refund_gas("OnBlockCreate", Gas::new(15)), Gas::new(15));
+        assert_eq!(
+            t.apply_refund(GasCharge::new("OnBlockCreate", Gas::zero(), Gas::new(15))),
+            Gas::new(5)
+        );
+        assert_eq!(t.refund_gas("OnBlockCreate", Gas::new(1)), Gas::zero());
+        assert_eq!(t.refund_gas("OnBlockRead", Gas::new(1)), Gas::zero());
+        assert_eq!(t.gas_used(), Gas::new(15));
+
+        // Refunds are traced separately from charges.
+        let refunds: Vec<_> = t
+            .drain_trace()
+            .filter_map(|e| match e {
+                ExecutionEvent::GasRefund(c) => Some(c.total()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            refunds,
+            [Gas::new(15), Gas::new(5), Gas::zero(), Gas::zero()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn price_list_refund_rows() -> Result<()> {
+        let mut prices =
+            price_list_by_network_version(fvm_shared::version::NetworkVersion::V21).clone();
+        assert!(prices.refundable_charges().is_empty());
+
+        prices.refundable_charges = &["OnBlockCreate"];
+        let t = GasTracker::new(Gas::new(1_000_000), Gas::zero(), false)
+            .with_refundable_charges(prices.refundable_charges());
+        let charge = prices.on_block_create(100, 0);
+        let charged = charge.total();
+        t.apply_refundable_charge(charge)?;
+        t.apply_refundable_charge(prices.on_block_read(100))?;
+
+        // Refunds are capped at the declared row's charges.
+        let refund = prices.on_refund("OnBlockCreate", charged + Gas::new(1));
+        assert_eq!(t.apply_refund(refund), charged);
+        assert_eq!(
+            t.apply_refund(prices.on_refund("OnBlockRead", Gas::new(1))),
+            Gas::zero()
+        );
+        assert_eq!(t.gas_used(), prices.on_block_read(100).total());
+        Ok(())
+    }
+
+    #[test]
+    fn random_refunds() -> Result<()> {
+        use rand::{Rng, SeedableRng};
+
+        const NAMES: [&str; 3] = ["A", "B", "C"];
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let limit = Gas::new(10_000_000);
+        let mut t = GasTracker::new(limit, Gas::zero(), false).with_refundable_charges(&["A", "B"]);
+
+        // Gas charged (less refunds) under each name.
+        let mut charged = [Gas::zero(); 3];
+        let mut depth = 0;
+        for _ in 0..100_000 {
+            let name = rng.gen_range(0..NAMES.len());
+            match rng.gen_range(0..100) {
+                0 => {
+                    t.push_limit(Gas::new(rng.gen_range(0..100_000)));
+                    depth += 1;
+                }
+                1 if depth > 0 => {
+                    t.pop_limit()?;
+                    depth -= 1;
+                }
+                2..=49 => {
+                    // Only "A" and "B" are declared refund rows, so charges under "C" are never
+                    // refundable, even when charged as such.
+                    let to_use = Gas::from_milligas(rng.gen_range(0..2_000_000));
+                    let charge = GasCharge::new(NAMES[name], to_use, Gas::zero());
+                    if t.apply_refundable_charge(charge).is_ok() && NAMES[name] != "C" {
+                        charged[name] += to_use;
+                    }
+                }
+                _ => {
+                    let used = t.gas_used();
+                    let amount = Gas::from_milligas(rng.gen_range(0..4_000_000));
+                    let refunded = t.refund_gas(NAMES[name], amount);
+                    assert!(refunded <= amount);
+                    assert!(refunded <= charged[name]);
+                    assert_eq!(t.gas_used(), used - refunded);
+                    if NAMES[name] == "C" {
+                        assert!(refunded.is_zero());
+                    }
+                    charged[name] -= refunded;
+                }
+            }
+            assert!(t.gas_used() <= t.gas_limit());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn arithmetic_near_max() {
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        for _ in 0..10_000 {
+            let a = Gas::from_milligas(u64::MAX - rng.gen_range(0..1_000_000));
+            let b = Gas::from_milligas(rng.gen_range(0..2_000_000));
+
+            // Operators saturate instead of panicking or wrapping.
+            assert_eq!(
+                (a + b).as_milligas(),
+                a.as_milligas().saturating_add(b.as_milligas())
+            );
+            assert_eq!(b - a, Gas::zero());
+            assert_eq!((a - b).as_milligas(), a.as_milligas() - b.as_milligas());
+            let mut c = a;
+            c += b;
+            assert_eq!(c, a + b);
+            c -= a;
+            c -= a;
+            assert_eq!(c, Gas::zero());
+            assert_eq!(
+                b * u64::MAX,
+                if b.is_zero() {
+                    b
+                } else {
+                    Gas::from_milligas(u64::MAX)
+                }
+            );
+            assert_eq!(Gas::new(a.as_milligas()), Gas::from_milligas(u64::MAX));
+        }
+
+        // The tracker caps its limit, so charges near the maximum run out of gas instead.
+        let t = GasTracker::new(Gas::from_milligas(u64::MAX), Gas::zero(), false);
+        assert_eq!(t.gas_limit(), Gas::from_milligas(i64::MAX as u64));
+        t.charge_gas("", Gas::from_milligas(i64::MAX as u64))
+            .unwrap();
+        assert!(t.charge_gas("", Gas::from_milligas(u64::MAX)).is_err());
+        assert_eq!(t.gas_used(), t.gas_limit());
+        assert_eq!(t.refund_gas("", Gas::from_milligas(u64::MAX)), Gas::zero());
+    }
+
     #[test]
     fn milligas_to_gas_round() {
         assert_eq!(milligas_to_gas(100, false), 0);
@@ -317,4 +869,32 @@ mod tests {
         assert_eq!(milligas_to_gas(MILLIGAS_PRECISION, true), 1);
         assert_eq!(milligas_to_gas(MILLIGAS_PRECISION, false), 1);
     }
+
+    #[test]
+    fn logical_clock_is_deterministic() -> Result<()> {
+        fn run() -> Result<Vec<Option<std::time::Duration>>> {
+            let t =
+                GasTracker::new(Gas::new(1000), Gas::zero(), true).with_clock(GasClock::logical());
+            let outer = t.charge_gas("OnSend", Gas::new(1))?;
+            t.charge_gas("OnBlockRead", Gas::new(2))?.stop();
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            t.charge_gas("OnBlockCreate", Gas::new(3))?
+                .stop_with(GasTimer::start());
+            outer.stop();
+            Ok(t.drain_trace()
+                .filter_map(|e| match e {
+                    ExecutionEvent::GasCharge(c) => Some(c.elapsed.get().copied()),
+                    _ => None,
+                })
+                .collect())
+        }
+
+        let trace = run()?;
+        assert_eq!(trace, run()?);
+        assert_eq!(
+            trace,
+            [5, 1, 1].map(|n| Some(std::time::Duration::from_nanos(n)))
+        );
+        Ok(())
+    }
 }
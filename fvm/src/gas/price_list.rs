@@ -8,7 +8,9 @@ use std::ops::Mul;
 use anyhow::Context;
 use fvm_shared::clock::ChainEpoch;
 use fvm_shared::crypto::signature::SignatureType;
+use fvm_shared::econ::TokenAmount;
 use fvm_shared::piece::PieceInfo;
+use fvm_shared::randomness::RANDOMNESS_LENGTH;
 use fvm_shared::sector::{
     AggregateSealVerifyProofAndInfos, RegisteredPoStProof, RegisteredSealProof, ReplicaUpdateInfo,
     SealVerifyInfo, WindowPoStVerifyInfo,
@@ -20,7 +22,7 @@ use lazy_static::lazy_static;
 use num_traits::Zero;
 
 use super::GasCharge;
-use crate::gas::Gas;
+use crate::gas::{Gas, GasOutputs};
 use crate::kernel::SupportedHashes;
 
 // Each element reserves a `usize` in the table, so we charge 8 bytes per pointer.
@@ -99,6 +101,8 @@ lazy_static! {
         actor_update: Gas::new(475_000),
         actor_create_storage: Gas::new(650_000),
 
+        storage_duration_per_epoch: Gas::new(1000),
+
         address_lookup: Gas::new(1_050_000),
         address_assignment: Gas::new(1_000_000),
 
@@ -191,6 +195,20 @@ lazy_static! {
         verify_consensus_fault: Gas::new(516422),
 
         verify_replica_update: Gas::new(36316136),
+        // PROVISIONAL: not benchmarked. Groth16 verification over BLS12-381 is dominated by the
+        // pairing check, so the flat cost borrows the PoSt verification cost. The scaling cost
+        // (per public input) also borrows PoSt's per-sector cost, and must be replaced with a
+        // benchmarked per-input cost before this is used on a live network.
+        groth16_verify: ScalingCost {
+            flat: Gas::new(117680921),
+            scale: Gas::new(43780),
+        },
+        // PROVISIONAL: not benchmarked. Parsing the verifying key checks every (uncompressed, 96
+        // byte) G1 point it declares, each of which takes ~50us to validate.
+        groth16_verifying_key: ScalingCost {
+            flat: Zero::zero(),
+            scale: Gas::new(5200),
+        },
         verify_post_lookup: [
             (RegisteredPoStProof::StackedDRGWindow512MiBV1P1,
                 ScalingCost {
@@ -225,6 +243,10 @@ lazy_static! {
             scale: Gas::new(75),
         },
 
+        // Covers the rejection sampling loop. A second hash is only needed with a probability of
+        // less than 1/16, so we don't charge for it separately.
+        random_sampling: Gas::new(1000),
+
         block_allocate: ScalingCost {
             flat: Gas::zero(),
             scale: Gas::new(2),
@@ -235,6 +257,21 @@ lazy_static! {
             scale: Gas::from_milligas(400),
         },
 
+        inflate: ScalingCost {
+            flat: Gas::new(1000),
+            scale: Gas::new(10),
+        },
+
+        inflate_output: ScalingCost {
+            flat: Gas::zero(),
+            scale: Gas::new(2),
+        },
+
+        block_handle_expansion: ScalingCost {
+            flat: Gas::zero(),
+            scale: Gas::new(100),
+        },
+
         block_memory_retention_minimum: ScalingCost {
             flat: Gas::zero(),
             scale: Gas::new(10),
@@ -268,6 +305,9 @@ lazy_static! {
         // TODO(#1347)
         message_context: Zero::zero(),
 
+        // A handful of big-integer multiplications, on top of reading the base fee.
+        fee_estimation: Gas::new(1000),
+
         install_wasm_per_byte_cost: Zero::zero(),
 
         wasm_rules: WasmGasPrices{
@@ -301,6 +341,9 @@ lazy_static! {
         // Preloaded actor IDs per FIP-0055.
         preloaded_actors: vec![0, 1, 2, 3, 4, 5, 6, 7, 10, 99],
 
+        // Nothing is refundable (yet).
+        refundable_charges: &[],
+
         ipld_cbor_scan_per_cid: Gas::new(400),
         ipld_cbor_scan_per_field: Gas::new(35),
         ipld_link_tracked: Gas::new(300),
@@ -384,6 +427,10 @@ pub struct PriceList {
     /// Gas cost charged for invoking an actor (compute only).
     pub(crate) send_invoke_method: Gas,
 
+    /// Gas cost per epoch for operations whose cost is proportional to how long something is
+    /// stored or locked. Not charged by the FVM itself, see [`PriceList::on_storage_duration`].
+    pub(crate) storage_duration_per_epoch: Gas,
+
     /// Gas cost to lookup an actor by address in the init actor's address table.
     pub(crate) address_lookup: Gas,
     /// Gas cost to assign an address to an actor in the init actor's address table.
@@ -410,6 +457,9 @@ pub struct PriceList {
     /// Applied to operations like getting randomness, tipset CIDs, etc.
     pub(crate) lookback_cost: ScalingCost,
 
+    /// Gas cost for mapping randomness to a uniform integer in a range (in addition to hashing).
+    pub(crate) random_sampling: Gas,
+
     pub(crate) compute_unsealed_sector_cid_base: Gas,
     pub(crate) verify_seal_base: Gas,
     pub(crate) verify_aggregate_seal_per: HashMap<RegisteredSealProof, Gas>,
@@ -418,6 +468,9 @@ pub struct PriceList {
     pub(crate) verify_post_lookup: HashMap<RegisteredPoStProof, ScalingCost>,
     pub(crate) verify_consensus_fault: Gas,
     pub(crate) verify_replica_update: Gas,
+    pub(crate) groth16_verify: ScalingCost,
+    /// Gas cost per byte of a Groth16 verifying key, covering parsing and validating its points.
+    pub(crate) groth16_verifying_key: ScalingCost,
 
     /// Gas cost per byte copied.
     pub(crate) block_memcpy: ScalingCost,
@@ -425,6 +478,15 @@ pub struct PriceList {
     /// Gas cost per byte allocated (computation cost).
     pub(crate) block_allocate: ScalingCost,
 
+    /// Gas cost per compressed byte inflated.
+    pub(crate) inflate: ScalingCost,
+
+    /// Gas cost per byte produced when inflating (in addition to allocation and copying).
+    pub(crate) inflate_output: ScalingCost,
+
+    /// Gas cost per block handle when temporarily raising the block registry's limit.
+    pub(crate) block_handle_expansion: ScalingCost,
+
     /// Minimum gas cost for every block retained in memory (read and/or written) to ensure we can't
     /// retain more than 1GiB of memory while executing a block.
     ///
@@ -461,6 +523,8 @@ pub struct PriceList {
     pub(crate) network_context: Gas,
     /// Gas cost of accessing the message context.
     pub(crate) message_context: Gas,
+    /// Gas cost of estimating a message's fee.
+    pub(crate) fee_estimation: Gas,
 
     /// Gas cost of compiling a Wasm module during install.
     pub(crate) install_wasm_per_byte_cost: Gas,
@@ -468,6 +532,10 @@ pub struct PriceList {
     /// Actor IDs that can be updated for free.
     pub(crate) preloaded_actors: Vec<ActorID>,
 
+    /// Names of the charges that may be refunded (up to the amount charged) with
+    /// [`PriceList::on_refund`].
+    pub(crate) refundable_charges: &'static [&'static str],
+
     /// Gas cost per field encountered when parsing CBOR.
     pub(crate) ipld_cbor_scan_per_field: Gas,
 
@@ -507,6 +575,20 @@ pub struct WasmGasPrices {
 }
 
 impl PriceList {
+    /// Returns the names of the charges that may be refunded.
+    pub fn refundable_charges(&self) -> &'static [&'static str] {
+        self.refundable_charges
+    }
+
+    /// Returns a refund of up to `gas` charged under the given refundable charge name, to be
+    /// applied with [`GasTracker::apply_refund`](super::GasTracker::apply_refund). When applied,
+    /// the refund is capped at the gas charged (less prior refunds) under that name, and refunds
+    /// of charges that aren't refundable are ignored.
+    #[inline]
+    pub fn on_refund(&self, name: &'static str, gas: Gas) -> GasCharge {
+        GasCharge::new(name, Zero::zero(), gas)
+    }
+
     /// Returns the gas required for storing a message of a given size in the chain, plus the cost
     /// of updating the sending actor's nonce and balance in the state-tree.
     #[inline]
@@ -518,12 +600,40 @@ impl PriceList {
         )
     }
 
+    /// Returns the gas an actor should charge (with
+    /// [`GasOps::charge_gas_per_epoch`](crate::kernel::GasOps::charge_gas_per_epoch)) for an
+    /// operation spanning the given number of epochs. Negative durations cost nothing.
+    #[inline]
+    pub fn on_storage_duration(&self, epochs: i64) -> Gas {
+        self.storage_duration_per_epoch * u64::try_from(epochs).unwrap_or(0)
+    }
+
     /// Returns the gas required when invoking a method.
     #[inline]
     pub fn on_value_transfer(&self) -> GasCharge {
         GasCharge::new("OnValueTransfer", self.send_transfer_funds, Zero::zero())
     }
 
+    /// Returns the minimum explicit gas limit a send may specify (enforced from nv22). This is the
+    /// base cost of invoking the recipient: sends with lower limits can't even pay for that, so
+    /// they fail before the recipient is instantiated.
+    #[inline]
+    pub fn min_send_gas_limit(&self) -> Gas {
+        self.send_invoke_method
+    }
+
+    /// Returns the gas charged for a send rejected because its explicit gas limit is below
+    /// [`min_send_gas_limit`](Self::min_send_gas_limit): just the send's base cost, as the
+    /// recipient is never instantiated.
+    #[inline]
+    pub fn on_send_below_min_gas_limit(&self) -> GasCharge {
+        GasCharge::new(
+            "OnSendBelowMinGasLimit",
+            self.send_invoke_method,
+            Zero::zero(),
+        )
+    }
+
     /// Returns the gas required when invoking a method.
     #[inline]
     pub fn on_method_invocation(&self, _param_size: u32, param_links: usize) -> GasCharge {
@@ -696,6 +806,17 @@ impl PriceList {
         GasCharge::new("OnVerifyPost", gas_used, Zero::zero())
     }
 
+    /// Returns gas required for Groth16 proof verification, given the number of public inputs and
+    /// the size of the (untrusted) verifying key.
+    #[inline]
+    pub fn on_groth16_verify(&self, num_public_inputs: usize, vk_len: usize) -> GasCharge {
+        GasCharge::new(
+            "OnGroth16Verify",
+            self.groth16_verify.apply(num_public_inputs) + self.groth16_verifying_key.apply(vk_len),
+            Zero::zero(),
+        )
+    }
+
     /// Returns gas required for verifying consensus fault.
     #[inline]
     pub fn on_verify_consensus_fault(
@@ -721,6 +842,20 @@ impl PriceList {
         )
     }
 
+    /// Returns the gas required for mapping randomness to a uniform integer in a range, after the
+    /// randomness itself has been drawn. This covers hashing the randomness, epoch, entropy, and a
+    /// counter, plus the sampling itself.
+    #[inline]
+    pub fn on_random_in_range(&self, entropy_len: usize) -> GasCharge {
+        let hashed_len = RANDOMNESS_LENGTH + 2 * std::mem::size_of::<u64>() + entropy_len;
+        GasCharge::new(
+            "OnRandomInRange",
+            self.hashing_cost[&SupportedHashes::Blake2b256].apply(hashed_len)
+                + self.random_sampling,
+            Zero::zero(),
+        )
+    }
+
     /// Returns the base gas required for loading an object, independent of the object's size.
     #[inline]
     pub fn on_block_open_base(&self) -> GasCharge {
@@ -761,6 +896,17 @@ impl PriceList {
         )
     }
 
+    /// Returns the gas required for reading a loaded object as a single CBOR integer: a read plus
+    /// decoding a single CBOR field.
+    #[inline]
+    pub fn on_block_read_u64(&self, data_size: usize) -> GasCharge {
+        GasCharge::new(
+            "OnBlockReadU64",
+            self.block_memcpy.apply(data_size) + self.ipld_cbor_scan_per_field,
+            Zero::zero(),
+        )
+    }
+
     /// Returns the gas required for adding an object to the FVM cache.
     #[inline]
     pub fn on_block_create(&self, data_size: usize, links: usize) -> GasCharge {
@@ -804,6 +950,94 @@ impl PriceList {
         GasCharge::new("OnBlockStat", Zero::zero(), Zero::zero())
     }
 
+    /// Returns the gas required for comparing two blocks, given the size of the smaller block.
+    /// Comparing is priced like copying.
+    #[inline]
+    pub fn on_block_eq(&self, data_size: usize) -> GasCharge {
+        GasCharge::new(
+            "OnBlockEq",
+            self.block_memcpy.apply(data_size),
+            Zero::zero(),
+        )
+    }
+
+    /// Returns the gas required for loading and reading a block while searching for a cycle, on
+    /// top of [`PriceList::on_block_open_base`] and scanning the block for links. The block is
+    /// only read, not retained, so there's no allocation or retention cost.
+    #[inline]
+    pub fn on_cycle_check_visit(&self, data_size: usize) -> GasCharge {
+        GasCharge::new(
+            "OnCycleCheckVisit",
+            self.block_memcpy.apply(data_size),
+            self.block_open.scale * data_size,
+        )
+    }
+
+    /// Returns the gas required for checking whether a block may be returned. This only inspects
+    /// the block's stat, so it costs the same as [`PriceList::on_block_stat`].
+    #[inline]
+    pub fn on_would_fit_as_return(&self) -> GasCharge {
+        GasCharge::new("OnWouldFitAsReturn", Zero::zero(), Zero::zero())
+    }
+
+    /// Returns the gas required for marking a block as retained. The block's memory has already
+    /// been paid for when it was created.
+    #[inline]
+    pub fn on_block_retain(&self) -> GasCharge {
+        GasCharge::new("OnBlockRetain", Zero::zero(), Zero::zero())
+    }
+
+    /// Returns the gas required for closing a block. Closing doesn't refund the block's memory.
+    #[inline]
+    pub fn on_block_close(&self) -> GasCharge {
+        GasCharge::new("OnBlockClose", Zero::zero(), Zero::zero())
+    }
+
+    /// Returns the gas required for temporarily raising the block registry's limit by the given
+    /// number of blocks.
+    #[inline]
+    pub fn on_expand_block_handles(&self, extra: u32) -> GasCharge {
+        GasCharge::new(
+            "OnExpandBlockHandles",
+            self.block_handle_expansion.apply(extra),
+            Zero::zero(),
+        )
+    }
+
+    /// Returns the gas required for constructing a delegated address. This is a pure (and cheap)
+    /// computation.
+    #[inline]
+    pub fn on_make_delegated_address(&self) -> GasCharge {
+        GasCharge::new("OnMakeDelegatedAddress", Zero::zero(), Zero::zero())
+    }
+
+    /// Returns the gas required for classifying a codec. This is a pure lookup, so the syscall
+    /// cost covers it.
+    #[inline]
+    pub fn on_is_dag_codec(&self) -> GasCharge {
+        GasCharge::new("OnIsDagCodec", Zero::zero(), Zero::zero())
+    }
+
+    /// Returns the gas required for decompressing the given number of compressed bytes.
+    #[inline]
+    pub fn on_inflate(&self, compressed_size: usize) -> GasCharge {
+        GasCharge::new(
+            "OnInflate",
+            self.inflate.apply(compressed_size),
+            Zero::zero(),
+        )
+    }
+
+    /// Returns the gas required for producing (and returning) the given number of decompressed
+    /// bytes.
+    #[inline]
+    pub fn on_inflate_output(&self, output_size: usize) -> GasCharge {
+        let compute = self.inflate_output.apply(output_size)
+            + self.block_allocate.apply(output_size)
+            + self.block_memcpy.apply(output_size);
+        GasCharge::new("OnInflateOutput", compute, Zero::zero())
+    }
+
     /// Returns the gas required to lookup an actor in the state-tree.
     #[inline]
     pub fn on_actor_lookup(&self) -> GasCharge {
@@ -830,12 +1064,33 @@ impl PriceList {
         GasCharge::new("OnSelfBalance", Zero::zero(), Zero::zero())
     }
 
+    /// Returns the gas required for looking up the current actor's delegated address. The actor has
+    /// already been loaded in order to execute it, so this is free.
+    #[inline]
+    pub fn on_self_delegated_address(&self) -> GasCharge {
+        GasCharge::new("OnSelfDelegatedAddress", Zero::zero(), Zero::zero())
+    }
+
+    /// Returns the gas required for scanning every actor in the state-tree, given the number of
+    /// actors scanned.
+    #[inline]
+    pub fn on_scan_actors(&self, actors: usize) -> GasCharge {
+        GasCharge::new("OnScanActors", Zero::zero(), self.actor_lookup * actors)
+    }
+
     /// Returns the gas required for accessing the balance of an actor.
     #[inline]
     pub fn on_balance_of(&self) -> GasCharge {
         GasCharge::new("OnBalanceOf", Zero::zero(), Zero::zero())
     }
 
+    /// Returns the gas required for checking whether an actor ID is in use. The actor lookup
+    /// itself is charged by the call manager, as with any other actor lookup.
+    #[inline]
+    pub fn on_actor_id_available(&self) -> GasCharge {
+        GasCharge::new("OnActorIdAvailable", Zero::zero(), Zero::zero())
+    }
+
     /// Returns the gas required for resolving an actor address.
     ///
     /// Might require lookup in the state tree as well as loading the state of the init actor.
@@ -894,12 +1149,26 @@ impl PriceList {
         GasCharge::new("OnNetworkContext", self.network_context, Zero::zero())
     }
 
+    /// Returns the gas required for checking whether an epoch has elapsed. This is a single
+    /// comparison against the current epoch, so the syscall cost covers it.
+    #[inline]
+    pub fn on_epoch_elapsed(&self) -> GasCharge {
+        GasCharge::new("OnEpochElapsed", Zero::zero(), Zero::zero())
+    }
+
     /// Returns the gas required for accessing the message context.
     #[inline]
     pub fn on_message_context(&self) -> GasCharge {
         GasCharge::new("OnMessageContext", self.message_context, Zero::zero())
     }
 
+    /// Returns the gas required for querying the memory usage and limit. This only reads a couple
+    /// of counters, so it's covered by the syscall gas.
+    #[inline]
+    pub fn on_memory_usage(&self) -> GasCharge {
+        GasCharge::new("OnMemoryUsage", Zero::zero(), Zero::zero())
+    }
+
     /// Returns the gas required for installing an actor.
     #[cfg(feature = "m2-native")]
     pub fn on_install_actor(&self, wasm_size: usize) -> GasCharge {
@@ -928,6 +1197,17 @@ impl PriceList {
             + self.wasm_rules.memory_fill_per_byte_cost * min_table_elements * TABLE_ELEMENT_SIZE
     }
 
+    /// Returns the gas required for comparing an event of the given size against the previously
+    /// emitted one, when deduplicating.
+    #[inline]
+    pub fn on_event_compare(&self, size: usize) -> GasCharge {
+        GasCharge::new(
+            "OnEventCompare",
+            self.block_memcpy.apply(size),
+            Zero::zero(),
+        )
+    }
+
     #[inline]
     pub fn on_actor_event(&self, entries: usize, keysize: usize, valuesize: usize) -> GasCharge {
         // Here we estimate per-event overhead given the constraints on event values.
@@ -959,6 +1239,68 @@ impl PriceList {
         )
     }
 
+    /// Returns the gas required for estimating the cost of emitting an event (see
+    /// [`PriceList::on_actor_event`]).
+    #[inline]
+    pub fn on_event_gas_cost(&self) -> GasCharge {
+        GasCharge::new("OnEventGasCost", self.event_per_entry.flat, Zero::zero())
+    }
+
+    /// Returns the gas required for querying how many more events may be emitted. This only reads
+    /// a counter, so it's covered by the syscall gas.
+    #[inline]
+    pub fn on_events_remaining(&self) -> GasCharge {
+        GasCharge::new("OnEventsRemaining", Zero::zero(), Zero::zero())
+    }
+
+    /// Returns the gas required for computing the events AMT root over `events` events with
+    /// `entries` entries and `data_len` bytes of keys and values in total. Each event is
+    /// serialized and hashed into the (in-memory) AMT.
+    #[inline]
+    pub fn on_events_root(&self, events: usize, entries: usize, data_len: usize) -> GasCharge {
+        let estimated_size = EVENT_OVERHEAD
+            .saturating_mul(events as u64)
+            .saturating_add(EVENT_ENTRY_OVERHEAD.saturating_mul(entries as u64))
+            .saturating_add(data_len as u64);
+
+        let hashing = &self.hashing_cost[&SupportedHashes::Blake2b256];
+        let hash = hashing.flat * events + hashing.scale * estimated_size;
+        let mem = self.block_memcpy.apply(estimated_size);
+
+        GasCharge::new("OnEventsRoot", hash + mem, Zero::zero())
+    }
+
+    /// Returns the gas required for estimating a message's fee (see
+    /// [`PriceList::estimate_message_cost`]).
+    #[inline]
+    pub fn on_estimate_fee(&self) -> GasCharge {
+        GasCharge::new("OnEstimateFee", self.fee_estimation, Zero::zero())
+    }
+
+    /// Estimates the total cost of a message that uses all of its gas limit: the base fee burned
+    /// plus the premium paid to the miner. This is the fee computed when applying the message,
+    /// assuming its fee cap covers both.
+    pub fn estimate_message_cost(
+        &self,
+        gas_limit: Gas,
+        gas_premium: &TokenAmount,
+        base_fee: &TokenAmount,
+    ) -> TokenAmount {
+        let gas_limit = gas_limit.round_up();
+        let GasOutputs {
+            base_fee_burn,
+            miner_tip,
+            ..
+        } = GasOutputs::compute(
+            gas_limit,
+            gas_limit,
+            base_fee,
+            &(base_fee + gas_premium),
+            gas_premium,
+        );
+        base_fee_burn + miner_tip
+    }
+
     #[inline]
     pub fn on_get_root(&self) -> GasCharge {
         GasCharge::new("OnActorGetRoot", self.ipld_link_tracked, Gas::zero())
@@ -1,6 +1,7 @@
 use std::fmt;
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -52,6 +53,58 @@ impl fmt::Debug for GasDuration {
 /// Type alias so that we can disable this with a compiler flag.
 pub type GasInstant = Instant;
 
+/// The clock [`GasTimer`]s measure elapsed time with.
+#[derive(Default, Clone, Debug)]
+pub enum GasClock {
+    /// Measure wall-clock time.
+    #[default]
+    Wall,
+    /// Never consult the wall clock. Instead, every reading advances a shared counter by one, and
+    /// timers record the number of readings between their start and stop as nanoseconds. The
+    /// recorded durations only depend on the order in which timers are started and stopped, so
+    /// they're identical between runs of the same message.
+    Logical(Arc<AtomicU64>),
+}
+
+impl GasClock {
+    /// Create a new deterministic clock, see [`GasClock::Logical`].
+    pub fn logical() -> Self {
+        GasClock::Logical(Default::default())
+    }
+
+    /// Returns true if this clock never consults the wall clock.
+    pub fn is_deterministic(&self) -> bool {
+        matches!(self, GasClock::Logical(_))
+    }
+
+    fn now(&self) -> ClockReading {
+        match self {
+            GasClock::Wall => ClockReading::Wall(GasInstant::now()),
+            GasClock::Logical(counter) => {
+                ClockReading::Logical(counter.clone(), counter.fetch_add(1, Ordering::Relaxed) + 1)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum ClockReading {
+    Wall(GasInstant),
+    Logical(Arc<AtomicU64>, u64),
+}
+
+impl ClockReading {
+    fn elapsed(&self) -> Duration {
+        match self {
+            ClockReading::Wall(start) => start.elapsed(),
+            ClockReading::Logical(counter, start) => {
+                let now = counter.fetch_add(1, Ordering::Relaxed) + 1;
+                Duration::from_nanos(now - start)
+            }
+        }
+    }
+}
+
 /// A handle returned by `charge_gas` which must be used to mark the end of
 /// the execution associated with that gas.
 #[derive(Debug)]
@@ -59,7 +112,7 @@ pub struct GasTimer(Option<GasTimerInner>);
 
 #[derive(Debug)]
 struct GasTimerInner {
-    start: GasInstant,
+    start: ClockReading,
     elapsed: DurationCell,
 }
 
@@ -83,6 +136,11 @@ impl GasTimer {
     ///
     /// When compiled in debug mode, passing a "filled" duration will panic.
     pub fn new(duration: &mut GasDuration) -> Self {
+        Self::new_with_clock(duration, &GasClock::Wall)
+    }
+
+    /// Like [`GasTimer::new`], but measures time with the given clock.
+    pub fn new_with_clock(duration: &mut GasDuration, clock: &GasClock) -> Self {
         debug_assert!(duration.get().is_none(), "GasCharge::elapsed already set!");
         let cell = match &duration.0 {
             GasDurationInner::None => {
@@ -96,7 +154,7 @@ impl GasTimer {
         };
 
         Self(Some(GasTimerInner {
-            start: Self::start(),
+            start: clock.now(),
             elapsed: cell,
         }))
     }
@@ -104,21 +162,26 @@ impl GasTimer {
     /// Record the elapsed time since the charge was made.
     pub fn stop(self) {
         if let Some(timer) = self.0 {
-            Self::set_elapsed(timer.elapsed, timer.start)
+            Self::set_elapsed(timer.elapsed, timer.start.elapsed())
         }
     }
 
     /// Record the elapsed time based on an instant taken before the charge was made.
+    ///
+    /// Timers using a [logical clock](GasClock::Logical) ignore the instant and behave like
+    /// [`GasTimer::stop`], as the instant was read from the wall clock.
     pub fn stop_with(self, start: GasInstant) {
         if let Some(timer) = self.0 {
-            Self::set_elapsed(timer.elapsed, start)
+            let elapsed = match timer.start {
+                ClockReading::Wall(_) => start.elapsed(),
+                ClockReading::Logical(..) => timer.start.elapsed(),
+            };
+            Self::set_elapsed(timer.elapsed, elapsed)
         }
     }
 
-    fn set_elapsed(elapsed: Arc<OnceCell<Duration>>, start: GasInstant) {
-        elapsed
-            .set(start.elapsed())
-            .expect("GasCharge::elapsed already set!")
+    fn set_elapsed(cell: Arc<OnceCell<Duration>>, elapsed: Duration) {
+        cell.set(elapsed).expect("GasCharge::elapsed already set!")
     }
 
     /// Convenience method to record the elapsed time only if some execution was successful.
@@ -105,6 +105,11 @@ where
         self.history.clear();
     }
 
+    /// Iterate over the current map.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.map.iter()
+    }
+
     /// Iterate mutably over the current map.
     pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
         self.map.iter_mut()
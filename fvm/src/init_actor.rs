@@ -21,7 +21,7 @@ use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::tuple::*;
 use fvm_ipld_encoding::CborStore;
 use fvm_ipld_hamt::Hamt;
-use fvm_shared::address::{Address, Payload};
+use fvm_shared::address::{Address, Payload, MAX_ADDRESS_LEN};
 use fvm_shared::{ActorID, HAMT_BIT_WIDTH};
 
 use crate::state_tree::{ActorState, StateTree};
@@ -126,8 +126,10 @@ impl State {
             .context("failed to load init actor address map")
             .or_fatal()?;
 
+        let mut key = [0u8; MAX_ADDRESS_LEN];
+        let len = addr.write_bytes(&mut key);
         Ok(map
-            .get(&addr.to_bytes())
+            .get(&key[..len])
             .context("failed to read init actor address map")
             .or_fatal()?
             .copied())
@@ -107,6 +107,9 @@ pub(super) fn scan_for_reachable_links(visitor: &mut LinkVisitor, mut buf: &[u8]
 
                 // Then visit it.
                 visitor.visit_cid(&cid)?;
+                if visitor.done() {
+                    return Ok(());
+                }
             }
             // MajArray
             4 => {
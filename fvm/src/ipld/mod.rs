@@ -6,36 +6,71 @@ use fvm_shared::commcid::{FIL_COMMITMENT_SEALED, FIL_COMMITMENT_UNSEALED};
 use num_traits::Zero;
 
 use crate::gas::{Gas, GasTimer, GasTracker, PriceList};
-use crate::kernel::{ExecutionError, Result};
+use crate::kernel::{ExecutionError, Result, SupportedHashes};
 use crate::syscall_error;
 
 mod cbor;
 
 struct LinkVisitor<'a> {
     pub price_list: &'a PriceList,
+    cid_hashes: &'a [SupportedHashes],
     gas_available: Gas,
     gas_remaining: Gas,
     links: Vec<Cid>,
+    /// If set, the link being searched for: links aren't collected, and the scan stops at the
+    /// first link equal to the target.
+    target: Option<&'a Cid>,
+    found: bool,
 }
 
 /// Codecs allowed by the IPLD subsystem.
 pub const ALLOWED_CODECS: &[u64] = &[CBOR, DAG_CBOR, IPLD_RAW];
 /// Codecs ignored by the IPLD subsystem.
 pub const IGNORED_CODECS: &[u64] = &[FIL_COMMITMENT_UNSEALED, FIL_COMMITMENT_SEALED];
-
-// TODO: Deduplicate
-const BLAKE2B_256: u64 = 0xb220;
+/// DAG codecs: codecs whose blocks may contain links. Note that, of these, the IPLD subsystem
+/// currently only allows DAG_CBOR.
+pub const DAG_CODECS: &[u64] = &[DAG_CBOR, DAG_PB, DAG_JSON];
+
+/// The DAG-PB codec.
+const DAG_PB: u64 = 0x70;
+/// The DAG-JSON codec.
+const DAG_JSON: u64 = 0x0129;
+
+/// Returns true if the codec is a DAG codec (may contain links), and false if it's a leaf codec
+/// (e.g., raw) or unknown.
+pub fn is_dag_codec(codec: u64) -> bool {
+    DAG_CODECS.contains(&codec)
+}
 
 impl<'a> LinkVisitor<'a> {
-    pub fn new(price_list: &'a PriceList, gas_available: Gas) -> Self {
+    pub fn new(
+        price_list: &'a PriceList,
+        cid_hashes: &'a [SupportedHashes],
+        gas_available: Gas,
+    ) -> Self {
         Self {
             price_list,
+            cid_hashes,
             gas_available,
             gas_remaining: gas_available,
             links: Vec::new(),
+            target: None,
+            found: false,
         }
     }
 
+    /// Search for the given link instead of collecting all links.
+    pub fn searching_for(mut self, target: &'a Cid) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    /// Returns true once the link being searched for has been found, and the scan should stop.
+    #[inline(always)]
+    pub fn done(&self) -> bool {
+        self.found
+    }
+
     pub fn finish(mut self) -> Vec<Cid> {
         self.links.shrink_to_fit();
         self.links
@@ -96,7 +131,13 @@ impl<'a> LinkVisitor<'a> {
             return scan_for_links_inner(self, cid.codec(), cid.hash().digest());
         }
 
-        if cid.hash().code() != BLAKE2B_256 || cid.hash().size() != 32 {
+        if SupportedHashes::find_cid_hash(
+            self.cid_hashes,
+            cid.hash().code(),
+            cid.hash().size().into(),
+        )
+        .is_none()
+        {
             return Err(syscall_error!(
                 NotFound; "block links to CID with forbidden multihash type (code: {}, len: {})",
                 cid.hash().code(), cid.hash().size()
@@ -104,9 +145,12 @@ impl<'a> LinkVisitor<'a> {
             .into());
         }
 
-        // TODO: Charge a memory retention fee here? Or bundle that into the CID charge
-        // above?
-        self.links.push(*cid);
+        match self.target {
+            Some(target) => self.found = cid == target,
+            // TODO: Charge a memory retention fee here? Or bundle that into the CID charge
+            // above?
+            None => self.links.push(*cid),
+        }
         Ok(())
     }
 }
@@ -119,15 +163,16 @@ fn scan_for_links_inner(visitor: &mut LinkVisitor, codec: u64, data: &[u8]) -> R
     }
 }
 
-/// Scan for reachable links in the given IPLD block.
+/// Scan for reachable links in the given IPLD block. Links must use one of the `cid_hashes`.
 pub fn scan_for_reachable_links(
     codec: u64,
     data: &[u8],
     price_list: &PriceList,
+    cid_hashes: &[SupportedHashes],
     gas_tracker: &GasTracker,
 ) -> Result<Vec<Cid>> {
     let start = GasTimer::start();
-    let mut visitor = LinkVisitor::new(price_list, gas_tracker.gas_available());
+    let mut visitor = LinkVisitor::new(price_list, cid_hashes, gas_tracker.gas_available());
     let ret = scan_for_links_inner(&mut visitor, codec, data);
     let t = gas_tracker.charge_gas("OnScanIpldLinks", visitor.gas_used())?;
     let ret = ret.map(|_| visitor.finish());
@@ -135,11 +180,47 @@ pub fn scan_for_reachable_links(
     ret
 }
 
+/// Scan the given IPLD block for a link to `target`, stopping at the first match. Only the part of
+/// the block scanned up to the match is charged for. Like
+/// [`scan_for_reachable_links`], inline (identity-hashed) CIDs are searched but never matched.
+pub fn scan_for_link(
+    codec: u64,
+    data: &[u8],
+    target: &Cid,
+    price_list: &PriceList,
+    cid_hashes: &[SupportedHashes],
+    gas_tracker: &GasTracker,
+) -> Result<bool> {
+    let start = GasTimer::start();
+    let mut visitor =
+        LinkVisitor::new(price_list, cid_hashes, gas_tracker.gas_available()).searching_for(target);
+    let ret = scan_for_links_inner(&mut visitor, codec, data);
+    let t = gas_tracker.charge_gas("OnScanIpldLinks", visitor.gas_used())?;
+    let ret = ret.map(|_| visitor.done());
+    t.stop_with(start);
+    ret
+}
+
+/// Scan for links in the given IPLD block without charging gas, for offline tooling (e.g., state
+/// analysis).
+#[cfg(feature = "state_analysis")]
+pub fn scan_for_links_unmetered(codec: u64, data: &[u8]) -> Result<Vec<Cid>> {
+    use fvm_shared::version::NetworkVersion;
+
+    let price_list = crate::gas::price_list_by_network_version(NetworkVersion::V21);
+    let mut visitor = LinkVisitor::new(
+        price_list,
+        &[SupportedHashes::Blake2b256],
+        Gas::from_milligas(u64::MAX),
+    );
+    scan_for_links_inner(&mut visitor, codec, data).map(|_| visitor.finish())
+}
+
 #[cfg(test)]
 mod test {
     use crate::gas::{price_list_by_network_version, Gas, GasTracker};
 
-    use crate::kernel::{ExecutionError, Result};
+    use crate::kernel::{ExecutionError, Result, SupportedHashes};
     use cid::Cid;
     use fvm_ipld_encoding::{CBOR, DAG_CBOR, IPLD_RAW};
     use fvm_shared::commcid::FIL_COMMITMENT_UNSEALED;
@@ -163,7 +244,42 @@ mod test {
         let expected_gas = price_list.ipld_cbor_scan_per_field * cbor_field_count
             + price_list.ipld_cbor_scan_per_cid * cbor_link_count;
         let tracker = GasTracker::new(expected_gas, Gas::zero(), false);
-        let res = super::scan_for_reachable_links(codec, data, &price_list, &tracker);
+        let res = super::scan_for_reachable_links(
+            codec,
+            data,
+            &price_list,
+            &[SupportedHashes::Blake2b256],
+            &tracker,
+        );
+        assert!(
+            tracker.gas_available().is_zero(),
+            "expected to run out of gas"
+        );
+        res
+    }
+
+    /// Like `scan_for_links`, but searches for `target`.
+    fn find_link(
+        data: &[u8],
+        target: &Cid,
+        cbor_field_count: u32,
+        cbor_link_count: u32,
+    ) -> Result<bool> {
+        let mut price_list = price_list_by_network_version(NetworkVersion::V21).clone();
+        price_list.ipld_cbor_scan_per_field = Gas::new(1);
+        price_list.ipld_cbor_scan_per_cid = Gas::new(1 << 16);
+
+        let expected_gas = price_list.ipld_cbor_scan_per_field * cbor_field_count
+            + price_list.ipld_cbor_scan_per_cid * cbor_link_count;
+        let tracker = GasTracker::new(expected_gas, Gas::zero(), false);
+        let res = super::scan_for_link(
+            DAG_CBOR,
+            data,
+            target,
+            &price_list,
+            &[SupportedHashes::Blake2b256],
+            &tracker,
+        );
         assert!(
             tracker.gas_available().is_zero(),
             "expected to run out of gas"
@@ -228,4 +344,23 @@ mod test {
         let data = fvm_ipld_encoding::to_vec(&Test(0, test_cid, 1)).unwrap();
         assert!(scan_for_links(DAG_CBOR, &data, 4, 1).unwrap().is_empty());
     }
+
+    #[test]
+    fn find_link_stops_early() {
+        let first = Cid::new_v1(IPLD_RAW, multihash::Code::Blake2b256.digest(b"foo"));
+        let second = Cid::new_v1(IPLD_RAW, multihash::Code::Blake2b256.digest(b"bar"));
+        let missing = Cid::new_v1(IPLD_RAW, multihash::Code::Blake2b256.digest(b"baz"));
+        let data = fvm_ipld_encoding::to_vec(&(first, second, 1u64)).unwrap();
+
+        // Only the fields up to the match are charged for.
+        assert!(find_link(&data, &first, 2, 1).unwrap());
+        assert!(find_link(&data, &second, 3, 2).unwrap());
+        assert!(!find_link(&data, &missing, 4, 2).unwrap());
+
+        // Links inside inline blocks are found too.
+        let inlined_data = fvm_ipld_encoding::to_vec(&Test(0, second, 1)).unwrap();
+        let inline_cid = Cid::new_v1(DAG_CBOR, Multihash::wrap(0, &inlined_data).unwrap());
+        let data = fvm_ipld_encoding::to_vec(&Test(0, inline_cid, 1)).unwrap();
+        assert!(find_link(&data, &second, 6, 2).unwrap());
+    }
 }
@@ -1,20 +1,28 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 use std::rc::Rc;
 
 use cid::Cid;
 use fvm_ipld_encoding::ipld_block::IpldBlock;
+use multihash::Hasher;
 
-use super::Result;
+use super::{HashHandle, Result, SupportedHashes};
 use crate::syscall_error;
 
-/// A registry of open blocks (per-kernel). Think "file descriptor" table. At the moment, there's no
-/// way to close/remove a block from this table.
-#[derive(Default)]
+/// A registry of open blocks (per-kernel). Think "file descriptor" table. Only the most recently
+/// added block can be closed (see [`BlockRegistry::close`]), so block handles stay dense.
 pub struct BlockRegistry {
     blocks: Vec<Block>,
+    /// The maximum number of blocks, at most [`MAX_BLOCK_HANDLES`].
+    limit: u32,
     reachable: HashSet<Cid>,
+    /// Blocks explicitly marked as long-lived by the actor. These are exempt from eviction until
+    /// they're linked.
+    retained: HashSet<BlockId>,
+    /// In-progress streaming hash computations, keyed by handle.
+    hashers: HashMap<u32, (SupportedHashes, Box<dyn Hasher>)>,
+    next_hasher: u32,
 }
 
 /// Blocks in the block registry are addressed by an ordinal, starting from 1 (`FIRST_ID`).
@@ -23,7 +31,9 @@ pub struct BlockRegistry {
 pub type BlockId = u32;
 
 const FIRST_ID: BlockId = 1;
-const MAX_BLOCKS: u32 = i32::MAX as u32; // TODO(M2): Limit
+
+/// The absolute maximum number of blocks in a block registry, regardless of the configured limit.
+pub const MAX_BLOCK_HANDLES: u32 = i32::MAX as u32; // TODO(M2): Limit
 
 #[derive(Debug, Copy, Clone)]
 pub struct BlockStat {
@@ -89,10 +99,28 @@ impl From<&Block> for IpldBlock {
     }
 }
 
+impl Default for BlockRegistry {
+    fn default() -> Self {
+        Self::with_limit(MAX_BLOCK_HANDLES)
+    }
+}
+
 impl BlockRegistry {
     pub(crate) fn new() -> Self {
         Self::default()
     }
+
+    /// Creates a registry holding at most `limit` blocks (capped at [`MAX_BLOCK_HANDLES`]).
+    pub fn with_limit(limit: u32) -> Self {
+        BlockRegistry {
+            blocks: Vec::new(),
+            limit: limit.min(MAX_BLOCK_HANDLES),
+            reachable: HashSet::new(),
+            retained: HashSet::new(),
+            hashers: HashMap::new(),
+            next_hasher: 0,
+        }
+    }
 }
 
 impl BlockRegistry {
@@ -105,7 +133,7 @@ impl BlockRegistry {
     /// Adds a new block to the registry, checking that all children are currently reachable,
     /// returning a handle to refer to it. Use this when creating a _new_ block.
     //
-    //  Returns a `NotFound` error if `block` references any unreachable CIDs.
+    //  Returns a `NotFound` error naming the first unreachable CID if `block` references any.
     pub fn put_check_reachable(&mut self, block: Block) -> Result<BlockId> {
         self.put_inner(block, true)
     }
@@ -126,13 +154,15 @@ impl BlockRegistry {
     /// Adds a new block to the registry, and returns a handle to refer to it.
     fn put_inner(&mut self, block: Block, check_reachable: bool) -> Result<BlockId> {
         if self.is_full() {
-            return Err(syscall_error!(LimitExceeded; "too many blocks").into());
+            return Err(syscall_error!(LimitExceeded;
+                "too many blocks: {} of {} allowed", self.len(), self.limit())
+            .into());
         }
 
         // We expect the caller to have already charged for gas.
         if check_reachable {
             if let Some(k) = block.links().iter().find(|k| !self.is_reachable(k)) {
-                return Err(syscall_error!(NotFound; "cannot put block: {k} not reachable").into());
+                return Err(syscall_error!(NotFound; "block links to unreachable CID: {k}").into());
             }
         } else {
             for k in block.links() {
@@ -171,7 +201,95 @@ impl BlockRegistry {
             })
     }
 
+    /// Marks a block as retained (exempt from eviction) until it is linked.
+    pub fn retain(&mut self, id: BlockId) -> Result<()> {
+        self.get(id)?;
+        self.retained.insert(id);
+        Ok(())
+    }
+
+    /// Releases a previously retained block. This is a no-op if the block isn't retained.
+    pub fn release(&mut self, id: BlockId) {
+        self.retained.remove(&id);
+    }
+
+    /// Closes the most recently added block, freeing its handle (which the next block added will
+    /// reuse).
+    pub fn close(&mut self, id: BlockId) -> Result<()> {
+        self.get(id)?;
+        let last = FIRST_ID + self.blocks.len() as u32 - 1;
+        if id != last {
+            return Err(syscall_error!(IllegalArgument;
+                "only the most recently opened block ({last}) may be closed, not {id}")
+            .into());
+        }
+        self.blocks.pop();
+        self.retained.remove(&id);
+        Ok(())
+    }
+
+    /// Returns true if the block has been retained and not yet linked.
+    pub fn is_retained(&self, id: BlockId) -> bool {
+        self.retained.contains(&id)
+    }
+
+    /// Returns true if the block handle refers to a block in this registry.
+    pub fn contains(&self, id: BlockId) -> bool {
+        id >= FIRST_ID && ((id - FIRST_ID) as usize) < self.blocks.len()
+    }
+
+    /// Returns the number of blocks in the registry.
+    pub fn len(&self) -> u32 {
+        self.blocks.len() as u32
+    }
+
+    /// Returns true if the registry holds no blocks.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Returns the maximum number of blocks the registry can hold.
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// Changes the maximum number of blocks (capped at [`MAX_BLOCK_HANDLES`]). Lowering the limit
+    /// below the number of blocks already in the registry doesn't remove any blocks, but prevents
+    /// new ones from being added.
+    pub(crate) fn set_limit(&mut self, limit: u32) {
+        self.limit = limit.min(MAX_BLOCK_HANDLES);
+    }
+
     pub fn is_full(&self) -> bool {
-        self.blocks.len() as u32 == MAX_BLOCKS
+        self.len() >= self.limit
+    }
+
+    /// Starts a new streaming hash computation, returning a handle to refer to it.
+    pub(crate) fn put_hasher(&mut self, code: SupportedHashes) -> HashHandle {
+        self.next_hasher += 1;
+        self.hashers
+            .insert(self.next_hasher, (code, code.streaming_hasher()));
+        HashHandle(self.next_hasher)
+    }
+
+    /// Gets the in-progress hash computation associated with a handle.
+    pub(crate) fn hasher_mut(
+        &mut self,
+        handle: &HashHandle,
+    ) -> Result<(SupportedHashes, &mut dyn Hasher)> {
+        self.hashers
+            .get_mut(&handle.0)
+            .map(|(code, hasher)| (*code, hasher.as_mut()))
+            .ok_or_else(|| syscall_error!(InvalidHandle; "invalid hash handle {}", handle.0).into())
+    }
+
+    /// Removes the in-progress hash computation associated with a handle.
+    pub(crate) fn take_hasher(
+        &mut self,
+        handle: HashHandle,
+    ) -> Result<(SupportedHashes, Box<dyn Hasher>)> {
+        self.hashers
+            .remove(&handle.0)
+            .ok_or_else(|| syscall_error!(InvalidHandle; "invalid hash handle {}", handle.0).into())
     }
 }
@@ -1,27 +1,36 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::convert::{TryFrom, TryInto};
+use std::io::Read;
 use std::panic::{self, UnwindSafe};
-use std::path::PathBuf;
 
 use anyhow::{anyhow, Context as _};
+use bellman::groth16::{self, VerificationError};
+use bls12_381::{Bls12, Scalar};
 use cid::Cid;
 use filecoin_proofs_api::{self as proofs, ProverId, PublicReplicaInfo, SectorId};
+use flate2::read::ZlibDecoder;
 use fvm_ipld_blockstore::Blockstore;
-use fvm_ipld_encoding::{bytes_32, IPLD_RAW};
-use fvm_shared::address::Payload;
+use fvm_ipld_encoding::{bytes_32, CBOR, DAG_CBOR, IPLD_RAW};
+use fvm_shared::address::{Payload, MAX_ADDRESS_LEN};
 use fvm_shared::consensus::ConsensusFault;
 use fvm_shared::crypto::signature;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ErrorNumber;
-use fvm_shared::event::{ActorEvent, Entry, Flags};
+use fvm_shared::event::{ActorEvent, Entry, EventKey, Flags, MAX_KEY_LEN, MAX_NR_ENTRIES};
 use fvm_shared::piece::{zero_piece_commitment, PaddedPieceSize};
+#[cfg(feature = "testing")]
+use fvm_shared::sector::RegisteredAggregateProof;
 use fvm_shared::sector::{RegisteredPoStProof, SectorInfo};
 use fvm_shared::sys::out::vm::ContextFlags;
-use fvm_shared::{commcid, ActorID};
+use fvm_shared::sys::ReadOnlyReason;
+use fvm_shared::version::NetworkVersion;
+use fvm_shared::{commcid, ActorID, MAX_CID_LEN, METHOD_SEND};
 use lazy_static::lazy_static;
 use multihash::MultihashDigest;
+use num_traits::Zero;
+use once_cell::unsync::OnceCell;
 use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use rayon::prelude::ParallelDrainRange;
 
@@ -30,22 +39,24 @@ use super::error::Result;
 use super::hash::SupportedHashes;
 use super::*;
 use crate::call_manager::{CallManager, InvocationResult, NO_DATA_BLOCK_ID};
-use crate::externs::{Chain, Consensus, Rand};
-use crate::gas::GasTimer;
+use crate::externs::{Consensus, Rand};
+use crate::gas::{GasTimer, MeterId};
 use crate::init_actor::INIT_ACTOR_ID;
 use crate::machine::{MachineContext, NetworkConfig, BURNT_FUNDS_ACTOR_ID};
 use crate::state_tree::ActorState;
-use crate::{ipld, syscall_error};
+use crate::system_actor::SYSTEM_ACTOR_ID;
+use crate::{ipld, syscall_error, EMPTY_ARR_CID};
 
 lazy_static! {
     static ref NUM_CPUS: usize = num_cpus::get();
     static ref INITIAL_RESERVE_BALANCE: TokenAmount = TokenAmount::from_whole(300_000_000);
 }
 
-const BLAKE2B_256: u64 = 0xb220;
-const ENV_ARTIFACT_DIR: &str = "FVM_STORE_ARTIFACT_DIR";
 const MAX_ARTIFACT_NAME_LEN: usize = 256;
 
+/// The number of bytes [`DefaultKernel::inflate`] decompresses between gas charges.
+pub const INFLATE_CHUNK_SIZE: u64 = 64 << 10;
+
 #[cfg(feature = "testing")]
 const TEST_ACTOR_ALLOWED_TO_CALL_CREATE_ACTOR: ActorID = 98;
 
@@ -67,6 +78,16 @@ pub struct DefaultKernel<C> {
     ///
     /// This does not yet reason about reachability.
     blocks: BlockRegistry,
+    /// Names of the debug artifacts stored by this invocation.
+    artifacts: Vec<String>,
+    /// The message context, computed (and charged for) on first use. It's constant for the
+    /// duration of the invocation.
+    cached_message_context: OnceCell<MessageContext>,
+    /// The actor's balance, looked up on first use. The balance only changes when this actor
+    /// sends or self-destructs, both of which clear the cache.
+    cached_balance: OnceCell<TokenAmount>,
+    /// Sub-meters created by this invocation, indexed by the handles given to the actor.
+    submeters: Vec<MeterId>,
 }
 
 // Even though all children traits are implemented, Rust needs to know that the
@@ -101,6 +122,10 @@ where
             method,
             value_received,
             read_only,
+            artifacts: Vec::new(),
+            cached_message_context: OnceCell::new(),
+            cached_balance: OnceCell::new(),
+            submeters: Vec::new(),
         }
     }
 
@@ -124,19 +149,55 @@ where
             return Err(syscall_error!(ReadOnly; "cannot transfer value when read-only").into());
         }
 
+        // From nv22, explicit gas limits too small to invoke the recipient fail here, before any
+        // work is done on the recipient's behalf. We still charge the send's base cost.
+        if let Some(gas_limit) = gas_limit {
+            let min = self.call_manager.price_list().min_send_gas_limit();
+            if gas_limit < min
+                && self.call_manager.context().network.network_version >= NetworkVersion::V22
+            {
+                self.call_manager
+                    .charge_gas(self.call_manager.price_list().on_send_below_min_gas_limit())?;
+                return Err(syscall_error!(LimitExceeded;
+                    "send gas limit {gas_limit} is below the minimum of {min}")
+                .into());
+            }
+        }
+
+        // From nv22, check the balance up-front, so sends the caller can't afford fail before we
+        // load the parameters and resolve the recipient. The transfer checks this again.
+        if value.is_positive()
+            && self.call_manager.context().network.network_version >= NetworkVersion::V22
+        {
+            let balance = self.get_self()?.map(|a| a.balance).unwrap_or_default();
+            if &balance < value {
+                return Err(syscall_error!(InsufficientFunds;
+                    "sender does not have funds to transfer (balance {}, transfer {})", balance, value)
+                .into());
+            }
+        }
+
         // Load parameters.
         let params = if params_id == NO_DATA_BLOCK_ID {
             None
+        } else if !self.blocks.contains(params_id) {
+            return Err(syscall_error!(InvalidHandle;
+                "params block {params_id} not found in registry, was it created in this call frame?")
+            .into());
         } else {
             Some(self.blocks.get(params_id)?.clone())
         };
 
         // Make sure we can actually store the return block.
         if self.blocks.is_full() {
-            return Err(syscall_error!(LimitExceeded; "cannot store return block").into());
+            return Err(syscall_error!(LimitExceeded;
+                "cannot store return block: block registry is full ({} of {} blocks)",
+                self.blocks.len(), self.blocks.limit())
+            .into());
         }
 
-        // Send.
+        // Send. The transfer (and anything the recipient does) may change our balance.
+        self.cached_balance.take();
         let result = self.call_manager.send::<K>(
             from, *recipient, method, params, value, gas_limit, read_only,
         )?;
@@ -179,10 +240,125 @@ impl<C> DefaultKernel<C>
 where
     C: CallManager,
 {
+    /// Verifies an aggregated batch of prove-commits like [`CryptoOps::verify_aggregate_seals`],
+    /// but reports which sectors are at fault if verification fails. This is intended for
+    /// operators debugging failed aggregates, not for use by actors.
+    ///
+    /// The fast aggregate path is tried first. Only if it fails are the sectors checked
+    /// individually, and that fallback isn't charged.
+    pub fn verify_aggregate_seals_with_diagnostics(
+        &self,
+        aggregate: &AggregateSealVerifyProofAndInfos,
+    ) -> Result<AggregateVerifyResult> {
+        let overall = match self.verify_aggregate_seals(aggregate) {
+            Ok(overall) => overall,
+            // Malformed inputs are reported per-sector below.
+            Err(ExecutionError::Syscall(_)) => false,
+            Err(e) => return Err(e),
+        };
+        let sector_results = if overall {
+            aggregate
+                .infos
+                .iter()
+                .map(|info| SectorVerifyResult {
+                    sector_number: info.sector_number,
+                    valid: true,
+                    error: None,
+                })
+                .collect()
+        } else {
+            check_aggregate_seal_inputs(aggregate)
+        };
+        Ok(AggregateVerifyResult {
+            overall,
+            sector_results,
+        })
+    }
+
     /// Returns `Some(actor_state)` or `None` if this actor has been deleted.
     fn get_self(&self) -> Result<Option<ActorState>> {
         self.call_manager.get_actor(self.actor_id)
     }
+
+    /// Checks that the network allows CIDs with the given multihash code and length, returning the
+    /// hash function.
+    fn check_cid_hash(&self, code: u64, len: u32) -> Result<SupportedHashes> {
+        SupportedHashes::find_cid_hash(&self.call_manager.context().network.cid_hashes, code, len)
+            .ok_or_else(|| {
+                syscall_error!(IllegalCid;
+                    "cids may not use multihash (code: {:#x}, len: {})", code, len)
+                .into()
+            })
+    }
+
+    /// Returns the flags describing the current invocation context.
+    fn context_flags(&self) -> ContextFlags {
+        let mut flags = ContextFlags::empty();
+        if self.read_only {
+            flags |= ContextFlags::READ_ONLY;
+        }
+        // The implicit flag is only exposed from nv22 onwards.
+        if self.call_manager.implicit()
+            && self.call_manager.context().network.network_version >= NetworkVersion::V22
+        {
+            flags |= ContextFlags::IMPLICIT;
+        }
+        flags
+    }
+
+    /// Registers a block that has been loaded from the blockstore, charging for the links it
+    /// contains.
+    fn put_opened_block(&mut self, cid: &Cid, data: Vec<u8>) -> Result<(BlockId, BlockStat)> {
+        let children = ipld::scan_for_reachable_links(
+            cid.codec(),
+            &data,
+            self.call_manager.price_list(),
+            &self.call_manager.context().network.cid_hashes,
+            self.call_manager.gas_tracker(),
+        )?;
+
+        let t = self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_block_open(data.len(), children.len()),
+        )?;
+
+        let block = Block::new(cid.codec(), data, children);
+        let stat = block.stat();
+        let id = self.blocks.put_reachable(block)?;
+        t.stop();
+        Ok((id, stat))
+    }
+
+    /// Resolves a sub-meter handle given to the actor to the sub-meter it refers to.
+    fn lookup_submeter(&self, meter: u32) -> Result<MeterId> {
+        self.submeters
+            .get(meter as usize)
+            .copied()
+            .ok_or_else(|| syscall_error!(NotFound; "no such sub-meter {meter}").into())
+    }
+}
+
+#[cfg(feature = "testing")]
+impl<C> DefaultKernel<C>
+where
+    C: CallManager,
+{
+    /// Verifies an aggregated batch of prove-commits exactly like
+    /// [`CryptoOps::verify_aggregate_seals`], except that the aggregate proof type is replaced with
+    /// `aggregate_proof`. This lets tests exercise the rejection of mismatched aggregation
+    /// parameters without constructing full invalid proofs.
+    pub fn verify_aggregate_seals_with_params(
+        &self,
+        aggregate: &AggregateSealVerifyProofAndInfos,
+        aggregate_proof: RegisteredAggregateProof,
+    ) -> Result<bool> {
+        let aggregate = AggregateSealVerifyProofAndInfos {
+            aggregate_proof,
+            ..aggregate.clone()
+        };
+        self.verify_aggregate_seals(&aggregate)
+    }
 }
 
 impl<C> SelfOps for DefaultKernel<C>
@@ -222,6 +398,7 @@ where
         if !self.blocks.is_reachable(&new) {
             return Err(syscall_error!(NotFound; "new root cid not reachable: {new}").into());
         }
+        self.call_manager.context().check_quarantine(&new)?;
 
         let mut state = self
             .call_manager
@@ -233,12 +410,57 @@ where
     }
 
     fn current_balance(&self) -> Result<TokenAmount> {
+        // The cache only saves the state-tree lookup; every call is charged the same.
         let t = self
             .call_manager
             .charge_gas(self.call_manager.price_list().on_self_balance())?;
 
+        if let Some(balance) = self.cached_balance.get() {
+            return t.record(Ok(balance.clone()));
+        }
+
         // If the actor doesn't exist, it has zero balance.
-        t.record(Ok(self.get_self()?.map(|a| a.balance).unwrap_or_default()))
+        let balance = self.get_self()?.map(|a| a.balance).unwrap_or_default();
+        t.record(Ok(self.cached_balance.get_or_init(|| balance).clone()))
+    }
+
+    fn balance_before_receipt(&self) -> Result<TokenAmount> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_self_balance())?;
+
+        // The value has already been credited, so this shouldn't underflow. But we saturate at
+        // zero just in case (e.g., if the actor has been deleted).
+        let balance = self.get_self()?.map(|a| a.balance).unwrap_or_default();
+        let before = if balance > self.value_received {
+            balance - &self.value_received
+        } else {
+            TokenAmount::zero()
+        };
+        t.stop();
+        Ok(before)
+    }
+
+    fn is_first_invocation(&self) -> Result<bool> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_self_balance())?;
+
+        let first = self
+            .get_self()?
+            .map(|a| a.sequence == 0 && a.state == *EMPTY_ARR_CID)
+            .unwrap_or(false);
+        t.stop();
+        Ok(first)
+    }
+
+    fn self_delegated_address(&self) -> Result<Option<Address>> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_self_delegated_address())?;
+
+        // If the actor doesn't exist, it has no delegated address.
+        t.record(Ok(self.get_self()?.and_then(|a| a.delegated_address)))
     }
 
     fn self_destruct(&mut self, burn_unspent: bool) -> Result<()> {
@@ -270,6 +492,7 @@ where
                 .transfer(self.actor_id, BURNT_FUNDS_ACTOR_ID, &balance)
                 .or_fatal()?;
         }
+        self.cached_balance.take();
 
         // Delete the executing actor.
         t.record(self.call_manager.delete_actor(self.actor_id))
@@ -288,6 +511,7 @@ where
         if !self.blocks.is_reachable(cid) {
             return Err(syscall_error!(NotFound; "block not reachable: {cid}").into());
         }
+        self.call_manager.context().check_quarantine(cid)?;
 
         let data = self
             .call_manager
@@ -301,25 +525,7 @@ where
 
         t.stop();
 
-        // This can fail because we can run out of gas.
-        let children = ipld::scan_for_reachable_links(
-            cid.codec(),
-            &data,
-            self.call_manager.price_list(),
-            self.call_manager.gas_tracker(),
-        )?;
-
-        let t = self.call_manager.charge_gas(
-            self.call_manager
-                .price_list()
-                .on_block_open(data.len(), children.len()),
-        )?;
-
-        let block = Block::new(cid.codec(), data, children);
-        let stat = block.stat();
-        let id = self.blocks.put_reachable(block)?;
-        t.stop();
-        Ok((id, stat))
+        self.put_opened_block(cid, data)
     }
 
     fn block_create(&mut self, codec: u64, data: &[u8]) -> Result<BlockId> {
@@ -335,6 +541,7 @@ where
             codec,
             data,
             self.call_manager.price_list(),
+            &self.call_manager.context().network.cid_hashes,
             self.call_manager.gas_tracker(),
         )?;
 
@@ -349,14 +556,72 @@ where
         t.record(Ok(self.blocks.put_check_reachable(blk)?))
     }
 
-    fn block_link(&mut self, id: BlockId, hash_fun: u64, hash_len: u32) -> Result<Cid> {
-        if hash_fun != BLAKE2B_256 || hash_len != 32 {
-            return Err(syscall_error!(IllegalCid; "cids must be 32-byte blake2b").into());
+    fn block_patch(&mut self, id: BlockId, offset: u32, data: &[u8]) -> Result<BlockId> {
+        let block = self.blocks.get(id)?.clone();
+
+        let start = offset as usize;
+        let end = start
+            .checked_add(data.len())
+            .filter(|&end| end <= block.data().len())
+            .ok_or_else(|| {
+                syscall_error!(IllegalArgument; "patch of {} bytes at offset {} exceeds block size {}",
+                    data.len(), offset, block.size())
+            })?;
+
+        let t = self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_block_read(block.data().len()),
+        )?;
+        let mut patched = block.data().to_vec();
+        patched[start..end].copy_from_slice(data);
+        t.stop();
+
+        // The patch may add or remove links, so this re-scans the result.
+        self.block_create(block.codec(), &patched)
+    }
+
+    fn block_open_or_create(
+        &mut self,
+        cid: &Cid,
+        codec: u64,
+        data: &[u8],
+    ) -> Result<(BlockId, bool)> {
+        self.check_cid_hash(cid.hash().code(), cid.hash().size().into())?;
+        if cid.codec() != codec {
+            return Err(
+                syscall_error!(IllegalArgument; "codec {} doesn't match cid {}", codec, cid).into(),
+            );
         }
+
+        if self.blocks.is_reachable(cid) {
+            self.call_manager.context().check_quarantine(cid)?;
+            let t = self
+                .call_manager
+                .charge_gas(self.call_manager.price_list().on_block_open_base())?;
+            let existing = self.call_manager.blockstore().get(cid).or_fatal()?;
+            t.stop();
+
+            if let Some(existing) = existing {
+                let (id, _) = self.put_opened_block(cid, existing)?;
+                return Ok((id, false));
+            }
+        }
+
+        let id = self.block_create(codec, data)?;
+        if !self.block_hash_eq(id, cid)? {
+            self.blocks.release(id);
+            return Err(
+                syscall_error!(IllegalArgument; "block data doesn't match cid {}", cid).into(),
+            );
+        }
+        Ok((id, true))
+    }
+
+    fn block_link(&mut self, id: BlockId, hash_fun: u64, hash_len: u32) -> Result<Cid> {
+        let code = self.check_cid_hash(hash_fun, hash_len)?;
         let start = GasTimer::start();
         let block = self.blocks.get(id)?;
-        let code = SupportedHashes::try_from(hash_fun)
-            .map_err(|_| syscall_error!(IllegalCid; "invalid CID codec"))?;
 
         let t = self.call_manager.charge_gas(
             self.call_manager
@@ -376,11 +641,32 @@ where
             // probably abort the entire block.
             .or_fatal()?;
         self.blocks.mark_reachable(&k);
+        self.blocks.release(id);
 
         t.stop_with(start);
         Ok(k)
     }
 
+    fn block_cid(&mut self, id: BlockId, hash_fun: u64, hash_len: u32) -> Result<Cid> {
+        let code = self.check_cid_hash(hash_fun, hash_len)?;
+        let block = self.blocks.get(id)?;
+
+        let t = self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_hashing(code, block.size() as usize),
+        )?;
+
+        let hash = code.digest(block.data());
+        if u32::from(hash.size()) < hash_len {
+            return Err(syscall_error!(IllegalCid; "invalid hash length: {}", hash_len).into());
+        }
+        t.record(Ok(Cid::new_v1(
+            block.codec(),
+            hash.truncate(hash_len as u8),
+        )))
+    }
+
     fn block_read(&self, id: BlockId, offset: u32, buf: &mut [u8]) -> Result<i32> {
         let tstart = GasTimer::start();
         // First, find the end of the _logical_ buffer (taking the offset into account).
@@ -389,7 +675,9 @@ where
         // We perform operations as u64, because we know that the buffer length and offset must fit
         // in a u32.
         let end = i32::try_from((offset as u64) + (buf.len() as u64))
-            .map_err(|_| syscall_error!(IllegalArgument; "offset plus buffer length did not fit into an i32"))?;
+            .map_err(|_| {
+                syscall_error!(IllegalArgument; "read would extend beyond 2GiB block boundary: offset={offset}, len={}", buf.len())
+            })?;
 
         // Then get the block.
         let block = self.blocks.get(id)?;
@@ -416,6 +704,29 @@ where
         Ok((data.len() as i32) - end)
     }
 
+    fn block_read_u64(&self, id: BlockId) -> Result<u64> {
+        let block = self.blocks.get(id)?;
+
+        let t = self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_block_read_u64(block.size() as usize),
+        )?;
+
+        if !matches!(block.codec(), CBOR | DAG_CBOR) {
+            return Err(
+                syscall_error!(IllegalArgument; "expected a CBOR block, got codec {}", block.codec())
+                    .into(),
+            );
+        }
+
+        // This rejects anything but a bare unsigned integer that fits in a u64 (including big
+        // integers, negative integers, and trailing data).
+        let value: u64 = fvm_ipld_encoding::from_slice(block.data())
+            .map_err(|e| syscall_error!(IllegalArgument; "block isn't a u64: {e}"))?;
+        t.record(Ok(value))
+    }
+
     fn block_stat(&self, id: BlockId) -> Result<BlockStat> {
         let t = self
             .call_manager
@@ -423,6 +734,180 @@ where
 
         t.record(Ok(self.blocks.stat(id)?))
     }
+
+    fn block_retain(&mut self, id: BlockId) -> Result<()> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_block_retain())?;
+
+        t.record(self.blocks.retain(id))
+    }
+
+    fn block_close(&mut self, id: BlockId) -> Result<()> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_block_close())?;
+
+        t.record(self.blocks.close(id))
+    }
+
+    fn inflate(&self, compressed: &[u8], max_output: u32) -> Result<Vec<u8>> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_inflate(compressed.len()))?;
+
+        // Read at most one byte more than allowed so we can detect (and stop early on) oversized
+        // outputs without fully decompressing them. The output is decompressed (and charged for)
+        // in chunks, so running out of gas stops the decompression after at most one chunk.
+        let mut decoder = ZlibDecoder::new(compressed).take(u64::from(max_output) + 1);
+        let mut out = Vec::new();
+        loop {
+            let n = (&mut decoder)
+                .take(INFLATE_CHUNK_SIZE)
+                .read_to_end(&mut out)
+                .map_err(|e| syscall_error!(IllegalArgument; "failed to inflate data: {}", e))?;
+            if n == 0 {
+                break;
+            }
+            self.call_manager
+                .charge_gas(self.call_manager.price_list().on_inflate_output(n))?
+                .stop();
+        }
+        t.stop();
+        if out.len() > max_output as usize {
+            return Err(syscall_error!(LimitExceeded;
+                "inflated data exceeds the maximum output size of {} bytes", max_output)
+            .into());
+        }
+
+        Ok(out)
+    }
+
+    fn is_dag_codec(&self, codec: u64) -> Result<bool> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_is_dag_codec())?;
+
+        t.record(Ok(ipld::is_dag_codec(codec)))
+    }
+
+    fn block_eq(&self, a: BlockId, b: BlockId) -> Result<bool> {
+        let a = self.blocks.get(a)?;
+        let b = self.blocks.get(b)?;
+
+        let t = self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_block_eq(std::cmp::min(a.size(), b.size()) as usize),
+        )?;
+
+        t.record(Ok(a.codec() == b.codec() && a.data() == b.data()))
+    }
+
+    fn block_hash_eq(&self, id: BlockId, expected: &Cid) -> Result<bool> {
+        let code = self.check_cid_hash(expected.hash().code(), expected.hash().size().into())?;
+        let block = self.blocks.get(id)?;
+
+        let t = self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_hashing(code, block.size() as usize),
+        )?;
+
+        let hash = code.digest(block.data());
+        t.record(Ok(
+            block.codec() == expected.codec() && hash.digest() == expected.hash().digest()
+        ))
+    }
+
+    fn block_contains_link(&self, id: BlockId, target: &Cid) -> Result<bool> {
+        let block = self.blocks.get(id)?;
+        ipld::scan_for_link(
+            block.codec(),
+            block.data(),
+            target,
+            self.call_manager.price_list(),
+            &self.call_manager.context().network.cid_hashes,
+            self.call_manager.gas_tracker(),
+        )
+    }
+
+    fn would_create_cycle(&self, root: Cid, candidate_child: Cid, max_depth: u32) -> Result<bool> {
+        if !self.blocks.is_reachable(&candidate_child) {
+            return Err(syscall_error!(NotFound; "block not reachable: {candidate_child}").into());
+        }
+
+        // Breadth-first, so we stop at the shortest path to the root.
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::from([(candidate_child, 0u32)]);
+        while let Some((cid, depth)) = queue.pop_front() {
+            if cid == root {
+                return Ok(true);
+            }
+            if depth >= max_depth || !visited.insert(cid) {
+                continue;
+            }
+
+            let t = self
+                .call_manager
+                .charge_gas(self.call_manager.price_list().on_block_open_base())?;
+            self.call_manager.context().check_quarantine(&cid)?;
+            let data = self
+                .call_manager
+                .blockstore()
+                .get(&cid)
+                .and_then(|b| b.ok_or_else(|| anyhow!("missing reachable state: {}", cid)))
+                .or_fatal()?;
+            t.stop();
+
+            let t = self.call_manager.charge_gas(
+                self.call_manager
+                    .price_list()
+                    .on_cycle_check_visit(data.len()),
+            )?;
+            t.stop();
+
+            let links = ipld::scan_for_reachable_links(
+                cid.codec(),
+                &data,
+                self.call_manager.price_list(),
+                &self.call_manager.context().network.cid_hashes,
+                self.call_manager.gas_tracker(),
+            )?;
+            queue.extend(links.into_iter().map(|l| (l, depth + 1)));
+        }
+        Ok(false)
+    }
+
+    fn would_fit_as_return(&self, id: BlockId) -> Result<bool> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_would_fit_as_return())?;
+
+        let block = self.blocks.get(id)?;
+        t.record(Ok(block.size() as usize
+            <= self.machine().context().max_block_size
+            && ipld::ALLOWED_CODECS.contains(&block.codec())))
+    }
+
+    fn expand_block_handles(&mut self, extra: u32) -> Result<u32> {
+        let prev = self.blocks.limit();
+        let limit = prev.saturating_add(extra).min(MAX_BLOCK_HANDLES);
+        let t = self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_expand_block_handles(limit - prev),
+        )?;
+        self.blocks.set_limit(limit);
+        t.stop();
+        Ok(prev)
+    }
+
+    fn restore_block_handles(&mut self, limit: u32) {
+        if limit < self.blocks.limit() {
+            self.blocks.set_limit(limit);
+        }
+    }
 }
 
 impl<C> MessageOps for DefaultKernel<C>
@@ -430,34 +915,99 @@ where
     C: CallManager,
 {
     fn msg_context(&self) -> Result<MessageContext> {
+        // Only the first call is charged, subsequent calls return the cached context.
+        self.cached_message_context
+            .get_or_try_init(|| -> Result<MessageContext> {
+                let t = self
+                    .call_manager
+                    .charge_gas(self.call_manager.price_list().on_message_context())?;
+
+                let ctx = MessageContext {
+                    caller: self.caller,
+                    origin: self.call_manager.origin(),
+                    receiver: self.actor_id,
+                    method_number: self.method,
+                    value_received: (&self.value_received)
+                        .try_into()
+                        .or_fatal()
+                        .context("invalid token amount")?,
+                    gas_premium: self
+                        .call_manager
+                        .gas_premium()
+                        .try_into()
+                        .or_fatal()
+                        .context("invalid gas premium")?,
+                    flags: self.context_flags(),
+                    nonce: self.call_manager.nonce(),
+                };
+                t.stop();
+                Ok(ctx)
+            })
+            .copied()
+    }
+
+    fn msg_context_v2(&self) -> Result<MessageContextV2> {
+        if self.call_manager.context().network.network_version < NetworkVersion::V22 {
+            return Err(syscall_error!(IllegalOperation;
+                "extended message context not supported before network version 22")
+            .into());
+        }
+
+        let context = self.msg_context()?;
+        let mut origin_delegated_address = [0u8; MAX_ADDRESS_LEN];
+        let origin_delegated_address_len = match self.call_manager.origin_delegated_address() {
+            Some(addr) => {
+                let bytes = addr.to_bytes();
+                origin_delegated_address[..bytes.len()].copy_from_slice(&bytes);
+                bytes.len() as u32
+            }
+            None => 0,
+        };
+        Ok(MessageContextV2 {
+            context,
+            origin_delegated_address_len,
+            origin_delegated_address,
+        })
+    }
+
+    fn caller_delegated_address(&self) -> Result<Option<Address>> {
         let t = self
             .call_manager
-            .charge_gas(self.call_manager.price_list().on_message_context())?;
-
-        let ctx = MessageContext {
-            caller: self.caller,
-            origin: self.call_manager.origin(),
-            receiver: self.actor_id,
-            method_number: self.method,
-            value_received: (&self.value_received)
-                .try_into()
-                .or_fatal()
-                .context("invalid token amount")?,
-            gas_premium: self
-                .call_manager
-                .gas_premium()
-                .try_into()
-                .or_fatal()
-                .context("invalid gas premium")?,
-            flags: if self.read_only {
-                ContextFlags::READ_ONLY
-            } else {
-                ContextFlags::empty()
-            },
-            nonce: self.call_manager.nonce(),
-        };
-        t.stop();
-        Ok(ctx)
+            .charge_gas(self.call_manager.price_list().on_lookup_delegated_address())?;
+
+        // The caller may have been deleted since it called us, in which case it no longer has a
+        // delegated address.
+        Ok(t.record(self.call_manager.get_actor(self.caller))?
+            .and_then(|a| a.delegated_address))
+    }
+
+    fn read_only_reason(&self) -> Result<ReadOnlyReason> {
+        if !self.debug_enabled() {
+            return Err(
+                syscall_error!(IllegalOperation; "read-only reason requires actor debugging")
+                    .into(),
+            );
+        }
+
+        // The call manager tracks where read-only mode was entered as it's propagated through
+        // `send`: by the top-level message, or by a caller setting the read-only flag.
+        Ok(match self.call_manager.read_only_depth() {
+            _ if !self.read_only => ReadOnlyReason::NotReadOnly,
+            Some(depth) if depth > 1 => ReadOnlyReason::InheritedFromCaller,
+            _ => ReadOnlyReason::MessageLevel,
+        })
+    }
+
+    fn origin_account_nonce(&self) -> Result<u64> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_balance_of())?;
+
+        let origin = self.call_manager.origin();
+        Ok(t.record(self.call_manager.get_actor(origin))?
+            .context("origin actor does not exist")
+            .or_fatal()?
+            .sequence)
     }
 }
 
@@ -502,9 +1052,11 @@ where
 
         // Verify signature, catching errors. Signature verification can include some complicated
         // math.
-        t.record(catch_and_log_panic("verifying signature", || {
-            Ok(signature::verify(sig_type, signature, plaintext, &signing_addr).is_ok())
-        }))
+        t.record(catch_and_log_panic(
+            "verifying signature",
+            self.call_manager.context().max_panic_message_len,
+            || Ok(signature::verify(sig_type, signature, plaintext, &signing_addr).is_ok()),
+        ))
     }
 
     fn recover_secp_public_key(
@@ -525,14 +1077,36 @@ where
         )
     }
 
+    fn recover_secp_public_keys(
+        &self,
+        items: &[([u8; SECP_SIG_MESSAGE_HASH_SIZE], [u8; SECP_SIG_LEN])],
+    ) -> Result<Vec<[u8; SECP_PUB_LEN]>> {
+        let mut timed = Vec::with_capacity(items.len());
+        for item in items {
+            let t = self
+                .call_manager
+                .charge_gas(self.call_manager.price_list().on_recover_secp_public_key())?;
+            timed.push((item, t));
+        }
+
+        timed
+            .par_drain(..)
+            .enumerate()
+            .map(|(i, ((hash, signature), t))| {
+                t.record(
+                    signature::ops::recover_secp_public_key(hash, signature)
+                        .map(|pubkey| pubkey.serialize())
+                        .map_err(|e| {
+                            syscall_error!(IllegalArgument; "public key recovery failed for item {}: {}", i, e)
+                                .into()
+                        }),
+                )
+            })
+            .collect()
+    }
+
     fn hash(&self, code: u64, data: &[u8]) -> Result<MultihashGeneric<64>> {
-        let hasher = SupportedHashes::try_from(code).map_err(|e| {
-            if let multihash::Error::UnsupportedCode(code) = e {
-                syscall_error!(IllegalArgument; "unsupported hash code {}", code)
-            } else {
-                syscall_error!(AssertionFailed; "hash expected unsupported code, got {}", e)
-            }
-        })?;
+        let hasher = supported_hash(code)?;
 
         let t = self.call_manager.charge_gas(
             self.call_manager
@@ -540,7 +1114,35 @@ where
                 .on_hashing(hasher, data.len()),
         )?;
 
-        t.record(Ok(hasher.digest(data)))
+        t.record(Ok(hasher.digest(data)))
+    }
+
+    fn hash_init(&mut self, code: u64) -> Result<HashHandle> {
+        let hasher = supported_hash(code)?;
+
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_hashing(hasher, 0))?;
+
+        t.record(Ok(self.blocks.put_hasher(hasher)))
+    }
+
+    fn hash_update(&mut self, handle: &HashHandle, data: &[u8]) -> Result<()> {
+        let (code, hasher) = self.blocks.hasher_mut(handle)?;
+
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_hashing(code, data.len()))?;
+
+        hasher.update(data);
+        t.stop();
+        Ok(())
+    }
+
+    fn hash_finalize(&mut self, handle: HashHandle) -> Result<MultihashGeneric<64>> {
+        let (code, mut hasher) = self.blocks.take_hasher(handle)?;
+        MultihashGeneric::wrap(code.into(), hasher.finalize())
+            .map_err(|e| ExecutionError::Fatal(anyhow!("failed to wrap hash digest: {}", e)))
     }
 
     fn compute_unsealed_sector_cid(
@@ -554,9 +1156,11 @@ where
                 .on_compute_unsealed_sector_cid(proof_type, pieces),
         )?;
 
-        t.record(catch_and_log_panic("computing unsealed sector CID", || {
-            compute_unsealed_sector_cid(proof_type, pieces)
-        }))
+        t.record(catch_and_log_panic(
+            "computing unsealed sector CID",
+            self.call_manager.context().max_panic_message_len,
+            || compute_unsealed_sector_cid(proof_type, pieces),
+        ))
     }
 
     fn verify_post(&self, verify_info: &WindowPoStVerifyInfo) -> Result<bool> {
@@ -564,10 +1168,19 @@ where
             .call_manager
             .charge_gas(self.call_manager.price_list().on_verify_post(verify_info))?;
 
+        // From nv22, a PoSt must challenge at least one sector.
+        if verify_info.challenged_sectors.is_empty()
+            && self.call_manager.context().network.network_version >= NetworkVersion::V22
+        {
+            return Err(syscall_error!(IllegalArgument; "no challenged sectors").into());
+        }
+
         // This is especially important to catch as, otherwise, a bad "post" could be undisputable.
-        t.record(catch_and_log_panic("verifying post", || {
-            verify_post(verify_info)
-        }))
+        t.record(catch_and_log_panic(
+            "verifying post",
+            self.call_manager.context().max_panic_message_len,
+            || verify_post(verify_info),
+        ))
     }
 
     fn verify_consensus_fault(
@@ -653,9 +1266,11 @@ where
                 .price_list()
                 .on_verify_aggregate_seals(aggregate),
         )?;
-        t.record(catch_and_log_panic("verifying aggregate seals", || {
-            verify_aggregate_seals(aggregate)
-        }))
+        t.record(catch_and_log_panic(
+            "verifying aggregate seals",
+            self.call_manager.context().max_panic_message_len,
+            || verify_aggregate_seals(aggregate),
+        ))
     }
 
     fn verify_replica_update(&self, replica: &ReplicaUpdateInfo) -> Result<bool> {
@@ -664,9 +1279,26 @@ where
                 .price_list()
                 .on_verify_replica_update(replica),
         )?;
-        t.record(catch_and_log_panic("verifying replica update", || {
-            verify_replica_update(replica)
-        }))
+        t.record(catch_and_log_panic(
+            "verifying replica update",
+            self.call_manager.context().max_panic_message_len,
+            || verify_replica_update(replica),
+        ))
+    }
+
+    fn groth16_verify(&self, vk: &[u8], proof: &[u8], public_inputs: &[u8]) -> Result<bool> {
+        // The verifying key is actor-supplied and every point it declares is validated when
+        // parsing it, so charge for its size as well as the number of inputs.
+        let t = self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_groth16_verify(public_inputs.len() / GROTH16_PUBLIC_INPUT_LEN, vk.len()),
+        )?;
+        t.record(catch_and_log_panic(
+            "verifying groth16 proof",
+            self.call_manager.context().max_panic_message_len,
+            || groth16_verify(vk, proof, public_inputs),
+        ))
     }
 }
 
@@ -686,9 +1318,63 @@ where
         self.call_manager.gas_tracker().charge_gas(name, compute)
     }
 
+    fn charge_gas_per_epoch(
+        &self,
+        name: &str,
+        cost_per_epoch: Gas,
+        epochs: i64,
+    ) -> Result<GasTimer> {
+        if epochs < 0 {
+            return Err(
+                syscall_error!(IllegalArgument; "negative number of epochs: {epochs}").into(),
+            );
+        }
+        self.charge_gas(name, cost_per_epoch * epochs as u64)
+    }
+
     fn price_list(&self) -> &PriceList {
         self.call_manager.price_list()
     }
+
+    fn estimate_fee(&self, gas_limit: Gas, gas_premium: &TokenAmount) -> Result<TokenAmount> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_estimate_fee())?;
+        t.record(Ok(self.call_manager.price_list().estimate_message_cost(
+            gas_limit,
+            gas_premium,
+            &self.call_manager.context().base_fee,
+        )))
+    }
+
+    fn gas_tracing_enabled(&self) -> bool {
+        self.call_manager.gas_tracker().tracing_enabled()
+    }
+
+    fn create_submeter(&mut self, budget: Gas) -> Result<u32> {
+        if self.call_manager.context().network.network_version < NetworkVersion::V22 {
+            return Err(syscall_error!(IllegalOperation;
+                "sub-meters not supported before network version 22")
+            .into());
+        }
+
+        let handle = self.submeters.len() as u32;
+        self.submeters
+            .push(self.call_manager.gas_tracker().create_submeter(budget));
+        Ok(handle)
+    }
+
+    fn charge_to(&self, meter: u32, compute: Gas) -> Result<GasTimer> {
+        self.call_manager
+            .gas_tracker()
+            .charge_to(self.lookup_submeter(meter)?, compute)
+    }
+
+    fn submeter_remaining(&self, meter: u32) -> Result<Gas> {
+        self.call_manager
+            .gas_tracker()
+            .submeter_remaining(self.lookup_submeter(meter)?)
+    }
 }
 
 impl<C> NetworkOps for DefaultKernel<C>
@@ -708,6 +1394,7 @@ where
                 NetworkConfig {
                     network_version,
                     chain_id,
+                    block_delay_seconds,
                     ..
                 },
             ..
@@ -718,6 +1405,7 @@ where
             epoch: *epoch,
             network_version: *network_version,
             timestamp: *timestamp,
+            block_delay_seconds: *block_delay_seconds,
             base_fee: base_fee
                 .try_into()
                 .or_fatal()
@@ -743,10 +1431,27 @@ where
             Greater => {}
         }
 
+        // We charge for the lookup even if the call manager has cached it.
         self.call_manager
             .charge_gas(self.call_manager.price_list().on_tipset_cid(offset))?;
 
-        self.call_manager.externs().get_tipset_cid(epoch).or_fatal()
+        self.call_manager.get_tipset_cid(epoch)
+    }
+
+    fn block_delay_seconds(&self) -> Result<u32> {
+        // This is part of the network context, so we charge the same.
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_network_context())?;
+        t.stop();
+        Ok(self.call_manager.context().network.block_delay_seconds)
+    }
+
+    fn epoch_elapsed(&self, target: ChainEpoch) -> Result<bool> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_epoch_elapsed())?;
+        t.record(Ok(self.call_manager.context().epoch >= target))
     }
 }
 
@@ -800,6 +1505,33 @@ where
                 .or_illegal_argument(),
         )
     }
+
+    fn random_in_range(
+        &self,
+        source: RandSource,
+        rand_epoch: ChainEpoch,
+        entropy: &[u8],
+        bound: u64,
+    ) -> Result<u64> {
+        if bound == 0 {
+            return Err(
+                syscall_error!(IllegalArgument; "random range bound must be non-zero").into(),
+            );
+        }
+
+        let randomness = match source {
+            RandSource::Chain => self.get_randomness_from_tickets(rand_epoch)?,
+            RandSource::Beacon => self.get_randomness_from_beacon(rand_epoch)?,
+        };
+
+        let t = self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_random_in_range(entropy.len()),
+        )?;
+
+        t.record(Ok(sample_uniform(&randomness, rand_epoch, entropy, bound)))
+    }
 }
 
 impl<C> ActorOps for DefaultKernel<C>
@@ -833,6 +1565,10 @@ where
         Ok(self.call_manager.next_actor_address())
     }
 
+    fn actors_created(&self) -> Result<u64> {
+        Ok(self.call_manager.actors_created())
+    }
+
     fn create_actor(
         &mut self,
         code_id: Cid,
@@ -879,6 +1615,21 @@ where
         Ok(id)
     }
 
+    fn address_builtin_type(&self, address: &Address) -> Result<Option<u32>> {
+        let id = self.resolve_address(address)?;
+
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_get_actor_code_cid())?;
+        let code = self.call_manager.get_actor(id)?.map(|a| a.code);
+        t.stop();
+
+        match code {
+            Some(code) => Ok(Some(self.get_builtin_actor_type(&code)?).filter(|&typ| typ != 0)),
+            None => Ok(None),
+        }
+    }
+
     fn get_code_cid_for_type(&self, typ: u32) -> Result<Cid> {
         let t = self
             .call_manager
@@ -923,6 +1674,89 @@ where
             .balance)
     }
 
+    fn balances_of(&self, actor_ids: &[ActorID]) -> Result<Vec<TokenAmount>> {
+        actor_ids
+            .iter()
+            .map(|&actor_id| {
+                let t = self
+                    .call_manager
+                    .charge_gas(self.call_manager.price_list().on_balance_of())?;
+
+                Ok(t.record(self.call_manager.get_actor(actor_id))?
+                    .map(|act| act.balance)
+                    .unwrap_or_default())
+            })
+            .collect()
+    }
+
+    fn can_receive_funds(&self, id: ActorID) -> Result<bool> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_get_actor_code_cid())?;
+
+        let Some(actor) = self.call_manager.get_actor(id)? else {
+            return t.record(Ok(false));
+        };
+
+        // Builtin actors with no fund-receiving entrypoint. Other actors (including non-builtin
+        // actors, whose exports we don't inspect) are assumed to accept funds.
+        let builtins = self.call_manager.machine().builtin_actors();
+        let non_receiving = [
+            builtins.get_system_code(),
+            builtins.get_init_code(),
+            builtins.get_eam_code(),
+        ];
+        let rejects = non_receiving.contains(&&actor.code)
+            || self.call_manager.context().rejects_value_send(
+                id,
+                METHOD_SEND,
+                &TokenAmount::from_atto(1),
+            );
+        t.record(Ok(!rejects))
+    }
+
+    fn enumerate_deployed_code_cids(&self) -> Result<Vec<(Cid, u64)>> {
+        if self.actor_id != SYSTEM_ACTOR_ID {
+            return Err(syscall_error!(Forbidden;
+                "enumerating code CIDs is restricted to the system actor, called by {}",
+                self.actor_id)
+            .into());
+        }
+
+        // The state-tree may be arbitrarily large, so charge for each actor as it's scanned and stop
+        // as soon as we run out of gas.
+        let mut counts = HashMap::<Cid, u64>::new();
+        let mut charge_err = None;
+        let res = self
+            .call_manager
+            .machine()
+            .state_tree()
+            .for_each_actor(|_, actor| {
+                let t = match self
+                    .call_manager
+                    .charge_gas(self.call_manager.price_list().on_scan_actors(1))
+                {
+                    Ok(t) => t,
+                    Err(e) => {
+                        charge_err = Some(e);
+                        return Err(anyhow!("actor scan aborted"));
+                    }
+                };
+                *counts.entry(actor.code).or_default() += 1;
+                t.stop();
+                Ok(())
+            });
+        if let Some(e) = charge_err {
+            return Err(e);
+        }
+        res.or_fatal()?;
+
+        // Sort so the result doesn't depend on hash map ordering.
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort();
+        Ok(counts)
+    }
+
     fn lookup_delegated_address(&self, actor_id: ActorID) -> Result<Option<Address>> {
         let t = self
             .call_manager
@@ -932,6 +1766,47 @@ where
             .ok_or_else(|| syscall_error!(NotFound; "actor not found"))?
             .delegated_address)
     }
+
+    fn make_delegated_address(&self, namespace: ActorID, subaddress: &[u8]) -> Result<Address> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_make_delegated_address())?;
+        t.record(Address::new_delegated(namespace, subaddress).map_err(|e| {
+            syscall_error!(IllegalArgument; "invalid delegated address: {}", e).into()
+        }))
+    }
+
+    fn delegated_namespace(&self, id: ActorID) -> Result<Option<ActorID>> {
+        Ok(self
+            .lookup_delegated_address(id)?
+            .and_then(|addr| match addr.payload() {
+                Payload::Delegated(delegated) => Some(delegated.namespace()),
+                _ => None,
+            }))
+    }
+
+    fn actor_id_available(&self, actor_id: ActorID) -> Result<bool> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_actor_id_available())?;
+
+        Ok(t.record(self.call_manager.get_actor(actor_id))?.is_none())
+    }
+}
+
+impl<C> EncodingOps for DefaultKernel<C>
+where
+    C: CallManager,
+{
+    fn is_valid_utf8(&self, bytes: &[u8]) -> Result<bool> {
+        let t = self.call_manager.charge_gas(
+            self.call_manager
+                .price_list()
+                .on_utf8_validation(bytes.len()),
+        )?;
+
+        t.record(Ok(std::str::from_utf8(bytes).is_ok()))
+    }
 }
 
 impl<C> DebugOps for DefaultKernel<C>
@@ -946,7 +1821,16 @@ where
         self.call_manager.context().actor_debugging
     }
 
-    fn store_artifact(&self, name: &str, data: &[u8]) -> Result<()> {
+    fn call_stack(&self) -> Result<Vec<(ActorID, MethodNum)>> {
+        if !self.debug_enabled() {
+            return Err(
+                syscall_error!(Forbidden; "the call stack is only available when debugging").into(),
+            );
+        }
+        Ok(self.call_manager.call_stack().to_vec())
+    }
+
+    fn store_artifact(&mut self, name: &str, data: &[u8]) -> Result<String> {
         // Ensure well formed artifact name
         {
             if name.len() > MAX_ARTIFACT_NAME_LEN {
@@ -967,33 +1851,61 @@ where
         }
         .or_error(fvm_shared::error::ErrorNumber::IllegalArgument)?;
 
+        // Enforce the artifact limits.
+        let max_artifacts = self.call_manager.context().max_artifacts_per_invocation;
+        if self.artifacts.len() >= max_artifacts as usize {
+            return Err(syscall_error!(LimitExceeded;
+                "actor invocation may not store more than {} debug artifacts", max_artifacts)
+            .into());
+        }
+        let max_bytes = self.call_manager.context().max_artifact_bytes_per_message;
+        if self
+            .call_manager
+            .artifact_bytes()
+            .saturating_add(data.len() as u64)
+            > max_bytes
+        {
+            return Err(syscall_error!(LimitExceeded;
+                "message may not store more than {} bytes of debug artifacts", max_bytes)
+            .into());
+        }
+        self.call_manager.record_artifact_bytes(data.len() as u64);
+
+        // Make the name unique within this invocation by appending the artifact's sequence number.
+        let name = if self.artifacts.iter().any(|n| n == name) {
+            let mut seq = self.artifacts.len();
+            loop {
+                let candidate = format!("{}.{}", name, seq);
+                if !self.artifacts.contains(&candidate) {
+                    break candidate;
+                }
+                seq += 1;
+            }
+        } else {
+            name.to_owned()
+        };
+        self.artifacts.push(name.clone());
+
         // Write to disk
-        if let Ok(dir) = std::env::var(ENV_ARTIFACT_DIR).as_deref() {
-            let dir: PathBuf = [
-                dir,
-                self.call_manager.machine().machine_id(),
-                &self.call_manager.origin().to_string(),
-                &self.call_manager.nonce().to_string(),
-                &self.actor_id.to_string(),
-                &self.call_manager.invocation_count().to_string(),
-            ]
-            .iter()
-            .collect();
+        if let Some(dir) = &self.call_manager.context().artifact_dir {
+            let dir = dir
+                .join(self.call_manager.machine().machine_id())
+                .join(self.call_manager.origin().to_string())
+                .join(self.call_manager.nonce().to_string())
+                .join(self.actor_id.to_string())
+                .join(self.call_manager.invocation_count().to_string());
 
             if let Err(e) = std::fs::create_dir_all(dir.clone()) {
                 log::error!("failed to make directory to store debug artifacts {}", e);
-            } else if let Err(e) = std::fs::write(dir.join(name), data) {
+            } else if let Err(e) = std::fs::write(dir.join(&name), data) {
                 log::error!("failed to store debug artifact {}", e)
             } else {
                 log::info!("wrote artifact: {} to {:?}", name, dir);
             }
         } else {
-            log::error!(
-                "store_artifact was ignored, env var {} was not set",
-                ENV_ARTIFACT_DIR
-            )
+            log::error!("store_artifact was ignored, no artifact directory was configured")
         }
-        Ok(())
+        Ok(name)
     }
 }
 
@@ -1006,26 +1918,76 @@ where
     fn limiter_mut(&mut self) -> &mut Self::Limiter {
         self.call_manager.limiter_mut()
     }
+
+    fn memory_usage(&self) -> Result<(u64, u64)> {
+        if self.call_manager.context().network.network_version < NetworkVersion::V22 {
+            return Err(syscall_error!(IllegalOperation;
+                "memory usage not supported before network version 22")
+            .into());
+        }
+
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_memory_usage())?;
+
+        let limiter = self.call_manager.limiter();
+        t.stop();
+        Ok((limiter.memory_used() as u64, limiter.memory_limit() as u64))
+    }
 }
 
 impl<C> EventOps for DefaultKernel<C>
 where
     C: CallManager,
 {
+    fn emit_events_dedup(&mut self, events: &[RawEvent<'_>]) -> Result<u32> {
+        let mut prev: Option<&RawEvent<'_>> = None;
+        let mut emitted = 0;
+        for evt in events {
+            if let Some(prev) = prev {
+                let t = self
+                    .call_manager
+                    .charge_gas(self.call_manager.price_list().on_event_compare(evt.size()))?;
+                let duplicate = prev == evt;
+                t.stop();
+                if duplicate {
+                    continue;
+                }
+            }
+            self.emit_event(evt.headers, evt.keys, evt.values)?;
+            emitted += 1;
+            prev = Some(evt);
+        }
+        Ok(emitted)
+    }
+
     fn emit_event(
         &mut self,
         event_headers: &[fvm_shared::sys::EventEntry],
         event_keys: &[u8],
         event_values: &[u8],
     ) -> Result<()> {
-        const MAX_NR_ENTRIES: usize = 255;
-        const MAX_KEY_LEN: usize = 31;
         const MAX_TOTAL_VALUES_LEN: usize = 8 << 10;
 
         if self.read_only {
             return Err(syscall_error!(ReadOnly; "cannot emit events while read-only").into());
         }
 
+        let depth = self.call_manager.call_stack_depth();
+        let max_depth = self.call_manager.context().network.max_event_emit_depth;
+        if depth > max_depth {
+            return Err(syscall_error!(LimitExceeded; "cannot emit events at call depth {depth} > {max_depth}").into());
+        }
+
+        if let Some(max_events) = self.call_manager.context().network.max_events_per_message {
+            if self.call_manager.events_emitted() >= max_events as usize {
+                return Err(
+                    syscall_error!(LimitExceeded; "message exceeded max events: {max_events}")
+                        .into(),
+                );
+            }
+        }
+
         let t = self
             .call_manager
             .charge_gas(self.call_manager.price_list().on_actor_event(
@@ -1042,11 +2004,6 @@ where
             return Err(syscall_error!(LimitExceeded; "total event value lengths exceeded the max size: {} > {MAX_TOTAL_VALUES_LEN}", event_values.len()).into());
         }
 
-        // We validate utf8 all at once for better performance.
-        let event_keys = std::str::from_utf8(event_keys)
-            .context("invalid event key")
-            .or_illegal_argument()?;
-
         let mut key_offset: usize = 0;
         let mut val_offset: usize = 0;
 
@@ -1061,9 +2018,19 @@ where
                 );
             }
 
-            if header.key_len > MAX_KEY_LEN as u32 {
+            // CID keys are only supported from nv22.
+            let cid_key = flags.contains(Flags::FLAG_CID_KEY);
+            if cid_key && self.call_manager.context().network.network_version < NetworkVersion::V22
+            {
+                return Err(
+                    syscall_error!(IllegalArgument; "event flags are invalid: {}", flags.bits())
+                        .into(),
+                );
+            }
+            let max_key_len = if cid_key { MAX_CID_LEN } else { MAX_KEY_LEN };
+            if header.key_len > max_key_len as u32 {
                 let tmp = header.key_len;
-                return Err(syscall_error!(LimitExceeded; "event key exceeded max size: {} > {MAX_KEY_LEN}", tmp).into());
+                return Err(syscall_error!(LimitExceeded; "event key exceeded max size: {} > {max_key_len}", tmp).into());
             }
 
             // We check this here purely to detect/prevent integer overflows below. That's why we
@@ -1075,10 +2042,24 @@ where
             }
 
             // parse the variable sized fields from the raw_key/raw_val buffers
-            let key = &event_keys
+            let key = event_keys
                 .get(key_offset..key_offset + header.key_len as usize)
                 .context("event entry key out of range")
                 .or_illegal_argument()?;
+            let key = if cid_key {
+                // Reject trailing bytes so that CID keys are canonical.
+                let cid = Cid::try_from(key)
+                    .ok()
+                    .filter(|c| c.encoded_len() == key.len())
+                    .context("invalid event CID key")
+                    .or_illegal_argument()?;
+                EventKey::Cid(cid)
+            } else {
+                let key = std::str::from_utf8(key)
+                    .context("invalid event key")
+                    .or_illegal_argument()?;
+                EventKey::String(key.to_owned())
+            };
 
             let value = &event_values
                 .get(val_offset..val_offset + header.val_len as usize)
@@ -1097,7 +2078,7 @@ where
             // we have all we need to construct a new Entry
             let entry = Entry {
                 flags: header.flags,
-                key: key.to_string(),
+                key,
                 codec: header.codec,
                 value: value.to_vec(),
             };
@@ -1140,22 +2121,107 @@ where
 
         Ok(())
     }
+
+    fn event_gas_cost(
+        &self,
+        event_headers: &[fvm_shared::sys::EventEntry],
+        event_keys: &[u8],
+        event_values: &[u8],
+    ) -> Result<Gas> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_event_gas_cost())?;
+        let cost = self
+            .call_manager
+            .price_list()
+            .on_actor_event(event_headers.len(), event_keys.len(), event_values.len())
+            .total();
+        t.record(Ok(cost))
+    }
+
+    fn events_remaining(&self) -> Result<u32> {
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_events_remaining())?;
+        let remaining = match self.call_manager.context().network.max_events_per_message {
+            Some(max_events) => max_events.saturating_sub(
+                self.call_manager
+                    .events_emitted()
+                    .try_into()
+                    .unwrap_or(u32::MAX),
+            ),
+            None => u32::MAX,
+        };
+        t.record(Ok(remaining))
+    }
+
+    fn events_root_so_far(&self) -> Result<Option<Cid>> {
+        let events = self.call_manager.events();
+        let (entries, data_len) = events
+            .iter()
+            .flat_map(|evt| &evt.event.entries)
+            .fold((0, 0), |(entries, len), e| {
+                (entries + 1, len + e.key.to_bytes().len() + e.value.len())
+            });
+        let t = self
+            .call_manager
+            .charge_gas(self.call_manager.price_list().on_events_root(
+                events.len(),
+                entries,
+                data_len,
+            ))?;
+        t.record(crate::call_manager::events_root(events).or_fatal())
+    }
 }
 
-fn catch_and_log_panic<F: FnOnce() -> Result<R> + UnwindSafe, R>(context: &str, f: F) -> Result<R> {
+fn catch_and_log_panic<F: FnOnce() -> Result<R> + UnwindSafe, R>(
+    context: &str,
+    max_message_len: usize,
+    f: F,
+) -> Result<R> {
     match panic::catch_unwind(f) {
         Ok(v) => v,
         Err(e) => {
-            log::error!("caught panic when {}: {:?}", context, e);
-            Err(syscall_error!(IllegalArgument; "caught panic when {}: {:?}", context, e).into())
+            let msg = panic_message(&*e, max_message_len);
+            log::error!("caught panic when {}: {}", context, msg);
+            Err(syscall_error!(IllegalArgument; "caught panic when {}: {}", context, msg).into())
         }
     }
 }
 
+/// Extracts the message from a panic payload, truncating it to at most `max_len` bytes (plus a
+/// suffix marking the truncation).
+fn panic_message(payload: &(dyn std::any::Any + Send), max_len: usize) -> String {
+    let msg = if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.as_str()
+    } else {
+        "unknown panic payload"
+    };
+    if msg.len() <= max_len {
+        return msg.to_owned();
+    }
+    let mut end = max_len;
+    while !msg.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}...(truncated)", &msg[..end])
+}
+
+fn supported_hash(code: u64) -> Result<SupportedHashes> {
+    SupportedHashes::try_from(code).map_err(|e| {
+        if let multihash::Error::UnsupportedCode(code) = e {
+            syscall_error!(IllegalArgument; "unsupported hash code {}", code).into()
+        } else {
+            syscall_error!(AssertionFailed; "hash expected unsupported code, got {}", e).into()
+        }
+    })
+}
+
 fn prover_id_from_u64(id: u64) -> ProverId {
     let mut prover_id = ProverId::default();
-    let prover_bytes = Address::new_id(id).payload().to_raw_bytes();
-    prover_id[..prover_bytes.len()].copy_from_slice(&prover_bytes);
+    Address::new_id(id).payload_bytes_into(&mut prover_id);
     prover_id
 }
 
@@ -1277,6 +2343,48 @@ fn verify_post(verify_info: &WindowPoStVerifyInfo) -> Result<bool> {
         .or_illegal_argument()
 }
 
+/// Checks the public inputs of each sector in an aggregate individually, for diagnostics.
+fn check_aggregate_seal_inputs(
+    aggregate: &AggregateSealVerifyProofAndInfos,
+) -> Vec<SectorVerifyResult> {
+    let spt: core::result::Result<proofs::RegisteredSealProof, _> = aggregate.seal_proof.try_into();
+    let prover_id = prover_id_from_u64(aggregate.miner);
+    aggregate
+        .infos
+        .par_iter()
+        .map(|info| {
+            let check = || -> anyhow::Result<()> {
+                let spt = spt
+                    .clone()
+                    .map_err(|e| anyhow!("invalid seal proof type: {}", e))?;
+                let commr = commcid::cid_to_replica_commitment_v1(&info.sealed_cid)
+                    .map_err(|e| anyhow!("invalid sealed CID: {}", e))?;
+                let commd = commcid::cid_to_data_commitment_v1(&info.unsealed_cid)
+                    .map_err(|e| anyhow!("invalid unsealed CID: {}", e))?;
+                proofs::seal::get_seal_inputs(
+                    spt,
+                    commr,
+                    commd,
+                    prover_id,
+                    SectorId::from(info.sector_number),
+                    bytes_32(&info.randomness.0),
+                    bytes_32(&info.interactive_randomness.0),
+                )?;
+                Ok(())
+            };
+            let error = match panic::catch_unwind(check) {
+                Ok(res) => res.err().map(|e| e.to_string()),
+                Err(e) => Some(format!("panicked: {:?}", e)),
+            };
+            SectorVerifyResult {
+                sector_number: info.sector_number,
+                valid: error.is_none(),
+                error,
+            }
+        })
+        .collect()
+}
+
 fn verify_aggregate_seals(aggregate: &AggregateSealVerifyProofAndInfos) -> Result<bool> {
     if aggregate.infos.is_empty() {
         return Err(syscall_error!(IllegalArgument; "no seal verify infos").into());
@@ -1410,3 +2518,182 @@ fn compute_unsealed_sector_cid(
 
     commcid::data_commitment_v1_to_cid(&comm_d).or_illegal_argument()
 }
+
+/// The size of a single encoded Groth16 public input (a BLS12-381 scalar).
+const GROTH16_PUBLIC_INPUT_LEN: usize = 32;
+
+fn groth16_verify(vk: &[u8], proof: &[u8], public_inputs: &[u8]) -> Result<bool> {
+    if public_inputs.len() % GROTH16_PUBLIC_INPUT_LEN != 0 {
+        return Err(syscall_error!(IllegalArgument;
+            "public inputs must be a multiple of {} bytes, got {}",
+            GROTH16_PUBLIC_INPUT_LEN, public_inputs.len())
+        .into());
+    }
+
+    let vk = groth16::VerifyingKey::<Bls12>::read(vk)
+        .or_illegal_argument()
+        .context("invalid groth16 verifying key")?;
+    let proof = groth16::Proof::<Bls12>::read(proof)
+        .or_illegal_argument()
+        .context("invalid groth16 proof")?;
+    let inputs = public_inputs
+        .chunks_exact(GROTH16_PUBLIC_INPUT_LEN)
+        .map(|input| Option::from(Scalar::from_bytes(&bytes_32(input))))
+        .collect::<Option<Vec<Scalar>>>()
+        .context("invalid groth16 public input")
+        .or_illegal_argument()?;
+
+    let pvk = groth16::prepare_verifying_key(&vk);
+    match groth16::verify_proof(&pvk, &proof, &inputs) {
+        Ok(()) => Ok(true),
+        Err(VerificationError::InvalidProof) => Ok(false),
+        Err(e) => {
+            Err(syscall_error!(IllegalArgument; "failed to verify groth16 proof: {}", e).into())
+        }
+    }
+}
+
+/// Derives a uniformly distributed integer in `[0, bound)` from the given randomness, epoch, and
+/// entropy.
+///
+/// The inputs (and a counter) are hashed into a stream of 64-bit samples. Samples falling into the
+/// final, partial multiple of `bound` are rejected to avoid modulo bias. Each sample is rejected
+/// with a probability of less than 1/2, so we almost never need more than one hash.
+fn sample_uniform(
+    randomness: &[u8; RANDOMNESS_LENGTH],
+    epoch: ChainEpoch,
+    entropy: &[u8],
+    bound: u64,
+) -> u64 {
+    // Accept samples below the largest multiple of `bound` that fits in a u64.
+    let zone = u64::MAX - (u64::MAX % bound);
+
+    let mut data = Vec::with_capacity(RANDOMNESS_LENGTH + 16 + entropy.len());
+    data.extend_from_slice(randomness);
+    data.extend_from_slice(&epoch.to_be_bytes());
+    data.extend_from_slice(entropy);
+    let counter_offset = data.len();
+    data.extend_from_slice(&0u64.to_be_bytes());
+
+    for counter in 0u64.. {
+        data[counter_offset..].copy_from_slice(&counter.to_be_bytes());
+        let hash = SupportedHashes::Blake2b256.digest(&data);
+        for sample in hash.digest().chunks_exact(8) {
+            let sample = u64::from_be_bytes(sample.try_into().expect("8 byte chunk"));
+            if sample < zone {
+                return sample % bound;
+            }
+        }
+    }
+    unreachable!("exhausted all sample counters")
+}
+
+#[cfg(test)]
+mod test {
+    use fvm_ipld_encoding::DAG_CBOR;
+    use fvm_shared::sys::EventEntry;
+
+    use super::*;
+    use crate::testing::build_kernel;
+
+    fn expect_err<T: std::fmt::Debug>(res: Result<T>, code: ErrorNumber) {
+        match res.expect_err("expected syscall to fail") {
+            ExecutionError::Syscall(SyscallError(_, c)) if c == code => {}
+            e => panic!("expected {code}, got {e:?}"),
+        }
+    }
+
+    fn entry(key_len: usize, val_len: usize) -> EventEntry {
+        EventEntry {
+            flags: Flags::FLAG_INDEXED_ALL,
+            codec: IPLD_RAW,
+            key_len: key_len as u32,
+            val_len: val_len as u32,
+        }
+    }
+
+    #[test]
+    fn panic_message_truncation() {
+        let huge = "é".repeat(10_000);
+        let res: Result<()> = catch_and_log_panic("testing", 512, || panic!("{}", huge));
+        match res.expect_err("expected the panic to be caught") {
+            ExecutionError::Syscall(SyscallError(msg, ErrorNumber::IllegalArgument)) => {
+                let expected = format!(
+                    "caught panic when testing: {}...(truncated)",
+                    "é".repeat(256)
+                );
+                assert_eq!(msg, expected);
+            }
+            e => panic!("expected IllegalArgument, got {e:?}"),
+        }
+
+        // Short messages are kept as-is.
+        let res: Result<()> = catch_and_log_panic("testing", 512, || panic!("short"));
+        expect_err(res, ErrorNumber::IllegalArgument);
+        assert_eq!(panic_message(&"short", 5), "short");
+        assert_eq!(panic_message(&String::from("é"), 1), "...(truncated)");
+        assert_eq!(panic_message(&1u8, 512), "unknown panic payload");
+    }
+
+    #[test]
+    fn block_create_limits() {
+        let (mut kern, _) = build_kernel();
+        let max = kern.machine().context().max_block_size;
+
+        kern.block_create(IPLD_RAW, &vec![0; max]).unwrap();
+        expect_err(
+            kern.block_create(IPLD_RAW, &vec![0; max + 1]),
+            ErrorNumber::LimitExceeded,
+        );
+        expect_err(kern.block_create(0xFF, b"foo"), ErrorNumber::IllegalCodec);
+        expect_err(kern.block_create(DAG_CBOR, &[]), ErrorNumber::Serialization);
+    }
+
+    #[test]
+    fn emit_event_limits() {
+        let (mut kern, _) = build_kernel();
+
+        // Entry count.
+        let entries = vec![entry(1, 0); MAX_NR_ENTRIES];
+        kern.emit_event(&entries, &[b'k'; MAX_NR_ENTRIES], &[])
+            .unwrap();
+        let entries = vec![entry(1, 0); MAX_NR_ENTRIES + 1];
+        expect_err(
+            kern.emit_event(&entries, &[b'k'; MAX_NR_ENTRIES + 1], &[]),
+            ErrorNumber::LimitExceeded,
+        );
+
+        // Key length.
+        let key = [b'k'; MAX_KEY_LEN + 1];
+        kern.emit_event(&[entry(MAX_KEY_LEN, 0)], &key[..MAX_KEY_LEN], &[])
+            .unwrap();
+        expect_err(
+            kern.emit_event(&[entry(key.len(), 0)], &key, &[]),
+            ErrorNumber::LimitExceeded,
+        );
+
+        // Total value length.
+        let value = vec![0; (8 << 10) + 1];
+        expect_err(
+            kern.emit_event(&[entry(1, value.len())], b"k", &value),
+            ErrorNumber::LimitExceeded,
+        );
+
+        // Malformed keys and buffers.
+        expect_err(
+            kern.emit_event(&[entry(1, 0)], &[0xff], &[]),
+            ErrorNumber::IllegalArgument,
+        );
+        expect_err(
+            kern.emit_event(&[entry(2, 0)], b"k", &[]),
+            ErrorNumber::IllegalArgument,
+        );
+        expect_err(
+            kern.emit_event(&[entry(1, 0)], b"kk", &[]),
+            ErrorNumber::IllegalArgument,
+        );
+
+        let (call_manager, _) = kern.into_inner();
+        assert_eq!(call_manager.events.len(), 2);
+    }
+}
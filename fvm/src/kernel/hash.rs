@@ -1,7 +1,7 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 use multihash::derive::Multihash;
-use multihash::{Blake2b256, Blake2b512, Keccak256, Ripemd160, Sha2_256};
+use multihash::{Blake2b256, Blake2b512, Hasher, Keccak256, Ripemd160, Sha2_256};
 
 #[derive(Clone, Copy, Debug, Eq, Multihash, PartialEq, Hash)]
 #[mh(alloc_size = 64)]
@@ -20,3 +20,45 @@ pub enum SupportedHashes {
     #[mh(code = 0x1053, hasher = Ripemd160)]
     Ripemd160,
 }
+
+impl SupportedHashes {
+    /// Returns the length (in bytes) of this hash function's (untruncated) digest.
+    pub const fn digest_size(self) -> u32 {
+        match self {
+            SupportedHashes::Sha2_256 => 32,
+            SupportedHashes::Blake2b256 => 32,
+            SupportedHashes::Blake2b512 => 64,
+            SupportedHashes::Keccak256 => 32,
+            SupportedHashes::Ripemd160 => 20,
+        }
+    }
+
+    /// Returns the hash function in `allowed` with the given multihash code, if the given length is
+    /// its full digest size.
+    pub(crate) fn find_cid_hash(
+        allowed: &[SupportedHashes],
+        code: u64,
+        len: u32,
+    ) -> Option<SupportedHashes> {
+        allowed
+            .iter()
+            .copied()
+            .find(|h| u64::from(*h) == code && h.digest_size() == len)
+    }
+
+    /// Returns a new streaming hasher for this hash function.
+    pub(crate) fn streaming_hasher(self) -> Box<dyn Hasher> {
+        match self {
+            SupportedHashes::Sha2_256 => Box::<Sha2_256>::default(),
+            SupportedHashes::Blake2b256 => Box::<Blake2b256>::default(),
+            SupportedHashes::Blake2b512 => Box::<Blake2b512>::default(),
+            SupportedHashes::Keccak256 => Box::<Keccak256>::default(),
+            SupportedHashes::Ripemd160 => Box::<Ripemd160>::default(),
+        }
+    }
+}
+
+/// A handle to an in-progress hash computation started with
+/// [`CryptoOps::hash_init`](super::CryptoOps::hash_init).
+#[derive(Debug, PartialEq, Eq)]
+pub struct HashHandle(pub(crate) u32);
@@ -1,6 +1,6 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
-pub use blocks::{Block, BlockId, BlockRegistry, BlockStat};
+pub use blocks::{Block, BlockId, BlockRegistry, BlockStat, MAX_BLOCK_HANDLES};
 use cid::Cid;
 use fvm_shared::address::Address;
 use fvm_shared::clock::ChainEpoch;
@@ -11,14 +11,14 @@ use fvm_shared::crypto::signature::{
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ExitCode;
 use fvm_shared::piece::PieceInfo;
-use fvm_shared::randomness::{Randomness, RANDOMNESS_LENGTH};
+use fvm_shared::randomness::{RandSource, Randomness, RANDOMNESS_LENGTH};
 use fvm_shared::sector::{
     AggregateSealVerifyProofAndInfos, RegisteredSealProof, ReplicaUpdateInfo, SealVerifyInfo,
-    WindowPoStVerifyInfo,
+    SectorNumber, WindowPoStVerifyInfo,
 };
 use fvm_shared::sys::out::network::NetworkContext;
-use fvm_shared::sys::out::vm::MessageContext;
-use fvm_shared::sys::SendFlags;
+use fvm_shared::sys::out::vm::{MessageContext, MessageContextV2};
+use fvm_shared::sys::{ReadOnlyReason, SendFlags};
 use fvm_shared::{ActorID, MethodNum};
 
 mod hash;
@@ -30,7 +30,7 @@ pub(crate) mod error;
 
 pub use error::{ClassifyResult, Context, ExecutionError, Result, SyscallError};
 use fvm_shared::event::StampedEvent;
-pub use hash::SupportedHashes;
+pub use hash::{HashHandle, SupportedHashes};
 use multihash::MultihashGeneric;
 
 use crate::call_manager::CallManager;
@@ -44,6 +44,28 @@ pub struct SendResult {
     pub exit_code: ExitCode,
 }
 
+/// The result of verifying an aggregated batch of prove-commits with per-sector diagnostics. See
+/// [`default::DefaultKernel::verify_aggregate_seals_with_diagnostics`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AggregateVerifyResult {
+    /// Whether the aggregate proof verified.
+    pub overall: bool,
+    /// Per-sector diagnostics, in the same order as the aggregate's infos.
+    pub sector_results: Vec<SectorVerifyResult>,
+}
+
+/// Diagnostics for a single sector in an aggregated batch of prove-commits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SectorVerifyResult {
+    pub sector_number: SectorNumber,
+    /// Whether the sector's public inputs are valid. An aggregate proof can't be split into
+    /// per-sector proofs, so a bad proof over otherwise valid inputs can't be attributed to any
+    /// single sector.
+    pub valid: bool,
+    /// Why the sector's public inputs are invalid, if they are.
+    pub error: Option<String>,
+}
+
 /// The "kernel" implements the FVM interface as presented to the actors. It:
 ///
 /// - Manages the Actor's state.
@@ -57,6 +79,7 @@ pub trait Kernel:
     + CircSupplyOps
     + CryptoOps
     + DebugOps
+    + EncodingOps
     + EventOps
     + GasOps
     + MessageOps
@@ -122,19 +145,42 @@ pub trait NetworkOps {
 
     /// The CID of the tipset at the specified epoch.
     fn tipset_cid(&self, epoch: ChainEpoch) -> Result<Cid>;
+
+    /// The expected delay between epochs, in seconds.
+    fn block_delay_seconds(&self) -> Result<u32>;
+
+    /// Returns true if the current epoch is at or past the `target` epoch.
+    fn epoch_elapsed(&self, target: ChainEpoch) -> Result<bool>;
 }
 
 /// Accessors to query attributes of the incoming message.
 pub trait MessageOps {
     /// Message information.
     fn msg_context(&self) -> Result<MessageContext>;
+
+    /// Extended message information, including the origin's delegated address. Only available
+    /// from network version 22.
+    fn msg_context_v2(&self) -> Result<MessageContextV2>;
+
+    /// Looks up the "delegated" (f4) address of the immediate caller, if any.
+    fn caller_delegated_address(&self) -> Result<Option<Address>>;
+
+    /// Reports why the current invocation is (or isn't) read-only. Only available when actor
+    /// debugging is enabled.
+    fn read_only_reason(&self) -> Result<ReadOnlyReason>;
+
+    /// Returns the origin account's current sequence number. Unlike the message context's nonce
+    /// (the sequence the message was sent with), this reflects the on-chain state at execution
+    /// time, i.e., after the origin's sequence has been incremented for this message.
+    fn origin_account_nonce(&self) -> Result<u64>;
 }
 
 /// The IPLD subset of the kernel.
 pub trait IpldBlockOps {
     /// Open a block.
     ///
-    /// This method will fail if the requested block isn't reachable.
+    /// This method will fail if the requested block isn't reachable, and fail fatally if it's
+    /// [quarantined](crate::machine::MachineContext::quarantined_cids).
     fn block_open(&mut self, cid: &Cid) -> Result<(BlockId, BlockStat)>;
 
     /// Create a new block.
@@ -144,6 +190,28 @@ pub trait IpldBlockOps {
     /// (SPEC_AUDIT).
     fn block_create(&mut self, codec: u64, data: &[u8]) -> Result<BlockId>;
 
+    /// Create a new block equal to an existing block, but with `data` overwritten at `offset`.
+    /// This is charged as reading the source block and creating the new one, without the actor
+    /// having to copy the source block in and out of its memory.
+    ///
+    /// This method will fail if the block handle is invalid, the patch extends past the end of the
+    /// source block, or for any of the reasons [`block_create`](Self::block_create) would fail on
+    /// the patched data.
+    fn block_patch(&mut self, id: BlockId, offset: u32, data: &[u8]) -> Result<BlockId>;
+
+    /// Open a block if it's reachable and present in the blockstore, otherwise create it from the
+    /// passed codec and data (like [`block_create`](Self::block_create)). Returns the block's ID,
+    /// and whether it was created.
+    ///
+    /// When creating, this method will fail if the codec or data don't match the CID, which must
+    /// be a 32-byte blake2b CID.
+    fn block_open_or_create(
+        &mut self,
+        cid: &Cid,
+        codec: u64,
+        data: &[u8],
+    ) -> Result<(BlockId, bool)>;
+
     /// Computes a CID for a block.
     ///
     /// This is the only way to add a new block to the "reachable" set.
@@ -151,15 +219,108 @@ pub trait IpldBlockOps {
     /// This method will fail if the block handle is invalid.
     fn block_link(&mut self, id: BlockId, hash_fun: u64, hash_len: u32) -> Result<Cid>;
 
+    /// Computes the CID [`block_link`](Self::block_link) would return for a block, without
+    /// writing the block to the blockstore or making it reachable. The block handle remains valid.
+    ///
+    /// This method will fail if the block handle is invalid.
+    fn block_cid(&mut self, id: BlockId, hash_fun: u64, hash_len: u32) -> Result<Cid>;
+
     /// Read data from a block.
     ///
     /// This method will fail if the block handle is invalid.
     fn block_read(&self, id: BlockId, offset: u32, buf: &mut [u8]) -> Result<i32>;
 
+    /// Reads a CBOR block containing a single unsigned integer (e.g., a counter or an epoch).
+    ///
+    /// This method will fail with `IllegalArgument` if the block isn't a CBOR block containing
+    /// just an unsigned integer that fits in a u64, or if the block handle is invalid.
+    fn block_read_u64(&self, id: BlockId) -> Result<u64>;
+
     /// Returns the blocks codec & size.
     ///
     /// This method will fail if the block handle is invalid.
     fn block_stat(&self, id: BlockId) -> Result<BlockStat>;
+
+    /// Marks a block as retained, exempting it from eviction until it's linked with
+    /// [`IpldBlockOps::block_link`]. Retained blocks still count towards the gas budget.
+    ///
+    /// This method will fail if the block handle is invalid.
+    fn block_retain(&mut self, id: BlockId) -> Result<()>;
+
+    /// Closes a block, freeing its handle for reuse. Only the most recently created or opened
+    /// block may be closed.
+    ///
+    /// This method will fail with `InvalidHandle` if the block handle is invalid, and with
+    /// `IllegalArgument` if it isn't the most recent one.
+    fn block_close(&mut self, id: BlockId) -> Result<()>;
+
+    /// Decompresses zlib-compressed data, returning at most `max_output` bytes.
+    ///
+    /// This method will fail with `LimitExceeded` if the decompressed data would exceed
+    /// `max_output` bytes, and with `IllegalArgument` if the compressed data is malformed.
+    fn inflate(&self, compressed: &[u8], max_output: u32) -> Result<Vec<u8>>;
+
+    /// Returns true if the codec is a DAG codec (one whose blocks may contain links), and false
+    /// if it's a leaf codec such as raw.
+    fn is_dag_codec(&self, codec: u64) -> Result<bool>;
+
+    /// Returns true if the two blocks have the same codec and contents.
+    ///
+    /// This method will fail if either block handle is invalid.
+    fn block_eq(&self, a: BlockId, b: BlockId) -> Result<bool>;
+
+    /// Returns true if hashing the block with the CID's multihash function yields the CID (i.e.,
+    /// the block's codec and hash match). Only 32-byte blake2b CIDs are supported.
+    ///
+    /// This method will fail if the block handle is invalid or the CID is unsupported.
+    fn block_hash_eq(&self, id: BlockId, expected: &Cid) -> Result<bool>;
+
+    /// Returns true if the block links to the target CID. The block is only scanned (and charged
+    /// for) up to the first matching link. Inline (identity-hashed) CIDs are searched for links,
+    /// but never match themselves.
+    ///
+    /// This method will fail if the block handle is invalid.
+    fn block_contains_link(&self, id: BlockId, target: &Cid) -> Result<bool>;
+
+    /// Returns true if `root` can be reached by following links from `candidate_child` within
+    /// `max_depth` links (or is `candidate_child`), i.e., if making `root` link to
+    /// `candidate_child` would form a logical cycle. Gas is charged for every block visited.
+    ///
+    /// This method will fail if `candidate_child` isn't reachable.
+    fn would_create_cycle(&self, root: Cid, candidate_child: Cid, max_depth: u32) -> Result<bool>;
+
+    /// Returns true if the block satisfies the constraints placed on return values (maximum block
+    /// size and allowed codecs). The caller's block registry capacity is checked when the value is
+    /// actually returned, and can't be checked ahead of time.
+    ///
+    /// This method will fail if the block handle is invalid.
+    fn would_fit_as_return(&self, id: BlockId) -> Result<bool>;
+
+    /// Raises the maximum number of blocks this invocation may hold by `extra` (up to
+    /// [`MAX_BLOCK_HANDLES`]), charging gas proportional to the increase. Returns the previous
+    /// limit, to be passed to [`restore_block_handles`](Self::restore_block_handles). Prefer
+    /// [`with_expanded_block_handles`](Self::with_expanded_block_handles).
+    fn expand_block_handles(&mut self, extra: u32) -> Result<u32>;
+
+    /// Restores a block limit returned by [`expand_block_handles`](Self::expand_block_handles).
+    /// This can only lower the limit. Blocks created while the limit was raised remain valid.
+    fn restore_block_handles(&mut self, limit: u32);
+
+    /// Runs `f` with the maximum number of blocks temporarily raised by `extra`. See
+    /// [`expand_block_handles`](Self::expand_block_handles).
+    fn with_expanded_block_handles<R>(
+        &mut self,
+        extra: u32,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> Result<R>
+    where
+        Self: Sized,
+    {
+        let limit = self.expand_block_handles(extra)?;
+        let ret = f(self);
+        self.restore_block_handles(limit);
+        Ok(ret)
+    }
 }
 
 /// Actor state access and manipulation.
@@ -170,14 +331,32 @@ pub trait SelfOps: IpldBlockOps {
 
     /// Update the state-root.
     ///
-    /// This method will fail if the new state-root isn't reachable.
+    /// This method will fail if the new state-root isn't reachable, and fail fatally if it's
+    /// [quarantined](crate::machine::MachineContext::quarantined_cids).
     fn set_root(&mut self, root: Cid) -> Result<()>;
 
     /// The balance of the receiver.
     fn current_balance(&self) -> Result<TokenAmount>;
 
+    /// The balance of the receiver before the value received by this invocation was credited
+    /// (i.e., the current balance minus the value received, saturating at zero).
+    fn balance_before_receipt(&self) -> Result<TokenAmount>;
+
+    /// Returns true if this appears to be the executing actor's first invocation, i.e., if its
+    /// `sequence` is zero and its state root is still the empty array CID it was created with.
+    ///
+    /// This is a heuristic: an actor that resets its state root to the empty array (and never
+    /// bumped its sequence) is indistinguishable from a newly created actor. It also only holds
+    /// until the actor first updates its state root, so it should be checked before doing so.
+    /// Returns false if the actor has been deleted.
+    fn is_first_invocation(&self) -> Result<bool>;
+
     /// Deletes the executing actor from the state tree, burning any remaining balance if requested.
     fn self_destruct(&mut self, burn_unspent: bool) -> Result<()>;
+
+    /// Returns the executing actor's "delegated" (f4) address, if any. Returns `None` if the actor
+    /// has been deleted.
+    fn self_delegated_address(&self) -> Result<Option<Address>>;
 }
 
 /// Actors operations whose scope of action is actors other than the calling
@@ -191,6 +370,19 @@ pub trait ActorOps {
     /// Looks up the "delegated" (f4) address of the specified actor, if any.
     fn lookup_delegated_address(&self, actor_id: ActorID) -> Result<Option<Address>>;
 
+    /// Looks up the namespace (the ID of the managing actor) of the specified actor's delegated
+    /// (f4) address, or `None` if it has no delegated address. Charges as
+    /// [`lookup_delegated_address`](Self::lookup_delegated_address).
+    ///
+    /// This method will fail with `NotFound` if the actor doesn't exist.
+    fn delegated_namespace(&self, id: ActorID) -> Result<Option<ActorID>>;
+
+    /// Constructs the delegated (f4) address with the specified namespace (the ID of the managing
+    /// actor) and subaddress.
+    ///
+    /// This method will fail with `IllegalArgument` if the subaddress is too long.
+    fn make_delegated_address(&self, namespace: ActorID, subaddress: &[u8]) -> Result<Address>;
+
     /// Look up the code CID of an actor.
     fn get_actor_code_cid(&self, id: ActorID) -> Result<Cid>;
 
@@ -200,6 +392,11 @@ pub trait ActorOps {
     /// Always an ActorExec address.
     fn next_actor_address(&self) -> Result<Address>;
 
+    /// Returns the number of actors created so far in this message. Actors can combine this with
+    /// the message's origin and nonce to compute the addresses of actors yet to be created (see
+    /// [`derive_actor_address`](fvm_shared::address::derive_actor_address)).
+    fn actors_created(&self) -> Result<u64>;
+
     /// Creates an actor with given `code_cid`, `actor_id`, `delegated_address` (if specified),
     /// and an empty state.
     fn create_actor(
@@ -216,11 +413,42 @@ pub trait ActorOps {
     /// Returns the actor's "type" (if builitin) or 0 (if not).
     fn get_builtin_actor_type(&self, code_cid: &Cid) -> Result<u32>;
 
+    /// Resolves an address and returns the builtin actor type of the target actor, or `None` if
+    /// the actor doesn't exist or isn't a builtin actor. Charges as
+    /// [`resolve_address`](Self::resolve_address),
+    /// [`get_actor_code_cid`](Self::get_actor_code_cid), and
+    /// [`get_builtin_actor_type`](Self::get_builtin_actor_type) combined.
+    ///
+    /// This method will fail with `NotFound` if the address can't be resolved.
+    fn address_builtin_type(&self, address: &Address) -> Result<Option<u32>>;
+
     /// Returns the CodeCID for the supplied built-in actor type.
     fn get_code_cid_for_type(&self, typ: u32) -> Result<Cid>;
 
     /// Returns the balance associated with an actor id
     fn balance_of(&self, actor_id: ActorID) -> Result<TokenAmount>;
+
+    /// Returns the balances associated with the specified actor ids, in order. Unlike
+    /// [`ActorOps::balance_of`], non-existent actors have a balance of zero.
+    fn balances_of(&self, actor_ids: &[ActorID]) -> Result<Vec<TokenAmount>>;
+
+    /// Returns true if no actor exists with the specified ID, i.e., if an actor could be created
+    /// with this ID.
+    fn actor_id_available(&self, actor_id: ActorID) -> Result<bool>;
+
+    /// Returns true if a plain value transfer (method 0) to the specified actor is expected to
+    /// succeed, based on the actor's builtin type: i.e., if the actor exists, isn't a builtin actor
+    /// without a fund-receiving entrypoint (the system, init, and EAM actors), and isn't a
+    /// singleton that rejects value transfers. Non-builtin actors are assumed to accept funds.
+    ///
+    /// This is best-effort: the only way to be certain is to attempt the transfer.
+    fn can_receive_funds(&self, id: ActorID) -> Result<bool>;
+
+    /// Returns every code CID deployed in the state-tree along with the number of actors using it,
+    /// ordered by CID. Gas is charged per actor scanned.
+    ///
+    /// This method is restricted to the system actor, and will fail with `Forbidden` otherwise.
+    fn enumerate_deployed_code_cids(&self) -> Result<Vec<(Cid, u64)>>;
 }
 
 /// Operations to query the circulating supply.
@@ -248,8 +476,41 @@ pub trait GasOps {
     /// `name` provides information about gas charging point.
     fn charge_gas(&self, name: &str, compute: Gas) -> Result<GasTimer>;
 
+    /// Charges `cost_per_epoch` for each of `epochs` epochs, for operations whose cost is
+    /// proportional to their duration (see [`PriceList::on_storage_duration`]).
+    ///
+    /// This method will fail with `IllegalArgument` if `epochs` is negative.
+    fn charge_gas_per_epoch(
+        &self,
+        name: &str,
+        cost_per_epoch: Gas,
+        epochs: i64,
+    ) -> Result<GasTimer>;
+
     /// Returns the currently active gas price list.
     fn price_list(&self) -> &PriceList;
+
+    /// Estimates the total fee (base fee plus premium) of a message with the given gas limit and
+    /// premium, at the current base fee. See [`PriceList::estimate_message_cost`].
+    fn estimate_fee(&self, gas_limit: Gas, gas_premium: &TokenAmount) -> Result<TokenAmount>;
+
+    /// Returns true if gas charges are being traced (e.g., during calibration runs), in which case
+    /// gas charges are timed and recorded.
+    fn gas_tracing_enabled(&self) -> bool;
+
+    /// Creates a sub-meter with the given budget, returning a handle to it. Handles are scoped to
+    /// the current invocation: they start at 0 for each invocation and can't be used to refer to
+    /// sub-meters created by other invocations.
+    fn create_submeter(&mut self, budget: Gas) -> Result<u32>;
+
+    /// Charges gas to a sub-meter created by this invocation, and to the global gas budget.
+    ///
+    /// Exhausting the sub-meter fails with `LimitExceeded` without charging anything. Unknown
+    /// handles fail with `NotFound`.
+    fn charge_to(&self, meter: u32, compute: Gas) -> Result<GasTimer>;
+
+    /// Returns the gas remaining in a sub-meter created by this invocation.
+    fn submeter_remaining(&self, meter: u32) -> Result<Gas>;
 }
 
 /// Cryptographic primitives provided by the kernel.
@@ -270,12 +531,32 @@ pub trait CryptoOps {
         signature: &[u8; SECP_SIG_LEN],
     ) -> Result<[u8; SECP_PUB_LEN]>;
 
+    /// Recovers the public keys of the signers of a batch of (message hash, signature) pairs, in
+    /// parallel. Gas is charged for every item up-front, and the whole batch fails with
+    /// `IllegalArgument` if recovery fails for any item.
+    fn recover_secp_public_keys(
+        &self,
+        items: &[([u8; SECP_SIG_MESSAGE_HASH_SIZE], [u8; SECP_SIG_LEN])],
+    ) -> Result<Vec<[u8; SECP_PUB_LEN]>>;
+
     /// Hashes input `data_in` using with the specified hash function, writing the output to
     /// `digest_out`, returning the size of the digest written to `digest_out`. If `digest_out` is
     /// to small to fit the entire digest, it will be truncated. If too large, the leftover space
     /// will not be overwritten.
     fn hash(&self, code: u64, data: &[u8]) -> Result<MultihashGeneric<64>>;
 
+    /// Starts hashing data in chunks with the specified hash function, for data too large to hash
+    /// with [`hash`](Self::hash) at once. Feed the data with [`hash_update`](Self::hash_update),
+    /// then call [`hash_finalize`](Self::hash_finalize) to get the digest.
+    fn hash_init(&mut self, code: u64) -> Result<HashHandle>;
+
+    /// Feeds the next chunk of data into an in-progress hash computation. Each chunk is charged
+    /// like hashing it with [`hash`](Self::hash).
+    fn hash_update(&mut self, handle: &HashHandle, data: &[u8]) -> Result<()>;
+
+    /// Completes an in-progress hash computation, returning the digest of all data fed into it.
+    fn hash_finalize(&mut self, handle: HashHandle) -> Result<MultihashGeneric<64>>;
+
     /// Computes an unsealed sector CID (CommD) from its constituent piece CIDs (CommPs) and sizes.
     fn compute_unsealed_sector_cid(
         &self,
@@ -316,6 +597,17 @@ pub trait CryptoOps {
     /// Verify replica update verifies a snap deal: an upgrade from a CC sector to a sector with
     /// deals.
     fn verify_replica_update(&self, replica: &ReplicaUpdateInfo) -> Result<bool>;
+
+    /// Verifies a Groth16 proof over BLS12-381.
+    ///
+    /// - `vk` is the serialized verifying key.
+    /// - `proof` is the serialized proof.
+    /// - `public_inputs` is the concatenation of the 32-byte little-endian encoded public inputs
+    ///   (scalars).
+    ///
+    /// Returns an error if any of the inputs are malformed, or if the number of public inputs
+    /// doesn't match the verifying key.
+    fn groth16_verify(&self, vk: &[u8], proof: &[u8], public_inputs: &[u8]) -> Result<bool>;
 }
 
 /// Randomness queries.
@@ -333,6 +625,19 @@ pub trait RandomnessOps {
     /// This randomness is not tied to any fork of the chain, and is unbiasable.
     fn get_randomness_from_beacon(&self, rand_epoch: ChainEpoch)
         -> Result<[u8; RANDOMNESS_LENGTH]>;
+
+    /// Draws randomness from the given source at the given epoch, mixes in the supplied entropy,
+    /// and maps the result to a uniformly distributed integer in `[0, bound)` (without modulo
+    /// bias).
+    ///
+    /// Returns an error if `bound` is zero.
+    fn random_in_range(
+        &self,
+        source: RandSource,
+        rand_epoch: ChainEpoch,
+        entropy: &[u8],
+        bound: u64,
+    ) -> Result<u64>;
 }
 
 /// Debugging APIs.
@@ -343,9 +648,26 @@ pub trait DebugOps {
     /// Returns whether debug mode is enabled.
     fn debug_enabled(&self) -> bool;
 
-    /// Store an artifact.
-    /// Returns error on malformed name, returns Ok and logs the error on system/os errors.
-    fn store_artifact(&self, name: &str, data: &[u8]) -> Result<()>;
+    /// Store an artifact, returning the name under which it was stored. If an artifact with the
+    /// same name has already been stored by this invocation, a sequence number is appended to the
+    /// name so earlier artifacts aren't overwritten.
+    ///
+    /// Returns error on malformed name or if the configured artifact limits are exceeded, returns
+    /// Ok and logs the error on system/os errors.
+    fn store_artifact(&mut self, name: &str, data: &[u8]) -> Result<String>;
+
+    /// Returns the actors currently being invoked along with the invoked methods, from the
+    /// top-level message down to (and including) the caller. Free of charge.
+    ///
+    /// Fails with `Forbidden` unless debugging is enabled.
+    fn call_stack(&self) -> Result<Vec<(ActorID, MethodNum)>>;
+}
+
+/// Operations for validating encoded data on behalf of actors.
+pub trait EncodingOps {
+    /// Returns whether the given bytes are valid UTF-8. Charged proportionally to the length of the
+    /// input.
+    fn is_valid_utf8(&self, bytes: &[u8]) -> Result<bool>;
 }
 
 /// Track and limit memory expansion.
@@ -357,6 +679,43 @@ pub trait LimiterOps {
     type Limiter: MemoryLimiter;
     /// Give access to the limiter of the underlying call manager.
     fn limiter_mut(&mut self) -> &mut Self::Limiter;
+
+    /// Returns the memory currently used by the call stack, and the maximum it may use (both in
+    /// bytes).
+    ///
+    /// This method will fail with `IllegalOperation` before network version 22.
+    fn memory_usage(&self) -> Result<(u64, u64)>;
+}
+
+/// An event in the raw form accepted by [`EventOps::emit_event`]: the entry headers, followed by
+/// the concatenated keys and values.
+#[derive(Clone, Copy)]
+pub struct RawEvent<'a> {
+    pub headers: &'a [fvm_shared::sys::EventEntry],
+    pub keys: &'a [u8],
+    pub values: &'a [u8],
+}
+
+impl RawEvent<'_> {
+    /// Returns the size of the raw event, in bytes.
+    pub fn size(&self) -> usize {
+        std::mem::size_of_val(self.headers) + self.keys.len() + self.values.len()
+    }
+}
+
+impl PartialEq for RawEvent<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        // The headers are packed, so we copy the fields out instead of comparing references.
+        let header = |h: &fvm_shared::sys::EventEntry| (h.flags, h.codec, h.key_len, h.val_len);
+        self.headers.len() == other.headers.len()
+            && self.keys == other.keys
+            && self.values == other.values
+            && self
+                .headers
+                .iter()
+                .zip(other.headers)
+                .all(|(a, b)| header(a) == header(b))
+    }
 }
 
 /// Eventing APIs.
@@ -368,4 +727,33 @@ pub trait EventOps {
         raw_key: &[u8],
         raw_val: &[u8],
     ) -> Result<()>;
+
+    /// Returns the gas [`emit_event`](EventOps::emit_event) would charge for the given event,
+    /// without emitting it. The event isn't validated.
+    fn event_gas_cost(
+        &self,
+        event_headers: &[fvm_shared::sys::EventEntry],
+        raw_key: &[u8],
+        raw_val: &[u8],
+    ) -> Result<Gas>;
+
+    /// Returns how many more events the current message may emit (see
+    /// [`NetworkConfig::max_events_per_message`](crate::machine::NetworkConfig::max_events_per_message)),
+    /// or `u32::MAX` if there's no limit.
+    fn events_remaining(&self) -> Result<u32>;
+
+    /// Computes the root of the events AMT (as committed in the message receipt) over the events
+    /// emitted so far in the current message. Events emitted by calls that have since been reverted
+    /// are excluded, but the remaining events may still be discarded if a caller later aborts.
+    ///
+    /// Returns `None` if no events have been emitted.
+    fn events_root_so_far(&self) -> Result<Option<Cid>>;
+
+    /// Emits each of the events (as [`emit_event`](EventOps::emit_event) would), skipping events
+    /// identical to the previously emitted one, so runs of duplicates collapse into a single
+    /// event. Returns the number of events actually emitted.
+    ///
+    /// Only emitted events are charged for, plus the cost of comparing each event to the previous
+    /// one. Events emitted before an error remain emitted.
+    fn emit_events_dedup(&mut self, events: &[RawEvent<'_>]) -> Result<u32>;
 }
@@ -20,6 +20,7 @@ pub mod machine;
 pub mod syscalls;
 
 pub mod gas;
+pub mod state_reader;
 pub mod state_tree;
 
 mod blockstore;
@@ -38,6 +39,9 @@ pub mod init_actor;
 #[cfg(feature = "testing")]
 pub mod system_actor;
 
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
 mod eam_actor;
 mod history_map;
 mod ipld;
@@ -0,0 +1,157 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Wall-clock benchmarking of individual kernel operations, for gas calibration.
+
+use std::time::Duration;
+
+use fvm_shared::address::Address;
+use num_traits::Zero;
+
+use super::Machine;
+use crate::call_manager::CallManager;
+use crate::engine::Engine;
+use crate::kernel::{BlockRegistry, Result};
+use crate::syscall_error;
+use crate::system_actor::SYSTEM_ACTOR_ID;
+use crate::Kernel;
+
+/// Wall-clock latencies of a benchmarked operation across all iterations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchmarkResult {
+    /// The number of iterations measured.
+    pub iterations: u32,
+    /// The fastest iteration.
+    pub min: Duration,
+    /// The slowest iteration.
+    pub max: Duration,
+    /// The mean across all iterations.
+    pub mean: Duration,
+    /// The 99th percentile.
+    pub p99: Duration,
+}
+
+impl BenchmarkResult {
+    /// Summarizes the given (non-empty) set of samples.
+    fn from_samples(mut samples: Vec<Duration>) -> Self {
+        samples.sort_unstable();
+        let n = samples.len();
+        let total: Duration = samples.iter().sum();
+        // Nearest-rank percentile.
+        let p99_rank = (n * 99 + 99) / 100;
+        Self {
+            iterations: n as u32,
+            min: samples[0],
+            max: samples[n - 1],
+            mean: total / n as u32,
+            p99: samples[p99_rank - 1],
+        }
+    }
+}
+
+/// Runs `f` against a fresh kernel `iterations` times, timing each iteration. The kernel executes
+/// as the system actor without metering gas, so that gas never interrupts the measurement.
+pub(super) fn benchmark_syscall<M, K, F>(
+    machine: M,
+    engine: Engine,
+    iterations: u32,
+    mut f: F,
+) -> (Result<BenchmarkResult>, M)
+where
+    M: Machine,
+    K: Kernel,
+    K::CallManager: CallManager<Machine = M>,
+    F: FnMut(&mut K) -> Result<()>,
+{
+    if iterations == 0 {
+        return (
+            Err(syscall_error!(IllegalArgument; "at least one iteration is required").into()),
+            machine,
+        );
+    }
+
+    let system = Address::new_id(SYSTEM_ACTOR_ID);
+    let call_manager = K::CallManager::new(
+        machine,
+        engine,
+        0,
+        SYSTEM_ACTOR_ID,
+        system,
+        None,
+        Some(SYSTEM_ACTOR_ID),
+        system,
+        0,
+        Zero::zero(),
+        false,
+    );
+    call_manager.gas_tracker().disable_metering();
+    let mut kernel = K::new(
+        call_manager,
+        BlockRegistry::default(),
+        SYSTEM_ACTOR_ID,
+        SYSTEM_ACTOR_ID,
+        0,
+        Zero::zero(),
+        false,
+    );
+
+    let mut samples = Vec::with_capacity(iterations as usize);
+    let mut result = Ok(());
+    for _ in 0..iterations {
+        let start = minstant::Instant::now();
+        result = f(&mut kernel);
+        samples.push(start.elapsed());
+        if result.is_err() {
+            break;
+        }
+    }
+
+    let (call_manager, _) = kernel.into_inner();
+    let (_, machine) = call_manager.finish();
+    (
+        result.map(|_| BenchmarkResult::from_samples(samples)),
+        machine,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::call_manager::DefaultCallManager;
+    use crate::engine::EnginePool;
+    use crate::gas::Gas;
+    use crate::kernel::GasOps;
+    use crate::testing::MockMachine;
+    use crate::DefaultKernel;
+
+    type TestKernel = DefaultKernel<DefaultCallManager<MockMachine>>;
+
+    #[test]
+    fn gas_is_not_metered() {
+        let machine = MockMachine::new_stub().unwrap();
+        let engine = EnginePool::new_default((&machine.context().network).into())
+            .unwrap()
+            .acquire();
+
+        let (res, _) = machine.benchmark_syscall::<TestKernel, _>(engine, 10, |k| {
+            k.charge_gas("test", Gas::new(1 << 40))?;
+            assert_eq!(k.gas_used(), Gas::zero());
+            Ok(())
+        });
+        assert_eq!(res.unwrap().iterations, 10);
+    }
+
+    #[test]
+    fn summarize_samples() {
+        let samples: Vec<_> = (1..=200).rev().map(Duration::from_micros).collect();
+        let res = BenchmarkResult::from_samples(samples);
+        assert_eq!(res.iterations, 200);
+        assert_eq!(res.min, Duration::from_micros(1));
+        assert_eq!(res.max, Duration::from_micros(200));
+        assert_eq!(res.mean, Duration::from_nanos(100_500));
+        assert_eq!(res.p99, Duration::from_micros(198));
+
+        let res = BenchmarkResult::from_samples(vec![Duration::from_micros(7)]);
+        assert_eq!(res.min, res.p99);
+        assert_eq!(res.mean, Duration::from_micros(7));
+    }
+}
@@ -1,8 +1,9 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
-use cid::Cid;
 
-use super::{Machine, MachineContext, Manifest};
+use fvm_shared::clock::ChainEpoch;
+
+use super::{FlushStats, Machine, MachineContext, Manifest};
 use crate::kernel::Result;
 use crate::state_tree::StateTree;
 
@@ -44,7 +45,7 @@ impl<M: Machine> Machine for Box<M> {
     }
 
     #[inline(always)]
-    fn flush(&mut self) -> Result<Cid> {
+    fn flush(&mut self) -> Result<FlushStats> {
         (**self).flush()
     }
 
@@ -62,4 +63,9 @@ impl<M: Machine> Machine for Box<M> {
     fn new_limiter(&self) -> Self::Limiter {
         (**self).new_limiter()
     }
+
+    #[inline(always)]
+    fn advance_epoch(&mut self, epoch: ChainEpoch, timestamp: u64) -> Result<()> {
+        (**self).advance_epoch(epoch, timestamp)
+    }
 }
@@ -4,15 +4,17 @@ use std::ops::RangeInclusive;
 
 use anyhow::{anyhow, Context as _};
 use cid::Cid;
-use fvm_ipld_blockstore::{Block, Blockstore, Buffered};
+use fvm_ipld_blockstore::{Block, Blockstore};
 use fvm_ipld_encoding::{to_vec, CborStore, DAG_CBOR};
+use fvm_shared::clock::ChainEpoch;
 use fvm_shared::version::NetworkVersion;
 use log::debug;
 use multihash::Code::Blake2b256;
 
-use super::{Machine, MachineContext};
+use super::{FlushStats, Machine, MachineContext};
 use crate::blockstore::BufferedBlockstore;
 use crate::externs::Externs;
+use crate::gas::GasTimer;
 use crate::kernel::{ClassifyResult, Result};
 use crate::machine::limiter::DefaultMemoryLimiter;
 use crate::machine::Manifest;
@@ -74,24 +76,11 @@ where
             ));
         }
 
-        // Sanity check that the blockstore contains the supplied state root.
-        if !blockstore
-            .has(&context.initial_state_root)
-            .context("failed to load initial state-root")?
-        {
-            return Err(anyhow!(
-                "blockstore doesn't have the initial state-root {}",
-                &context.initial_state_root
-            ));
-        }
-
-        put_empty_blocks(&blockstore)?;
+        context
+            .validate()
+            .map_err(|e| anyhow!("invalid machine context: {e}"))?;
 
-        // Create a new state tree from the supplied root.
-        let state_tree = {
-            let bstore = BufferedBlockstore::new(blockstore);
-            StateTree::new_from_root(bstore, &context.initial_state_root)?
-        };
+        let state_tree = load_state_tree(context, blockstore)?;
 
         // Load the built-in actors manifest.
         let (builtin_actors_cid, manifest_version) = match context.builtin_actors_override {
@@ -113,7 +102,7 @@ where
         // 16 bytes is random _enough_
         let randomness: [u8; 16] = rand::random();
 
-        Ok(DefaultMachine {
+        let machine = DefaultMachine {
             context: context.clone(),
             externs,
             state_tree,
@@ -123,7 +112,13 @@ where
                 context.epoch,
                 cid::multibase::encode(cid::multibase::Base::Base32Lower, randomness)
             ),
-        })
+        };
+
+        if context.verify_manifest {
+            machine.verify_actor_manifest_integrity()?;
+        }
+
+        Ok(machine)
     }
 }
 
@@ -160,15 +155,41 @@ where
         &mut self.state_tree
     }
 
-    /// Flushes the state-tree and returns the new root CID.
+    /// Flushes the state-tree and returns the new root CID, along with statistics describing the
+    /// flush.
     ///
     /// This method also flushes all new blocks (reachable from this new root CID) from the write
     /// buffer into the underlying blockstore (the blockstore with which the machine was
-    /// constructed).
-    fn flush(&mut self) -> Result<Cid> {
-        let root = self.state_tree_mut().flush()?;
-        self.blockstore().flush(&root).or_fatal()?;
-        Ok(root)
+    /// constructed). Blocks written to the buffer while flushing the state-tree are reported as
+    /// state-tree blocks, all others as user data.
+    fn flush(&mut self) -> Result<FlushStats> {
+        // Don't read the clock at all when restricted to deterministic operations.
+        let timed = !self.context().deterministic_only;
+
+        let start = timed.then(GasTimer::start);
+        self.blockstore().start_recording();
+        let root = self.state_tree_mut().flush();
+        let state_tree_keys = self.blockstore().stop_recording();
+        let mut stats = FlushStats {
+            state_tree_time: start.map(|s| s.elapsed()).unwrap_or_default(),
+            ..FlushStats::new(root?)
+        };
+
+        let start = timed.then(GasTimer::start);
+        let root = stats.root;
+        self.blockstore()
+            .flush_with(&root, |k, size| {
+                if state_tree_keys.contains(k) {
+                    stats.state_tree_blocks += 1;
+                    stats.state_tree_bytes += size as u64;
+                } else {
+                    stats.user_blocks += 1;
+                    stats.user_bytes += size as u64;
+                }
+            })
+            .or_fatal()?;
+        stats.write_time = start.map(|s| s.elapsed()).unwrap_or_default();
+        Ok(stats)
     }
 
     fn into_store(self) -> Self::Blockstore {
@@ -182,10 +203,48 @@ where
     fn new_limiter(&self) -> Self::Limiter {
         DefaultMemoryLimiter::for_network(&self.context().network)
     }
+
+    fn advance_epoch(&mut self, epoch: ChainEpoch, timestamp: u64) -> Result<()> {
+        self.context.advance_epoch(epoch, timestamp)?;
+        // The machine ID is prefixed with the epoch.
+        let suffix = self.id.split_once('-').map_or("", |(_, suffix)| suffix);
+        self.id = format!("{epoch}-{suffix}");
+        Ok(())
+    }
 }
 
 // Helper method that puts certain "empty" types in the blockstore.
 // These types are privileged by some parts of the system (eg. as the default actor state).
+/// Loads the machine's initial state tree from the blockstore, refusing state trees that can't be
+/// interpreted under the configured network version.
+fn load_state_tree<B: Blockstore>(
+    context: &MachineContext,
+    blockstore: B,
+) -> anyhow::Result<StateTree<BufferedBlockstore<B>>> {
+    // Sanity check that the blockstore contains the supplied state root.
+    if !blockstore
+        .has(&context.initial_state_root)
+        .context("failed to load initial state-root")?
+    {
+        return Err(anyhow!(
+            "blockstore doesn't have the initial state-root {}",
+            &context.initial_state_root
+        ));
+    }
+
+    put_empty_blocks(&blockstore)?;
+
+    // Create a new state tree from the supplied root.
+    let state_tree = {
+        let bstore =
+            BufferedBlockstore::new(blockstore).with_cid_hashes(context.network.cid_hashes.clone());
+        StateTree::new_from_root(bstore, &context.initial_state_root)?
+    };
+    context.check_state_version(state_tree.version())?;
+
+    Ok(state_tree)
+}
+
 fn put_empty_blocks<B: Blockstore>(blockstore: B) -> anyhow::Result<()> {
     let empty_arr_cid = blockstore.put(Blake2b256, &EMPTY_ARRAY_BLOCK)?;
 
@@ -196,3 +255,47 @@ fn put_empty_blocks<B: Blockstore>(blockstore: B) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use fvm_shared::state::StateTreeVersion;
+    use fvm_shared::version::NetworkVersion;
+
+    use super::load_state_tree;
+    use crate::machine::{NetworkConfig, NetworkVersionTooOld};
+    use crate::state_tree::StateTree;
+
+    #[test]
+    fn rejects_newer_state_tree() {
+        let store = MemoryBlockstore::new();
+        let mut tree = StateTree::new(&store, StateTreeVersion::V5).unwrap();
+        let root = tree.flush().unwrap();
+
+        // A v5 state tree loads under nv18. Only nv21 configs can be built (and nv21 reads every
+        // state tree version), so lower the version afterwards to exercise the check...
+        let mut config = NetworkConfig::new(NetworkVersion::V21);
+        config.network_version = NetworkVersion::V18;
+        let ctx = config.for_epoch(0, 0, root);
+        let loaded = load_state_tree(&ctx, &store).unwrap();
+        assert_eq!(loaded.version(), StateTreeVersion::V5);
+
+        // ...but is refused under nv17.
+        config.network_version = NetworkVersion::V17;
+        let ctx = config.for_epoch(0, 0, root);
+        let err = load_state_tree(&ctx, &store).err().unwrap();
+        assert_eq!(
+            err.downcast_ref::<NetworkVersionTooOld>(),
+            Some(&NetworkVersionTooOld {
+                state_version: StateTreeVersion::V5,
+                required: NetworkVersion::V18,
+                configured: NetworkVersion::V17,
+            })
+        );
+
+        // ...unless explicitly allowed.
+        config.allow_state_version_downgrade();
+        let ctx = config.for_epoch(0, 0, root);
+        load_state_tree(&ctx, &store).unwrap();
+    }
+}
@@ -0,0 +1,87 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use anyhow::anyhow;
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::ActorID;
+
+use crate::kernel::{ExecutionError, Result};
+use crate::state_tree::{ActorState, StateTree};
+
+/// Creates an actor directly in the state tree rooted at `state_root`, updating the root. This
+/// bypasses the machine entirely (no gas, no constructor, no address registration), so it's
+/// intended for building genesis state before a machine can be constructed.
+///
+/// Fails if an actor with the specified ID already exists.
+pub fn create_actor_external(
+    blockstore: &impl Blockstore,
+    state_root: &mut Cid,
+    actor_id: ActorID,
+    code_cid: Cid,
+    state: Cid,
+    balance: TokenAmount,
+) -> Result<()> {
+    let mut state_tree = StateTree::new_from_root(blockstore, state_root)?;
+    if state_tree.get_actor(actor_id)?.is_some() {
+        return Err(ExecutionError::Fatal(anyhow!(
+            "actor {actor_id} already exists"
+        )));
+    }
+    state_tree.set_actor(actor_id, ActorState::new(code_cid, state, balance, 0, None));
+    *state_root = state_tree.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_ipld_blockstore::MemoryBlockstore;
+    use fvm_shared::econ::TokenAmount;
+    use fvm_shared::state::StateTreeVersion;
+
+    use super::create_actor_external;
+    use crate::state_tree::StateTree;
+    use crate::EMPTY_ARR_CID;
+
+    #[test]
+    fn create_actor() {
+        let bs = MemoryBlockstore::default();
+        let mut root = StateTree::new(&bs, StateTreeVersion::V5)
+            .unwrap()
+            .flush()
+            .unwrap();
+
+        let balance = TokenAmount::from_atto(42);
+        create_actor_external(
+            &bs,
+            &mut root,
+            100,
+            *EMPTY_ARR_CID,
+            *EMPTY_ARR_CID,
+            balance.clone(),
+        )
+        .unwrap();
+
+        let actor = StateTree::new_from_root(&bs, &root)
+            .unwrap()
+            .get_actor(100)
+            .unwrap()
+            .expect("actor should exist");
+        assert_eq!(actor.code, *EMPTY_ARR_CID);
+        assert_eq!(actor.balance, balance);
+        assert_eq!(actor.sequence, 0);
+
+        // Actors can't be overwritten.
+        let before = root;
+        assert!(create_actor_external(
+            &bs,
+            &mut root,
+            100,
+            *EMPTY_ARR_CID,
+            *EMPTY_ARR_CID,
+            balance
+        )
+        .is_err());
+        assert_eq!(root, before);
+    }
+}
@@ -14,6 +14,12 @@ pub trait MemoryLimiter: Sized {
     /// In the future, this will likely be extended to include IPLD blocks, actor code, etc.
     fn memory_used(&self) -> usize;
 
+    /// Get the maximum memory the callstack may use (in bytes), as measured by
+    /// [`memory_used`](Self::memory_used). Defaults to `usize::MAX` (no limit).
+    fn memory_limit(&self) -> usize {
+        usize::MAX
+    }
+
     /// Returns `true` if growing by `delta` bytes is allowed. Implement this memory to track and
     /// limit memory usage.
     fn grow_memory(&mut self, delta: usize) -> bool;
@@ -64,6 +70,10 @@ impl MemoryLimiter for DefaultMemoryLimiter {
         self.curr_memory_bytes
     }
 
+    fn memory_limit(&self) -> usize {
+        self.max_memory_bytes
+    }
+
     fn grow_memory(&mut self, bytes: usize) -> bool {
         let total_desired = self.curr_memory_bytes.saturating_add(bytes);
 
@@ -97,6 +107,7 @@ mod tests {
     #[test]
     fn basics() {
         let mut limits = DefaultMemoryLimiter::new(4);
+        assert_eq!(limits.memory_limit(), 4);
         assert!(limits.grow_memory(3));
         assert!(limits.grow_memory(1)); // Ok, just at memory limit.
         assert!(!limits.grow_memory(1)); // Fail, over memory limit.
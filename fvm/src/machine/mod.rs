@@ -1,17 +1,23 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
 use cid::Cid;
 use derive_more::{Deref, DerefMut};
 use fvm_ipld_blockstore::Blockstore;
 use fvm_shared::clock::ChainEpoch;
 use fvm_shared::econ::TokenAmount;
+use fvm_shared::state::StateTreeVersion;
 use fvm_shared::version::NetworkVersion;
-use fvm_shared::ActorID;
+use fvm_shared::{ActorID, MethodNum, METHOD_SEND};
 use num_traits::Zero;
 
+use crate::executor::{CancellationToken, EventFilter};
 use crate::externs::Externs;
-use crate::gas::{price_list_by_network_version, PriceList};
-use crate::kernel::Result;
+use crate::gas::{price_list_by_network_version, GasTimer, PriceList};
+use crate::kernel::{ClassifyResult, ExecutionError, Result, SupportedHashes, MAX_BLOCK_HANDLES};
 use crate::state_tree::StateTree;
 
 mod default;
@@ -19,17 +25,74 @@ mod default;
 pub use default::DefaultMachine;
 use fvm_shared::chainid::ChainID;
 
+mod genesis;
 pub mod limiter;
 mod manifest;
 
+pub use genesis::create_actor_external;
 pub use manifest::Manifest;
 
 use self::limiter::MemoryLimiter;
 
 mod boxed;
 
+#[cfg(feature = "benchmarking")]
+mod benchmark;
+
+#[cfg(feature = "benchmarking")]
+pub use benchmark::BenchmarkResult;
+
+/// Statistics describing a [`Machine::flush`]. Flushing happens outside of any message and isn't
+/// charged gas, so these are reported in wall-clock time and blocks/bytes written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlushStats {
+    /// The new state root.
+    pub root: Cid,
+    /// Time spent flushing the state-tree and computing the new state root. Always zero when the
+    /// machine is restricted to deterministic operations.
+    pub state_tree_time: Duration,
+    /// Time spent writing reachable blocks from the write buffer to the underlying blockstore.
+    /// Always zero when the machine is restricted to deterministic operations.
+    pub write_time: Duration,
+    /// Number of state-tree blocks (HAMT nodes and the state root) written.
+    pub state_tree_blocks: u64,
+    /// Total size of the state-tree blocks written.
+    pub state_tree_bytes: u64,
+    /// Number of user data blocks (actor state, etc.) written.
+    pub user_blocks: u64,
+    /// Total size of the user data blocks written.
+    pub user_bytes: u64,
+}
+
+impl FlushStats {
+    /// Returns empty statistics for a flush that produced the given root.
+    pub fn new(root: Cid) -> Self {
+        FlushStats {
+            root,
+            state_tree_time: Duration::ZERO,
+            write_time: Duration::ZERO,
+            state_tree_blocks: 0,
+            state_tree_bytes: 0,
+            user_blocks: 0,
+            user_bytes: 0,
+        }
+    }
+
+    /// Returns the total number of blocks written.
+    pub fn total_blocks(&self) -> u64 {
+        self.state_tree_blocks + self.user_blocks
+    }
+
+    /// Returns the total number of bytes written.
+    pub fn total_bytes(&self) -> u64 {
+        self.state_tree_bytes + self.user_bytes
+    }
+}
+
 pub const REWARD_ACTOR_ID: ActorID = 2;
 
+pub const CRON_ACTOR_ID: ActorID = 3;
+
 /// Distinguished Account actor that is the destination of all burnt funds.
 pub const BURNT_FUNDS_ACTOR_ID: ActorID = 99;
 
@@ -68,9 +131,15 @@ pub trait Machine: 'static {
     /// Returns a mutable reference to the state tree.
     fn state_tree_mut(&mut self) -> &mut StateTree<Self::Blockstore>;
 
-    /// Flushes the state-tree and returns the new root CID.
-    fn flush(&mut self) -> Result<Cid> {
-        self.state_tree_mut().flush()
+    /// Flushes the state-tree and returns the new root CID, along with statistics describing the
+    /// flush.
+    fn flush(&mut self) -> Result<FlushStats> {
+        let start = (!self.context().deterministic_only).then(GasTimer::start);
+        let root = self.state_tree_mut().flush()?;
+        Ok(FlushStats {
+            state_tree_time: start.map(|s| s.elapsed()).unwrap_or_default(),
+            ..FlushStats::new(root)
+        })
     }
 
     /// Consumes the machine and returns the owned blockstore.
@@ -81,6 +150,54 @@ pub trait Machine: 'static {
 
     /// Creates a new limiter to track the resources of a message execution.
     fn new_limiter(&self) -> Self::Limiter;
+
+    /// Advances the machine to a later epoch (with the given timestamp), e.g., to simulate a
+    /// sequence of epochs without reconstructing the machine. The state tree carries over as-is.
+    ///
+    /// Fails if the epoch doesn't increase or the timestamp decreases. See
+    /// [`MachineContext::advance_epoch`].
+    fn advance_epoch(&mut self, epoch: ChainEpoch, timestamp: u64) -> Result<()>;
+
+    /// Checks that the code of every builtin actor in the manifest is present in the blockstore,
+    /// returning a fatal error listing the missing code CIDs otherwise.
+    fn verify_actor_manifest_integrity(&self) -> Result<()> {
+        let mut missing = Vec::new();
+        for code in self.builtin_actors().builtin_actor_codes() {
+            if !self.blockstore().has(code).or_fatal()? {
+                missing.push(code.to_string());
+            }
+        }
+        if !missing.is_empty() {
+            missing.sort();
+            return Err(ExecutionError::Fatal(anyhow::anyhow!(
+                "builtin actor code missing from the blockstore: {}",
+                missing.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    /// Measures the wall-clock latency of a kernel operation (usually a single syscall) for gas
+    /// calibration. `f` is invoked `iterations` times on a kernel of type `K` running as the system
+    /// actor, without metering gas so that gas doesn't interfere with the measurements. Consumes
+    /// the machine and returns it along with the result.
+    ///
+    /// Benchmarking stops at the first error returned by `f`.
+    #[cfg(feature = "benchmarking")]
+    fn benchmark_syscall<K, F>(
+        self,
+        engine: crate::engine::Engine,
+        iterations: u32,
+        f: F,
+    ) -> (Result<BenchmarkResult>, Self)
+    where
+        Self: Sized,
+        K: crate::Kernel,
+        K::CallManager: crate::call_manager::CallManager<Machine = Self>,
+        F: FnMut(&mut K) -> Result<()>,
+    {
+        benchmark::benchmark_syscall(self, engine, iterations, f)
+    }
 }
 
 /// Network-level settings. Except when testing locally, changing any of these likely requires a
@@ -95,11 +212,28 @@ pub struct NetworkConfig {
     /// DEFAULT: 0 (Invalid)
     pub chain_id: ChainID,
 
+    /// The expected delay between epochs (the block time), in seconds.
+    ///
+    /// DEFAULT: 30 (mainnet)
+    pub block_delay_seconds: u32,
+
     /// The maximum call depth.
     ///
     /// DEFAULT: 1024
     pub max_call_depth: u32,
 
+    /// The maximum call depth at which actors may still emit events. Actors in deeper call frames
+    /// will fail to emit events with `LimitExceeded`.
+    ///
+    /// DEFAULT: 1024 (the maximum call depth)
+    pub max_event_emit_depth: u32,
+
+    /// The maximum number of events a message may emit (not counting events discarded when the
+    /// calls that emitted them were reverted), or `None` for no limit.
+    ///
+    /// DEFAULT: `None`
+    pub max_events_per_message: Option<u32>,
+
     /// The maximum number of elements on wasm stack
     /// DEFAULT: 64Ki (512KiB of u64 elements)
     pub max_wasm_stack: u32,
@@ -121,6 +255,14 @@ pub struct NetworkConfig {
     /// DEFAULT: 1MiB
     pub max_block_size: usize,
 
+    /// The maximum number of blocks a single actor invocation may hold open (including its
+    /// parameters). Actors may temporarily raise this limit, up to
+    /// [`MAX_BLOCK_HANDLES`], with
+    /// [`IpldBlockOps::with_expanded_block_handles`](crate::kernel::IpldBlockOps::with_expanded_block_handles).
+    ///
+    /// DEFAULT: [`MAX_BLOCK_HANDLES`]
+    pub max_block_handles: u32,
+
     /// An override for builtin-actors. If specified, this should be the CID of a builtin-actors
     /// "manifest".
     ///
@@ -132,6 +274,37 @@ pub struct NetworkConfig {
     /// DEFAULT: `false`
     pub actor_debugging: bool,
 
+    /// Allow a [cancellation token](MachineContext::cancellation) to interrupt actors while
+    /// they're running (via wasmtime's epoch interruption). Otherwise, cancellation is only
+    /// noticed at the next syscall. This adds a small overhead to all actor code.
+    ///
+    /// DEFAULT: `false`
+    pub interruptible: bool,
+
+    /// The maximum number of debug artifacts a single actor invocation may store. Only relevant
+    /// when actor debugging is enabled.
+    ///
+    /// DEFAULT: 64
+    pub max_artifacts_per_invocation: u32,
+
+    /// The maximum total size (in bytes) of the debug artifacts stored while executing a single
+    /// message. Only relevant when actor debugging is enabled.
+    ///
+    /// DEFAULT: 256MiB
+    pub max_artifact_bytes_per_message: u64,
+
+    /// The directory under which debug artifacts are written, or `None` to discard them. Only
+    /// relevant when actor debugging is enabled.
+    ///
+    /// DEFAULT: The value of the `FVM_STORE_ARTIFACT_DIR` environment variable, if set.
+    pub artifact_dir: Option<PathBuf>,
+
+    /// The maximum length (in bytes) of a panic message embedded in errors and logs when a panic
+    /// is caught (e.g., while verifying proofs). Longer messages are truncated.
+    ///
+    /// DEFAULT: 512
+    pub max_panic_message_len: usize,
+
     /// The price list.
     ///
     /// DEFAULT: The price-list for the current network version.
@@ -139,6 +312,28 @@ pub struct NetworkConfig {
 
     /// Actor redirects for debug execution
     pub actor_redirect: Vec<(Cid, Cid)>,
+
+    /// Singleton actors that reject plain value transfers (method 0 with non-zero value) from
+    /// network version 22 onwards. These actors have no way to withdraw funds sent to them, so
+    /// such transfers would otherwise burn the funds.
+    ///
+    /// DEFAULT: The system, reward, and cron actors.
+    pub value_rejecting_actors: Vec<ActorID>,
+
+    /// Allow loading state trees newer than the configured network version supports. This will
+    /// likely misinterpret state, so it should only be used to intentionally inspect old/new state.
+    ///
+    /// Currently only nv21 machines can be constructed, and every state tree version (up to v5,
+    /// introduced in nv18) is readable under nv21, so this has no effect yet.
+    ///
+    /// DEFAULT: `false`
+    pub allow_state_version_downgrade: bool,
+
+    /// The hash functions actors may use in the CIDs of the blocks they link. CIDs must use the
+    /// full digest of one of these.
+    ///
+    /// DEFAULT: blake2b-256
+    pub cid_hashes: Vec<SupportedHashes>,
 }
 
 impl NetworkConfig {
@@ -147,15 +342,31 @@ impl NetworkConfig {
         NetworkConfig {
             chain_id: ChainID::from(0u64),
             network_version,
+            block_delay_seconds: 30,
             max_call_depth: 1024,
+            max_event_emit_depth: 1024,
+            max_events_per_message: None,
             max_wasm_stack: 2048,
             max_inst_memory_bytes: 512 * (1 << 20),
             max_memory_bytes: 2 * (1 << 30),
             actor_debugging: false,
+            interruptible: false,
+            max_artifacts_per_invocation: 64,
+            max_artifact_bytes_per_message: 256 * (1 << 20),
+            artifact_dir: std::env::var_os("FVM_STORE_ARTIFACT_DIR").map(PathBuf::from),
+            max_panic_message_len: 512,
             builtin_actors_override: None,
             price_list: price_list_by_network_version(network_version),
             actor_redirect: vec![],
             max_block_size: 1 << 20,
+            max_block_handles: MAX_BLOCK_HANDLES,
+            value_rejecting_actors: vec![
+                crate::system_actor::SYSTEM_ACTOR_ID,
+                REWARD_ACTOR_ID,
+                CRON_ACTOR_ID,
+            ],
+            allow_state_version_downgrade: false,
+            cid_hashes: vec![SupportedHashes::Blake2b256],
         }
     }
 
@@ -166,6 +377,18 @@ impl NetworkConfig {
         self
     }
 
+    /// Allow cancellation to interrupt running actors. See [`NetworkConfig::interruptible`].
+    pub fn enable_interruption(&mut self) -> &mut Self {
+        self.interruptible = true;
+        self
+    }
+
+    /// Write debug artifacts under the given directory. See [`NetworkConfig::artifact_dir`].
+    pub fn store_artifacts_in(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        self.artifact_dir = Some(dir.into());
+        self
+    }
+
     /// Override actors with the specific manifest. This is primarily useful for testing, or
     /// networks prior to NV16 (where the actor's "manifest" isn't specified on-chain).
     pub fn override_actors(&mut self, manifest: Cid) -> &mut Self {
@@ -179,6 +402,66 @@ impl NetworkConfig {
         self
     }
 
+    /// Set the hash functions actors may use in CIDs. See [`NetworkConfig::cid_hashes`].
+    pub fn allow_cid_hashes(&mut self, hashes: Vec<SupportedHashes>) -> &mut Self {
+        self.cid_hashes = hashes;
+        self
+    }
+
+    /// Set the singleton actors that reject plain value transfers. See
+    /// [`NetworkConfig::value_rejecting_actors`].
+    pub fn reject_value_sends_to(&mut self, actors: Vec<ActorID>) -> &mut Self {
+        self.value_rejecting_actors = actors;
+        self
+    }
+
+    /// Allow loading state trees produced by a newer network version. See
+    /// [`NetworkConfig::allow_state_version_downgrade`].
+    pub fn allow_state_version_downgrade(&mut self) -> &mut Self {
+        self.allow_state_version_downgrade = true;
+        self
+    }
+
+    /// Checks that state trees of the given version can be interpreted under the configured
+    /// network version.
+    ///
+    /// Machines can currently only be constructed for nv21, which can read every state tree
+    /// version, so this never rejects a state tree when constructing a machine. It guards against
+    /// future state tree versions, and network versions older than nv21 if they're ever
+    /// supported again.
+    pub fn check_state_version(
+        &self,
+        version: StateTreeVersion,
+    ) -> std::result::Result<(), NetworkVersionTooOld> {
+        let required = version.min_network_version();
+        if self.network_version < required && !self.allow_state_version_downgrade {
+            return Err(NetworkVersionTooOld {
+                state_version: version,
+                required,
+                configured: self.network_version,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns true if a send of `value` to `to` invoking `method` must be rejected because the
+    /// recipient is a value-rejecting singleton. Only plain value transfers (method 0) are
+    /// rejected; any other method is left up to the actor itself.
+    pub fn rejects_value_send(&self, to: ActorID, method: MethodNum, value: &TokenAmount) -> bool {
+        self.network_version >= NetworkVersion::V22
+            && method == METHOD_SEND
+            && !value.is_zero()
+            && self.value_rejecting_actors.contains(&to)
+    }
+
+    /// Returns true if actors deleted during a message are tombstoned for the remainder of that
+    /// message: plain value sends to them fail with
+    /// [`SYS_RECIPIENT_DELETED`](fvm_shared::error::ExitCode::SYS_RECIPIENT_DELETED), and their
+    /// IDs can't be re-bound to new actors.
+    pub fn tombstones_deleted_actors(&self) -> bool {
+        self.network_version >= NetworkVersion::V22
+    }
+
     /// Create a ['MachineContext'] for a given epoch, timestamp, and initial state.
     pub fn for_epoch(
         &self,
@@ -194,6 +477,14 @@ impl NetworkConfig {
             initial_state_root: initial_state,
             circ_supply: fvm_shared::TOTAL_FILECOIN.clone(),
             tracing: false,
+            check_invariants: false,
+            verify_manifest: false,
+            deterministic_only: false,
+            event_filter: None,
+            quarantined_cids: HashSet::new(),
+            allow_quarantine_on_mainnet: false,
+            miner_tip_recipient: None,
+            cancellation: None,
         }
     }
 
@@ -204,6 +495,25 @@ impl NetworkConfig {
     }
 }
 
+/// Returned when constructing a machine over a state tree produced by a newer network version
+/// than the one configured.
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+#[error("state tree version {state_version:?} requires network version {required} or later, but the machine is configured for {configured}")]
+pub struct NetworkVersionTooOld {
+    pub state_version: StateTreeVersion,
+    pub required: NetworkVersion,
+    pub configured: NetworkVersion,
+}
+
+/// Returned (as a fatal error) when an actor opens a quarantined block, or sets one as its state
+/// root. See [`MachineContext::quarantined_cids`].
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+#[error("cid {0} is quarantined")]
+pub struct QuarantinedCid(pub Cid);
+
+/// The chain ID of Filecoin mainnet.
+const MAINNET_CHAIN_ID: u64 = 314;
+
 /// Per-epoch machine context.
 #[derive(Clone, Debug, Deref, DerefMut)]
 pub struct MachineContext {
@@ -240,6 +550,60 @@ pub struct MachineContext {
     /// Whether or not to produce execution traces in the returned result.
     /// Not consensus-critical, but has a performance impact.
     pub tracing: bool,
+
+    /// Whether or not to check token conservation after every message, failing the message
+    /// application if violated. Intended for devnets and tests.
+    ///
+    /// Default: false
+    pub check_invariants: bool,
+
+    /// Whether or not to check that all builtin actor code is present in the blockstore when
+    /// constructing the machine. See [`Machine::verify_actor_manifest_integrity`].
+    ///
+    /// Default: false
+    pub verify_manifest: bool,
+
+    /// Whether or not to avoid consulting the wall clock during execution. When set, gas charges
+    /// in execution traces are timed with a [logical clock](crate::gas::GasClock::Logical), so
+    /// traces are identical between runs of the same message. Intended for conformance testing.
+    ///
+    /// Default: false
+    pub deterministic_only: bool,
+
+    /// Restricts the events returned in [`ApplyRet::events`](crate::executor::ApplyRet::events)
+    /// to those matching the filter. Doesn't affect the events root committed in the receipt.
+    ///
+    /// Default: None (all events are returned)
+    pub event_filter: Option<EventFilter>,
+
+    /// Blocks actors may not open or set as their state root, e.g. because they're known to be
+    /// corrupt. Attempts fail with a fatal [`QuarantinedCid`] error. This changes the outcome of
+    /// messages, so it can't be used on mainnet unless
+    /// [`MachineContext::allow_quarantine_on_mainnet`] is set.
+    ///
+    /// Default: empty
+    pub quarantined_cids: HashSet<Cid>,
+
+    /// Allow [`MachineContext::quarantined_cids`] on mainnet.
+    ///
+    /// Default: false
+    pub allow_quarantine_on_mainnet: bool,
+
+    /// Pays miner tips to this actor instead of the reward actor. Only honored when applying
+    /// [simulated](crate::executor::ApplyKind::Simulated) messages; consensus messages ignore it
+    /// (logging a warning). When tracing, the payout is recorded as a final send from the message
+    /// sender to the recipient.
+    ///
+    /// Default: None
+    pub miner_tip_recipient: Option<ActorID>,
+
+    /// Allows aborting message execution from another thread. Cancelled messages have no effect
+    /// on the state tree and produce a [cancelled](crate::executor::ApplyRet::cancelled) result,
+    /// which isn't a consensus outcome. Running actors are only interrupted if the engine was
+    /// created with [`NetworkConfig::interruptible`] set.
+    ///
+    /// Default: None
+    pub cancellation: Option<CancellationToken>,
 }
 
 impl MachineContext {
@@ -260,4 +624,298 @@ impl MachineContext {
         self.tracing = true;
         self
     }
+
+    /// Enable invariant checks. [`MachineContext::check_invariants`].
+    pub fn enable_invariant_checks(&mut self) -> &mut Self {
+        self.check_invariants = true;
+        self
+    }
+
+    /// Enable builtin actor manifest verification. [`MachineContext::verify_manifest`].
+    pub fn enable_manifest_verification(&mut self) -> &mut Self {
+        self.verify_manifest = true;
+        self
+    }
+
+    /// Enable deterministic-only execution. [`MachineContext::deterministic_only`].
+    pub fn enable_deterministic_only(&mut self) -> &mut Self {
+        self.deterministic_only = true;
+        self
+    }
+
+    /// Sets [`MachineContext::event_filter`].
+    pub fn set_event_filter(&mut self, filter: EventFilter) -> &mut Self {
+        self.event_filter = Some(filter);
+        self
+    }
+
+    /// Sets [`MachineContext::quarantined_cids`].
+    pub fn quarantine_cids(&mut self, cids: HashSet<Cid>) -> &mut Self {
+        self.quarantined_cids = cids;
+        self
+    }
+
+    /// Sets [`MachineContext::miner_tip_recipient`].
+    pub fn set_miner_tip_recipient(&mut self, recipient: ActorID) -> &mut Self {
+        self.miner_tip_recipient = Some(recipient);
+        self
+    }
+
+    /// Sets [`MachineContext::cancellation`].
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) -> &mut Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Moves the context forward to a later epoch and timestamp. Fails if the epoch doesn't
+    /// increase or the timestamp decreases.
+    pub fn advance_epoch(&mut self, epoch: ChainEpoch, timestamp: u64) -> Result<()> {
+        if epoch <= self.epoch {
+            return Err(ExecutionError::Fatal(anyhow::anyhow!(
+                "cannot advance the machine from epoch {} to epoch {epoch}",
+                self.epoch
+            )));
+        }
+        if timestamp < self.timestamp {
+            return Err(ExecutionError::Fatal(anyhow::anyhow!(
+                "cannot move the machine timestamp backwards from {} to {timestamp}",
+                self.timestamp
+            )));
+        }
+        self.epoch = epoch;
+        self.timestamp = timestamp;
+        Ok(())
+    }
+
+    /// Returns an error if the CID is quarantined. See [`MachineContext::quarantined_cids`].
+    pub fn check_quarantine(&self, cid: &Cid) -> Result<()> {
+        if self.quarantined_cids.contains(cid) {
+            return Err(ExecutionError::Fatal(QuarantinedCid(*cid).into()));
+        }
+        Ok(())
+    }
+
+    /// Checks that the context is self-consistent. This is called when constructing a machine so
+    /// that configuration bugs surface at startup instead of during message execution.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |msg: String| Err(ExecutionError::Fatal(anyhow::anyhow!(msg)));
+
+        if self.epoch < 0 {
+            return invalid(format!("machine epoch is negative: {}", self.epoch));
+        }
+        if self.base_fee.is_negative() {
+            return invalid(format!("base fee is negative: {}", self.base_fee));
+        }
+        // The base fee is handed to actors as a u128 (see `NetworkContext`).
+        if fvm_shared::sys::TokenAmount::try_from(&self.base_fee).is_err() {
+            return invalid(format!("base fee exceeds u128 limit: {}", self.base_fee));
+        }
+        if self.circ_supply.is_negative() {
+            return invalid(format!(
+                "circulating supply is negative: {}",
+                self.circ_supply
+            ));
+        }
+        // The circulating supply is provided by the client and only used for informational
+        // purposes by actors, so an implausible value isn't fatal.
+        if self.circ_supply > *fvm_shared::TOTAL_FILECOIN {
+            log::warn!(
+                "circulating supply exceeds total supply: {}",
+                self.circ_supply
+            );
+        }
+        if self.max_call_depth == 0 {
+            return invalid("max call depth must be non-zero".into());
+        }
+        if self.max_block_handles == 0 || self.max_block_handles > MAX_BLOCK_HANDLES {
+            return invalid(format!(
+                "max block handles must be between 1 and {}: {}",
+                MAX_BLOCK_HANDLES, self.max_block_handles
+            ));
+        }
+        if !self.quarantined_cids.is_empty()
+            && u64::from(self.chain_id) == MAINNET_CHAIN_ID
+            && !self.allow_quarantine_on_mainnet
+        {
+            return invalid("cids may not be quarantined on mainnet".into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
+    use fvm_shared::econ::TokenAmount;
+    use fvm_shared::state::StateTreeVersion;
+    use fvm_shared::version::NetworkVersion;
+
+    use super::{
+        Machine, Manifest, NetworkConfig, NetworkVersionTooOld, CRON_ACTOR_ID, REWARD_ACTOR_ID,
+    };
+    use crate::init_actor::INIT_ACTOR_ID;
+    use crate::state_tree::StateTree;
+    use crate::system_actor::SYSTEM_ACTOR_ID;
+    use crate::testing::MockMachine;
+    use crate::EMPTY_ARR_CID;
+
+    #[test]
+    fn validate_context() {
+        let config = NetworkConfig::new(NetworkVersion::V21);
+
+        let ctx = config.for_epoch(10, 0, *EMPTY_ARR_CID);
+        ctx.validate().expect("default context should be valid");
+
+        let ctx = config.for_epoch(-1, 0, *EMPTY_ARR_CID);
+        assert!(ctx.validate().unwrap_err().is_fatal());
+
+        let mut ctx = config.for_epoch(10, 0, *EMPTY_ARR_CID);
+        ctx.set_base_fee(TokenAmount::from_atto(-1));
+        assert!(ctx.validate().is_err());
+
+        let mut ctx = config.for_epoch(10, 0, *EMPTY_ARR_CID);
+        ctx.set_circulating_supply(TokenAmount::from_atto(-1));
+        assert!(ctx.validate().is_err());
+
+        let mut ctx = config.for_epoch(10, 0, *EMPTY_ARR_CID);
+        ctx.set_circulating_supply(&*fvm_shared::TOTAL_FILECOIN + TokenAmount::from_atto(1));
+        ctx.validate()
+            .expect("an implausible circulating supply should only warn");
+    }
+
+    #[test]
+    fn advance_epoch() {
+        let config = NetworkConfig::new(NetworkVersion::V21);
+        let mut ctx = config.for_epoch(10, 100, *EMPTY_ARR_CID);
+
+        ctx.advance_epoch(11, 130).unwrap();
+        assert_eq!((ctx.epoch, ctx.timestamp), (11, 130));
+
+        // Null rounds may be skipped.
+        ctx.advance_epoch(15, 250).unwrap();
+        assert_eq!((ctx.epoch, ctx.timestamp), (15, 250));
+
+        // The epoch must increase, and the timestamp must not decrease.
+        assert!(ctx.advance_epoch(15, 280).unwrap_err().is_fatal());
+        assert!(ctx.advance_epoch(14, 280).unwrap_err().is_fatal());
+        assert!(ctx.advance_epoch(16, 249).unwrap_err().is_fatal());
+        assert_eq!((ctx.epoch, ctx.timestamp), (15, 250));
+    }
+
+    #[test]
+    fn quarantine_on_mainnet() {
+        let mut config = NetworkConfig::new(NetworkVersion::V21);
+        config.chain_id(314.into());
+
+        let mut ctx = config.for_epoch(10, 0, *EMPTY_ARR_CID);
+        ctx.validate().expect("mainnet context should be valid");
+        ctx.quarantine_cids([*EMPTY_ARR_CID].into());
+        assert!(ctx.validate().is_err());
+        ctx.allow_quarantine_on_mainnet = true;
+        ctx.validate()
+            .expect("quarantine should be allowed with the override");
+
+        config.chain_id(314159.into());
+        let mut ctx = config.for_epoch(10, 0, *EMPTY_ARR_CID);
+        ctx.quarantine_cids([*EMPTY_ARR_CID].into());
+        ctx.validate()
+            .expect("quarantine should be allowed off mainnet");
+    }
+
+    #[test]
+    fn value_sends_to_singletons() {
+        let mut config = NetworkConfig::new(NetworkVersion::V21);
+        config.network_version = NetworkVersion::V22;
+        let value = TokenAmount::from_atto(1);
+
+        // Blocked singletons.
+        for id in [SYSTEM_ACTOR_ID, REWARD_ACTOR_ID, CRON_ACTOR_ID] {
+            assert!(config.rejects_value_send(id, 0, &value));
+            // The actor decides for non-zero methods.
+            assert!(!config.rejects_value_send(id, 2, &value));
+            // Zero-value sends are always fine.
+            assert!(!config.rejects_value_send(id, 0, &TokenAmount::from_atto(0)));
+        }
+
+        // Other actors are unaffected.
+        assert!(!config.rejects_value_send(INIT_ACTOR_ID, 0, &value));
+        assert!(!config.rejects_value_send(1000, 0, &value));
+
+        // The set is configurable.
+        config.reject_value_sends_to(vec![1000]);
+        assert!(config.rejects_value_send(1000, 0, &value));
+        assert!(!config.rejects_value_send(REWARD_ACTOR_ID, 0, &value));
+
+        // Older network versions accept the funds.
+        config.network_version = NetworkVersion::V21;
+        assert!(!config.rejects_value_send(1000, 0, &value));
+    }
+
+    #[test]
+    fn manifest_integrity() {
+        let machine = MockMachine::new_stub().unwrap();
+        let err = machine.verify_actor_manifest_integrity().unwrap_err();
+        assert!(err.is_fatal());
+
+        // Add all but one of the actors.
+        let (_, last) = Manifest::DUMMY_CODES.last().unwrap();
+        for (name, code) in Manifest::DUMMY_CODES {
+            if code != last {
+                machine
+                    .blockstore()
+                    .put_keyed(code, name.as_bytes())
+                    .unwrap();
+            }
+        }
+        let err = machine.verify_actor_manifest_integrity().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("builtin actor code missing from the blockstore: {last}")
+        );
+
+        machine
+            .blockstore()
+            .put_keyed(last, b"placeholder")
+            .unwrap();
+        machine.verify_actor_manifest_integrity().unwrap();
+    }
+
+    #[test]
+    fn state_version_downgrade() {
+        // Produce a state tree under the current network version.
+        let store = MemoryBlockstore::new();
+        let mut tree = StateTree::new(&store, StateTreeVersion::V5).unwrap();
+        let root = tree.flush().unwrap();
+        let version = StateTree::new_from_root(&store, &root).unwrap().version();
+        assert_eq!(version, StateTreeVersion::V5);
+
+        // Only nv21 configs can be built, so lower the version afterwards.
+        let mut config = NetworkConfig::new(NetworkVersion::V21);
+        config.network_version = NetworkVersion::V18;
+        config.check_state_version(version).unwrap();
+
+        // It can't be opened under the previous network version...
+        config.network_version = NetworkVersion::V17;
+        assert_eq!(
+            config.check_state_version(version),
+            Err(NetworkVersionTooOld {
+                state_version: StateTreeVersion::V5,
+                required: NetworkVersion::V18,
+                configured: NetworkVersion::V17,
+            })
+        );
+
+        // ...unless explicitly allowed.
+        config.allow_state_version_downgrade();
+        config.check_state_version(version).unwrap();
+    }
+
+    #[test]
+    fn deterministic_flush_is_untimed() {
+        let mut machine = MockMachine::new_stub().unwrap();
+        machine.ctx.deterministic_only = true;
+        let stats = machine.flush().unwrap();
+        assert_eq!(stats.state_tree_time, std::time::Duration::ZERO);
+        assert_eq!(stats.write_time, std::time::Duration::ZERO);
+    }
 }
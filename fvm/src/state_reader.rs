@@ -0,0 +1,81 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use anyhow::anyhow;
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+use fvm_shared::address::Address;
+use fvm_shared::ActorID;
+
+use crate::kernel::{ClassifyResult, Result};
+use crate::state_tree::{ActorState, StateTree};
+
+/// A read-only view over the state at a given state root, for answering state queries (actor
+/// lookups, address resolution, etc.) without constructing a machine or executor, and without
+/// charging gas.
+///
+/// Construction is free: nothing is loaded until the first query, and each query only loads the
+/// parts of the state-tree it needs. Queries don't share any mutable state, so a `StateReader` may
+/// be shared between threads (as long as the blockstore can be) and queried concurrently.
+#[derive(Debug, Clone)]
+pub struct StateReader<BS> {
+    store: BS,
+    root: Cid,
+}
+
+impl<BS> StateReader<BS>
+where
+    BS: Blockstore,
+{
+    /// Creates a new state reader over the given state root.
+    pub fn new(store: BS, root: Cid) -> Self {
+        Self { store, root }
+    }
+
+    /// Returns the state root this reader queries.
+    pub fn root(&self) -> &Cid {
+        &self.root
+    }
+
+    /// Returns the underlying blockstore.
+    pub fn store(&self) -> &BS {
+        &self.store
+    }
+
+    /// Looks up an actor by ID, returning `None` if it doesn't exist.
+    pub fn get_actor(&self, id: ActorID) -> Result<Option<ActorState>> {
+        self.state_tree()?.get_actor(id)
+    }
+
+    /// Resolves an address to an actor ID, returning `None` if the address isn't assigned to an
+    /// actor. Like the kernel, ID addresses are returned as-is.
+    pub fn resolve_address(&self, address: &Address) -> Result<Option<ActorID>> {
+        self.state_tree()?.lookup_id(address)
+    }
+
+    /// Looks up the delegated (f4) address of an actor, returning `None` if the actor doesn't exist
+    /// or doesn't have a delegated address.
+    pub fn lookup_delegated_address(&self, id: ActorID) -> Result<Option<Address>> {
+        Ok(self.get_actor(id)?.and_then(|act| act.delegated_address))
+    }
+
+    /// Returns the CID and contents of an actor's state root block, or `None` if the actor doesn't
+    /// exist. Returns a fatal error if the actor exists but its state block is missing.
+    pub fn get_actor_state_block(&self, id: ActorID) -> Result<Option<(Cid, Vec<u8>)>> {
+        let Some(act) = self.get_actor(id)? else {
+            return Ok(None);
+        };
+        let data = self
+            .store
+            .get(&act.state)
+            .or_fatal()?
+            .ok_or_else(|| anyhow!("missing state block {} for actor {}", act.state, id))
+            .or_fatal()?;
+        Ok(Some((act.state, data)))
+    }
+
+    /// Loads the state-tree. This only loads the state root and the root of the actors HAMT; the
+    /// rest is loaded lazily.
+    fn state_tree(&self) -> Result<StateTree<&BS>> {
+        StateTree::new_from_root(&self.store, &self.root)
+    }
+}
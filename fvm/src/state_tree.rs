@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use std::cell::RefCell;
+use std::collections::HashMap;
 
 use anyhow::{anyhow, Context as _};
 use cid::{multihash, Cid};
@@ -10,7 +11,7 @@ use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::tuple::*;
 use fvm_ipld_encoding::CborStore;
 use fvm_ipld_hamt::Hamt;
-use fvm_shared::address::{Address, Payload};
+use fvm_shared::address::{Address, Payload, MAX_ID_PAYLOAD_LEN};
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::state::{StateInfo0, StateRoot, StateTreeVersion};
 use fvm_shared::{ActorID, HAMT_BIT_WIDTH};
@@ -38,6 +39,9 @@ pub struct StateTree<S> {
     /// Snapshot layers. Each layer contains points in the actor/resolve cache histories to which
     /// said caches will be reverted on revert.
     layers: Vec<StateSnapLayer>,
+    /// When balance tracking is enabled, the balance of each modified actor as of just before its
+    /// first modification.
+    balance_snapshot: Option<HashMap<ActorID, Result<TokenAmount>>>,
 }
 
 /// An entry in the actor cache.
@@ -91,6 +95,7 @@ where
             actor_cache: Default::default(),
             resolve_cache: Default::default(),
             layers: Vec::new(),
+            balance_snapshot: None,
         })
     }
 
@@ -140,11 +145,17 @@ where
                     actor_cache: Default::default(),
                     resolve_cache: Default::default(),
                     layers: Vec::new(),
+                    balance_snapshot: None,
                 })
             }
         }
     }
 
+    /// Returns the version of this state tree.
+    pub fn version(&self) -> StateTreeVersion {
+        self.version
+    }
+
     /// Retrieve store reference to modify db.
     pub fn store(&self) -> &S {
         self.hamt.store()
@@ -166,12 +177,13 @@ where
             .borrow_mut()
             .get_or_try_insert_with(id, || {
                 // It's not cached/dirty, so we look it up and cache it.
-                let key = Address::new_id(id).to_bytes();
+                let mut key = [0u8; 1 + MAX_ID_PAYLOAD_LEN];
+                let len = Address::new_id(id).write_bytes(&mut key);
                 Ok(ActorCacheEntry {
                     dirty: false,
                     actor: self
                         .hamt
-                        .get(&key)
+                        .get(&key[..len])
                         .with_context(|| format!("failed to lookup actor {}", id))
                         .or_fatal()?
                         .cloned(),
@@ -182,6 +194,7 @@ where
 
     /// Set actor state with an actor ID.
     pub fn set_actor(&mut self, id: ActorID, actor: ActorState) {
+        self.snapshot_balance(id);
         self.actor_cache.borrow_mut().insert(
             id,
             ActorCacheEntry {
@@ -215,6 +228,7 @@ where
 
     /// Delete actor identified by the supplied ID.
     pub fn delete_actor(&mut self, id: ActorID) {
+        self.snapshot_balance(id);
         // Record that we've deleted the actor.
         self.actor_cache.borrow_mut().insert(
             id,
@@ -313,6 +327,39 @@ where
         !self.layers.is_empty()
     }
 
+    /// Starts recording the balances of actors as they're modified, for checking balance
+    /// invariants. See [`StateTree::stop_balance_tracking`].
+    pub fn start_balance_tracking(&mut self) {
+        self.balance_snapshot = Some(HashMap::new());
+    }
+
+    /// Stops balance tracking, returning the balance of every actor modified since tracking
+    /// started, as of just before it was first modified. Actors that didn't exist have a balance
+    /// of zero.
+    pub fn stop_balance_tracking(&mut self) -> Result<HashMap<ActorID, TokenAmount>> {
+        self.balance_snapshot
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(id, balance)| Ok((id, balance?)))
+            .collect()
+    }
+
+    /// Records the actor's current balance if balance tracking is enabled and it hasn't already
+    /// been recorded. Lookup errors are deferred until tracking is stopped.
+    fn snapshot_balance(&mut self, id: ActorID) {
+        match &self.balance_snapshot {
+            Some(snapshot) if !snapshot.contains_key(&id) => {}
+            _ => return,
+        }
+        let balance = self
+            .get_actor(id)
+            .map(|act| act.map(|act| act.balance).unwrap_or_default());
+        if let Some(snapshot) = &mut self.balance_snapshot {
+            snapshot.insert(id, balance);
+        }
+    }
+
     /// Flush state tree and return Cid root.
     pub fn flush(&mut self) -> Result<Cid> {
         if self.in_transaction() {
@@ -375,6 +422,57 @@ where
         })?;
         Ok(())
     }
+
+    /// Returns the actors that differ between two flushed state trees as `(id, old, new)`, where
+    /// `old` is `None` for created actors and `new` is `None` for deleted actors. Only the parts
+    /// of the trees that changed are loaded.
+    pub fn diff(
+        store: &S,
+        old_root: Cid,
+        new_root: Cid,
+    ) -> Result<impl Iterator<Item = (ActorID, Option<ActorState>, Option<ActorState>)>> {
+        let old = StateTree::new_from_root(store, &old_root)?;
+        let new = StateTree::new_from_root(store, &new_root)?;
+        let changes = old
+            .hamt
+            .diff(&new.hamt)
+            .context("failed to diff state trees")
+            .or_fatal()?;
+        changes
+            .into_iter()
+            .map(|c| {
+                let id = Address::from_bytes(&c.key.0)
+                    .and_then(|addr| addr.id())
+                    .context("invalid actor key in state tree")
+                    .or_fatal()?;
+                Ok((id, c.before, c.after))
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(Vec::into_iter)
+    }
+
+    /// Calls `f` with each actor in the state-tree by ID, including changes that haven't been
+    /// flushed yet (unlike [`StateTree::for_each`]). `f` must not access the state-tree.
+    pub fn for_each_actor<F>(&self, mut f: F) -> anyhow::Result<()>
+    where
+        F: FnMut(ActorID, &ActorState) -> anyhow::Result<()>,
+    {
+        let cache = self.actor_cache.borrow();
+        for (&id, entry) in cache.iter() {
+            if let Some(actor) = &entry.actor {
+                f(id, actor)?;
+            }
+        }
+        self.hamt.for_each(|k, v| {
+            let id = Address::from_bytes(&k.0)?.id()?;
+            // Cached actors (including deleted ones) have already been visited.
+            if cache.get(&id).is_some() {
+                return Ok(());
+            }
+            f(id, v)
+        })?;
+        Ok(())
+    }
 }
 
 /// State of all actor implementations.
@@ -532,6 +630,135 @@ pub mod json {
     }
 }
 
+/// Limits and options for [`measure_actor_state_with`].
+#[cfg(feature = "state_analysis")]
+#[derive(Clone, Debug, Default)]
+pub struct StateSizeOptions {
+    /// Blocks deeper than this (the state root is at depth 0) aren't counted.
+    pub max_depth: u32,
+    /// If set, stop counting once this many bytes have been counted.
+    pub max_bytes: Option<u64>,
+    /// Other actors whose state is walked (with the same limits) to find blocks they share with
+    /// the measured actor.
+    pub shared_with: Vec<ActorID>,
+}
+
+/// The state attributed to an actor by [`measure_actor_state`].
+#[cfg(feature = "state_analysis")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateSizeReport {
+    /// The number of unique blocks reachable from the actor's state root.
+    pub blocks: u64,
+    /// The total size of those blocks, in bytes.
+    pub bytes: u64,
+    /// True if the walk was cut off by the depth or size limit, in which case the counts are lower
+    /// bounds.
+    pub truncated: bool,
+    /// The blocks also reachable from the state of the actors in
+    /// [`StateSizeOptions::shared_with`], along with the actors reaching them.
+    pub shared: std::collections::BTreeMap<Cid, Vec<ActorID>>,
+    /// The total size of the shared blocks, in bytes.
+    pub shared_bytes: u64,
+}
+
+/// Measures the state owned by an actor, by walking its state DAG from [`ActorState::state`] down
+/// to `max_depth` and counting each unique block once. Inline (identity-hashed) blocks aren't
+/// counted, as they take no space of their own.
+#[cfg(feature = "state_analysis")]
+pub fn measure_actor_state<BS: Blockstore>(
+    store: &BS,
+    state_root: &Cid,
+    actor_id: ActorID,
+    max_depth: u32,
+) -> anyhow::Result<StateSizeReport> {
+    measure_actor_state_with(
+        store,
+        state_root,
+        actor_id,
+        &StateSizeOptions {
+            max_depth,
+            ..Default::default()
+        },
+    )
+}
+
+/// Like [`measure_actor_state`], but with a size limit and a sharing report.
+#[cfg(feature = "state_analysis")]
+pub fn measure_actor_state_with<BS: Blockstore>(
+    store: &BS,
+    state_root: &Cid,
+    actor_id: ActorID,
+    options: &StateSizeOptions,
+) -> anyhow::Result<StateSizeReport> {
+    let tree = StateTree::new_from_root(store, state_root)
+        .map_err(|e| anyhow!("failed to load state tree {}: {}", state_root, e))?;
+    let actor_root = |id| -> anyhow::Result<Cid> {
+        Ok(tree
+            .get_actor(id)
+            .map_err(|e| anyhow!("failed to load actor {}: {}", id, e))?
+            .ok_or_else(|| anyhow!("actor {} not found", id))?
+            .state)
+    };
+
+    let (sizes, truncated) = walk_actor_state(store, actor_root(actor_id)?, options)?;
+    let mut report = StateSizeReport {
+        blocks: sizes.len() as u64,
+        bytes: sizes.values().sum(),
+        truncated,
+        ..Default::default()
+    };
+
+    for &other in &options.shared_with {
+        let (other_sizes, _) = walk_actor_state(store, actor_root(other)?, options)?;
+        for (cid, size) in sizes.iter().filter(|(k, _)| other_sizes.contains_key(k)) {
+            let actors = report.shared.entry(*cid).or_default();
+            if actors.is_empty() {
+                report.shared_bytes += size;
+            }
+            actors.push(other);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Walks a state DAG breadth-first, returning the size of each unique block reached within the
+/// limits, and whether the limits cut the walk short.
+#[cfg(feature = "state_analysis")]
+fn walk_actor_state<BS: Blockstore>(
+    store: &BS,
+    root: Cid,
+    options: &StateSizeOptions,
+) -> anyhow::Result<(HashMap<Cid, u64>, bool)> {
+    let mut sizes = HashMap::new();
+    let mut bytes = 0u64;
+    let mut truncated = false;
+    let mut queue = std::collections::VecDeque::from([(root, 0u32)]);
+    while let Some((cid, depth)) = queue.pop_front() {
+        if sizes.contains_key(&cid) || cid.hash().code() == fvm_shared::IDENTITY_HASH {
+            continue;
+        }
+        if options.max_bytes.map_or(false, |max| bytes >= max) {
+            return Ok((sizes, true));
+        }
+
+        let data = store
+            .get(&cid)?
+            .ok_or_else(|| anyhow!("missing state block {}", cid))?;
+        bytes += data.len() as u64;
+        sizes.insert(cid, data.len() as u64);
+
+        let links = crate::ipld::scan_for_links_unmetered(cid.codec(), &data)
+            .map_err(|e| anyhow!("failed to scan state block {}: {}", cid, e))?;
+        if depth >= options.max_depth {
+            truncated |= !links.is_empty();
+            continue;
+        }
+        queue.extend(links.into_iter().map(|k| (k, depth + 1)));
+    }
+    Ok((sizes, truncated))
+}
+
 #[cfg(test)]
 mod tests {
     use cid::multihash::Code::Blake2b256;
@@ -544,6 +771,7 @@ mod tests {
     use fvm_shared::state::StateTreeVersion;
     use fvm_shared::{ActorID, IDENTITY_HASH, IPLD_RAW};
     use lazy_static::lazy_static;
+    use num_traits::Zero;
 
     use crate::init_actor;
     use crate::init_actor::INIT_ACTOR_ID;
@@ -745,6 +973,41 @@ mod tests {
         assert_eq!(tree.get_actor(actor_id).unwrap(), None);
     }
 
+    #[test]
+    fn balance_tracking() {
+        let store = MemoryBlockstore::default();
+        let mut tree = StateTree::new(&store, StateTreeVersion::V5).unwrap();
+        let actor = |balance| {
+            ActorState::new(
+                *DUMMY_ACCOUNT_ACTOR_CODE_ID,
+                *DUMMY_ACCOUNT_ACTOR_CODE_ID,
+                TokenAmount::from_atto(balance),
+                1,
+                None,
+            )
+        };
+
+        tree.set_actor(1, actor(10));
+        tree.set_actor(2, actor(20));
+
+        tree.start_balance_tracking();
+        tree.set_actor(1, actor(5));
+        tree.set_actor(1, actor(3));
+        tree.delete_actor(2);
+        tree.set_actor(3, actor(7));
+        let balances = tree.stop_balance_tracking().unwrap();
+
+        // Only the balances from before the first modification are recorded.
+        assert_eq!(balances.len(), 3);
+        assert_eq!(balances[&1], TokenAmount::from_atto(10));
+        assert_eq!(balances[&2], TokenAmount::from_atto(20));
+        assert_eq!(balances[&3], TokenAmount::zero());
+
+        // Nothing is recorded once tracking stops.
+        tree.set_actor(4, actor(1));
+        assert!(tree.stop_balance_tracking().unwrap().is_empty());
+    }
+
     #[test]
     fn unsupported_versions() {
         let unsupported = vec![
@@ -761,4 +1024,47 @@ mod tests {
             assert!(err.is_fatal());
         }
     }
+
+    #[test]
+    fn diff() {
+        let store = MemoryBlockstore::default();
+        let mut tree = StateTree::new(&store, StateTreeVersion::V5).unwrap();
+        let actor = |balance| {
+            ActorState::new(
+                *DUMMY_ACCOUNT_ACTOR_CODE_ID,
+                *DUMMY_ACCOUNT_ACTOR_CODE_ID,
+                TokenAmount::from_atto(balance),
+                1,
+                None,
+            )
+        };
+        for id in 100..400 {
+            tree.set_actor(id, actor(id));
+        }
+        let old_root = tree.flush().unwrap();
+
+        tree.set_actor(100, actor(1));
+        tree.delete_actor(200);
+        tree.set_actor(500, actor(5));
+        tree.set_actor(300, actor(300));
+        let new_root = tree.flush().unwrap();
+
+        let mut changes: Vec<_> = StateTree::diff(&store, old_root, new_root)
+            .unwrap()
+            .collect();
+        changes.sort_by_key(|(id, _, _)| *id);
+        assert_eq!(
+            changes,
+            vec![
+                (100, Some(actor(100)), Some(actor(1))),
+                (200, Some(actor(200)), None),
+                (500, None, Some(actor(5))),
+            ]
+        );
+
+        assert_eq!(
+            StateTree::diff(&store, new_root, new_root).unwrap().count(),
+            0
+        );
+    }
 }
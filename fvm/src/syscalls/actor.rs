@@ -38,6 +38,49 @@ pub fn lookup_delegated_address(
     }
 }
 
+/// Looks up the namespace of the target actor's delegated (f4) address, if any.
+pub fn delegated_namespace(
+    context: Context<'_, impl Kernel>,
+    actor_id: ActorID,
+) -> Result<sys::out::actor::DelegatedNamespace> {
+    Ok(match context.kernel.delegated_namespace(actor_id)? {
+        Some(namespace) => sys::out::actor::DelegatedNamespace {
+            namespace,
+            delegated: 1,
+        },
+        None => sys::out::actor::DelegatedNamespace {
+            namespace: 0,
+            delegated: 0,
+        },
+    })
+}
+
+/// Constructs the delegated (f4) address with the specified namespace and subaddress, writing it
+/// to the output buffer and returning its length.
+pub fn make_delegated_address(
+    context: Context<'_, impl Kernel>,
+    namespace: ActorID,
+    subaddr_off: u32,
+    subaddr_len: u32,
+    obuf_off: u32, // Address (out)
+    obuf_len: u32,
+) -> Result<u32> {
+    // Check arguments first.
+    context.memory.check_bounds(obuf_off, obuf_len)?;
+
+    let subaddress = context.memory.try_slice(subaddr_off, subaddr_len)?;
+    let address = context
+        .kernel
+        .make_delegated_address(namespace, subaddress)?
+        .to_bytes();
+
+    let obuf = context.memory.try_slice_mut(obuf_off, obuf_len)?;
+    obuf.get_mut(..address.len())
+        .ok_or_else(|| syscall_error!(BufferTooSmall; "address output buffer is too small"))?
+        .copy_from_slice(&address);
+    Ok(address.len() as u32)
+}
+
 pub fn get_actor_code_cid(
     context: Context<'_, impl Kernel>,
     actor_id: u64,
@@ -52,6 +95,11 @@ pub fn get_actor_code_cid(
     context.memory.write_cid(&typ, obuf_off, obuf_len)
 }
 
+/// Returns the number of actors created so far in this message.
+pub fn actors_created(context: Context<'_, impl Kernel>) -> Result<u64> {
+    context.kernel.actors_created()
+}
+
 /// Generates a new actor address, and writes it into the supplied output buffer.
 ///
 /// The output buffer must be at least 21 bytes long, which is the length of a class 2 address
@@ -117,6 +165,15 @@ pub fn get_builtin_actor_type(
     Ok(context.kernel.get_builtin_actor_type(&cid)? as i32)
 }
 
+pub fn address_builtin_type(
+    context: Context<'_, impl Kernel>,
+    addr_off: u32, // Address
+    addr_len: u32,
+) -> Result<i32> {
+    let addr = context.memory.read_address(addr_off, addr_len)?;
+    Ok(context.kernel.address_builtin_type(&addr)?.unwrap_or(0) as i32)
+}
+
 pub fn get_code_cid_for_type(
     context: Context<'_, impl Kernel>,
     typ: i32,
@@ -138,6 +195,30 @@ pub fn install_actor(
     context.kernel.install_actor(typ)
 }
 
+/// Checks whether an actor ID is free.
+///
+/// The return i32 indicates whether the ID is available:
+///  - 0: yes, no actor exists with this ID.
+///  - -1: no, the ID is in use.
+pub fn actor_id_available(context: Context<'_, impl Kernel>, actor_id: u64) -> Result<i32> {
+    context
+        .kernel
+        .actor_id_available(actor_id)
+        .map(|v| if v { 0 } else { -1 })
+}
+
+/// Checks whether an actor is expected to accept a plain value transfer (best-effort).
+///
+/// The return i32 indicates whether the actor can receive funds:
+///  - 0: yes, the actor exists and its builtin type (if any) accepts value transfers.
+///  - -1: no.
+pub fn can_receive_funds(context: Context<'_, impl Kernel>, actor_id: u64) -> Result<i32> {
+    context
+        .kernel
+        .can_receive_funds(actor_id)
+        .map(|v| if v { 0 } else { -1 })
+}
+
 pub fn balance_of(context: Context<'_, impl Kernel>, actor_id: u64) -> Result<sys::TokenAmount> {
     let balance = context.kernel.balance_of(actor_id)?;
     balance
@@ -145,3 +226,43 @@ pub fn balance_of(context: Context<'_, impl Kernel>, actor_id: u64) -> Result<sy
         .context("balance exceeds u128 limit")
         .or_fatal()
 }
+
+/// Looks up the balances of `ids_len` actors, reading the actor IDs (as u64s) from `ids_off` and
+/// writing the balances (as [`sys::TokenAmount`]s) to `obuf_off`, in order.
+pub fn balances_of(
+    context: Context<'_, impl Kernel>,
+    ids_off: u32,
+    ids_len: u32,
+    obuf_off: u32,
+) -> Result<()> {
+    const ID_SIZE: u32 = std::mem::size_of::<ActorID>() as u32;
+    const BALANCE_SIZE: u32 = std::mem::size_of::<sys::TokenAmount>() as u32;
+
+    let (Some(ids_size), Some(obuf_size)) = (
+        ids_len.checked_mul(ID_SIZE),
+        ids_len.checked_mul(BALANCE_SIZE),
+    ) else {
+        return Err(syscall_error!(IllegalArgument; "too many actor ids: {}", ids_len).into());
+    };
+    context.memory.check_bounds(obuf_off, obuf_size)?;
+
+    let ids: Vec<ActorID> = context
+        .memory
+        .try_slice(ids_off, ids_size)?
+        .chunks_exact(ID_SIZE as usize)
+        .map(|id| ActorID::from_le_bytes(id.try_into().expect("8 byte chunk")))
+        .collect();
+
+    let balances = context.kernel.balances_of(&ids)?;
+
+    let obuf = context.memory.try_slice_mut(obuf_off, obuf_size)?;
+    for (out, balance) in obuf.chunks_exact_mut(BALANCE_SIZE as usize).zip(balances) {
+        let balance: sys::TokenAmount = balance
+            .try_into()
+            .context("balance exceeds u128 limit")
+            .or_fatal()?;
+        out[..8].copy_from_slice(&{ balance.lo }.to_le_bytes());
+        out[8..].copy_from_slice(&{ balance.hi }.to_le_bytes());
+    }
+    Ok(())
+}
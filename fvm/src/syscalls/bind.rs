@@ -1,22 +1,26 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use std::marker::PhantomData;
 use std::mem;
 
 use fvm_shared::error::ErrorNumber;
-use fvm_shared::sys::SyscallSafe;
+use fvm_shared::sys::{layout, SyscallSafe};
+use fvm_shared::version::NetworkVersion;
 use wasmtime::{Caller, Linker, WasmTy};
 
 use super::context::Memory;
 use super::error::Abort;
 use super::{charge_for_exec, update_gas_available, Context, InvocationData};
 use crate::call_manager::backtrace;
+use crate::executor::Cancelled;
 use crate::kernel::{self, ExecutionError, Kernel, SyscallError};
+use crate::machine::Machine;
 
 /// Binds syscalls to a linker, converting the returned error according to the syscall convention:
 ///
 /// 1. If the error is a syscall error, it's returned as the first return value.
 /// 2. If the error is a fatal error, a Trap is returned.
-pub(super) trait BindSyscall<Args, Ret, Func> {
+pub(super) trait BindSyscall<K> {
     /// Bind a syscall to the linker.
     ///
     /// 1. The return type will be automatically adjusted to return `Result<u32, Trap>` where
@@ -41,12 +45,149 @@ pub(super) trait BindSyscall<Args, Ret, Func> {
     /// let mut linker = wasmtime::Linker::new(&engine);
     /// linker.bind("my_module", "zero", my_module::zero);
     /// ```
-    fn bind(
+    fn bind<Args, Ret, Func>(
         &mut self,
         module: &'static str,
         name: &'static str,
         syscall: Func,
-    ) -> anyhow::Result<&mut Self>;
+    ) -> anyhow::Result<&mut Self>
+    where
+        Func: Syscall<K, Args, Ret>;
+
+    /// Declares the first network version at which the syscall most recently bound with
+    /// [`BindSyscall::bind`] is part of the consensus ABI. Defaults to [`BASE_NETWORK_VERSION`].
+    ///
+    /// This is informational: it's recorded in the syscall ABI, but syscalls are linked at every
+    /// network version. Syscalls whose behavior must not be available before `nv` have to check
+    /// the network version themselves (in the kernel).
+    fn since(&mut self, _nv: NetworkVersion) -> &mut Self {
+        self
+    }
+
+    /// Declares the error numbers the syscall most recently bound with [`BindSyscall::bind`] may
+    /// return.
+    fn errors(&mut self, _errors: &'static [ErrorNumber]) -> &mut Self {
+        self
+    }
+}
+
+/// The earliest network version supported by this FVM. Syscalls are assumed to be part of the ABI
+/// at this version unless declared otherwise when bound.
+pub const BASE_NETWORK_VERSION: NetworkVersion = NetworkVersion::V21;
+
+impl<K: Kernel> BindSyscall<K> for Linker<InvocationData<K>> {
+    fn bind<Args, Ret, Func>(
+        &mut self,
+        module: &'static str,
+        name: &'static str,
+        syscall: Func,
+    ) -> anyhow::Result<&mut Self>
+    where
+        Func: Syscall<K, Args, Ret>,
+    {
+        syscall.link(self, module, name)?;
+        Ok(self)
+    }
+}
+
+/// The ABI of a syscall as seen by actors, as bound by
+/// [`bind_syscalls`](super::bind_syscalls). Every syscall returns an error number (0 on success)
+/// as a `u32`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyscallSignature {
+    pub module: &'static str,
+    pub name: &'static str,
+    /// The types of the syscall's parameters, excluding the out-pointer (if any).
+    pub params: Vec<&'static str>,
+    /// The size (in bytes) of the value written to the out-pointer on success, or 0 if the syscall
+    /// returns no value. If non-zero, the out-pointer is passed as an additional `u32` parameter
+    /// before all other parameters. For structs with
+    /// [versioned layouts](fvm_shared::sys::layout), this is the size of the latest layout.
+    pub return_size: usize,
+    /// The first network version at which this syscall is part of the consensus ABI.
+    ///
+    /// This is informational and isn't enforced when linking: syscalls are linked at every network
+    /// version. It records when each syscall was added, so that the declared ABI of released
+    /// network versions can be kept from changing.
+    pub since: NetworkVersion,
+    /// The error numbers this syscall may return, other than on success.
+    pub errors: &'static [ErrorNumber],
+}
+
+impl SyscallSignature {
+    /// Formats the signature as a single-line JSON object.
+    pub fn to_json(&self) -> String {
+        let params: Vec<_> = self.params.iter().map(|p| format!("\"{p}\"")).collect();
+        let errors: Vec<_> = self.errors.iter().map(|e| format!("\"{e:?}\"")).collect();
+        format!(
+            "{{\"module\": \"{}\", \"name\": \"{}\", \"params\": [{}], \"return_size\": {}, \"since\": {}, \"errors\": [{}]}}",
+            self.module,
+            self.name,
+            params.join(", "),
+            self.return_size,
+            self.since,
+            errors.join(", "),
+        )
+    }
+}
+
+/// Records the signatures of bound syscalls instead of linking them.
+pub(super) struct SyscallTable<K> {
+    pub(super) syscalls: Vec<SyscallSignature>,
+    _kernel: PhantomData<K>,
+}
+
+impl<K> Default for SyscallTable<K> {
+    fn default() -> Self {
+        Self {
+            syscalls: Vec::new(),
+            _kernel: PhantomData,
+        }
+    }
+}
+
+impl<K: Kernel> BindSyscall<K> for SyscallTable<K> {
+    fn bind<Args, Ret, Func>(
+        &mut self,
+        module: &'static str,
+        name: &'static str,
+        _syscall: Func,
+    ) -> anyhow::Result<&mut Self>
+    where
+        Func: Syscall<K, Args, Ret>,
+    {
+        self.syscalls.push(Func::signature(module, name));
+        Ok(self)
+    }
+
+    fn since(&mut self, nv: NetworkVersion) -> &mut Self {
+        if let Some(last) = self.syscalls.last_mut() {
+            last.since = nv;
+        }
+        self
+    }
+
+    fn errors(&mut self, errors: &'static [ErrorNumber]) -> &mut Self {
+        if let Some(last) = self.syscalls.last_mut() {
+            last.errors = errors;
+        }
+        self
+    }
+}
+
+/// A syscall implementation taking the given arguments and returning `Ret`. Implemented for
+/// functions of up to 8 arguments (not including the context).
+pub(super) trait Syscall<K, Args, Ret>: Send + Sync + 'static {
+    /// Describes the syscall's ABI.
+    fn signature(module: &'static str, name: &'static str) -> SyscallSignature;
+
+    /// Links the syscall into the given linker under the given module and name.
+    fn link(
+        self,
+        linker: &mut Linker<InvocationData<K>>,
+        module: &'static str,
+        name: &'static str,
+    ) -> anyhow::Result<()>;
 }
 
 /// The helper trait used by `BindSyscall` to convert kernel results with execution errors into
@@ -94,6 +235,15 @@ fn memory_and_data<'a, K: Kernel>(
     (Memory::new(mem), data)
 }
 
+/// Aborts if execution has been cancelled. See
+/// [`MachineContext::cancellation`](crate::machine::MachineContext::cancellation).
+fn check_cancelled<K: Kernel>(kernel: &K) -> Result<(), Abort> {
+    match &kernel.machine().context().cancellation {
+        Some(token) if token.is_cancelled() => Err(Abort::Fatal(Cancelled.into())),
+        _ => Ok(()),
+    }
+}
+
 macro_rules! charge_syscall_gas {
     ($kernel:expr) => {
         let charge = $kernel.price_list().on_syscall();
@@ -107,23 +257,36 @@ macro_rules! charge_syscall_gas {
 macro_rules! impl_bind_syscalls {
     ($($t:ident)*) => {
         #[allow(non_snake_case)]
-        impl<$($t,)* Ret, K, Func> BindSyscall<($($t,)*), Ret, Func> for Linker<InvocationData<K>>
+        impl<$($t,)* Ret, K, Func> Syscall<K, ($($t,)*), Ret> for Func
         where
             K: Kernel,
             Func: Fn(Context<'_, K> $(, $t)*) -> Ret + Send + Sync + 'static,
             Ret: IntoSyscallResult,
            $($t: WasmTy+SyscallSafe,)*
         {
-            fn bind(
-                &mut self,
+            fn signature(module: &'static str, name: &'static str) -> SyscallSignature {
+                SyscallSignature {
+                    module,
+                    name,
+                    params: vec![$(std::any::type_name::<$t>(),)*],
+                    return_size: mem::size_of::<Ret::Value>(),
+                    since: BASE_NETWORK_VERSION,
+                    errors: &[],
+                }
+            }
+
+            fn link(
+                self,
+                linker: &mut Linker<InvocationData<K>>,
                 module: &'static str,
                 name: &'static str,
-                syscall: Func,
-            ) -> anyhow::Result<&mut Self> {
+            ) -> anyhow::Result<()> {
+                let syscall = self;
                 if mem::size_of::<Ret::Value>() == 0 {
                     // If we're returning a zero-sized "value", we return no value therefore and expect no out pointer.
-                    self.func_wrap(module, name, move |mut caller: Caller<'_, InvocationData<K>> $(, $t: $t)*| {
+                    linker.func_wrap(module, name, move |mut caller: Caller<'_, InvocationData<K>> $(, $t: $t)*| {
                         charge_for_exec(&mut caller)?;
+                        check_cancelled(&caller.data().kernel)?;
 
                         let (mut memory, mut data) = memory_and_data(&mut caller);
                         charge_syscall_gas!(data.kernel);
@@ -149,18 +312,24 @@ macro_rules! impl_bind_syscalls {
                         update_gas_available(&mut caller)?;
 
                         result
-                    })
+                    })?;
                 } else {
                     // If we're returning an actual value, we need to write it back into the wasm module's memory.
-                    self.func_wrap(module, name, move |mut caller: Caller<'_, InvocationData<K>>, ret: u32 $(, $t: $t)*| {
+                    linker.func_wrap(module, name, move |mut caller: Caller<'_, InvocationData<K>>, ret: u32 $(, $t: $t)*| {
                         charge_for_exec(&mut caller)?;
+                        check_cancelled(&caller.data().kernel)?;
 
                         let (mut memory, mut data) = memory_and_data(&mut caller);
                         charge_syscall_gas!(data.kernel);
 
+                        // Structs that grew over time are returned in the layout of the current
+                        // network version, so actors compiled against older layouts keep working.
+                        let nv = data.kernel.machine().context().network_version;
+                        let size = layout::abi_size::<Ret::Value>(nv);
+
                         // We need to check to make sure we can store the return value _before_ we do anything.
                         if (ret as u64) > (memory.len() as u64)
-                            || memory.len() - (ret as usize) < mem::size_of::<Ret::Value>() {
+                            || memory.len() - (ret as usize) < size {
                             let code = ErrorNumber::IllegalArgument;
                             data.last_error = Some(backtrace::Cause::from_syscall(module, name, SyscallError(format!("no space for return value"), code)));
                             return Ok(code as u32);
@@ -170,11 +339,7 @@ macro_rules! impl_bind_syscalls {
                         let result = match syscall(ctx $(, $t)*).into() {
                             Ok(Ok(value)) => {
                                 log::trace!("syscall {}::{}: ok", module, name);
-                                unsafe {
-                                    // We're writing into a user-specified pointer, so avoid
-                                    // derefering it as it may not be aligned.
-                                    (memory.as_mut_ptr().offset(ret as isize) as *mut Ret::Value).write_unaligned(value);
-                                }
+                                memory[ret as usize..][..size].copy_from_slice(layout::encode(&value, nv));
                                 data.last_error = None;
                                 Ok(0)
                             },
@@ -190,8 +355,9 @@ macro_rules! impl_bind_syscalls {
                         update_gas_available(&mut caller)?;
 
                         result
-                    })
+                    })?;
                 }
+                Ok(())
             }
         }
     }
@@ -15,7 +15,7 @@ use fvm_shared::sys;
 use num_traits::FromPrimitive;
 
 use super::Context;
-use crate::kernel::{ClassifyResult, Result};
+use crate::kernel::{ClassifyResult, HashHandle, Result};
 use crate::{syscall_error, Kernel};
 
 /// Verifies that a signature is valid for an address and plaintext.
@@ -69,6 +69,46 @@ pub fn recover_secp_public_key(
         .recover_secp_public_key(&hash_bytes, &sig_bytes)
 }
 
+/// Recovers the public keys of `items_len` signers, reading the (message hash, signature) pairs
+/// from `items_off` and writing the public keys to `obuf_off`, in order.
+pub fn recover_secp_public_keys(
+    context: Context<'_, impl Kernel>,
+    items_off: u32,
+    items_len: u32,
+    obuf_off: u32,
+) -> Result<()> {
+    const ITEM_SIZE: u32 = (SECP_SIG_MESSAGE_HASH_SIZE + SECP_SIG_LEN) as u32;
+
+    let (Some(items_size), Some(obuf_size)) = (
+        items_len.checked_mul(ITEM_SIZE),
+        items_len.checked_mul(SECP_PUB_LEN as u32),
+    ) else {
+        return Err(syscall_error!(IllegalArgument; "too many items: {}", items_len).into());
+    };
+    context.memory.check_bounds(obuf_off, obuf_size)?;
+
+    let items: Vec<_> = context
+        .memory
+        .try_slice(items_off, items_size)?
+        .chunks_exact(ITEM_SIZE as usize)
+        .map(|item| {
+            let (hash, sig) = item.split_at(SECP_SIG_MESSAGE_HASH_SIZE);
+            (
+                hash.try_into().expect("hash size"),
+                sig.try_into().expect("signature size"),
+            )
+        })
+        .collect();
+
+    let pubkeys = context.kernel.recover_secp_public_keys(&items)?;
+
+    let obuf = context.memory.try_slice_mut(obuf_off, obuf_size)?;
+    for (out, pubkey) in obuf.chunks_exact_mut(SECP_PUB_LEN).zip(pubkeys) {
+        out.copy_from_slice(&pubkey);
+    }
+    Ok(())
+}
+
 /// Hashes input data using the specified hash function, writing the digest into the provided
 /// buffer.
 pub fn hash(
@@ -95,6 +135,41 @@ pub fn hash(
     Ok(length as u32)
 }
 
+/// Starts hashing data in chunks using the specified hash function, returning a handle to the
+/// in-progress computation.
+pub fn hash_init(context: Context<'_, impl Kernel>, hash_code: u64) -> Result<u32> {
+    Ok(context.kernel.hash_init(hash_code)?.0)
+}
+
+/// Feeds the next chunk of input data into an in-progress hash computation.
+pub fn hash_update(
+    context: Context<'_, impl Kernel>,
+    handle: u32,
+    data_off: u32, // input
+    data_len: u32,
+) -> Result<()> {
+    let data = context.memory.try_slice(data_off, data_len)?;
+    context.kernel.hash_update(&HashHandle(handle), data)
+}
+
+/// Completes an in-progress hash computation, writing the digest into the provided buffer.
+pub fn hash_finalize(
+    context: Context<'_, impl Kernel>,
+    handle: u32,
+    digest_off: u32, // output
+    digest_len: u32,
+) -> Result<u32> {
+    // Check the digest bounds first so we don't consume the handle if they're incorrect.
+    context.memory.check_bounds(digest_off, digest_len)?;
+
+    let digest = context.kernel.hash_finalize(HashHandle(handle))?;
+
+    let digest_out = context.memory.try_slice_mut(digest_off, digest_len)?;
+    let length = cmp::min(digest_out.len(), digest.digest().len());
+    digest_out[..length].copy_from_slice(&digest.digest()[..length]);
+    Ok(length as u32)
+}
+
 /// Computes an unsealed sector CID (CommD) from its constituent piece CIDs
 /// (CommPs) and sizes.
 ///
@@ -143,6 +218,29 @@ pub fn verify_post(
         .map(|v| if v { 0 } else { -1 })
 }
 
+/// Verifies a Groth16 proof over BLS12-381.
+///
+/// The return i32 indicates the status code of the verification:
+///  - 0: verification ok.
+///  - -1: verification failed.
+pub fn groth16_verify(
+    context: Context<'_, impl Kernel>,
+    vk_off: u32,
+    vk_len: u32,
+    proof_off: u32,
+    proof_len: u32,
+    inputs_off: u32,
+    inputs_len: u32,
+) -> Result<i32> {
+    let vk = context.memory.try_slice(vk_off, vk_len)?;
+    let proof = context.memory.try_slice(proof_off, proof_len)?;
+    let inputs = context.memory.try_slice(inputs_off, inputs_len)?;
+    context
+        .kernel
+        .groth16_verify(vk, proof, inputs)
+        .map(|v| if v { 0 } else { -1 })
+}
+
 /// Verifies that two block headers provide proof of a consensus fault:
 /// - both headers mined by the same actor
 /// - headers are different
@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 use crate::kernel::{ClassifyResult, Result};
 use crate::syscalls::context::Context;
-use crate::Kernel;
+use crate::{syscall_error, Kernel};
 
 pub fn log(context: Context<'_, impl Kernel>, msg_off: u32, msg_len: u32) -> Result<()> {
     // No-op if disabled.
@@ -45,3 +45,54 @@ pub fn store_artifact(
 
     Ok(())
 }
+
+/// Stores a debug artifact, writing the name under which it was stored into the output buffer and
+/// returning its length. Returns 0 (writing nothing) if debugging is disabled.
+#[allow(clippy::too_many_arguments)]
+pub fn store_artifact_unique(
+    context: Context<'_, impl Kernel>,
+    name_off: u32,
+    name_len: u32,
+    data_off: u32,
+    data_len: u32,
+    obuf_off: u32,
+    obuf_len: u32,
+) -> Result<u32> {
+    // No-op if disabled.
+    if !context.kernel.debug_enabled() {
+        return Ok(0);
+    }
+
+    context.memory.check_bounds(obuf_off, obuf_len)?;
+
+    let data = context.memory.try_slice(data_off, data_len)?;
+    let name = context.memory.try_slice(name_off, name_len)?;
+    let name =
+        std::str::from_utf8(name).or_error(fvm_shared::error::ErrorNumber::IllegalArgument)?;
+
+    let name = context.kernel.store_artifact(name, data)?;
+
+    let obuf = context.memory.try_slice_mut(obuf_off, obuf_len)?;
+    obuf.get_mut(..name.len())
+        .ok_or_else(|| syscall_error!(BufferTooSmall; "artifact name output buffer is too small"))?
+        .copy_from_slice(name.as_bytes());
+    Ok(name.len() as u32)
+}
+
+/// Writes the current call stack (outermost first) into the output buffer as pairs of
+/// little-endian `u64`s (actor ID, method number), returning the total number of frames. Only as
+/// many frames as fit in the output buffer are written.
+pub fn call_stack(context: Context<'_, impl Kernel>, obuf_off: u32, obuf_len: u32) -> Result<u32> {
+    const FRAME_SIZE: usize = 2 * std::mem::size_of::<u64>();
+
+    context.memory.check_bounds(obuf_off, obuf_len)?;
+
+    let frames = context.kernel.call_stack()?;
+
+    let obuf = context.memory.try_slice_mut(obuf_off, obuf_len)?;
+    for (out, (actor, method)) in obuf.chunks_exact_mut(FRAME_SIZE).zip(&frames) {
+        out[..8].copy_from_slice(&actor.to_le_bytes());
+        out[8..].copy_from_slice(&method.to_le_bytes());
+    }
+    Ok(frames.len() as u32)
+}
@@ -3,9 +3,10 @@
 
 use anyhow::Context as _;
 
+use super::context::Memory;
 use super::Context;
-use crate::kernel::{ClassifyResult, Result};
-use crate::Kernel;
+use crate::kernel::{ClassifyResult, RawEvent, Result};
+use crate::{syscall_error, Kernel};
 
 /// Emits an actor event. The event is split into three raw byte buffers that have
 /// been written to Wasm memory. This is done so that the FVM can accurately charge
@@ -33,21 +34,139 @@ pub fn emit_event(
     val_off: u32,
     val_len: u32,
 ) -> Result<()> {
-    let event_headers = unsafe {
-        const EVENT_SIZE: u32 = std::mem::size_of::<fvm_shared::sys::EventEntry>() as u32;
-        // assert the alignment so we can safely cast from a byte-slice
-        static_assertions::assert_eq_align!(fvm_shared::sys::EventEntry, u8);
-        let size = event_len
-            .checked_mul(EVENT_SIZE)
-            .context("events index out of bounds")
-            .or_illegal_argument()?;
-        let buf = context.memory.try_slice(event_off, size)?;
+    let event_headers = read_event_headers(context.memory, event_off, event_len)?;
+    let raw_key = context.memory.try_slice(key_off, key_len)?;
+    let raw_val = context.memory.try_slice(val_off, val_len)?;
+    context.kernel.emit_event(event_headers, raw_key, raw_val)
+}
+
+/// Returns the gas (rounded up to whole gas units) that [`emit_event`] would charge for emitting
+/// the supplied event, without emitting it. The buffers are laid out as for [`emit_event`], but
+/// the event isn't validated.
+pub fn gas_cost(
+    context: Context<'_, impl Kernel>,
+    event_off: u32,
+    event_len: u32,
+    key_off: u32,
+    key_len: u32,
+    val_off: u32,
+    val_len: u32,
+) -> Result<u64> {
+    let event_headers = read_event_headers(context.memory, event_off, event_len)?;
+    let raw_key = context.memory.try_slice(key_off, key_len)?;
+    let raw_val = context.memory.try_slice(val_off, val_len)?;
+    Ok(context
+        .kernel
+        .event_gas_cost(event_headers, raw_key, raw_val)?
+        .round_up())
+}
+
+/// Computes the root of the events AMT over the events emitted so far in the current message, and
+/// writes it into the supplied output buffer.
+///
+/// Returns the length of the CID, or 0 if no events have been emitted.
+pub fn root_so_far(
+    context: Context<'_, impl Kernel>,
+    obuf_off: u32, // Cid
+    obuf_len: u32,
+) -> Result<u32> {
+    context.memory.check_bounds(obuf_off, obuf_len)?;
+
+    match context.kernel.events_root_so_far()? {
+        Some(root) => context.memory.write_cid(&root, obuf_off, obuf_len),
+        None => Ok(0),
+    }
+}
+
+/// Emits a batch of actor events, skipping events identical to the previously emitted one, and
+/// returns the number of events actually emitted.
+///
+/// The entries, keys, and values of all events are concatenated into the same three buffers as
+/// for [`emit_event`]. `sizes_off/sizes_len` point to an array of `u32`s giving the number of
+/// entries in each event; each event's keys and values are then located by the lengths recorded
+/// in its entries. Errors with `IllegalArgument` if the sizes don't exactly cover the buffers.
+#[allow(clippy::too_many_arguments)]
+pub fn emit_events_dedup(
+    context: Context<'_, impl Kernel>,
+    sizes_off: u32,
+    sizes_len: u32,
+    event_off: u32,
+    event_len: u32,
+    key_off: u32,
+    key_len: u32,
+    val_off: u32,
+    val_len: u32,
+) -> Result<u32> {
+    const SIZE_SIZE: u32 = std::mem::size_of::<u32>() as u32;
+
+    let sizes = context.memory.try_slice(
+        sizes_off,
+        sizes_len
+            .checked_mul(SIZE_SIZE)
+            .context("event sizes out of bounds")
+            .or_illegal_argument()?,
+    )?;
+    let mut headers = read_event_headers(context.memory, event_off, event_len)?;
+    let mut keys = context.memory.try_slice(key_off, key_len)?;
+    let mut values = context.memory.try_slice(val_off, val_len)?;
+
+    let mut events = Vec::with_capacity(sizes_len as usize);
+    for size in sizes.chunks_exact(SIZE_SIZE as usize) {
+        let size = u32::from_le_bytes(size.try_into().expect("4 byte chunk")) as usize;
+        if size > headers.len() {
+            return Err(syscall_error!(IllegalArgument; "event sizes exceed the entries").into());
+        }
+        let (evt_headers, rest) = headers.split_at(size);
+        headers = rest;
+
+        let evt_key_len: usize = evt_headers.iter().map(|h| h.key_len as usize).sum();
+        let evt_val_len: usize = evt_headers.iter().map(|h| h.val_len as usize).sum();
+        if evt_key_len > keys.len() || evt_val_len > values.len() {
+            return Err(
+                syscall_error!(IllegalArgument; "event entries exceed the keys or values").into(),
+            );
+        }
+        let (evt_keys, rest) = keys.split_at(evt_key_len);
+        keys = rest;
+        let (evt_values, rest) = values.split_at(evt_val_len);
+        values = rest;
+
+        events.push(RawEvent {
+            headers: evt_headers,
+            keys: evt_keys,
+            values: evt_values,
+        });
+    }
+    if !headers.is_empty() || !keys.is_empty() || !values.is_empty() {
+        return Err(syscall_error!(IllegalArgument; "event sizes don't cover the entries").into());
+    }
+
+    context.kernel.emit_events_dedup(&events)
+}
+
+/// Reads `event_len` event entry headers starting at `event_off`.
+fn read_event_headers(
+    memory: &Memory,
+    event_off: u32,
+    event_len: u32,
+) -> Result<&[fvm_shared::sys::EventEntry]> {
+    const EVENT_SIZE: u32 = std::mem::size_of::<fvm_shared::sys::EventEntry>() as u32;
+    // assert the alignment so we can safely cast from a byte-slice
+    static_assertions::assert_eq_align!(fvm_shared::sys::EventEntry, u8);
+    let size = event_len
+        .checked_mul(EVENT_SIZE)
+        .context("events index out of bounds")
+        .or_illegal_argument()?;
+    let buf = memory.try_slice(event_off, size)?;
+    Ok(unsafe {
         std::slice::from_raw_parts(
             buf.as_ptr() as *const fvm_shared::sys::EventEntry,
             event_len as usize,
         )
-    };
-    let raw_key = context.memory.try_slice(key_off, key_len)?;
-    let raw_val = context.memory.try_slice(val_off, val_len)?;
-    context.kernel.emit_event(event_headers, raw_key, raw_val)
+    })
+}
+
+/// Returns how many more events the current message may emit, or `u32::MAX` if there's no limit.
+pub fn events_remaining(context: Context<'_, impl Kernel>) -> Result<u32> {
+    context.kernel.events_remaining()
 }
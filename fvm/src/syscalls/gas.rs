@@ -2,6 +2,11 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 use std::str;
 
+use anyhow::Context as _;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ErrorNumber;
+use fvm_shared::sys;
+
 use super::Context;
 use crate::gas::Gas;
 use crate::kernel::{ClassifyResult, Result};
@@ -25,3 +30,40 @@ pub fn charge_gas(
 pub fn available(context: Context<'_, impl Kernel>) -> Result<u64> {
     Ok(context.kernel.gas_available().round_down())
 }
+
+/// Estimates the total fee (base fee plus premium) of a message with the given gas limit (in whole
+/// gas units) and premium, at the current base fee.
+pub fn estimate_fee(
+    context: Context<'_, impl Kernel>,
+    gas_limit: u64,
+    premium_hi: u64,
+    premium_lo: u64,
+) -> Result<sys::TokenAmount> {
+    let premium = TokenAmount::from_atto((premium_hi as u128) << 64 | premium_lo as u128);
+    context
+        .kernel
+        .estimate_fee(Gas::new(gas_limit), &premium)?
+        .try_into()
+        .context("estimated fee exceeds u128 limit")
+        .or_error(ErrorNumber::LimitExceeded)
+}
+
+/// Creates a sub-meter with the given budget (in whole gas units), returning a handle to it. The
+/// handle is only valid within the current invocation.
+pub fn create_submeter(context: Context<'_, impl Kernel>, budget: u64) -> Result<u32> {
+    context.kernel.create_submeter(Gas::new(budget))
+}
+
+/// Charges gas (in whole gas units) to a sub-meter created by the current invocation.
+pub fn charge_to(context: Context<'_, impl Kernel>, meter: u32, compute: u64) -> Result<()> {
+    context
+        .kernel
+        .charge_to(meter, Gas::new(compute))
+        .map(|_| ())
+}
+
+/// Returns the gas remaining in a sub-meter created by the current invocation, rounded down to
+/// whole gas units.
+pub fn submeter_remaining(context: Context<'_, impl Kernel>, meter: u32) -> Result<u64> {
+    Ok(context.kernel.submeter_remaining(meter)?.round_down())
+}
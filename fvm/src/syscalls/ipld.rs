@@ -26,6 +26,33 @@ pub fn block_create(
     context.kernel.block_create(codec, data)
 }
 
+pub fn block_patch(
+    context: Context<'_, impl Kernel>,
+    id: u32,
+    offset: u32,
+    data_off: u32,
+    data_len: u32,
+) -> Result<u32> {
+    let data = context.memory.try_slice(data_off, data_len)?;
+    context.kernel.block_patch(id, offset, data)
+}
+
+pub fn block_open_or_create(
+    context: Context<'_, impl Kernel>,
+    cid: u32,
+    codec: u64,
+    data_off: u32,
+    data_len: u32,
+) -> Result<sys::out::ipld::IpldOpenOrCreate> {
+    let cid = context.memory.read_cid(cid)?;
+    let data = context.memory.try_slice(data_off, data_len)?;
+    let (id, created) = context.kernel.block_open_or_create(&cid, codec, data)?;
+    Ok(sys::out::ipld::IpldOpenOrCreate {
+        id,
+        created: created as u32,
+    })
+}
+
 pub fn block_link(
     context: Context<'_, impl Kernel>,
     id: u32,
@@ -44,6 +71,19 @@ pub fn block_link(
     context.memory.write_cid(&cid, cid_off, cid_len)
 }
 
+pub fn block_cid(
+    context: Context<'_, impl Kernel>,
+    id: u32,
+    hash_fun: u64,
+    hash_len: u32,
+    cid_off: u32,
+    cid_len: u32,
+) -> Result<u32> {
+    context.memory.check_bounds(cid_off, cid_len)?;
+    let cid = context.kernel.block_cid(id, hash_fun, hash_len)?;
+    context.memory.write_cid(&cid, cid_off, cid_len)
+}
+
 pub fn block_read(
     context: Context<'_, impl Kernel>,
     id: u32,
@@ -55,6 +95,11 @@ pub fn block_read(
     context.kernel.block_read(id, offset, data)
 }
 
+/// Reads a CBOR block containing a single unsigned integer.
+pub fn block_read_u64(context: Context<'_, impl Kernel>, id: u32) -> Result<u64> {
+    context.kernel.block_read_u64(id)
+}
+
 pub fn block_stat(context: Context<'_, impl Kernel>, id: u32) -> Result<sys::out::ipld::IpldStat> {
     context
         .kernel
@@ -64,3 +109,129 @@ pub fn block_stat(context: Context<'_, impl Kernel>, id: u32) -> Result<sys::out
             size: stat.size,
         })
 }
+
+pub fn block_retain(context: Context<'_, impl Kernel>, id: u32) -> Result<()> {
+    context.kernel.block_retain(id)
+}
+
+pub fn block_close(context: Context<'_, impl Kernel>, id: u32) -> Result<()> {
+    context.kernel.block_close(id)
+}
+
+/// Compares two blocks.
+///
+/// The return i32 indicates whether the blocks are equal:
+///  - 0: yes.
+///  - -1: no.
+pub fn block_eq(context: Context<'_, impl Kernel>, a: u32, b: u32) -> Result<i32> {
+    context
+        .kernel
+        .block_eq(a, b)
+        .map(|v| if v { 0 } else { -1 })
+}
+
+/// Checks whether a block hashes to the expected CID.
+///
+/// The return i32 indicates whether the block matches the CID:
+///  - 0: yes.
+///  - -1: no.
+pub fn block_hash_eq(context: Context<'_, impl Kernel>, id: u32, cid_off: u32) -> Result<i32> {
+    let cid = context.memory.read_cid(cid_off)?;
+    context
+        .kernel
+        .block_hash_eq(id, &cid)
+        .map(|v| if v { 0 } else { -1 })
+}
+
+/// Checks whether a block links to the target CID.
+///
+/// The return i32 indicates whether the block contains the link:
+///  - 0: yes.
+///  - -1: no.
+pub fn block_contains_link(
+    context: Context<'_, impl Kernel>,
+    id: u32,
+    cid_off: u32,
+) -> Result<i32> {
+    let cid = context.memory.read_cid(cid_off)?;
+    context
+        .kernel
+        .block_contains_link(id, &cid)
+        .map(|v| if v { 0 } else { -1 })
+}
+
+/// Checks whether `root` can be reached by following links from `child` within `max_depth` links,
+/// i.e., whether making `root` link to `child` would form a cycle.
+///
+/// The return i32 indicates whether linking would create a cycle:
+///  - 0: yes.
+///  - -1: no.
+pub fn would_create_cycle(
+    context: Context<'_, impl Kernel>,
+    root_off: u32,
+    child_off: u32,
+    max_depth: u32,
+) -> Result<i32> {
+    let root = context.memory.read_cid(root_off)?;
+    let child = context.memory.read_cid(child_off)?;
+    context
+        .kernel
+        .would_create_cycle(root, child, max_depth)
+        .map(|v| if v { 0 } else { -1 })
+}
+
+/// Checks whether a block could be returned from the current invocation.
+///
+/// The return i32 indicates whether the block fits:
+///  - 0: yes.
+///  - -1: no.
+pub fn would_fit_as_return(context: Context<'_, impl Kernel>, id: u32) -> Result<i32> {
+    context
+        .kernel
+        .would_fit_as_return(id)
+        .map(|v| if v { 0 } else { -1 })
+}
+
+/// Checks whether a codec is a DAG codec (one whose blocks may contain links).
+///
+/// The return i32 indicates whether the codec is a DAG codec:
+///  - 0: yes.
+///  - -1: no.
+pub fn is_dag_codec(context: Context<'_, impl Kernel>, codec: u64) -> Result<i32> {
+    context
+        .kernel
+        .is_dag_codec(codec)
+        .map(|v| if v { 0 } else { -1 })
+}
+
+/// Decompresses zlib-compressed data into the output buffer, returning the number of bytes
+/// written. Fails if the decompressed data doesn't fit in the output buffer.
+pub fn inflate(
+    context: Context<'_, impl Kernel>,
+    data_off: u32,
+    data_len: u32,
+    obuf_off: u32,
+    obuf_len: u32,
+) -> Result<u32> {
+    // Check arguments first.
+    context.memory.check_bounds(obuf_off, obuf_len)?;
+
+    let data = context.memory.try_slice(data_off, data_len)?;
+    let inflated = context.kernel.inflate(data, obuf_len)?;
+
+    let obuf = context.memory.try_slice_mut(obuf_off, obuf_len)?;
+    obuf[..inflated.len()].copy_from_slice(&inflated);
+    Ok(inflated.len() as u32)
+}
+
+/// Raises the maximum number of blocks the current invocation may hold by `extra`, returning the
+/// previous limit.
+pub fn expand_block_handles(context: Context<'_, impl Kernel>, extra: u32) -> Result<u32> {
+    context.kernel.expand_block_handles(extra)
+}
+
+/// Restores a block limit returned by [`expand_block_handles`]. This can only lower the limit.
+pub fn restore_block_handles(context: Context<'_, impl Kernel>, limit: u32) -> Result<()> {
+    context.kernel.restore_block_handles(limit);
+    Ok(())
+}
@@ -230,7 +230,8 @@ fn min_table_elements(module: &Module) -> Option<u32> {
     }
 }
 
-use self::bind::BindSyscall;
+use self::bind::{BindSyscall, SyscallTable};
+pub use self::bind::{SyscallSignature, BASE_NETWORK_VERSION};
 use self::error::Abort;
 
 // Binds the syscall handlers so they can handle invocations
@@ -238,8 +239,56 @@ use self::error::Abort;
 pub fn bind_syscalls(
     linker: &mut Linker<InvocationData<impl Kernel + 'static>>,
 ) -> anyhow::Result<()> {
+    bind_all(linker)
+}
+
+/// Returns the ABI of each syscall bound by [`bind_syscalls`] (in binding order), as seen by actors
+/// running on the kernel `K`.
+pub fn syscall_abi<K: Kernel>() -> Vec<SyscallSignature> {
+    let mut table = SyscallTable::<K>::default();
+    bind_all(&mut table).expect("recording syscall signatures can't fail");
+    table.syscalls
+}
+
+/// Formats the given syscall ABI as a JSON array with one syscall per line.
+pub fn syscall_abi_json(abi: &[SyscallSignature]) -> String {
+    let entries: Vec<_> = abi.iter().map(|s| format!("  {}", s.to_json())).collect();
+    format!("[\n{}\n]\n", entries.join(",\n"))
+}
+
+fn bind_all<K: Kernel>(linker: &mut impl BindSyscall<K>) -> anyhow::Result<()> {
+    use fvm_shared::error::ErrorNumber::*;
+    use fvm_shared::version::NetworkVersion;
+
     linker.bind("vm", "exit", vm::exit)?;
     linker.bind("vm", "message_context", vm::message_context)?;
+    linker
+        .bind("vm", "message_context_v2", vm::message_context_v2)?
+        .since(NetworkVersion::V22)
+        .errors(&[IllegalOperation]);
+    linker
+        .bind("vm", "read_only_reason", vm::read_only_reason)?
+        .since(NetworkVersion::V22)
+        .errors(&[IllegalOperation]);
+    linker
+        .bind("vm", "memory_usage", vm::memory_usage)?
+        .since(NetworkVersion::V22)
+        .errors(&[IllegalOperation]);
+    linker
+        .bind("vm", "origin_account_nonce", vm::origin_account_nonce)?
+        .since(NetworkVersion::V22);
+    linker
+        .bind("vm", "is_valid_utf8", vm::is_valid_utf8)?
+        .since(NetworkVersion::V22)
+        .errors(&[IllegalArgument]);
+    linker
+        .bind(
+            "vm",
+            "caller_delegated_address",
+            vm::caller_delegated_address,
+        )?
+        .since(NetworkVersion::V22)
+        .errors(&[BufferTooSmall, IllegalArgument]);
 
     linker.bind(
         "network",
@@ -247,88 +296,353 @@ pub fn bind_syscalls(
         network::total_fil_circ_supply,
     )?;
     linker.bind("network", "context", network::context)?;
-    linker.bind("network", "tipset_cid", network::tipset_cid)?;
-
-    linker.bind("ipld", "block_open", ipld::block_open)?;
-    linker.bind("ipld", "block_create", ipld::block_create)?;
-    linker.bind("ipld", "block_read", ipld::block_read)?;
-    linker.bind("ipld", "block_stat", ipld::block_stat)?;
-    linker.bind("ipld", "block_link", ipld::block_link)?;
-
-    linker.bind("self", "root", sself::root)?;
-    linker.bind("self", "set_root", sself::set_root)?;
+    linker
+        .bind("network", "tipset_cid", network::tipset_cid)?
+        .errors(&[IllegalArgument, LimitExceeded]);
+    linker
+        .bind("network", "epoch_elapsed", network::epoch_elapsed)?
+        .since(NetworkVersion::V22);
+
+    linker
+        .bind("ipld", "block_open", ipld::block_open)?
+        .errors(&[NotFound, IllegalArgument]);
+    linker
+        .bind("ipld", "block_create", ipld::block_create)?
+        .errors(&[
+            LimitExceeded,
+            NotFound,
+            IllegalCodec,
+            Serialization,
+            IllegalArgument,
+        ]);
+    linker
+        .bind("ipld", "block_open_or_create", ipld::block_open_or_create)?
+        .since(NetworkVersion::V22)
+        .errors(&[
+            LimitExceeded,
+            NotFound,
+            IllegalCodec,
+            IllegalCid,
+            Serialization,
+            IllegalArgument,
+        ]);
+    linker
+        .bind("ipld", "block_read", ipld::block_read)?
+        .errors(&[InvalidHandle, IllegalArgument]);
+    linker
+        .bind("ipld", "block_read_u64", ipld::block_read_u64)?
+        .since(NetworkVersion::V22)
+        .errors(&[InvalidHandle, IllegalArgument]);
+    linker
+        .bind("ipld", "block_stat", ipld::block_stat)?
+        .errors(&[InvalidHandle]);
+    linker
+        .bind("ipld", "block_patch", ipld::block_patch)?
+        .since(NetworkVersion::V22)
+        .errors(&[
+            InvalidHandle,
+            IllegalArgument,
+            LimitExceeded,
+            NotFound,
+            Serialization,
+        ]);
+    linker
+        .bind("ipld", "block_link", ipld::block_link)?
+        .errors(&[InvalidHandle, IllegalCid, BufferTooSmall, IllegalArgument]);
+    linker
+        .bind("ipld", "block_cid", ipld::block_cid)?
+        .since(NetworkVersion::V22)
+        .errors(&[InvalidHandle, IllegalCid, BufferTooSmall, IllegalArgument]);
+    linker
+        .bind("ipld", "block_retain", ipld::block_retain)?
+        .since(NetworkVersion::V22)
+        .errors(&[InvalidHandle]);
+    linker
+        .bind("ipld", "block_close", ipld::block_close)?
+        .since(NetworkVersion::V22)
+        .errors(&[InvalidHandle, IllegalArgument]);
+    linker
+        .bind("ipld", "inflate", ipld::inflate)?
+        .since(NetworkVersion::V22)
+        .errors(&[LimitExceeded, IllegalArgument]);
+    linker
+        .bind("ipld", "expand_block_handles", ipld::expand_block_handles)?
+        .since(NetworkVersion::V22);
+    linker
+        .bind("ipld", "restore_block_handles", ipld::restore_block_handles)?
+        .since(NetworkVersion::V22);
+    linker
+        .bind("ipld", "is_dag_codec", ipld::is_dag_codec)?
+        .since(NetworkVersion::V22);
+    linker
+        .bind("ipld", "block_eq", ipld::block_eq)?
+        .since(NetworkVersion::V22)
+        .errors(&[InvalidHandle]);
+    linker
+        .bind("ipld", "block_hash_eq", ipld::block_hash_eq)?
+        .since(NetworkVersion::V22)
+        .errors(&[InvalidHandle, IllegalCid, IllegalArgument]);
+    linker
+        .bind("ipld", "block_contains_link", ipld::block_contains_link)?
+        .since(NetworkVersion::V22)
+        .errors(&[InvalidHandle, IllegalArgument]);
+    linker
+        .bind("ipld", "would_create_cycle", ipld::would_create_cycle)?
+        .since(NetworkVersion::V22)
+        .errors(&[NotFound, IllegalArgument]);
+    linker
+        .bind("ipld", "would_fit_as_return", ipld::would_fit_as_return)?
+        .since(NetworkVersion::V22)
+        .errors(&[InvalidHandle]);
+
+    linker.bind("self", "root", sself::root)?.errors(&[
+        IllegalOperation,
+        IllegalArgument,
+        BufferTooSmall,
+    ]);
+    linker.bind("self", "set_root", sself::set_root)?.errors(&[
+        IllegalOperation,
+        ReadOnly,
+        NotFound,
+    ]);
     linker.bind("self", "current_balance", sself::current_balance)?;
-    linker.bind("self", "self_destruct", sself::self_destruct)?;
-
-    linker.bind("actor", "resolve_address", actor::resolve_address)?;
-    linker.bind(
-        "actor",
-        "lookup_delegated_address",
-        actor::lookup_delegated_address,
-    )?;
-    linker.bind("actor", "get_actor_code_cid", actor::get_actor_code_cid)?;
-    linker.bind("actor", "next_actor_address", actor::next_actor_address)?;
-    linker.bind("actor", "create_actor", actor::create_actor)?;
-    linker.bind(
-        "actor",
-        "get_builtin_actor_type",
-        actor::get_builtin_actor_type,
-    )?;
-    linker.bind(
-        "actor",
-        "get_code_cid_for_type",
-        actor::get_code_cid_for_type,
-    )?;
-    linker.bind("actor", "balance_of", actor::balance_of)?;
+    linker
+        .bind(
+            "self",
+            "balance_before_receipt",
+            sself::balance_before_receipt,
+        )?
+        .since(NetworkVersion::V22);
+    linker
+        .bind("self", "self_destruct", sself::self_destruct)?
+        .errors(&[IllegalOperation, ReadOnly]);
+    linker
+        .bind("self", "delegated_address", sself::delegated_address)?
+        .since(NetworkVersion::V22)
+        .errors(&[BufferTooSmall, IllegalArgument]);
+
+    linker
+        .bind("actor", "resolve_address", actor::resolve_address)?
+        .errors(&[NotFound, IllegalArgument]);
+    linker
+        .bind(
+            "actor",
+            "lookup_delegated_address",
+            actor::lookup_delegated_address,
+        )?
+        .errors(&[NotFound, BufferTooSmall, IllegalArgument]);
+    linker
+        .bind("actor", "delegated_namespace", actor::delegated_namespace)?
+        .since(NetworkVersion::V22)
+        .errors(&[NotFound]);
+    linker
+        .bind(
+            "actor",
+            "make_delegated_address",
+            actor::make_delegated_address,
+        )?
+        .since(NetworkVersion::V22)
+        .errors(&[BufferTooSmall, IllegalArgument]);
+    linker
+        .bind("actor", "get_actor_code_cid", actor::get_actor_code_cid)?
+        .errors(&[NotFound, BufferTooSmall, IllegalArgument]);
+    linker
+        .bind("actor", "next_actor_address", actor::next_actor_address)?
+        .errors(&[BufferTooSmall, IllegalArgument]);
+    linker
+        .bind("actor", "actors_created", actor::actors_created)?
+        .since(NetworkVersion::V22);
+    linker
+        .bind("actor", "create_actor", actor::create_actor)?
+        .errors(&[IllegalArgument, Forbidden, ReadOnly]);
+    linker
+        .bind(
+            "actor",
+            "get_builtin_actor_type",
+            actor::get_builtin_actor_type,
+        )?
+        .errors(&[IllegalArgument]);
+    linker
+        .bind("actor", "address_builtin_type", actor::address_builtin_type)?
+        .since(NetworkVersion::V22)
+        .errors(&[NotFound, IllegalArgument]);
+    linker
+        .bind(
+            "actor",
+            "get_code_cid_for_type",
+            actor::get_code_cid_for_type,
+        )?
+        .errors(&[IllegalArgument]);
+    linker
+        .bind("actor", "balance_of", actor::balance_of)?
+        .errors(&[NotFound]);
+    linker
+        .bind("actor", "balances_of", actor::balances_of)?
+        .since(NetworkVersion::V22)
+        .errors(&[IllegalArgument]);
+    linker
+        .bind("actor", "actor_id_available", actor::actor_id_available)?
+        .since(NetworkVersion::V22);
+    linker
+        .bind("actor", "can_receive_funds", actor::can_receive_funds)?
+        .since(NetworkVersion::V22);
 
     // Only wire this syscall when M2 native is enabled.
     #[cfg(feature = "m2-native")]
     linker.bind("actor", "install_actor", actor::install_actor)?;
 
-    linker.bind("crypto", "verify_signature", crypto::verify_signature)?;
-    linker.bind(
-        "crypto",
-        "recover_secp_public_key",
-        crypto::recover_secp_public_key,
-    )?;
-    linker.bind("crypto", "hash", crypto::hash)?;
-    linker.bind("crypto", "verify_post", crypto::verify_post)?;
-    linker.bind(
-        "crypto",
-        "compute_unsealed_sector_cid",
-        crypto::compute_unsealed_sector_cid,
-    )?;
-    linker.bind(
-        "crypto",
-        "verify_consensus_fault",
-        crypto::verify_consensus_fault,
-    )?;
-    linker.bind(
-        "crypto",
-        "verify_aggregate_seals",
-        crypto::verify_aggregate_seals,
-    )?;
-    linker.bind(
-        "crypto",
-        "verify_replica_update",
-        crypto::verify_replica_update,
-    )?;
-    linker.bind("crypto", "batch_verify_seals", crypto::batch_verify_seals)?;
-
-    linker.bind("event", "emit_event", event::emit_event)?;
-
-    linker.bind("rand", "get_chain_randomness", rand::get_chain_randomness)?;
-    linker.bind("rand", "get_beacon_randomness", rand::get_beacon_randomness)?;
-
-    linker.bind("gas", "charge", gas::charge_gas)?;
+    linker
+        .bind("crypto", "verify_signature", crypto::verify_signature)?
+        .errors(&[IllegalArgument]);
+    linker
+        .bind(
+            "crypto",
+            "recover_secp_public_key",
+            crypto::recover_secp_public_key,
+        )?
+        .errors(&[IllegalArgument]);
+    linker
+        .bind(
+            "crypto",
+            "recover_secp_public_keys",
+            crypto::recover_secp_public_keys,
+        )?
+        .since(NetworkVersion::V22)
+        .errors(&[IllegalArgument]);
+    linker
+        .bind("crypto", "hash", crypto::hash)?
+        .errors(&[IllegalArgument]);
+    linker
+        .bind("crypto", "hash_init", crypto::hash_init)?
+        .since(NetworkVersion::V22)
+        .errors(&[IllegalArgument]);
+    linker
+        .bind("crypto", "hash_update", crypto::hash_update)?
+        .since(NetworkVersion::V22)
+        .errors(&[InvalidHandle, IllegalArgument]);
+    linker
+        .bind("crypto", "hash_finalize", crypto::hash_finalize)?
+        .since(NetworkVersion::V22)
+        .errors(&[InvalidHandle, IllegalArgument]);
+    linker
+        .bind("crypto", "verify_post", crypto::verify_post)?
+        .errors(&[IllegalArgument]);
+    linker
+        .bind(
+            "crypto",
+            "compute_unsealed_sector_cid",
+            crypto::compute_unsealed_sector_cid,
+        )?
+        .errors(&[IllegalArgument, BufferTooSmall]);
+    linker
+        .bind(
+            "crypto",
+            "verify_consensus_fault",
+            crypto::verify_consensus_fault,
+        )?
+        .errors(&[LimitExceeded, IllegalArgument]);
+    linker
+        .bind(
+            "crypto",
+            "verify_aggregate_seals",
+            crypto::verify_aggregate_seals,
+        )?
+        .errors(&[LimitExceeded, IllegalArgument]);
+    linker
+        .bind(
+            "crypto",
+            "verify_replica_update",
+            crypto::verify_replica_update,
+        )?
+        .errors(&[LimitExceeded, IllegalArgument]);
+    linker
+        .bind("crypto", "batch_verify_seals", crypto::batch_verify_seals)?
+        .errors(&[IllegalArgument]);
+    linker
+        .bind("crypto", "groth16_verify", crypto::groth16_verify)?
+        .since(NetworkVersion::V22)
+        .errors(&[IllegalArgument]);
+
+    linker
+        .bind("event", "emit_event", event::emit_event)?
+        .errors(&[IllegalArgument, ReadOnly, LimitExceeded]);
+    linker
+        .bind("event", "root_so_far", event::root_so_far)?
+        .since(NetworkVersion::V22)
+        .errors(&[BufferTooSmall, IllegalArgument]);
+    linker
+        .bind("event", "gas_cost", event::gas_cost)?
+        .since(NetworkVersion::V22)
+        .errors(&[IllegalArgument]);
+    linker
+        .bind("event", "events_remaining", event::events_remaining)?
+        .since(NetworkVersion::V22);
+    linker
+        .bind("event", "emit_events_dedup", event::emit_events_dedup)?
+        .since(NetworkVersion::V22)
+        .errors(&[IllegalArgument, ReadOnly, LimitExceeded]);
+
+    linker
+        .bind("rand", "get_chain_randomness", rand::get_chain_randomness)?
+        .errors(&[LimitExceeded, IllegalArgument]);
+    linker
+        .bind("rand", "get_beacon_randomness", rand::get_beacon_randomness)?
+        .errors(&[LimitExceeded, IllegalArgument]);
+    linker
+        .bind("rand", "random_in_range", rand::random_in_range)?
+        .since(NetworkVersion::V22)
+        .errors(&[LimitExceeded, IllegalArgument]);
+
+    linker
+        .bind("gas", "charge", gas::charge_gas)?
+        .errors(&[IllegalArgument]);
     linker.bind("gas", "available", gas::available)?;
+    linker
+        .bind("gas", "estimate_fee", gas::estimate_fee)?
+        .since(NetworkVersion::V22)
+        .errors(&[LimitExceeded]);
+    linker
+        .bind("gas", "create_submeter", gas::create_submeter)?
+        .since(NetworkVersion::V22)
+        .errors(&[IllegalOperation]);
+    linker
+        .bind("gas", "charge_to", gas::charge_to)?
+        .since(NetworkVersion::V22)
+        .errors(&[NotFound, LimitExceeded]);
+    linker
+        .bind("gas", "submeter_remaining", gas::submeter_remaining)?
+        .since(NetworkVersion::V22)
+        .errors(&[NotFound]);
 
     // Ok, this singled-out syscall should probably be in another category.
-    linker.bind("send", "send", send::send)?;
-
-    linker.bind("debug", "log", debug::log)?;
+    linker.bind("send", "send", send::send)?.errors(&[
+        NotFound,
+        InsufficientFunds,
+        InvalidHandle,
+        LimitExceeded,
+        IllegalArgument,
+        ReadOnly,
+    ]);
+
+    linker
+        .bind("debug", "log", debug::log)?
+        .errors(&[IllegalArgument]);
     linker.bind("debug", "enabled", debug::enabled)?;
-    linker.bind("debug", "store_artifact", debug::store_artifact)?;
+    linker
+        .bind("debug", "store_artifact", debug::store_artifact)?
+        .errors(&[IllegalArgument, LimitExceeded]);
+    linker
+        .bind(
+            "debug",
+            "store_artifact_unique",
+            debug::store_artifact_unique,
+        )?
+        .since(NetworkVersion::V22)
+        .errors(&[IllegalArgument, LimitExceeded, BufferTooSmall]);
+    linker
+        .bind("debug", "call_stack", debug::call_stack)?
+        .since(NetworkVersion::V22)
+        .errors(&[Forbidden, IllegalArgument]);
 
     Ok(())
 }
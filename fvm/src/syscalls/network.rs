@@ -1,6 +1,7 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 use anyhow::Context as _;
+use fvm_shared::clock::ChainEpoch;
 use fvm_shared::sys;
 use fvm_shared::sys::out::network::NetworkContext;
 
@@ -33,3 +34,15 @@ pub fn tipset_cid(
     let cid = context.kernel.tipset_cid(epoch)?;
     context.memory.write_cid(&cid, obuf_off, obuf_len)
 }
+
+/// Checks whether the current epoch is at or past the target epoch.
+///
+/// The return i32 indicates whether the epoch has elapsed:
+///  - 0: yes.
+///  - -1: no.
+pub fn epoch_elapsed(context: Context<'_, impl Kernel>, target: ChainEpoch) -> Result<i32> {
+    context
+        .kernel
+        .epoch_elapsed(target)
+        .map(|v| if v { 0 } else { -1 })
+}
@@ -1,9 +1,11 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
-use fvm_shared::randomness::RANDOMNESS_LENGTH;
+use anyhow::Context as _;
+use fvm_shared::randomness::{RandSource, RANDOMNESS_LENGTH};
+use num_traits::FromPrimitive;
 
 use super::Context;
-use crate::kernel::Result;
+use crate::kernel::{ClassifyResult, Result};
 use crate::Kernel;
 
 /// Gets 32 bytes of randomness from the ticket chain.
@@ -27,3 +29,22 @@ pub fn get_beacon_randomness(
 ) -> Result<[u8; RANDOMNESS_LENGTH]> {
     context.kernel.get_randomness_from_beacon(round)
 }
+
+/// Draws randomness from the given source (see [`RandSource`]), mixes in the supplied entropy, and
+/// maps it to a uniformly distributed integer in `[0, bound)`.
+pub fn random_in_range(
+    context: Context<'_, impl Kernel>,
+    source: u32,
+    round: i64, // ChainEpoch
+    entropy_off: u32,
+    entropy_len: u32,
+    bound: u64,
+) -> Result<u64> {
+    let source = RandSource::from_u32(source)
+        .with_context(|| format!("unknown randomness source {}", source))
+        .or_illegal_argument()?;
+    let entropy = context.memory.try_slice(entropy_off, entropy_len)?;
+    context
+        .kernel
+        .random_in_range(source, round, entropy, bound)
+}
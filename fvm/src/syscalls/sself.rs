@@ -5,6 +5,7 @@ use fvm_shared::sys;
 
 use super::Context;
 use crate::kernel::{ClassifyResult, Kernel, Result};
+use crate::syscall_error;
 
 /// Returns the root CID of the actor's state by writing it in the specified buffer.
 ///
@@ -33,7 +34,37 @@ pub fn current_balance(context: Context<'_, impl Kernel>) -> Result<sys::TokenAm
         .or_fatal()
 }
 
+pub fn balance_before_receipt(context: Context<'_, impl Kernel>) -> Result<sys::TokenAmount> {
+    let balance = context.kernel.balance_before_receipt()?;
+    balance
+        .try_into()
+        .context("balance exceeds u128")
+        .or_fatal()
+}
+
 pub fn self_destruct(context: Context<'_, impl Kernel>, burn_unspent: u32) -> Result<()> {
     context.kernel.self_destruct(burn_unspent > 0)?;
     Ok(())
 }
+
+/// Writes the actor's delegated (f4) address into the specified buffer, returning its length, or
+/// 0 if the actor has no delegated address.
+pub fn delegated_address(
+    context: Context<'_, impl Kernel>,
+    obuf_off: u32,
+    obuf_len: u32,
+) -> Result<u32> {
+    let obuf = context.memory.try_slice_mut(obuf_off, obuf_len)?;
+    match context.kernel.self_delegated_address()? {
+        Some(address) => {
+            let address = address.to_bytes();
+            obuf.get_mut(..address.len())
+                .ok_or_else(
+                    || syscall_error!(BufferTooSmall; "address output buffer is too small"),
+                )?
+                .copy_from_slice(&address);
+            Ok(address.len() as u32)
+        }
+        None => Ok(0),
+    }
+}
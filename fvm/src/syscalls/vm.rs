@@ -1,12 +1,13 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 use fvm_shared::error::ExitCode;
-use fvm_shared::sys::out::vm::MessageContext;
+use fvm_shared::sys::out::vm::{MemoryUsage, MessageContext, MessageContextV2};
 use fvm_shared::sys::SyscallSafe;
 
 use super::error::Abort;
 use super::Context;
 use crate::kernel::Kernel;
+use crate::syscall_error;
 
 /// An uninhabited type. We use this in `abort` to make sure there's no way to return without
 /// returning an error.
@@ -63,3 +64,59 @@ pub fn exit(
 pub fn message_context(context: Context<'_, impl Kernel>) -> crate::kernel::Result<MessageContext> {
     context.kernel.msg_context()
 }
+
+pub fn message_context_v2(
+    context: Context<'_, impl Kernel>,
+) -> crate::kernel::Result<MessageContextV2> {
+    context.kernel.msg_context_v2()
+}
+
+pub fn memory_usage(context: Context<'_, impl Kernel>) -> crate::kernel::Result<MemoryUsage> {
+    let (used, limit) = context.kernel.memory_usage()?;
+    Ok(MemoryUsage { used, limit })
+}
+
+pub fn read_only_reason(context: Context<'_, impl Kernel>) -> crate::kernel::Result<u32> {
+    context.kernel.read_only_reason().map(|r| r as u32)
+}
+
+pub fn origin_account_nonce(context: Context<'_, impl Kernel>) -> crate::kernel::Result<u64> {
+    context.kernel.origin_account_nonce()
+}
+
+/// Checks whether a buffer is valid UTF-8.
+///
+/// The return i32 indicates the result:
+///  - 0: the buffer is valid UTF-8.
+///  - -1: the buffer isn't valid UTF-8.
+pub fn is_valid_utf8(
+    context: Context<'_, impl Kernel>,
+    data_off: u32,
+    data_len: u32,
+) -> crate::kernel::Result<i32> {
+    let data = context.memory.try_slice(data_off, data_len)?;
+    context
+        .kernel
+        .is_valid_utf8(data)
+        .map(|v| if v { 0 } else { -1 })
+}
+
+pub fn caller_delegated_address(
+    context: Context<'_, impl Kernel>,
+    obuf_off: u32,
+    obuf_len: u32,
+) -> crate::kernel::Result<u32> {
+    let obuf = context.memory.try_slice_mut(obuf_off, obuf_len)?;
+    match context.kernel.caller_delegated_address()? {
+        Some(address) => {
+            let address = address.to_bytes();
+            obuf.get_mut(..address.len())
+                .ok_or_else(
+                    || syscall_error!(BufferTooSmall; "address output buffer is too small"),
+                )?
+                .copy_from_slice(&address);
+            Ok(address.len() as u32)
+        }
+        None => Ok(0),
+    }
+}
@@ -0,0 +1,549 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Mock implementations of the [`Machine`] and [`CallManager`] traits, for unit testing kernel
+//! methods without instantiating the full engine and call stack.
+//!
+//! [`build_kernel`] constructs a [`DefaultKernel`] over a [`MockCallManager`] backed by an
+//! in-memory state tree and blockstore. The call manager's gas tracker, origin, nonce, etc. are
+//! public so tests can script them, and actors can be installed with [`CallManager::set_actor`].
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use anyhow::Context;
+use cid::Cid;
+use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
+use fvm_ipld_encoding::{CborStore, DAG_CBOR};
+use fvm_shared::address::{derive_actor_address, Address};
+use fvm_shared::clock::ChainEpoch;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ExitCode;
+use fvm_shared::event::StampedEvent;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+use fvm_shared::{ActorID, MethodNum, IDENTITY_HASH};
+use multihash::{Code, Multihash};
+use num_traits::Zero;
+
+use crate::call_manager::{Backtrace, CallManager, FinishRet, InvocationResult};
+use crate::engine::{Engine, EnginePool};
+use crate::executor::EventChunks;
+use crate::externs::{Chain, Consensus, Externs, Rand};
+use crate::gas::{Gas, GasCharge, GasTimer, GasTracker};
+use crate::kernel::{self, BlockRegistry};
+use crate::machine::limiter::MemoryLimiter;
+use crate::machine::{Machine, MachineContext, Manifest, NetworkConfig};
+use crate::state_tree::{ActorState, StateTree};
+use crate::{syscall_error, DefaultKernel, Kernel};
+
+pub const STUB_NETWORK_VER: NetworkVersion = NetworkVersion::V21;
+
+/// Mostly unimplemented `Externs` impl. Randomness is fixed per source.
+pub struct MockExterns;
+
+impl Externs for MockExterns {}
+
+impl Rand for MockExterns {
+    fn get_chain_randomness(
+        &self,
+        _round: fvm_shared::clock::ChainEpoch,
+    ) -> anyhow::Result<[u8; 32]> {
+        Ok([1; 32])
+    }
+
+    fn get_beacon_randomness(
+        &self,
+        _round: fvm_shared::clock::ChainEpoch,
+    ) -> anyhow::Result<[u8; 32]> {
+        Ok([2; 32])
+    }
+}
+
+impl Consensus for MockExterns {
+    fn verify_consensus_fault(
+        &self,
+        _h1: &[u8],
+        _h2: &[u8],
+        _extra: &[u8],
+    ) -> anyhow::Result<(Option<fvm_shared::consensus::ConsensusFault>, i64)> {
+        // consensus is always valid for tests :)
+        anyhow::Result::Ok((None, 0))
+    }
+}
+
+impl Chain for MockExterns {
+    fn get_tipset_cid(&self, epoch: fvm_shared::clock::ChainEpoch) -> anyhow::Result<Cid> {
+        Ok(Cid::new_v1(
+            DAG_CBOR,
+            Multihash::wrap(IDENTITY_HASH, &epoch.to_be_bytes()).unwrap(),
+        ))
+    }
+}
+
+#[derive(Default)]
+pub struct MockLimiter {
+    curr_exec_memory_bytes: usize,
+}
+
+impl MemoryLimiter for MockLimiter {
+    fn with_stack_frame<T, G, F, R>(t: &mut T, g: G, f: F) -> R
+    where
+        G: Fn(&mut T) -> &mut Self,
+        F: FnOnce(&mut T) -> R,
+    {
+        let memory_bytes = g(t).curr_exec_memory_bytes;
+        let ret = f(t);
+        g(t).curr_exec_memory_bytes = memory_bytes;
+        ret
+    }
+
+    fn memory_used(&self) -> usize {
+        self.curr_exec_memory_bytes
+    }
+
+    fn grow_memory(&mut self, bytes: usize) -> bool {
+        self.curr_exec_memory_bytes += bytes;
+        true
+    }
+}
+
+/// Minimal *pseudo-functional* implementation of `Machine` for tests
+pub struct MockMachine {
+    pub state_tree: StateTree<MemoryBlockstore>,
+    pub ctx: MachineContext,
+    pub builtin_actors: Manifest,
+}
+
+impl MockMachine {
+    /// Build a mock machine with no builtin actors and an empty state-tree.
+    pub fn new_stub() -> anyhow::Result<Self> {
+        let bs = MemoryBlockstore::new();
+
+        // generate new state root
+        let mut state_tree = StateTree::new(bs, StateTreeVersion::V5)?;
+        let root = state_tree.flush()?;
+        let bs = state_tree.into_store();
+
+        // Add empty built-in actors manifest to blockstore.
+        let manifest = Manifest::dummy();
+        let manifest_cid = bs.put_cbor(&Manifest::DUMMY_CODES, Code::Blake2b256)?;
+
+        // sanity checks
+        bs.has(&root).context("failed to load initial state-root")?;
+        bs.has(&manifest_cid)
+            .context("failed to load builtin actor manifest")?;
+
+        // add bundle root with version 1 and CID of the empty manifest
+        let actors_cid = bs.put_cbor(&(1, manifest_cid), Code::Blake2b256).unwrap();
+
+        // construct state tree from empty root state
+        let state_tree = StateTree::new_from_root(bs, &root)?;
+
+        let mut config = NetworkConfig::new(STUB_NETWORK_VER);
+
+        // generate context from the new generated root and override actors with empty list
+        let ctx = config.override_actors(actors_cid).for_epoch(0, 0, root);
+
+        Ok(Self {
+            ctx,
+            state_tree,
+            builtin_actors: manifest,
+        })
+    }
+}
+
+impl Machine for MockMachine {
+    type Blockstore = MemoryBlockstore;
+    type Externs = MockExterns;
+    type Limiter = MockLimiter;
+
+    fn blockstore(&self) -> &Self::Blockstore {
+        self.state_tree.store()
+    }
+
+    fn context(&self) -> &MachineContext {
+        &self.ctx
+    }
+
+    fn externs(&self) -> &Self::Externs {
+        &MockExterns
+    }
+
+    fn builtin_actors(&self) -> &Manifest {
+        &self.builtin_actors
+    }
+
+    fn state_tree(&self) -> &StateTree<Self::Blockstore> {
+        &self.state_tree
+    }
+
+    fn state_tree_mut(&mut self) -> &mut StateTree<Self::Blockstore> {
+        &mut self.state_tree
+    }
+
+    fn into_store(self) -> Self::Blockstore {
+        self.state_tree.into_store()
+    }
+
+    fn machine_id(&self) -> &str {
+        "mock"
+    }
+
+    fn new_limiter(&self) -> Self::Limiter {
+        MockLimiter::default()
+    }
+
+    fn advance_epoch(&mut self, epoch: ChainEpoch, timestamp: u64) -> kernel::Result<()> {
+        self.ctx.advance_epoch(epoch, timestamp)
+    }
+}
+
+/// Minimal *pseudo-functional* implementation CallManager
+pub struct MockCallManager {
+    pub machine: MockMachine,
+    pub gas_tracker: GasTracker,
+    pub gas_premium: TokenAmount,
+    pub origin: ActorID,
+    pub origin_address: Address,
+    pub origin_delegated_address: Option<Address>,
+    pub nonce: u64,
+    /// The number of actors created so far, which determines the next actor address.
+    pub actors_created: u64,
+    pub implicit: bool,
+    pub call_stack_depth: u32,
+    pub read_only_depth: Option<u32>,
+    pub call_stack: Vec<(ActorID, MethodNum)>,
+    pub events: EventChunks,
+    pub test_data: Rc<RefCell<TestData>>,
+    pub artifact_bytes: u64,
+    limits: MockLimiter,
+    engine: Engine,
+}
+
+/// Information to be read by external tests
+pub struct TestData {
+    pub charge_gas_calls: usize,
+}
+
+const BLOCK_GAS_LIMIT: Gas = Gas::new(fvm_shared::BLOCK_GAS_LIMIT);
+
+impl MockCallManager {
+    pub fn new_stub() -> (Self, Rc<RefCell<TestData>>) {
+        let rc = Rc::new(RefCell::new(TestData {
+            charge_gas_calls: 0,
+        }));
+        let cell_ref = rc.clone();
+        let machine = MockMachine::new_stub().unwrap();
+        let engine = new_engine(&machine);
+        (
+            Self {
+                machine,
+                gas_tracker: GasTracker::new(BLOCK_GAS_LIMIT, Gas::new(0), false),
+                origin: 0,
+                nonce: 0,
+                actors_created: 0,
+                implicit: false,
+                call_stack_depth: 1,
+                read_only_depth: None,
+                call_stack: Vec::new(),
+                events: EventChunks::new(),
+                test_data: rc,
+                artifact_bytes: 0,
+                limits: MockLimiter::default(),
+                origin_address: Address::new_id(0),
+                origin_delegated_address: None,
+                gas_premium: TokenAmount::zero(),
+                engine,
+            },
+            cell_ref,
+        )
+    }
+
+    pub fn new_with_gas(gas_tracker: GasTracker) -> (Self, Rc<RefCell<TestData>>) {
+        let rc = Rc::new(RefCell::new(TestData {
+            charge_gas_calls: 0,
+        }));
+        let cell_ref = rc.clone();
+        let machine = MockMachine::new_stub().unwrap();
+        let engine = new_engine(&machine);
+        (
+            Self {
+                machine,
+                gas_tracker,
+                origin: 0,
+                nonce: 0,
+                actors_created: 0,
+                implicit: false,
+                call_stack_depth: 1,
+                read_only_depth: None,
+                call_stack: Vec::new(),
+                events: EventChunks::new(),
+                test_data: rc,
+                artifact_bytes: 0,
+                limits: MockLimiter::default(),
+                origin_address: Address::new_id(0),
+                origin_delegated_address: None,
+                gas_premium: TokenAmount::zero(),
+                engine,
+            },
+            cell_ref,
+        )
+    }
+}
+
+/// Creates an engine for the mock call manager from the machine's network config. The mock never
+/// executes actor code, but kernel methods may still need the engine (e.g., to preload code).
+fn new_engine(machine: &MockMachine) -> Engine {
+    EnginePool::new_default((&machine.context().network).into())
+        .expect("failed to create engine")
+        .acquire()
+}
+
+impl CallManager for MockCallManager {
+    type Machine = MockMachine;
+
+    fn new(
+        machine: Self::Machine,
+        engine: Engine,
+        _gas_limit: u64,
+        origin: ActorID,
+        origin_address: Address,
+        origin_delegated_address: Option<Address>,
+        _receiver: Option<ActorID>,
+        _receiver_address: Address,
+        nonce: u64,
+        gas_premium: TokenAmount,
+        implicit: bool,
+    ) -> Self {
+        let rc = Rc::new(RefCell::new(TestData {
+            charge_gas_calls: 0,
+        }));
+        let limits = machine.new_limiter();
+        Self {
+            machine,
+            gas_tracker: GasTracker::new(BLOCK_GAS_LIMIT, Gas::new(0), false),
+            gas_premium,
+            origin,
+            origin_address,
+            origin_delegated_address,
+            nonce,
+            actors_created: 0,
+            implicit,
+            call_stack_depth: 1,
+            read_only_depth: None,
+            call_stack: Vec::new(),
+            events: EventChunks::new(),
+            test_data: rc,
+            artifact_bytes: 0,
+            limits,
+            engine,
+        }
+    }
+
+    fn send<K: Kernel<CallManager = Self>>(
+        &mut self,
+        _from: fvm_shared::ActorID,
+        _to: Address,
+        _method: fvm_shared::MethodNum,
+        _params: Option<kernel::Block>,
+        _value: &TokenAmount,
+        _gas_limit: Option<Gas>,
+        _read_only: bool,
+    ) -> kernel::Result<InvocationResult> {
+        Ok(InvocationResult {
+            exit_code: ExitCode::OK,
+            value: None,
+        })
+    }
+
+    fn finish(self) -> (kernel::Result<FinishRet>, Self::Machine) {
+        (
+            Ok(FinishRet {
+                gas_used: 0,
+                backtrace: Backtrace {
+                    frames: Vec::new(),
+                    cause: None,
+                },
+                exec_trace: Vec::new(),
+                events: EventChunks::new(),
+                events_root: None,
+            }),
+            self.machine,
+        )
+    }
+
+    fn machine(&self) -> &Self::Machine {
+        &self.machine
+    }
+
+    fn machine_mut(&mut self) -> &mut Self::Machine {
+        &mut self.machine
+    }
+
+    fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    fn gas_tracker(&self) -> &GasTracker {
+        &self.gas_tracker
+    }
+
+    fn charge_gas(&self, charge: GasCharge) -> kernel::Result<GasTimer> {
+        self.test_data.borrow_mut().charge_gas_calls += 1;
+        self.gas_tracker().apply_charge(charge)
+    }
+
+    fn origin(&self) -> ActorID {
+        self.origin
+    }
+
+    fn origin_delegated_address(&self) -> Option<Address> {
+        self.origin_delegated_address
+    }
+
+    fn implicit(&self) -> bool {
+        self.implicit
+    }
+
+    fn gas_premium(&self) -> &TokenAmount {
+        &self.gas_premium
+    }
+
+    fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    fn next_actor_address(&self) -> Address {
+        derive_actor_address(&self.origin_address, self.nonce, self.actors_created)
+    }
+
+    fn actors_created(&self) -> u64 {
+        self.actors_created
+    }
+
+    fn create_actor(
+        &mut self,
+        code_id: Cid,
+        actor_id: ActorID,
+        delegated_address: Option<Address>,
+    ) -> kernel::Result<()> {
+        if self.machine.state_tree().get_actor(actor_id)?.is_some() {
+            return Err(syscall_error!(Forbidden; "Actor address already exists").into());
+        }
+        self.machine
+            .state_tree_mut()
+            .set_actor(actor_id, ActorState::new_empty(code_id, delegated_address));
+        self.actors_created += 1;
+        Ok(())
+    }
+
+    fn invocation_count(&self) -> u64 {
+        0
+    }
+
+    fn call_stack_depth(&self) -> u32 {
+        self.call_stack_depth
+    }
+
+    fn read_only_depth(&self) -> Option<u32> {
+        self.read_only_depth
+    }
+
+    fn call_stack(&self) -> &[(ActorID, MethodNum)] {
+        &self.call_stack
+    }
+
+    fn artifact_bytes(&self) -> u64 {
+        self.artifact_bytes
+    }
+
+    fn record_artifact_bytes(&mut self, size: u64) {
+        self.artifact_bytes += size;
+    }
+
+    fn limiter_mut(&mut self) -> &mut <Self::Machine as Machine>::Limiter {
+        &mut self.limits
+    }
+
+    fn limiter(&self) -> &<Self::Machine as Machine>::Limiter {
+        &self.limits
+    }
+
+    fn append_event(&mut self, evt: StampedEvent) {
+        self.events.push(evt)
+    }
+
+    fn events_emitted(&self) -> usize {
+        self.events.len()
+    }
+
+    fn events(&self) -> &EventChunks {
+        &self.events
+    }
+
+    fn resolve_address(&self, address: &Address) -> kernel::Result<Option<ActorID>> {
+        self.machine.state_tree().lookup_id(address)
+    }
+
+    fn get_actor(&self, id: ActorID) -> kernel::Result<Option<ActorState>> {
+        self.machine.state_tree().get_actor(id)
+    }
+
+    fn set_actor(&mut self, id: ActorID, state: ActorState) -> kernel::Result<()> {
+        self.machine.state_tree_mut().set_actor(id, state);
+        Ok(())
+    }
+
+    fn delete_actor(&mut self, id: ActorID) -> kernel::Result<()> {
+        self.machine.state_tree_mut().delete_actor(id);
+        Ok(())
+    }
+
+    fn transfer(&mut self, from: ActorID, to: ActorID, value: &TokenAmount) -> kernel::Result<()> {
+        let mut from_actor = self.get_actor(from)?.ok_or_else(
+            || syscall_error!(InsufficientFunds; "transfer sender {from} does not exist"),
+        )?;
+        if &from_actor.balance < value {
+            return Err(
+                syscall_error!(InsufficientFunds; "sender does not have funds to transfer").into(),
+            );
+        }
+        if from == to {
+            return Ok(());
+        }
+        let mut to_actor = self
+            .get_actor(to)?
+            .ok_or_else(|| syscall_error!(NotFound; "transfer recipient {to} does not exist"))?;
+        from_actor.deduct_funds(value)?;
+        to_actor.deposit_funds(value);
+        self.set_actor(from, from_actor)?;
+        self.set_actor(to, to_actor)
+    }
+}
+
+/// A [`DefaultKernel`] running against a [`MockCallManager`].
+pub type MockKernel = DefaultKernel<MockCallManager>;
+
+/// Builds a [`MockKernel`] with a stub call manager, an empty block registry, and actor ID 0 as
+/// the caller and receiver.
+pub fn build_kernel() -> (MockKernel, Rc<RefCell<TestData>>) {
+    let (call_manager, test_data) = MockCallManager::new_stub();
+    (new_kernel(call_manager), test_data)
+}
+
+/// Like [`build_kernel`], but charging gas against the supplied [`GasTracker`].
+pub fn build_kernel_with_gas(gas_tracker: GasTracker) -> (MockKernel, Rc<RefCell<TestData>>) {
+    let (call_manager, test_data) = MockCallManager::new_with_gas(gas_tracker);
+    (new_kernel(call_manager), test_data)
+}
+
+fn new_kernel(call_manager: MockCallManager) -> MockKernel {
+    MockKernel::new(
+        call_manager,
+        BlockRegistry::default(),
+        0,
+        0,
+        0,
+        Zero::zero(),
+        false,
+    )
+}
@@ -4,6 +4,7 @@ use fvm_ipld_encoding::ipld_block::IpldBlock;
 use fvm_shared::address::Address;
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ExitCode;
+use fvm_shared::event::StampedEvent;
 use fvm_shared::{ActorID, MethodNum};
 
 use crate::gas::GasCharge;
@@ -20,6 +21,9 @@ pub type ExecutionTrace = Vec<ExecutionEvent>;
 #[non_exhaustive]
 pub enum ExecutionEvent {
     GasCharge(GasCharge),
+    /// Emitted when gas previously charged under the given name is refunded. The refunded amount
+    /// is the charge's total.
+    GasRefund(GasCharge),
     /// Emitted on each send call regardless whether we actually end up invoking the
     /// actor or not (e.g. if we don't have enough gas or if the actor does not exist)
     Call {
@@ -35,4 +39,10 @@ pub enum ExecutionEvent {
     CallError(SyscallError),
     /// Emitted every time we successfully invoke an actor
     InvokeActor(Cid),
+    /// Emitted every time an actor emits an event, along with the depth of the call frame that
+    /// emitted it.
+    Event {
+        depth: u32,
+        event: StampedEvent,
+    },
 }
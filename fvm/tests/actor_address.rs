@@ -0,0 +1,75 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use fvm::call_manager::{CallManager, DefaultCallManager};
+use fvm::engine::EnginePool;
+use fvm::kernel::{ActorOps, BlockRegistry, Kernel};
+use fvm::machine::Machine;
+use fvm::DefaultKernel;
+use fvm_shared::address::{derive_actor_address, Address};
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::ActorID;
+use pretty_assertions::assert_eq;
+
+use super::*;
+
+type TestCallManager = DefaultCallManager<MockMachine>;
+type TestKernel = DefaultKernel<TestCallManager>;
+
+const SENDER: ActorID = 100;
+
+fn build(origin_address: Address, nonce: u64) -> anyhow::Result<TestCallManager> {
+    let machine = MockMachine::new_stub()?;
+    let engine = EnginePool::new_default((&machine.ctx.network).into())?.acquire();
+    Ok(TestCallManager::new(
+        machine,
+        engine,
+        1_000_000_000,
+        SENDER,
+        origin_address,
+        None,
+        None,
+        Address::new_id(SENDER),
+        nonce,
+        TokenAmount::from_atto(0),
+        false,
+    ))
+}
+
+/// The call manager assigns actor addresses exactly as `derive_actor_address` predicts, whatever
+/// kind of address the message was sent from.
+#[test]
+fn matches_derivation() -> anyhow::Result<()> {
+    let origins = [
+        Address::new_id(SENDER),
+        Address::new_secp256k1(&[1; fvm_shared::address::SECP_PUB_LEN])?,
+        Address::new_delegated(10, &[2; 20])?,
+    ];
+    for origin in origins {
+        for nonce in [0, 1, u64::MAX] {
+            let mut cm = build(origin, nonce)?;
+            let code = *cm.machine().builtin_actors().get_account_code();
+            for count in 0..5u64 {
+                let expected = derive_actor_address(&origin, nonce, count);
+                assert_eq!(cm.actors_created(), count);
+                assert_eq!(cm.next_actor_address(), expected, "{origin}, {nonce}");
+
+                // The kernel (and therefore the syscalls) report the same.
+                let kern = TestKernel::new(
+                    cm,
+                    BlockRegistry::default(),
+                    SENDER,
+                    SENDER,
+                    0,
+                    TokenAmount::from_atto(0),
+                    false,
+                );
+                assert_eq!(kern.actors_created()?, count);
+                assert_eq!(kern.next_actor_address()?, expected);
+                (cm, _) = kern.into_inner();
+
+                cm.create_actor(code, 1000 + count, None)?;
+            }
+        }
+    }
+    Ok(())
+}
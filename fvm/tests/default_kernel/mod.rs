@@ -3,9 +3,7 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
-// test target
-use fvm::kernel::default::DefaultKernel;
-use fvm::kernel::{Block, BlockRegistry};
+use fvm::kernel::Block;
 use fvm::Kernel;
 use multihash::Code;
 use num_traits::Zero;
@@ -14,44 +12,52 @@ use super::*;
 
 mod ops;
 
-type TestingKernel = DefaultKernel<DummyCallManager>;
+type TestingKernel = MockKernel;
 
 /// build a kernel for testing
 pub fn build_inspecting_test() -> anyhow::Result<(TestingKernel, Rc<RefCell<TestData>>)> {
-    // call_manager is not dropped till the end of the function
-    let (call_manager, test_data) = dummy::DummyCallManager::new_stub();
-    // variable for value inspection, only upgrade after done mutating to avoid panic
-
-    let kern = TestingKernel::new(
-        call_manager,
-        BlockRegistry::default(),
-        0,
-        0,
-        0,
-        Zero::zero(),
-        false,
-    );
-    Ok((kern, test_data))
+    Ok(build_kernel())
 }
 
 /// build a kernel with a GasTracker
 pub fn build_inspecting_gas_test(
     gas_tracker: fvm::gas::GasTracker,
 ) -> anyhow::Result<(TestingKernel, Rc<RefCell<TestData>>)> {
-    // call_manager is not dropped till the end of the function
-    let (call_manager, test_data) = dummy::DummyCallManager::new_with_gas(gas_tracker);
-    // variable for value inspection, only upgrade after done mutating to avoid panic
+    Ok(build_kernel_with_gas(gas_tracker))
+}
 
-    let kern = TestingKernel::new(
+/// Rebuilds `kern` as `actor_id` (called by `caller`), after applying `f` to its call manager (e.g.,
+/// to tweak the machine context or state tree). Blocks are kept.
+pub fn rebuild_kernel_as(
+    kern: TestingKernel,
+    caller: fvm_shared::ActorID,
+    actor_id: fvm_shared::ActorID,
+    f: impl FnOnce(&mut MockCallManager),
+) -> TestingKernel {
+    let (mut call_manager, blocks) = kern.into_inner();
+    f(&mut call_manager);
+    TestingKernel::new(
         call_manager,
-        BlockRegistry::default(),
-        0,
-        0,
+        blocks,
+        caller,
+        actor_id,
         0,
         Zero::zero(),
         false,
-    );
-    Ok((kern, test_data))
+    )
+}
+
+/// Rebuilds `kern` after applying `f` to its call manager. See [`rebuild_kernel_as`].
+pub fn rebuild_kernel(kern: TestingKernel, f: impl FnOnce(&mut MockCallManager)) -> TestingKernel {
+    rebuild_kernel_as(kern, 0, 0, f)
+}
+
+/// Rebuilds `kern` under the network version `nv`.
+pub fn rebuild_at_nv(
+    kern: TestingKernel,
+    nv: fvm_shared::version::NetworkVersion,
+) -> TestingKernel {
+    rebuild_kernel(kern, |cm| cm.machine.ctx.network.network_version = nv)
 }
 
 #[macro_export]
@@ -5,10 +5,11 @@ use super::*;
 mod ipld {
 
     use cid::Cid;
-    use fvm::kernel::{IpldBlockOps, SupportedHashes};
+    use fvm::gas::Gas;
+    use fvm::kernel::{BlockId, GasOps, IpldBlockOps, SupportedHashes};
     use fvm::machine::Machine;
     use fvm_ipld_blockstore::Blockstore;
-    use fvm_ipld_encoding::{DAG_CBOR, IPLD_RAW};
+    use fvm_ipld_encoding::{CBOR, DAG_CBOR, IPLD_RAW};
     use multihash::MultihashDigest;
     use pretty_assertions::{assert_eq, assert_ne};
 
@@ -68,6 +69,127 @@ mod ipld {
         Ok(())
     }
 
+    #[test]
+    fn is_dag_codec() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+
+        assert!(kern.is_dag_codec(DAG_CBOR)?);
+        assert!(!kern.is_dag_codec(IPLD_RAW)?);
+        assert!(!kern.is_dag_codec(fvm_ipld_encoding::CBOR)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn block_eq() -> anyhow::Result<()> {
+        let (mut kern, test_data) = build_inspecting_test()?;
+
+        let a = kern.block_create(IPLD_RAW, b"foo bar")?;
+        let same = kern.block_create(IPLD_RAW, b"foo bar")?;
+        let other_codec = kern.block_create(DAG_CBOR, b"foo bar")?;
+        let longer = kern.block_create(IPLD_RAW, b"foo bar baz")?;
+        let different = kern.block_create(IPLD_RAW, b"foo baz")?;
+
+        let calls = test_data.borrow().charge_gas_calls;
+        assert!(kern.block_eq(a, a)?);
+        assert!(kern.block_eq(a, same)?);
+        assert!(!kern.block_eq(a, other_codec)?);
+        assert!(!kern.block_eq(a, longer)?);
+        assert!(!kern.block_eq(longer, a)?);
+        assert!(!kern.block_eq(a, different)?);
+        assert_eq!(test_data.borrow().charge_gas_calls, calls + 6);
+
+        expect_syscall_err!(InvalidHandle, kern.block_eq(a, 42));
+        expect_syscall_err!(InvalidHandle, kern.block_eq(0, a));
+
+        Ok(())
+    }
+
+    #[test]
+    fn block_hash_eq() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+
+        let id = kern.block_create(IPLD_RAW, b"foo bar")?;
+        let other = kern.block_create(IPLD_RAW, b"foo baz")?;
+        let other_codec = kern.block_create(DAG_CBOR, b"foo bar")?;
+        let cid = Cid::new_v1(IPLD_RAW, SupportedHashes::Blake2b256.digest(b"foo bar"));
+
+        assert!(kern.block_hash_eq(id, &cid)?);
+        assert!(!kern.block_hash_eq(other, &cid)?);
+        assert!(!kern.block_hash_eq(other_codec, &cid)?);
+
+        // Only blake2b CIDs are supported.
+        let sha_cid = Cid::new_v1(IPLD_RAW, SupportedHashes::Sha2_256.digest(b"foo bar"));
+        expect_syscall_err!(IllegalCid, kern.block_hash_eq(id, &sha_cid));
+        // And the handle must be valid.
+        expect_syscall_err!(InvalidHandle, kern.block_hash_eq(42, &cid));
+
+        Ok(())
+    }
+
+    #[test]
+    fn block_open_or_create() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+
+        let block = b"foo bar";
+        let cid = Cid::new_v1(IPLD_RAW, SupportedHashes::Blake2b256.digest(block));
+
+        // Not yet reachable, so the block is created.
+        let (id, created) = kern.block_open_or_create(&cid, IPLD_RAW, block)?;
+        assert!(created);
+        assert!(kern.block_hash_eq(id, &cid)?);
+
+        // Once linked, it's opened instead.
+        assert_eq!(kern.block_link(id, Code::Blake2b256.into(), 32)?, cid);
+        let (opened_id, created) = kern.block_open_or_create(&cid, IPLD_RAW, block)?;
+        assert!(!created);
+        assert_ne!(opened_id, id);
+        let mut buf = [0u8; 7];
+        assert_eq!(kern.block_read(opened_id, 0, &mut buf)?, 0);
+        assert_eq!(&buf, block);
+
+        // The data and codec must match the CID.
+        let other = Cid::new_v1(IPLD_RAW, SupportedHashes::Blake2b256.digest(b"foo baz"));
+        expect_syscall_err!(
+            IllegalArgument,
+            kern.block_open_or_create(&other, IPLD_RAW, block)
+        );
+        expect_syscall_err!(
+            IllegalArgument,
+            kern.block_open_or_create(&other, DAG_CBOR, b"foo baz")
+        );
+
+        // Only blake2b CIDs are supported.
+        let sha_cid = Cid::new_v1(IPLD_RAW, SupportedHashes::Sha2_256.digest(block));
+        expect_syscall_err!(
+            IllegalCid,
+            kern.block_open_or_create(&sha_cid, IPLD_RAW, block)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn would_fit_as_return() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+
+        let small = kern.block_create(IPLD_RAW, &[0u8; 1024])?;
+        let big = kern.block_create(IPLD_RAW, &[0u8; 1025])?;
+        assert!(kern.would_fit_as_return(small)?);
+        assert!(kern.would_fit_as_return(big)?);
+
+        // Lower the maximum block size so the bigger block is just over the limit.
+        let kern = rebuild_kernel(kern, |call_manager| {
+            call_manager.machine.ctx.max_block_size = 1024;
+        });
+
+        assert!(kern.would_fit_as_return(small)?);
+        assert!(!kern.would_fit_as_return(big)?);
+        expect_syscall_err!(InvalidHandle, kern.would_fit_as_return(42));
+
+        Ok(())
+    }
+
     #[test]
     fn create() -> anyhow::Result<()> {
         let (mut kern, _) = build_inspecting_test()?;
@@ -129,6 +251,40 @@ mod ipld {
         Ok(())
     }
 
+    #[test]
+    fn patch() -> anyhow::Result<()> {
+        let (mut kern, test_data) = build_inspecting_test()?;
+
+        let id = kern.block_create(IPLD_RAW, b"hello world")?;
+        test_data.borrow_mut().charge_gas_calls = 0;
+
+        let patched = kern.block_patch(id, 6, b"there")?;
+        assert_ne!(patched, id);
+        assert_eq!(
+            test_data.borrow().charge_gas_calls,
+            2,
+            "block_patch should charge for one read and one create"
+        );
+
+        let mut buf = [0u8; 11];
+        kern.block_read(patched, 0, &mut buf)?;
+        assert_eq!(&buf, b"hello there");
+        assert_eq!(kern.block_stat(patched)?.codec, IPLD_RAW);
+
+        // The source block is unchanged.
+        kern.block_read(id, 0, &mut buf)?;
+        assert_eq!(&buf, b"hello world");
+
+        // Patching up to the end is fine, but not past it.
+        kern.block_patch(id, 11, b"")?;
+        kern.block_patch(id, 0, b"hello world")?;
+        expect_syscall_err!(IllegalArgument, kern.block_patch(id, 7, b"there"));
+        expect_syscall_err!(IllegalArgument, kern.block_patch(id, u32::MAX, b"x"));
+        expect_syscall_err!(InvalidHandle, kern.block_patch(42, 0, b"x"));
+
+        Ok(())
+    }
+
     #[test]
     fn link() -> anyhow::Result<()> {
         let (mut kern, _) = build_inspecting_test()?;
@@ -203,6 +359,36 @@ mod ipld {
         Ok(())
     }
 
+    #[test]
+    fn cid() -> anyhow::Result<()> {
+        let (mut kern, test_data) = build_inspecting_test()?;
+
+        let block = "foo".as_bytes();
+        let id = kern.block_create(IPLD_RAW, block)?;
+        test_data.borrow_mut().charge_gas_calls = 0;
+
+        // Computing the CID doesn't make the block reachable.
+        let cid = kern.block_cid(id, Code::Blake2b256.into(), 32)?;
+        assert_eq!(cid, Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(block)));
+        expect_syscall_err!(NotFound, kern.block_open(&cid));
+        expect_syscall_err!(IllegalCid, kern.block_cid(id, Code::Blake2b256.into(), 20));
+        expect_syscall_err!(
+            InvalidHandle,
+            kern.block_cid(42, Code::Blake2b256.into(), 32)
+        );
+        assert_eq!(
+            test_data.borrow().charge_gas_calls,
+            1,
+            "block_cid should charge gas exactly once"
+        );
+
+        // The handle is still valid, and linking yields the same CID.
+        assert_eq!(kern.block_link(id, Code::Blake2b256.into(), 32)?, cid);
+        kern.block_open(&cid)?;
+
+        Ok(())
+    }
+
     #[test]
     fn link_unexpected() -> anyhow::Result<()> {
         let (mut kern, test_data) = build_inspecting_test()?;
@@ -236,6 +422,132 @@ mod ipld {
         Ok(())
     }
 
+    /// Calls `block_contains_link`, returning the gas it used.
+    fn contains_link(
+        kern: TestingKernel,
+        id: BlockId,
+        target: &Cid,
+    ) -> anyhow::Result<(TestingKernel, bool, Gas)> {
+        let (call_manager, blocks) = kern.into_inner();
+        let before = call_manager.gas_tracker.gas_used();
+        let kern = TestingKernel::new(call_manager, blocks, 0, 0, 0, Zero::zero(), false);
+        let found = kern.block_contains_link(id, target)?;
+        let (call_manager, blocks) = kern.into_inner();
+        let used = call_manager.gas_tracker.gas_used() - before;
+        let kern = TestingKernel::new(call_manager, blocks, 0, 0, 0, Zero::zero(), false);
+        Ok((kern, found, used))
+    }
+
+    #[test]
+    fn block_contains_link() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+
+        let first_id = kern.block_create(IPLD_RAW, b"foo")?;
+        let first = kern.block_link(first_id, Code::Blake2b256.into(), 32)?;
+        let second_id = kern.block_create(IPLD_RAW, b"bar")?;
+        let second = kern.block_link(second_id, Code::Blake2b256.into(), 32)?;
+        let missing = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(b"baz"));
+
+        let data = fvm_ipld_encoding::to_vec(&(first, second, 1u64))?;
+        let id = kern.block_create(DAG_CBOR, &data)?;
+
+        let (kern, found, first_gas) = contains_link(kern, id, &first)?;
+        assert!(found);
+        let (kern, found, second_gas) = contains_link(kern, id, &second)?;
+        assert!(found);
+        let (kern, found, missing_gas) = contains_link(kern, id, &missing)?;
+        assert!(!found);
+
+        // Scanning stops at the match, so earlier links are cheaper to find.
+        assert!(first_gas < second_gas, "{first_gas} >= {second_gas}");
+        assert!(second_gas < missing_gas, "{second_gas} >= {missing_gas}");
+
+        // Leaf blocks don't contain links.
+        let (kern, found, _) = contains_link(kern, first_id, &first)?;
+        assert!(!found);
+
+        expect_syscall_err!(InvalidHandle, kern.block_contains_link(123456, &first));
+
+        Ok(())
+    }
+
+    #[test]
+    fn would_create_cycle() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+
+        // top -> mid -> leaf
+        let leaf_id = kern.block_create(IPLD_RAW, b"leaf")?;
+        let leaf = kern.block_link(leaf_id, Code::Blake2b256.into(), 32)?;
+        let mid_id = kern.block_create(DAG_CBOR, &fvm_ipld_encoding::to_vec(&(leaf,))?)?;
+        let mid = kern.block_link(mid_id, Code::Blake2b256.into(), 32)?;
+        let top_id = kern.block_create(DAG_CBOR, &fvm_ipld_encoding::to_vec(&(mid, 1u64))?)?;
+        let top = kern.block_link(top_id, Code::Blake2b256.into(), 32)?;
+
+        // Linking the leaf to the top would form a cycle, but only if we look deep enough to see
+        // it.
+        let before = kern.gas_used();
+        assert!(kern.would_create_cycle(leaf, top, 2)?);
+        let deep_gas = kern.gas_used() - before;
+        let before = kern.gas_used();
+        assert!(!kern.would_create_cycle(leaf, top, 1)?);
+        let shallow_gas = kern.gas_used() - before;
+        assert!(shallow_gas < deep_gas, "{shallow_gas} >= {deep_gas}");
+        assert!(kern.would_create_cycle(mid, top, 1)?);
+
+        // Linking downwards is fine.
+        assert!(!kern.would_create_cycle(top, mid, 10)?);
+        assert!(!kern.would_create_cycle(top, leaf, 10)?);
+
+        // Self-links are always cycles.
+        assert!(kern.would_create_cycle(top, top, 0)?);
+
+        let missing = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(b"missing"));
+        expect_syscall_err!(NotFound, kern.would_create_cycle(top, missing, 10));
+
+        Ok(())
+    }
+
+    #[test]
+    fn link_allowed_hashes() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+        let block = "foo".as_bytes();
+
+        // Only blake2b-256 is allowed by default.
+        let id = kern.block_create(IPLD_RAW, block)?;
+        expect_syscall_err!(
+            IllegalCid,
+            kern.block_link(id, SupportedHashes::Sha2_256.into(), 32)
+        );
+
+        let mut kern = rebuild_kernel(kern, |call_manager| {
+            call_manager
+                .machine
+                .ctx
+                .network
+                .allow_cid_hashes(vec![SupportedHashes::Blake2b256, SupportedHashes::Sha2_256]);
+        });
+
+        let cid = kern.block_link(id, SupportedHashes::Sha2_256.into(), 32)?;
+        assert_eq!(
+            cid,
+            Cid::new_v1(IPLD_RAW, SupportedHashes::Sha2_256.digest(block))
+        );
+        assert!(kern.block_hash_eq(id, &cid)?);
+
+        // Hashes outside the configured set are still rejected.
+        expect_syscall_err!(
+            IllegalCid,
+            kern.block_link(id, SupportedHashes::Keccak256.into(), 32)
+        );
+        // As are truncated digests of allowed hashes.
+        expect_syscall_err!(
+            IllegalCid,
+            kern.block_link(id, SupportedHashes::Sha2_256.into(), 20)
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn read() -> anyhow::Result<()> {
         let (mut kern, _) = build_inspecting_test()?;
@@ -379,6 +691,69 @@ mod ipld {
         Ok(())
     }
 
+    #[test]
+    fn read_u64() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+
+        for value in [0, 23, 24, 1 << 40, u64::MAX] {
+            let id = kern.block_create(DAG_CBOR, &fvm_ipld_encoding::to_vec(&value)?)?;
+            let before = kern.gas_used();
+            assert_eq!(kern.block_read_u64(id)?, value);
+            assert_eq!(
+                kern.gas_used() - before,
+                kern.price_list()
+                    .on_block_read_u64(kern.block_stat(id)?.size as usize)
+                    .total()
+            );
+        }
+
+        // Plain CBOR blocks work too.
+        let id = kern.block_create(CBOR, &[0x18, 0x2a])?;
+        assert_eq!(kern.block_read_u64(id)?, 42);
+
+        // 2^64, encoded as a CBOR big integer.
+        let overflow = [0xc2, 0x49, 0x01, 0, 0, 0, 0, 0, 0, 0, 0];
+        let id = kern.block_create(CBOR, &overflow)?;
+        expect_syscall_err!(IllegalArgument, kern.block_read_u64(id));
+
+        // Not unsigned integers.
+        for bad in [
+            fvm_ipld_encoding::to_vec(&-1i64)?,
+            fvm_ipld_encoding::to_vec("foo")?,
+            fvm_ipld_encoding::to_vec(&[1u64])?,
+            // Trailing data.
+            vec![0x01, 0x02],
+        ] {
+            let id = kern.block_create(CBOR, &bad)?;
+            expect_syscall_err!(IllegalArgument, kern.block_read_u64(id));
+        }
+
+        // Not CBOR.
+        let id = kern.block_create(IPLD_RAW, &[0x01])?;
+        expect_syscall_err!(IllegalArgument, kern.block_read_u64(id));
+
+        expect_syscall_err!(InvalidHandle, kern.block_read_u64(0xFF));
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_near_overflow() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+        let id = kern.block_create(IPLD_RAW, b"foo")?;
+        let buf = &mut [0u8; 1];
+
+        // The read would end just past i32::MAX.
+        expect_syscall_err!(IllegalArgument, kern.block_read(id, i32::MAX as u32, buf));
+
+        // The read ends exactly at i32::MAX.
+        let diff = kern.block_read(id, (i32::MAX - 1) as u32, buf)?;
+        assert_eq!(diff, 3 - i32::MAX);
+        assert_eq!(buf, &[0]);
+
+        Ok(())
+    }
+
     #[test]
     fn stat() -> anyhow::Result<()> {
         let (mut kern, _) = build_inspecting_test()?;
@@ -431,98 +806,2029 @@ mod ipld {
 
         Ok(())
     }
-}
 
-mod gas {
-    use fvm::gas::*;
-    use fvm::kernel::GasOps;
-    use pretty_assertions::assert_eq;
+    #[test]
+    fn create_unreachable_link() -> anyhow::Result<()> {
+        use fvm::kernel::{ExecutionError, SyscallError};
+        use fvm_shared::error::ErrorNumber;
 
-    use super::*;
+        let (mut kern, _) = build_inspecting_test()?;
+        let unreachable = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(b"missing"));
+        let data = fvm_ipld_encoding::to_vec(&unreachable)?;
+        match kern.block_create(DAG_CBOR, &data) {
+            Err(ExecutionError::Syscall(SyscallError(msg, ErrorNumber::NotFound))) => {
+                assert_eq!(
+                    msg,
+                    format!("block links to unreachable CID: {unreachable}")
+                )
+            }
+            res => panic!("expected the block to be rejected, got {res:?}"),
+        }
 
-    const BLOCK_GAS_LIMIT: Gas = Gas::new(10_000_000_000);
+        Ok(())
+    }
 
     #[test]
-    fn test() -> anyhow::Result<()> {
-        let avaliable = Gas::new(10);
-        let gas_tracker = GasTracker::new(avaliable, Gas::new(0), false);
+    fn expanded_block_handles() -> anyhow::Result<()> {
+        use fvm::kernel::BlockRegistry;
 
-        let (kern, _) = build_inspecting_gas_test(gas_tracker)?;
+        let (kern, _) = build_inspecting_test()?;
+        let (call_manager, _) = kern.into_inner();
+        let mut kern = TestingKernel::new(
+            call_manager,
+            BlockRegistry::with_limit(2),
+            0,
+            0,
+            0,
+            Zero::zero(),
+            false,
+        );
 
-        assert_eq!(kern.gas_available(), avaliable);
-        assert_eq!(kern.gas_used(), Gas::new(0));
+        kern.block_create(IPLD_RAW, b"a")?;
+        kern.block_create(IPLD_RAW, b"b")?;
+        expect_syscall_err!(LimitExceeded, kern.block_create(IPLD_RAW, b"c"));
 
-        let _ = kern.charge_gas("charge 6 gas", Gas::new(6))?;
+        // Succeeds within the scope, paying for the expansion.
+        let expansion = kern.price_list().on_expand_block_handles(1).total();
+        let gas_before = kern.gas_used();
+        let id = kern.with_expanded_block_handles(1, |k| k.block_create(IPLD_RAW, b"c"))??;
+        assert_eq!(kern.block_stat(id)?.size, 1);
+        assert!(kern.gas_used() - gas_before >= expansion);
 
-        assert_eq!(kern.gas_available(), Gas::new(4));
-        assert_eq!(kern.gas_used(), Gas::new(6));
+        // But the limit is restored afterwards (and blocks created in the scope still count).
+        expect_syscall_err!(LimitExceeded, kern.block_create(IPLD_RAW, b"d"));
 
         Ok(())
     }
+}
 
-    #[test]
-    fn used() -> anyhow::Result<()> {
-        let used = Gas::new(123456);
-        let gas_tracker = GasTracker::new(BLOCK_GAS_LIMIT, used, false);
+mod message {
+    use fvm::kernel::MessageOps;
+    use fvm::machine::Machine;
+    use fvm::state_tree::ActorState;
+    use fvm_shared::address::Address;
+    use pretty_assertions::assert_eq;
 
-        let (kern, _) = build_inspecting_gas_test(gas_tracker)?;
+    use super::*;
 
-        assert_eq!(kern.gas_used(), used);
+    const CALLER: fvm_shared::ActorID = 100;
 
-        Ok(())
+    fn build_with_caller(delegated: Option<Address>) -> anyhow::Result<TestingKernel> {
+        let (kern, _) = build_inspecting_test()?;
+        Ok(rebuild_kernel_as(kern, CALLER, 0, |call_manager| {
+            let code = *call_manager.machine.builtin_actors().get_account_code();
+            call_manager
+                .machine
+                .state_tree_mut()
+                .set_actor(CALLER, ActorState::new_empty(code, delegated));
+        }))
     }
 
     #[test]
-    fn available() -> anyhow::Result<()> {
-        let avaliable = Gas::new(123456);
-        let gas_tracker = GasTracker::new(avaliable, Gas::new(0), false);
+    fn caller_delegated_address() -> anyhow::Result<()> {
+        let f4 = Address::new_delegated(10, b"foobar")?;
 
-        let (kern, _) = build_inspecting_gas_test(gas_tracker)?;
+        let kern = build_with_caller(Some(f4))?;
+        assert_eq!(kern.caller_delegated_address()?, Some(f4));
 
-        assert_eq!(kern.gas_available(), avaliable);
+        let kern = build_with_caller(None)?;
+        assert_eq!(kern.caller_delegated_address()?, None);
+
+        // A caller that doesn't exist has no delegated address.
+        let (kern, _) = build_inspecting_test()?;
+        assert_eq!(kern.caller_delegated_address()?, None);
 
         Ok(())
     }
 
-    #[test]
-    fn charge() -> anyhow::Result<()> {
-        let test_gas = Gas::new(123456);
-        let gas_tracker = GasTracker::new(test_gas, Gas::new(0), false);
+    /// Builds a kernel whose origin is `CALLER`, as the executor would set it up at the start of
+    /// a message.
+    fn build_with_origin(
+        delegated: Option<Address>,
+        nv: fvm_shared::version::NetworkVersion,
+    ) -> anyhow::Result<TestingKernel> {
+        let (kern, _) = build_inspecting_test()?;
+        Ok(rebuild_kernel_as(kern, CALLER, 0, |call_manager| {
+            let code = *call_manager.machine.builtin_actors().get_account_code();
+            call_manager
+                .machine
+                .state_tree_mut()
+                .set_actor(CALLER, ActorState::new_empty(code, delegated));
+            call_manager.machine.ctx.network.network_version = nv;
+            call_manager.origin = CALLER;
+            call_manager.origin_delegated_address = delegated;
+        }))
+    }
 
-        let (kern, _) = build_inspecting_gas_test(gas_tracker)?;
+    fn build_with_implicit(
+        implicit: bool,
+        nv: fvm_shared::version::NetworkVersion,
+    ) -> anyhow::Result<TestingKernel> {
+        let (kern, _) = build_inspecting_test()?;
+        Ok(rebuild_kernel_as(kern, CALLER, 0, |call_manager| {
+            call_manager.machine.ctx.network.network_version = nv;
+            call_manager.implicit = implicit;
+        }))
+    }
 
-        // charge exactly as much as avaliable
-        let _ = kern.charge_gas("test test 123", test_gas)?;
-        assert_eq!(kern.gas_used(), test_gas);
+    #[test]
+    fn implicit_flag() -> anyhow::Result<()> {
+        use fvm_shared::sys::out::vm::{ContextFlags, MessageContext};
+        use fvm_shared::version::NetworkVersion;
 
-        // charge over by 1
-        expect_out_of_gas!(kern.charge_gas("spend more!", Gas::new(1)));
+        let explicit = build_with_implicit(false, NetworkVersion::V22)?.msg_context()?;
+        let implicit = build_with_implicit(true, NetworkVersion::V22)?.msg_context()?;
+        assert_eq!({ explicit.flags }, ContextFlags::empty());
+        assert_eq!({ implicit.flags }, ContextFlags::IMPLICIT);
+        assert!({ implicit.flags }.implicit());
 
+        // Everything else matches.
         assert_eq!(
-            kern.gas_used(),
-            test_gas,
-            "charging gas over what is avaliable and failing should not affect gas used"
+            MessageContext {
+                flags: explicit.flags,
+                ..implicit
+            },
+            explicit
         );
 
-        // kernel with 0 avaliable gas
-        let gas_tracker = GasTracker::new(Gas::new(0), Gas::new(0), false);
-        let (kern, _) = build_inspecting_gas_test(gas_tracker)?;
-        expect_out_of_gas!(kern.charge_gas("spend more!", test_gas));
+        // The flag isn't exposed before nv22.
+        let implicit = build_with_implicit(true, NetworkVersion::V21)?.msg_context()?;
+        assert_eq!({ implicit.flags }, ContextFlags::empty());
 
         Ok(())
     }
 
-    #[test]
-    fn price_list() -> anyhow::Result<()> {
+    /// Builds a kernel at call depth `depth`, that became read-only at `read_only_depth` (if any).
+    fn build_read_only(
+        read_only_depth: Option<u32>,
+        depth: u32,
+        debugging: bool,
+    ) -> anyhow::Result<TestingKernel> {
         let (kern, _) = build_inspecting_test()?;
+        let (mut call_manager, blocks) = kern.into_inner();
+        call_manager.machine.ctx.actor_debugging = debugging;
+        call_manager.call_stack_depth = depth;
+        call_manager.read_only_depth = read_only_depth;
+        Ok(TestingKernel::new(
+            call_manager,
+            blocks,
+            CALLER,
+            0,
+            0,
+            Zero::zero(),
+            read_only_depth.is_some(),
+        ))
+    }
 
-        let expected_list = price_list_by_network_version(STUB_NETWORK_VER);
+    #[test]
+    fn read_only_reason() -> anyhow::Result<()> {
+        use fvm_shared::sys::ReadOnlyReason;
+
+        // Not read-only, at any depth.
+        let kern = build_read_only(None, 1, true)?;
+        assert_eq!(kern.read_only_reason()?, ReadOnlyReason::NotReadOnly);
+        let kern = build_read_only(None, 2, true)?;
+        assert_eq!(kern.read_only_reason()?, ReadOnlyReason::NotReadOnly);
+
+        // A read-only top-level message, and its callees.
+        let kern = build_read_only(Some(1), 1, true)?;
+        assert_eq!(kern.read_only_reason()?, ReadOnlyReason::MessageLevel);
+        let kern = build_read_only(Some(1), 3, true)?;
+        assert_eq!(kern.read_only_reason()?, ReadOnlyReason::MessageLevel);
+
+        // The child of a read-only send, and its callees.
+        let kern = build_read_only(Some(2), 2, true)?;
         assert_eq!(
-            kern.price_list(),
-            expected_list,
-            "price list should be the same as the one used in the kernel {}",
-            STUB_NETWORK_VER
+            kern.read_only_reason()?,
+            ReadOnlyReason::InheritedFromCaller
         );
+        let kern = build_read_only(Some(2), 3, true)?;
+        assert_eq!(
+            kern.read_only_reason()?,
+            ReadOnlyReason::InheritedFromCaller
+        );
+
+        // Only available when debugging.
+        let kern = build_read_only(Some(1), 1, false)?;
+        expect_syscall_err!(IllegalOperation, kern.read_only_reason());
+
+        Ok(())
+    }
+
+    fn origin_delegated_address(kern: &TestingKernel) -> anyhow::Result<Option<Address>> {
+        let ctx = kern.msg_context_v2()?;
+        Ok(match ctx.origin_delegated_address_len {
+            0 => None,
+            len => Some(Address::from_bytes(
+                &ctx.origin_delegated_address[..len as usize],
+            )?),
+        })
+    }
+
+    #[test]
+    fn origin_delegated_address_in_context() -> anyhow::Result<()> {
+        use fvm_shared::version::NetworkVersion;
+
+        let f4 = Address::new_delegated(10, b"foobar")?;
+
+        let kern = build_with_origin(Some(f4), NetworkVersion::V22)?;
+        assert_eq!(origin_delegated_address(&kern)?, Some(f4));
+        let origin = kern.msg_context_v2()?.context.origin;
+        assert_eq!(origin, CALLER);
+
+        let kern = build_with_origin(None, NetworkVersion::V22)?;
+        assert_eq!(origin_delegated_address(&kern)?, None);
+
+        // The extended context isn't available before nv22.
+        let kern = build_with_origin(Some(f4), NetworkVersion::V21)?;
+        expect_syscall_err!(IllegalOperation, kern.msg_context_v2());
+
+        Ok(())
+    }
+
+    #[test]
+    fn origin_account_nonce() -> anyhow::Result<()> {
+        let kern = build_with_origin(None, fvm_shared::version::NetworkVersion::V21)?;
+        let (mut call_manager, blocks) = kern.into_inner();
+        let mut origin = call_manager
+            .machine
+            .state_tree()
+            .get_actor(CALLER)?
+            .unwrap();
+        origin.sequence = 42;
+        call_manager
+            .machine
+            .state_tree_mut()
+            .set_actor(CALLER, origin);
+        // The message nonce is independent of the origin's current sequence.
+        call_manager.nonce = 41;
+        let kern = TestingKernel::new(call_manager, blocks, CALLER, 0, 0, Zero::zero(), false);
+
+        assert_eq!(kern.origin_account_nonce()?, 42);
+        assert_eq!({ kern.msg_context()?.nonce }, 41);
+
+        Ok(())
+    }
+
+    #[test]
+    fn msg_context_cached() -> anyhow::Result<()> {
+        let (kern, test_data) = build_inspecting_test()?;
+
+        let ctx = kern.msg_context()?;
+        assert_eq!(test_data.borrow().charge_gas_calls, 1);
+
+        // Subsequent calls are free and return the same context.
+        assert_eq!(kern.msg_context()?, ctx);
+        assert_eq!(kern.msg_context()?, ctx);
+        assert_eq!(test_data.borrow().charge_gas_calls, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn origin_delegated_address_is_snapshot() -> anyhow::Result<()> {
+        use fvm_shared::version::NetworkVersion;
+
+        let f4 = Address::new_delegated(10, b"foobar")?;
+        let kern = build_with_origin(Some(f4), NetworkVersion::V22)?;
+
+        // Change the origin's binding mid-message.
+        let kern = rebuild_kernel_as(kern, CALLER, 0, |call_manager| {
+            let code = *call_manager.machine.builtin_actors().get_account_code();
+            call_manager
+                .machine
+                .state_tree_mut()
+                .set_actor(CALLER, ActorState::new_empty(code, None));
+        });
+
+        // The caller lookup sees the change, but the origin's address is from message start.
+        assert_eq!(kern.caller_delegated_address()?, None);
+        assert_eq!(origin_delegated_address(&kern)?, Some(f4));
+
+        Ok(())
+    }
+}
+
+mod actor {
+    use cid::Cid;
+    use fvm::gas::{Gas, GasTracker};
+    use fvm::init_actor::INIT_ACTOR_ID;
+    use fvm::kernel::{ActorOps, SelfOps};
+    use fvm::machine::Machine;
+    use fvm::state_reader::StateReader;
+    use fvm::state_tree::ActorState;
+    use fvm_ipld_encoding::{CborStore, IPLD_RAW};
+    use fvm_shared::address::Address;
+    use fvm_shared::econ::TokenAmount;
+    use multihash::MultihashDigest;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn actor_id_available() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+        let kern = rebuild_kernel(kern, |call_manager| {
+            let code = *call_manager.machine.builtin_actors().get_account_code();
+            call_manager
+                .machine
+                .state_tree_mut()
+                .set_actor(100, ActorState::new_empty(code, None));
+        });
+
+        assert!(!kern.actor_id_available(100)?);
+        assert!(kern.actor_id_available(101)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn self_delegated_address() -> anyhow::Result<()> {
+        let f4 = Address::new_delegated(10, b"foobar")?;
+        let (kern, _) = build_inspecting_test()?;
+        let kern = rebuild_kernel_as(kern, 0, 100, |call_manager| {
+            let code = *call_manager.machine.builtin_actors().get_account_code();
+            call_manager
+                .machine
+                .state_tree_mut()
+                .set_actor(100, ActorState::new_empty(code, Some(f4)));
+            call_manager
+                .machine
+                .state_tree_mut()
+                .set_actor(101, ActorState::new_empty(code, None));
+        });
+        assert_eq!(kern.self_delegated_address()?, Some(f4));
+        assert_eq!(
+            kern.self_delegated_address()?,
+            kern.lookup_delegated_address(100)?
+        );
+
+        let kern = rebuild_kernel_as(kern, 0, 101, |_| {});
+        assert_eq!(kern.self_delegated_address()?, None);
+
+        // A deleted (or missing) actor has no delegated address.
+        let kern = rebuild_kernel_as(kern, 0, 102, |_| {});
+        assert_eq!(kern.self_delegated_address()?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn make_delegated_address() -> anyhow::Result<()> {
+        use fvm_shared::address::MAX_SUBADDRESS_LEN;
+
+        let (kern, _) = build_inspecting_test()?;
+
+        let eth = [
+            0x52, 0x96, 0x3e, 0xf5, 0x0e, 0x27, 0xe0, 0x6d, 0x72, 0xd5, 0x9f, 0xcb, 0x4f, 0x3c,
+            0x2a, 0x68, 0x7b, 0xe3, 0xcf, 0xef,
+        ];
+        let addr = kern.make_delegated_address(10, &eth)?;
+        assert_eq!(
+            addr.to_string(),
+            "f410fkkld55ioe7qg24wvt7fu6pbknb56ht7pt4zamxa"
+        );
+        assert_eq!(addr, Address::new_delegated(10, &eth)?);
+
+        expect_syscall_err!(
+            IllegalArgument,
+            kern.make_delegated_address(10, &[0; MAX_SUBADDRESS_LEN + 1])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn delegated_namespace() -> anyhow::Result<()> {
+        let f4 = Address::new_delegated(10, b"foobar")?;
+        let (kern, _) = build_inspecting_test()?;
+        let kern = rebuild_kernel(kern, |call_manager| {
+            let code = *call_manager.machine.builtin_actors().get_account_code();
+            call_manager
+                .machine
+                .state_tree_mut()
+                .set_actor(100, ActorState::new_empty(code, Some(f4)));
+            call_manager
+                .machine
+                .state_tree_mut()
+                .set_actor(101, ActorState::new_empty(code, None));
+        });
+
+        assert_eq!(kern.delegated_namespace(100)?, Some(10));
+        assert_eq!(kern.delegated_namespace(101)?, None);
+        expect_syscall_err!(NotFound, kern.delegated_namespace(102));
+
+        Ok(())
+    }
+
+    #[test]
+    fn balances_of() -> anyhow::Result<()> {
+        let (kern, test_data) = build_inspecting_test()?;
+        let kern = rebuild_kernel(kern, |call_manager| {
+            let code = *call_manager.machine.builtin_actors().get_account_code();
+            call_manager.machine.state_tree_mut().set_actor(
+                100,
+                ActorState::new(
+                    code,
+                    *fvm::EMPTY_ARR_CID,
+                    TokenAmount::from_atto(10),
+                    0,
+                    None,
+                ),
+            );
+            call_manager
+                .machine
+                .state_tree_mut()
+                .set_actor(101, ActorState::new_empty(code, None));
+        });
+
+        let calls = test_data.borrow().charge_gas_calls;
+        let balances = kern.balances_of(&[100, 102, 101, 100])?;
+        assert_eq!(
+            balances,
+            vec![
+                TokenAmount::from_atto(10),
+                TokenAmount::zero(),
+                TokenAmount::zero(),
+                TokenAmount::from_atto(10),
+            ]
+        );
+        // One balance lookup charge per actor.
+        assert_eq!(test_data.borrow().charge_gas_calls, calls + 4);
+
+        assert!(kern.balances_of(&[])?.is_empty());
+        // Unlike balance_of, missing actors aren't an error.
+        expect_syscall_err!(NotFound, kern.balance_of(102));
+
+        Ok(())
+    }
+
+    #[test]
+    fn address_builtin_type() -> anyhow::Result<()> {
+        let (kern, test_data) = build_inspecting_test()?;
+        let (mut call_manager, blocks) = kern.into_inner();
+        let account_code = *call_manager.machine.builtin_actors().get_account_code();
+        let account_type = call_manager
+            .machine
+            .builtin_actors()
+            .id_by_code(&account_code);
+        let custom_code = Cid::new_v1(IPLD_RAW, Code::Identity.digest(b"custom"));
+        call_manager
+            .machine
+            .state_tree_mut()
+            .set_actor(100, ActorState::new_empty(account_code, None));
+        call_manager
+            .machine
+            .state_tree_mut()
+            .set_actor(101, ActorState::new_empty(custom_code, None));
+        let kern = TestingKernel::new(call_manager, blocks, 0, 0, 0, Zero::zero(), false);
+
+        let calls = test_data.borrow().charge_gas_calls;
+        assert_eq!(
+            kern.address_builtin_type(&Address::new_id(100))?,
+            Some(account_type)
+        );
+        // Resolution, code lookup, and type lookup are each charged.
+        assert_eq!(test_data.borrow().charge_gas_calls, calls + 3);
+
+        // Not a builtin actor.
+        assert_eq!(kern.address_builtin_type(&Address::new_id(101))?, None);
+        // The address resolves, but the actor doesn't exist.
+        assert_eq!(kern.address_builtin_type(&Address::new_id(102))?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_receive_funds() -> anyhow::Result<()> {
+        let (kern, test_data) = build_inspecting_test()?;
+        let (mut call_manager, blocks) = kern.into_inner();
+        call_manager.machine.ctx.network.network_version = fvm_shared::version::NetworkVersion::V22;
+        let account_code = *call_manager.machine.builtin_actors().get_account_code();
+        let init_code = *call_manager.machine.builtin_actors().get_init_code();
+        let custom_code = Cid::new_v1(IPLD_RAW, Code::Identity.digest(b"custom"));
+        let reward = fvm::machine::REWARD_ACTOR_ID;
+        let state_tree = call_manager.machine.state_tree_mut();
+        for (id, code) in [
+            (100, account_code),
+            (reward, account_code),
+            (102, init_code),
+            (103, custom_code),
+        ] {
+            state_tree.set_actor(id, ActorState::new_empty(code, None));
+        }
+        let kern = TestingKernel::new(call_manager, blocks, 0, 100, 0, Zero::zero(), false);
+
+        let calls = test_data.borrow().charge_gas_calls;
+        assert!(kern.can_receive_funds(100)?);
+        assert_eq!(test_data.borrow().charge_gas_calls, calls + 1);
+
+        // Builtin actors without a fund-receiving entrypoint can't receive funds...
+        assert!(!kern.can_receive_funds(102)?);
+        // ...nor can value-rejecting singletons or missing actors.
+        assert!(!kern.can_receive_funds(reward)?);
+        assert!(!kern.can_receive_funds(101)?);
+        // Non-builtin actors are assumed to accept funds.
+        assert!(kern.can_receive_funds(103)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn create_actor() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+        let mut kern = rebuild_kernel_as(kern, 0, INIT_ACTOR_ID, |_| {});
+        let account_code = *kern.machine().builtin_actors().get_account_code();
+        let delegated = Address::new_delegated(10, &[1; 20])?;
+
+        kern.create_actor(account_code, 100, Some(delegated))?;
+        assert_eq!(kern.get_actor_code_cid(100)?, account_code);
+        assert_eq!(kern.lookup_delegated_address(100)?, Some(delegated));
+        assert_eq!(kern.actors_created()?, 1);
+
+        // Existing actors can't be replaced.
+        expect_syscall_err!(Forbidden, kern.create_actor(account_code, 100, None));
+
+        // Only the init actor may create actors.
+        let mut kern = rebuild_kernel_as(kern, 0, 101, |_| {});
+        expect_syscall_err!(Forbidden, kern.create_actor(account_code, 102, None));
+
+        Ok(())
+    }
+
+    #[test]
+    fn enumerate_deployed_code_cids() -> anyhow::Result<()> {
+        let (kern, test_data) = build_inspecting_test()?;
+        let (mut call_manager, blocks) = kern.into_inner();
+        let account_code = *call_manager.machine.builtin_actors().get_account_code();
+        let custom_code = Cid::new_v1(IPLD_RAW, Code::Identity.digest(b"custom"));
+        let state_tree = call_manager.machine.state_tree_mut();
+        for (id, code) in [(100, account_code), (101, account_code), (102, custom_code)] {
+            state_tree.set_actor(id, ActorState::new_empty(code, None));
+        }
+        state_tree.flush()?;
+        // Unflushed changes are included, and deleted actors aren't.
+        state_tree.set_actor(103, ActorState::new_empty(custom_code, None));
+        state_tree.set_actor(102, ActorState::new_empty(account_code, None));
+        state_tree.delete_actor(101);
+
+        let kern = TestingKernel::new(call_manager, blocks, 0, 0, 0, Zero::zero(), false);
+        let calls = test_data.borrow().charge_gas_calls;
+        let mut expected = vec![(account_code, 2), (custom_code, 1)];
+        expected.sort();
+        assert_eq!(kern.enumerate_deployed_code_cids()?, expected);
+        // Each actor is charged for as it's scanned.
+        assert_eq!(test_data.borrow().charge_gas_calls, calls + 3);
+
+        let kern = rebuild_kernel(kern, |call_manager| {
+            let price_list = call_manager.machine.context().price_list;
+            let expected_price = price_list.on_scan_actors(3).total();
+            assert_eq!(call_manager.gas_tracker.gas_used(), expected_price);
+
+            // The scan stops as soon as it runs out of gas.
+            let budget = price_list.on_scan_actors(2).total();
+            call_manager.gas_tracker = GasTracker::new(budget, Gas::zero(), false);
+        });
+        let calls = test_data.borrow().charge_gas_calls;
+        expect_out_of_gas!(kern.enumerate_deployed_code_cids());
+        assert_eq!(test_data.borrow().charge_gas_calls, calls + 3);
+
+        // Only the system actor may enumerate code CIDs.
+        let (kern, _) = build_inspecting_test()?;
+        let kern = rebuild_kernel_as(kern, 0, 100, |_| {});
+        expect_syscall_err!(Forbidden, kern.enumerate_deployed_code_cids());
+
+        Ok(())
+    }
+
+    #[test]
+    fn state_reader_matches_kernel() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+        let (mut call_manager, blocks) = kern.into_inner();
+        let code = *call_manager.machine.builtin_actors().get_account_code();
+        let f4 = Address::new_delegated(10, b"foobar")?;
+        let state = call_manager
+            .machine
+            .state_tree()
+            .store()
+            .put_cbor(&"some state", Code::Blake2b256)?;
+        call_manager.machine.state_tree_mut().set_actor(
+            100,
+            ActorState::new(code, state, TokenAmount::from_atto(10), 0, Some(f4)),
+        );
+        call_manager
+            .machine
+            .state_tree_mut()
+            .set_actor(101, ActorState::new_empty(code, None));
+        let root = call_manager.machine.state_tree_mut().flush()?;
+        let mut kern = TestingKernel::new(call_manager, blocks, 0, 100, 0, Zero::zero(), false);
+
+        // Ask the kernel first, then compare against a reader over the same state root.
+        let kernel_code = kern.get_actor_code_cid(100)?;
+        let kernel_f4 = kern.lookup_delegated_address(100)?;
+        let kernel_no_f4 = kern.lookup_delegated_address(101)?;
+        let kernel_id = kern.resolve_address(&Address::new_id(101))?;
+        let kernel_root = kern.root()?;
+        expect_syscall_err!(NotFound, kern.get_actor_code_cid(102));
+
+        let (call_manager, _) = kern.into_inner();
+        let reader = StateReader::new(call_manager.machine.state_tree().store(), root);
+
+        assert_eq!(reader.get_actor(100)?.map(|a| a.code), Some(kernel_code));
+        assert_eq!(reader.get_actor(102)?, None);
+        assert_eq!(reader.lookup_delegated_address(100)?, kernel_f4);
+        assert_eq!(reader.lookup_delegated_address(101)?, kernel_no_f4);
+        assert_eq!(reader.lookup_delegated_address(102)?, None);
+        assert_eq!(
+            reader.resolve_address(&Address::new_id(101))?,
+            Some(kernel_id)
+        );
+
+        let (state_cid, state_data) = reader.get_actor_state_block(100)?.unwrap();
+        assert_eq!(state_cid, kernel_root);
+        assert_eq!(
+            fvm_ipld_encoding::from_slice::<String>(&state_data)?,
+            "some state"
+        );
+        assert_eq!(reader.get_actor_state_block(102)?, None);
+
+        Ok(())
+    }
+}
+
+mod debug {
+    use fvm::kernel::DebugOps;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn store_artifact_collisions() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("fvm-artifacts-{}", std::process::id()));
+
+        let (kern, _) = build_inspecting_test()?;
+        let mut kern = rebuild_kernel(kern, |call_manager| {
+            call_manager.machine.ctx.max_artifact_bytes_per_message = 10;
+            call_manager.machine.ctx.store_artifacts_in(&dir);
+        });
+
+        let names = [
+            kern.store_artifact("out", b"a")?,
+            kern.store_artifact("out", b"b")?,
+            kern.store_artifact("out", b"c")?,
+        ];
+        assert_eq!(names, ["out", "out.1", "out.2"]);
+
+        // All three artifacts are stored in distinct files.
+        let artifact_dir = dir.join("dummy").join("0").join("0").join("0").join("0");
+        for (name, data) in names.iter().zip(["a", "b", "c"]) {
+            assert_eq!(std::fs::read(artifact_dir.join(name))?, data.as_bytes());
+        }
+
+        // The per-message byte cap is enforced, and rejected artifacts don't count towards it.
+        expect_syscall_err!(LimitExceeded, kern.store_artifact("big", &[0u8; 8]));
+        assert_eq!(kern.store_artifact("fits", &[0u8; 7])?, "fits");
+        expect_syscall_err!(LimitExceeded, kern.store_artifact("full", b"x"));
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn call_stack() -> anyhow::Result<()> {
+        // Nested sends are covered by the call stack integration test; here we only check that
+        // it's free, and gated on debugging.
+        let (kern, test_data) = build_inspecting_test()?;
+        let kern = rebuild_kernel(kern, |call_manager| {
+            call_manager.machine.ctx.actor_debugging = true;
+        });
+
+        let calls = test_data.borrow().charge_gas_calls;
+        assert_eq!(kern.call_stack()?, vec![]);
+        assert_eq!(test_data.borrow().charge_gas_calls, calls);
+
+        let kern = rebuild_kernel(kern, |call_manager| {
+            call_manager.machine.ctx.actor_debugging = false;
+        });
+        expect_syscall_err!(Forbidden, kern.call_stack());
+
+        Ok(())
+    }
+}
+
+mod send {
+    use fvm::kernel::{ExecutionError, GasOps, SyscallError};
+    use fvm::machine::Machine;
+    use fvm::state_tree::ActorState;
+    use fvm_shared::address::Address;
+    use fvm_shared::econ::TokenAmount;
+    use fvm_shared::sys::SendFlags;
+    use fvm_shared::version::NetworkVersion;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const ACTOR: fvm_shared::ActorID = 100;
+
+    #[test]
+    fn missing_params_block() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+
+        let err = kern
+            .send::<TestingKernel>(
+                &Address::new_id(100),
+                0,
+                42,
+                &Zero::zero(),
+                None,
+                SendFlags::empty(),
+            )
+            .expect_err("expected send to fail");
+        match err {
+            ExecutionError::Syscall(SyscallError(msg, code)) => {
+                assert_eq!(code, fvm_shared::error::ErrorNumber::InvalidHandle);
+                assert!(
+                    msg.contains("params block 42 not found in registry"),
+                    "unexpected error message: {msg}"
+                );
+            }
+            e => panic!("unexpected error: {e:?}"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn insufficient_funds() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+        let mut kern = rebuild_kernel_as(kern, 0, ACTOR, |call_manager| {
+            let mut actor =
+                ActorState::new_empty(*call_manager.machine.builtin_actors().get_init_code(), None);
+            actor.balance = TokenAmount::from_atto(10);
+            call_manager
+                .machine
+                .state_tree_mut()
+                .set_actor(ACTOR, actor);
+        });
+
+        let send = |kern: &mut TestingKernel, value: u64| {
+            kern.send::<TestingKernel>(
+                &Address::new_id(101),
+                0,
+                42,
+                &TokenAmount::from_atto(value),
+                None,
+                SendFlags::empty(),
+            )
+        };
+
+        // Before nv22, the (missing) params block is checked first.
+        expect_syscall_err!(InvalidHandle, send(&mut kern, 11));
+
+        let mut kern = rebuild_kernel_as(kern, 0, ACTOR, |call_manager| {
+            call_manager.machine.ctx.network.network_version = NetworkVersion::V22;
+        });
+
+        // From nv22, the balance is checked before the params block, without charging gas.
+        let before = kern.gas_used();
+        expect_syscall_err!(InsufficientFunds, send(&mut kern, 11));
+        assert_eq!(kern.gas_used(), before);
+
+        // Affordable sends get past the check.
+        expect_syscall_err!(InvalidHandle, send(&mut kern, 10));
+
+        Ok(())
+    }
+}
+
+mod inflate {
+    use std::io::Write;
+
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use fvm::kernel::default::INFLATE_CHUNK_SIZE;
+    use fvm::kernel::IpldBlockOps;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn compress(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn valid() -> anyhow::Result<()> {
+        let (kern, test_data) = build_inspecting_test()?;
+        let data = b"foo bar baz ".repeat(100);
+        let compressed = compress(&data);
+
+        assert_eq!(kern.inflate(&compressed, data.len() as u32)?, data);
+        // Input and output are charged separately.
+        assert_eq!(test_data.borrow().charge_gas_calls, 2);
+
+        // Empty data is fine too.
+        assert_eq!(kern.inflate(&compress(&[]), 0)?, Vec::<u8>::new());
+        Ok(())
+    }
+
+    #[test]
+    fn oversized() -> anyhow::Result<()> {
+        let (kern, test_data) = build_inspecting_test()?;
+        let data = vec![0u8; 1 << 20];
+        let compressed = compress(&data);
+
+        expect_syscall_err!(
+            LimitExceeded,
+            kern.inflate(&compressed, data.len() as u32 - 1)
+        );
+        // The input and every chunk of output decompressed up to the limit are charged.
+        let chunks = data.len() as u64 / INFLATE_CHUNK_SIZE;
+        assert_eq!(test_data.borrow().charge_gas_calls, 1 + chunks as usize);
+        Ok(())
+    }
+
+    #[test]
+    fn bomb_runs_out_of_gas() -> anyhow::Result<()> {
+        use fvm::gas::{Gas, GasTracker};
+
+        // Highly compressible: ~1KiB of input inflates to 1MiB.
+        let data = vec![0u8; 1 << 20];
+        let compressed = compress(&data);
+
+        let price_list =
+            fvm::gas::price_list_by_network_version(fvm_shared::version::NetworkVersion::V21);
+        let budget = price_list.on_inflate(compressed.len()).total()
+            + price_list
+                .on_inflate_output(INFLATE_CHUNK_SIZE as usize)
+                .total();
+        let (kern, _) = build_inspecting_gas_test(GasTracker::new(budget, Gas::zero(), false))?;
+
+        // The first chunk is paid for, the second runs out of gas.
+        expect_out_of_gas!(kern.inflate(&compressed, data.len() as u32));
+        Ok(())
+    }
+
+    #[test]
+    fn corrupt() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+        let compressed = compress(b"foo bar baz");
+
+        // Bad header.
+        let mut bad_header = compressed.clone();
+        bad_header[0] ^= 0xff;
+        expect_syscall_err!(IllegalArgument, kern.inflate(&bad_header, 1024));
+
+        // Bad checksum.
+        let mut bad_checksum = compressed;
+        *bad_checksum.last_mut().unwrap() ^= 0xff;
+        expect_syscall_err!(IllegalArgument, kern.inflate(&bad_checksum, 1024));
+
+        // Not compressed at all.
+        expect_syscall_err!(IllegalArgument, kern.inflate(b"not zlib", 1024));
+        Ok(())
+    }
+}
+
+mod balance {
+    use fvm::kernel::SelfOps;
+    use fvm::machine::Machine;
+    use fvm::state_tree::ActorState;
+    use fvm_shared::econ::TokenAmount;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const ACTOR: fvm_shared::ActorID = 100;
+
+    fn build_with_balance(balance: u64, value_received: u64) -> anyhow::Result<TestingKernel> {
+        let (kern, _) = build_inspecting_test()?;
+        let (mut call_manager, blocks) = kern.into_inner();
+        let mut actor =
+            ActorState::new_empty(*call_manager.machine.builtin_actors().get_init_code(), None);
+        actor.balance = TokenAmount::from_atto(balance);
+        call_manager
+            .machine
+            .state_tree_mut()
+            .set_actor(ACTOR, actor);
+        Ok(TestingKernel::new(
+            call_manager,
+            blocks,
+            0,
+            ACTOR,
+            0,
+            TokenAmount::from_atto(value_received),
+            false,
+        ))
+    }
+
+    #[test]
+    fn balance_before_receipt() -> anyhow::Result<()> {
+        let kern = build_with_balance(100, 30)?;
+        assert_eq!(kern.current_balance()?, TokenAmount::from_atto(100));
+        assert_eq!(kern.balance_before_receipt()?, TokenAmount::from_atto(70));
+
+        // Everything was received.
+        let kern = build_with_balance(30, 30)?;
+        assert_eq!(kern.balance_before_receipt()?, TokenAmount::zero());
+
+        // More was received than the actor holds; saturate at zero.
+        let kern = build_with_balance(10, 30)?;
+        assert_eq!(kern.balance_before_receipt()?, TokenAmount::zero());
+
+        Ok(())
+    }
+
+    #[test]
+    fn cached() -> anyhow::Result<()> {
+        use fvm::gas::{Gas, GasTracker};
+
+        let mut kern = rebuild_kernel_as(build_with_balance(100, 0)?, 0, ACTOR, |call_manager| {
+            call_manager.gas_tracker = GasTracker::new(Gas::new(1_000_000), Gas::zero(), true);
+        });
+
+        assert_eq!(kern.current_balance()?, TokenAmount::from_atto(100));
+        assert_eq!(kern.current_balance()?, TokenAmount::from_atto(100));
+
+        // Self-destructing invalidates the cache.
+        kern.self_destruct(true)?;
+        assert_eq!(kern.current_balance()?, TokenAmount::zero());
+
+        let (call_manager, _) = kern.into_inner();
+        let lookups: Vec<_> = call_manager
+            .gas_tracker
+            .drain_trace()
+            .filter_map(|e| match e {
+                fvm::trace::ExecutionEvent::GasCharge(c) => Some(c.name),
+                _ => None,
+            })
+            .filter(|n| n.starts_with("OnSelfBalance"))
+            .collect();
+        // Cached or not, every lookup is charged the same. Self-destruct looks up the balance to
+        // burn it.
+        assert_eq!(lookups, ["OnSelfBalance"; 4]);
+
+        Ok(())
+    }
+}
+
+mod first_invocation {
+    use fvm::kernel::{IpldBlockOps, SelfOps};
+    use fvm::machine::Machine;
+    use fvm::state_tree::ActorState;
+    use fvm_ipld_encoding::IPLD_RAW;
+
+    use super::*;
+
+    const ACTOR: fvm_shared::ActorID = 100;
+
+    fn build() -> anyhow::Result<TestingKernel> {
+        let (kern, _) = build_inspecting_test()?;
+        Ok(rebuild_kernel_as(kern, 0, ACTOR, |call_manager| {
+            let code = *call_manager.machine.builtin_actors().get_init_code();
+            call_manager
+                .machine
+                .state_tree_mut()
+                .set_actor(ACTOR, ActorState::new_empty(code, None));
+        }))
+    }
+
+    #[test]
+    fn fresh_actor() -> anyhow::Result<()> {
+        let kern = build()?;
+        assert!(kern.is_first_invocation()?);
+
+        // The actor doesn't exist.
+        let (kern, _) = build_inspecting_test()?;
+        assert!(!kern.is_first_invocation()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn after_update() -> anyhow::Result<()> {
+        // Bumping the sequence.
+        let (mut call_manager, blocks) = build()?.into_inner();
+        call_manager
+            .machine
+            .state_tree_mut()
+            .mutate_actor(ACTOR, |actor| {
+                actor.sequence += 1;
+                Ok(())
+            })?;
+        let kern = TestingKernel::new(call_manager, blocks, 0, ACTOR, 0, Zero::zero(), false);
+        assert!(!kern.is_first_invocation()?);
+
+        // Setting a non-empty state root.
+        let mut kern = build()?;
+        let id = kern.block_create(IPLD_RAW, b"state")?;
+        let root = kern.block_link(id, Code::Blake2b256.into(), 32)?;
+        kern.set_root(root)?;
+        assert!(!kern.is_first_invocation()?);
+
+        Ok(())
+    }
+}
+
+mod quarantine {
+    use cid::Cid;
+    use fvm::kernel::{ExecutionError, IpldBlockOps, SelfOps};
+    use fvm::machine::{Machine, QuarantinedCid};
+    use fvm::state_tree::ActorState;
+    use fvm_ipld_blockstore::Blockstore;
+    use fvm_ipld_encoding::IPLD_RAW;
+    use multihash::MultihashDigest;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const ACTOR: fvm_shared::ActorID = 100;
+
+    /// Builds a kernel for an actor whose state root is quarantined.
+    fn build() -> anyhow::Result<(TestingKernel, Cid)> {
+        let (kern, _) = build_inspecting_test()?;
+        let (mut call_manager, blocks) = kern.into_inner();
+
+        let state = b"corrupt";
+        let root = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(state));
+        call_manager.machine.blockstore().put_keyed(&root, state)?;
+        let code = *call_manager.machine.builtin_actors().get_init_code();
+        let mut actor = ActorState::new_empty(code, None);
+        actor.state = root;
+        call_manager
+            .machine
+            .state_tree_mut()
+            .set_actor(ACTOR, actor);
+        call_manager.machine.ctx.quarantine_cids([root].into());
+
+        let kern = TestingKernel::new(call_manager, blocks, 0, ACTOR, 0, Zero::zero(), false);
+        Ok((kern, root))
+    }
+
+    fn assert_quarantined<T: std::fmt::Debug>(res: fvm::kernel::Result<T>, cid: &Cid) {
+        match res.expect_err("expected quarantined cid to be rejected") {
+            ExecutionError::Fatal(e) => assert_eq!(
+                e.downcast_ref::<QuarantinedCid>(),
+                Some(&QuarantinedCid(*cid))
+            ),
+            e => panic!("expected a fatal error, got {e:?}"),
+        }
+    }
+
+    #[test]
+    fn open() -> anyhow::Result<()> {
+        let (mut kern, root) = build()?;
+        assert_eq!(kern.root()?, root);
+        assert_quarantined(kern.block_open(&root), &root);
+        assert_quarantined(
+            kern.block_open_or_create(&root, IPLD_RAW, b"corrupt"),
+            &root,
+        );
+
+        // Other blocks can still be opened.
+        let id = kern.block_create(IPLD_RAW, b"fine")?;
+        let cid = kern.block_link(id, Code::Blake2b256.into(), 32)?;
+        kern.block_open(&cid)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn set_root() -> anyhow::Result<()> {
+        let (mut kern, root) = build()?;
+        let id = kern.block_create(IPLD_RAW, b"fine")?;
+        let fine = kern.block_link(id, Code::Blake2b256.into(), 32)?;
+        kern.set_root(fine)?;
+
+        // The old root is still reachable, but can't be restored.
+        assert_quarantined(kern.set_root(root), &root);
+        assert_eq!(kern.root()?, fine);
+
+        Ok(())
+    }
+}
+
+mod retain {
+    use fvm::kernel::IpldBlockOps;
+    use fvm_ipld_encoding::IPLD_RAW;
+
+    use super::*;
+
+    #[test]
+    fn retain_until_link() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+
+        let id = kern.block_create(IPLD_RAW, b"foo")?;
+        let other = kern.block_create(IPLD_RAW, b"bar")?;
+        kern.block_retain(id)?;
+        kern.block_retain(other)?;
+        kern.block_link(id, Code::Blake2b256.into(), 32)?;
+
+        expect_syscall_err!(InvalidHandle, kern.block_retain(0xFF));
+
+        let (_, blocks) = kern.into_inner();
+        assert!(!blocks.is_retained(id), "linking should release the block");
+        assert!(
+            blocks.is_retained(other),
+            "unlinked block should remain retained"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn close_last_block() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+
+        let first = kern.block_create(IPLD_RAW, b"foo")?;
+        let last = kern.block_create(IPLD_RAW, b"bar")?;
+        kern.block_retain(last)?;
+
+        // Only the most recent block may be closed.
+        expect_syscall_err!(IllegalArgument, kern.block_close(first));
+        expect_syscall_err!(InvalidHandle, kern.block_close(0xFF));
+        kern.block_close(last)?;
+        expect_syscall_err!(InvalidHandle, kern.block_stat(last));
+
+        // The handle is reused, and no longer retained.
+        assert_eq!(kern.block_create(IPLD_RAW, b"baz")?, last);
+        kern.block_close(last)?;
+        kern.block_close(first)?;
+
+        let (_, blocks) = kern.into_inner();
+        assert!(blocks.is_empty());
+        assert!(!blocks.is_retained(last));
+
+        Ok(())
+    }
+}
+
+mod crypto {
+    use fvm::kernel::{CryptoOps, GasOps, SupportedHashes};
+    use fvm_shared::commcid;
+    use fvm_shared::randomness::Randomness;
+    use fvm_shared::sector::{
+        AggregateSealVerifyInfo, AggregateSealVerifyProofAndInfos, PoStProof,
+        RegisteredAggregateProof, RegisteredPoStProof, RegisteredSealProof, WindowPoStVerifyInfo,
+    };
+
+    use super::*;
+
+    #[test]
+    fn hash_in_chunks() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+        let data = b"hello streaming world";
+
+        for code in [SupportedHashes::Sha2_256, SupportedHashes::Blake2b512] {
+            let handle = kern.hash_init(code.into())?;
+            for chunk in data.chunks(4) {
+                kern.hash_update(&handle, chunk)?;
+            }
+            assert_eq!(kern.hash_finalize(handle)?, kern.hash(code.into(), data)?);
+        }
+
+        // Computations are independent of each other.
+        let a = kern.hash_init(SupportedHashes::Sha2_256.into())?;
+        let b = kern.hash_init(SupportedHashes::Sha2_256.into())?;
+        kern.hash_update(&a, data)?;
+        let empty = kern.hash(SupportedHashes::Sha2_256.into(), &[])?;
+        assert_eq!(kern.hash_finalize(b)?, empty);
+        let digest = kern.hash_finalize(a)?;
+        assert_eq!(digest, kern.hash(SupportedHashes::Sha2_256.into(), data)?);
+
+        expect_syscall_err!(IllegalArgument, kern.hash_init(0xdead));
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_post_no_sectors() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+
+        let info = WindowPoStVerifyInfo {
+            randomness: Randomness(vec![0u8; 32]),
+            proofs: vec![PoStProof {
+                post_proof: RegisteredPoStProof::StackedDRGWindow2KiBV1P1,
+                proof_bytes: vec![0u8; 192],
+            }],
+            challenged_sectors: vec![],
+            prover: 1000,
+        };
+
+        // Before nv22, there's nothing to verify, so the PoSt passes.
+        assert!(kern.verify_post(&info)?);
+
+        let kern = rebuild_at_nv(kern, fvm_shared::version::NetworkVersion::V22);
+        expect_syscall_err!(IllegalArgument, kern.verify_post(&info));
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_aggregate_seals_mismatched_params() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+
+        let aggregate = |aggregate_proof| AggregateSealVerifyProofAndInfos {
+            miner: 1000,
+            seal_proof: RegisteredSealProof::StackedDRG32GiBV1P1,
+            aggregate_proof,
+            proof: vec![0u8; 32],
+            infos: vec![AggregateSealVerifyInfo {
+                sector_number: 1,
+                randomness: Randomness(vec![0u8; 32]),
+                interactive_randomness: Randomness(vec![0u8; 32]),
+                sealed_cid: commcid::replica_commitment_v1_to_cid(&[0u8; 32]).unwrap(),
+                unsealed_cid: commcid::data_commitment_v1_to_cid(&[0u8; 32]).unwrap(),
+            }],
+        };
+
+        // Both proof types are valid, so the inputs get past the proof type conversion and the
+        // override is verified against a proof declared (and aggregated) with the other type.
+        for (declared, overridden) in [
+            (
+                RegisteredAggregateProof::SnarkPackV2,
+                RegisteredAggregateProof::SnarkPackV1,
+            ),
+            (
+                RegisteredAggregateProof::SnarkPackV1,
+                RegisteredAggregateProof::SnarkPackV2,
+            ),
+        ] {
+            let res = kern.verify_aggregate_seals_with_params(&aggregate(declared), overridden);
+            assert!(
+                !matches!(res, Ok(true)),
+                "{declared:?} aggregate verified as {overridden:?}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_aggregate_seals_diagnostics() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+
+        let info = |sector_number, sealed_cid| AggregateSealVerifyInfo {
+            sector_number,
+            randomness: Randomness(vec![0u8; 32]),
+            interactive_randomness: Randomness(vec![0u8; 32]),
+            sealed_cid,
+            unsealed_cid: commcid::data_commitment_v1_to_cid(&[0u8; 32]).unwrap(),
+        };
+        let aggregate = AggregateSealVerifyProofAndInfos {
+            miner: 1000,
+            seal_proof: RegisteredSealProof::StackedDRG2KiBV1P1,
+            aggregate_proof: RegisteredAggregateProof::SnarkPackV2,
+            proof: vec![0u8; 32],
+            infos: vec![
+                info(
+                    1,
+                    commcid::replica_commitment_v1_to_cid(&[0u8; 32]).unwrap(),
+                ),
+                // A data commitment isn't a valid sealed CID.
+                info(2, commcid::data_commitment_v1_to_cid(&[0u8; 32]).unwrap()),
+            ],
+        };
+
+        let res = kern.verify_aggregate_seals_with_diagnostics(&aggregate)?;
+        assert!(!res.overall);
+        assert_eq!(res.sector_results.len(), 2);
+
+        let (good, bad) = (&res.sector_results[0], &res.sector_results[1]);
+        assert_eq!(good.sector_number, 1);
+        assert!(good.valid, "unexpected error: {:?}", good.error);
+        assert_eq!(bad.sector_number, 2);
+        assert!(!bad.valid);
+        assert!(bad.error.as_ref().unwrap().contains("sealed CID"));
+
+        // An empty aggregate is invalid, but has no sectors to blame.
+        let empty = AggregateSealVerifyProofAndInfos {
+            infos: vec![],
+            ..aggregate
+        };
+        let res = kern.verify_aggregate_seals_with_diagnostics(&empty)?;
+        assert!(!res.overall);
+        assert!(res.sector_results.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn recover_secp_public_keys() -> anyhow::Result<()> {
+        use libsecp256k1::{sign, Message, PublicKey, SecretKey};
+
+        let (kern, test_data) = build_inspecting_test()?;
+
+        let (items, expected): (Vec<_>, Vec<_>) = (1u8..=4)
+            .map(|i| {
+                let privkey = SecretKey::parse(&[i; 32]).unwrap();
+                let hash = [i + 100; 32];
+                let (sig, recovery_id) = sign(&Message::parse(&hash), &privkey);
+                let mut signature = [0; 65];
+                signature[..64].copy_from_slice(&sig.serialize());
+                signature[64] = recovery_id.serialize();
+                (
+                    (hash, signature),
+                    PublicKey::from_secret_key(&privkey).serialize(),
+                )
+            })
+            .unzip();
+
+        assert_eq!(kern.recover_secp_public_keys(&items)?, expected);
+
+        // Each item is charged like an individual recovery.
+        let expected_gas = kern.price_list().on_recover_secp_public_key().total() * 4u64;
+        assert_eq!(test_data.borrow().charge_gas_calls, 4);
+        assert_eq!(kern.gas_used(), expected_gas);
+
+        // A single bad signature fails the whole batch.
+        let mut bad = items.clone();
+        bad[2].1[64] = 42;
+        expect_syscall_err!(IllegalArgument, kern.recover_secp_public_keys(&bad));
+
+        Ok(())
+    }
+
+    #[test]
+    fn groth16_verify_malformed() -> anyhow::Result<()> {
+        let (kern, test_data) = build_inspecting_test()?;
+
+        // Public inputs must be a whole number of scalars.
+        expect_syscall_err!(IllegalArgument, kern.groth16_verify(&[], &[], &[0u8; 33]));
+        // The verifying key must be well-formed.
+        expect_syscall_err!(
+            IllegalArgument,
+            kern.groth16_verify(&[0u8; 16], &[], &[0u8; 32])
+        );
+
+        // Gas is charged up-front, scaled by the number of public inputs and the key size.
+        let prices = kern.price_list();
+        let expected =
+            prices.on_groth16_verify(1, 0).total() + prices.on_groth16_verify(1, 16).total();
+        assert_eq!(test_data.borrow().charge_gas_calls, 2);
+        assert_eq!(kern.gas_used(), expected);
+        assert!(prices.on_groth16_verify(1, 16).total() > prices.on_groth16_verify(1, 0).total());
+
+        Ok(())
+    }
+}
+
+mod gas {
+    use fvm::gas::*;
+    use fvm::kernel::GasOps;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const BLOCK_GAS_LIMIT: Gas = Gas::new(10_000_000_000);
+
+    #[test]
+    fn tracing_enabled() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+        assert!(!kern.gas_tracing_enabled());
+
+        let gas_tracker = GasTracker::new(BLOCK_GAS_LIMIT, Gas::new(0), true);
+        let (kern, _) = build_inspecting_gas_test(gas_tracker)?;
+        assert!(kern.gas_tracing_enabled());
+
+        // Reading the flag is free.
+        assert_eq!(kern.gas_used(), Gas::new(0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test() -> anyhow::Result<()> {
+        let avaliable = Gas::new(10);
+        let gas_tracker = GasTracker::new(avaliable, Gas::new(0), false);
+
+        let (kern, _) = build_inspecting_gas_test(gas_tracker)?;
+
+        assert_eq!(kern.gas_available(), avaliable);
+        assert_eq!(kern.gas_used(), Gas::new(0));
+
+        let _ = kern.charge_gas("charge 6 gas", Gas::new(6))?;
+
+        assert_eq!(kern.gas_available(), Gas::new(4));
+        assert_eq!(kern.gas_used(), Gas::new(6));
+
+        Ok(())
+    }
+
+    #[test]
+    fn per_epoch() -> anyhow::Result<()> {
+        let gas_tracker = GasTracker::new(BLOCK_GAS_LIMIT, Gas::new(0), false);
+        let (kern, _) = build_inspecting_gas_test(gas_tracker)?;
+
+        let _ = kern.charge_gas_per_epoch("per epoch", Gas::new(7), 3)?;
+        assert_eq!(kern.gas_used(), Gas::new(21));
+        let _ = kern.charge_gas_per_epoch("per epoch", Gas::new(7), 0)?;
+        assert_eq!(kern.gas_used(), Gas::new(21));
+        expect_syscall_err!(
+            IllegalArgument,
+            kern.charge_gas_per_epoch("per epoch", Gas::new(7), -1)
+        );
+        assert_eq!(kern.gas_used(), Gas::new(21));
+
+        // Huge durations saturate, and run out of gas rather than wrapping.
+        expect_out_of_gas!(kern.charge_gas_per_epoch("per epoch", Gas::new(7), i64::MAX));
+
+        Ok(())
+    }
+
+    #[test]
+    fn submeters() -> anyhow::Result<()> {
+        use fvm_shared::version::NetworkVersion;
+
+        let gas_tracker = GasTracker::new(BLOCK_GAS_LIMIT, Gas::new(0), false);
+        let (kern, _) = build_inspecting_gas_test(gas_tracker)?;
+
+        // Sub-meters aren't supported before nv22.
+        let mut kern = rebuild_at_nv(kern, NetworkVersion::V21);
+        expect_syscall_err!(IllegalOperation, kern.create_submeter(Gas::new(10)));
+
+        let mut kern = rebuild_at_nv(kern, NetworkVersion::V22);
+        let meter = kern.create_submeter(Gas::new(10))?;
+        assert_eq!(meter, 0);
+        let _ = kern.charge_to(meter, Gas::new(6))?;
+        assert_eq!(kern.submeter_remaining(meter)?, Gas::new(4));
+        assert_eq!(kern.gas_used(), Gas::new(6));
+
+        // Exhausting the sub-meter is recoverable and charges nothing.
+        expect_syscall_err!(LimitExceeded, kern.charge_to(meter, Gas::new(5)));
+        assert_eq!(kern.submeter_remaining(meter)?, Gas::new(4));
+        assert_eq!(kern.gas_used(), Gas::new(6));
+        expect_syscall_err!(NotFound, kern.submeter_remaining(1));
+
+        // Handles are scoped to the invocation that created them: another invocation sharing the
+        // same gas tracker can't see this invocation's sub-meters, and its handles start over.
+        let mut kern = rebuild_kernel_as(kern, 0, 100, |_| {});
+        expect_syscall_err!(NotFound, kern.charge_to(meter, Gas::new(1)));
+        expect_syscall_err!(NotFound, kern.submeter_remaining(meter));
+        let other = kern.create_submeter(Gas::new(50))?;
+        assert_eq!(other, 0);
+        assert_eq!(kern.submeter_remaining(other)?, Gas::new(50));
+        assert_eq!(kern.gas_used(), Gas::new(6));
+
+        Ok(())
+    }
+
+    #[test]
+    fn used() -> anyhow::Result<()> {
+        let used = Gas::new(123456);
+        let gas_tracker = GasTracker::new(BLOCK_GAS_LIMIT, used, false);
+
+        let (kern, _) = build_inspecting_gas_test(gas_tracker)?;
+
+        assert_eq!(kern.gas_used(), used);
+
+        Ok(())
+    }
+
+    #[test]
+    fn available() -> anyhow::Result<()> {
+        let avaliable = Gas::new(123456);
+        let gas_tracker = GasTracker::new(avaliable, Gas::new(0), false);
+
+        let (kern, _) = build_inspecting_gas_test(gas_tracker)?;
+
+        assert_eq!(kern.gas_available(), avaliable);
+
+        Ok(())
+    }
+
+    #[test]
+    fn charge() -> anyhow::Result<()> {
+        let test_gas = Gas::new(123456);
+        let gas_tracker = GasTracker::new(test_gas, Gas::new(0), false);
+
+        let (kern, _) = build_inspecting_gas_test(gas_tracker)?;
+
+        // charge exactly as much as avaliable
+        let _ = kern.charge_gas("test test 123", test_gas)?;
+        assert_eq!(kern.gas_used(), test_gas);
+
+        // charge over by 1
+        expect_out_of_gas!(kern.charge_gas("spend more!", Gas::new(1)));
+
+        assert_eq!(
+            kern.gas_used(),
+            test_gas,
+            "charging gas over what is avaliable and failing should not affect gas used"
+        );
+
+        // kernel with 0 avaliable gas
+        let gas_tracker = GasTracker::new(Gas::new(0), Gas::new(0), false);
+        let (kern, _) = build_inspecting_gas_test(gas_tracker)?;
+        expect_out_of_gas!(kern.charge_gas("spend more!", test_gas));
+
+        Ok(())
+    }
+
+    #[test]
+    fn price_list() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+
+        let expected_list = price_list_by_network_version(STUB_NETWORK_VER);
+        assert_eq!(
+            kern.price_list(),
+            expected_list,
+            "price list should be the same as the one used in the kernel {}",
+            STUB_NETWORK_VER
+        );
+
+        Ok(())
+    }
+
+    /// Reads a block in small chunks until we run out of gas, returning the number of successful
+    /// reads and the gas used.
+    fn read_until_out_of_gas(gas_tracker: GasTracker) -> anyhow::Result<(usize, Gas)> {
+        use fvm::kernel::{ExecutionError, IpldBlockOps};
+        use fvm_ipld_encoding::IPLD_RAW;
+
+        let (mut kern, _) = build_inspecting_gas_test(gas_tracker)?;
+        let id = kern.block_create(IPLD_RAW, &[0xaa; 4096])?;
+        let mut buf = [0u8; 32];
+        let mut reads = 0u32;
+        loop {
+            match kern.block_read(id, (reads % 128) * 32, &mut buf) {
+                Ok(_) => reads += 1,
+                Err(ExecutionError::OutOfGas) => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok((reads as usize, kern.gas_used()))
+    }
+
+    #[test]
+    fn batched_charges() -> anyhow::Result<()> {
+        let limit = Gas::new(1_000_000);
+        let batched = read_until_out_of_gas(GasTracker::new(limit, Gas::new(0), false))?;
+        let unbatched =
+            read_until_out_of_gas(GasTracker::new(limit, Gas::new(0), false).without_batching())?;
+        assert_eq!(batched, unbatched);
+        assert_eq!(batched.1, limit);
+
+        Ok(())
+    }
+
+    #[test]
+    fn estimate_fee() -> anyhow::Result<()> {
+        use fvm_shared::econ::TokenAmount;
+
+        let (kern, _) = build_inspecting_test()?;
+        let kern = rebuild_kernel(kern, |call_manager| {
+            call_manager.machine.ctx.base_fee = TokenAmount::from_atto(100);
+        });
+
+        let premium = TokenAmount::from_atto(5);
+        assert_eq!(
+            kern.estimate_fee(Gas::new(1000), &premium)?,
+            TokenAmount::from_atto(105_000)
+        );
+        // Fractional gas is rounded up, as the message's gas limit is whole gas.
+        assert_eq!(
+            kern.estimate_fee(Gas::from_milligas(1500), &premium)?,
+            TokenAmount::from_atto(210)
+        );
+        assert_eq!(
+            kern.estimate_fee(Gas::new(1000), &Zero::zero())?,
+            TokenAmount::from_atto(100_000)
+        );
+        // Each estimate is charged.
+        let price_list =
+            fvm::gas::price_list_by_network_version(fvm_shared::version::NetworkVersion::V21);
+        assert_eq!(kern.gas_used(), price_list.on_estimate_fee().total() * 3u64);
+
+        Ok(())
+    }
+}
+
+mod randomness {
+    use fvm::kernel::RandomnessOps;
+    use fvm_shared::randomness::RandSource;
+    use pretty_assertions::{assert_eq, assert_ne};
+
+    use super::*;
+
+    #[test]
+    fn random_in_range_zero_bound() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+        expect_syscall_err!(
+            IllegalArgument,
+            kern.random_in_range(RandSource::Chain, 0, b"entropy", 0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn random_in_range_deterministic() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+
+        assert_eq!(kern.random_in_range(RandSource::Chain, 0, b"foo", 1)?, 0);
+
+        let a = kern.random_in_range(RandSource::Chain, 0, b"foo", u64::MAX)?;
+        let b = kern.random_in_range(RandSource::Chain, 0, b"foo", u64::MAX)?;
+        assert_eq!(a, b, "same inputs should produce the same value");
+
+        let c = kern.random_in_range(RandSource::Beacon, 0, b"foo", u64::MAX)?;
+        let d = kern.random_in_range(RandSource::Chain, 0, b"bar", u64::MAX)?;
+        assert_ne!(a, c, "the source should be mixed in");
+        assert_ne!(a, d, "the entropy should be mixed in");
+        Ok(())
+    }
+
+    #[test]
+    fn random_in_range_uniform() -> anyhow::Result<()> {
+        const BOUND: u64 = 10;
+        const DRAWS: u64 = 10_000;
+
+        let (kern, _) = build_inspecting_test()?;
+
+        let mut counts = [0u64; BOUND as usize];
+        for i in 0..DRAWS {
+            let v = kern.random_in_range(RandSource::Beacon, 0, &i.to_be_bytes(), BOUND)?;
+            assert!(v < BOUND, "value {} out of range", v);
+            counts[v as usize] += 1;
+        }
+
+        // Pearson's chi-squared test with 9 degrees of freedom. The critical value for p = 0.001
+        // is 27.88.
+        let expected = (DRAWS / BOUND) as f64;
+        let chi_squared: f64 = counts
+            .iter()
+            .map(|&c| (c as f64 - expected).powi(2) / expected)
+            .sum();
+        assert!(
+            chi_squared < 27.88,
+            "distribution isn't uniform (chi-squared = {}): {:?}",
+            chi_squared,
+            counts
+        );
+        Ok(())
+    }
+}
+
+mod network {
+    use fvm::kernel::{GasOps, NetworkOps};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn block_delay_seconds() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+        assert_eq!(kern.block_delay_seconds()?, 30);
+        assert_eq!({ kern.network_context()?.block_delay_seconds }, 30);
+
+        let kern = rebuild_kernel(kern, |call_manager| {
+            call_manager.machine.ctx.network.block_delay_seconds = 4;
+        });
+        assert_eq!(kern.block_delay_seconds()?, 4);
+        assert_eq!({ kern.network_context()?.block_delay_seconds }, 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn epoch_elapsed() -> anyhow::Result<()> {
+        let (kern, test_data) = build_inspecting_test()?;
+        let kern = rebuild_kernel(kern, |call_manager| {
+            call_manager.machine.ctx.epoch = 100;
+        });
+        let calls = test_data.borrow().charge_gas_calls;
+
+        assert!(!kern.epoch_elapsed(101)?);
+        assert!(kern.epoch_elapsed(100)?);
+        assert!(kern.epoch_elapsed(99)?);
+
+        // A single comparison; the syscall cost covers it.
+        assert_eq!(test_data.borrow().charge_gas_calls, calls + 3);
+        assert!(kern.gas_used().is_zero());
+
+        Ok(())
+    }
+}
+
+mod encoding {
+    use fvm::kernel::{EncodingOps, GasOps};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn is_valid_utf8() -> anyhow::Result<()> {
+        let (kern, test_data) = build_inspecting_test()?;
+
+        assert!(kern.is_valid_utf8(b"")?);
+        assert!(kern.is_valid_utf8("hello, world".as_bytes())?);
+        assert!(kern.is_valid_utf8("f\u{00fc}r \u{1F980}".as_bytes())?);
+
+        // Truncated multi-byte sequence.
+        assert!(!kern.is_valid_utf8(&[0xe2, 0x82])?);
+        // Overlong encoding of '/'.
+        assert!(!kern.is_valid_utf8(&[0xc0, 0xaf])?);
+        // Encoded surrogate.
+        assert!(!kern.is_valid_utf8(&[0xed, 0xa0, 0x80])?);
+        // Lone continuation byte after valid ASCII.
+        assert!(!kern.is_valid_utf8(b"abc\x80")?);
+
+        assert_eq!(test_data.borrow().charge_gas_calls, 7);
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_valid_utf8_charges_by_length() -> anyhow::Result<()> {
+        let (kern, _) = build_inspecting_test()?;
+
+        let expected = kern.price_list().on_utf8_validation(1024).total();
+        let before = kern.gas_used();
+        kern.is_valid_utf8(&[b'a'; 1024])?;
+        assert_eq!(kern.gas_used() - before, expected);
+        assert!(expected > kern.price_list().on_utf8_validation(1).total());
+
+        Ok(())
+    }
+}
+
+mod limiter {
+    use fvm::kernel::LimiterOps;
+    use fvm::machine::limiter::MemoryLimiter;
+    use fvm_shared::version::NetworkVersion;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn memory_usage() -> anyhow::Result<()> {
+        let (kern, test_data) = build_inspecting_test()?;
+
+        // Not available before nv22.
+        expect_syscall_err!(IllegalOperation, kern.memory_usage());
+        assert_eq!(test_data.borrow().charge_gas_calls, 0);
+
+        let mut kern = rebuild_at_nv(kern, NetworkVersion::V22);
+
+        let (used, limit) = kern.memory_usage()?;
+        assert_eq!(test_data.borrow().charge_gas_calls, 1);
+
+        // Growing the instance's memory (as the actor would when allocating) increases usage.
+        assert!(kern.limiter_mut().grow_instance_memory(0, 1 << 20));
+        let (grown, new_limit) = kern.memory_usage()?;
+        assert_eq!(grown, used + (1 << 20));
+        assert_eq!(new_limit, limit);
+
+        Ok(())
+    }
+}
+
+mod event {
+    use cid::Cid;
+    use fvm::kernel::{EventOps, GasOps, RawEvent, SupportedHashes};
+    use fvm::machine::Machine;
+    use fvm_ipld_amt::Amt;
+    use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
+    use fvm_ipld_encoding::IPLD_RAW;
+    use fvm_shared::event::{EventKey, Flags, StampedEvent};
+    use fvm_shared::sys::EventEntry;
+    use multihash::MultihashDigest;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn build_at_depth(depth: u32, max_emit_depth: u32) -> anyhow::Result<TestingKernel> {
+        let (kern, _) = build_inspecting_test()?;
+        Ok(rebuild_kernel(kern, |call_manager| {
+            call_manager.machine.ctx.max_event_emit_depth = max_emit_depth;
+            call_manager.call_stack_depth = depth;
+        }))
+    }
+
+    fn emit(kern: &mut TestingKernel) -> fvm::kernel::Result<()> {
+        let entry = EventEntry {
+            flags: Flags::FLAG_INDEXED_ALL,
+            codec: IPLD_RAW,
+            key_len: 1,
+            val_len: 3,
+        };
+        kern.emit_event(&[entry], b"k", b"foo")
+    }
+
+    #[test]
+    fn max_emit_depth() -> anyhow::Result<()> {
+        // Below and at the limit.
+        for depth in [2, 3] {
+            let mut kern = build_at_depth(depth, 3)?;
+            emit(&mut kern)?;
+            let (call_manager, _) = kern.into_inner();
+            assert_eq!(call_manager.events.len(), 1);
+        }
+
+        // Above the limit.
+        let mut kern = build_at_depth(4, 3)?;
+        expect_syscall_err!(LimitExceeded, emit(&mut kern));
+        let (call_manager, _) = kern.into_inner();
+        assert!(call_manager.events.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn cid_key() -> anyhow::Result<()> {
+        let cid = Cid::new_v1(IPLD_RAW, SupportedHashes::Blake2b256.digest(b"foo"));
+        let key = cid.to_bytes();
+        let entry = EventEntry {
+            flags: Flags::FLAG_INDEXED_ALL | Flags::FLAG_CID_KEY,
+            codec: IPLD_RAW,
+            key_len: key.len() as u32,
+            val_len: 3,
+        };
+
+        // Before nv22, the flag is invalid.
+        let (mut kern, _) = build_inspecting_test()?;
+        expect_syscall_err!(IllegalArgument, kern.emit_event(&[entry], &key, b"bar"));
+
+        let mut kern = rebuild_at_nv(kern, fvm_shared::version::NetworkVersion::V22);
+        kern.emit_event(&[entry], &key, b"bar")?;
+
+        // Keys that aren't exactly one CID are rejected.
+        let mut bad_key = key.clone();
+        bad_key.push(0);
+        let bad_entry = EventEntry {
+            key_len: bad_key.len() as u32,
+            ..entry
+        };
+        expect_syscall_err!(
+            IllegalArgument,
+            kern.emit_event(&[bad_entry], &bad_key, b"bar")
+        );
+        expect_syscall_err!(
+            IllegalArgument,
+            kern.emit_event(&[entry], &vec![0xff; key.len()], b"bar")
+        );
+
+        let (call_manager, _) = kern.into_inner();
+        assert_eq!(call_manager.events.len(), 1);
+        let evt = &call_manager.events[0];
+        assert_eq!(evt.event.entries[0].key, EventKey::Cid(cid));
+
+        // The key round-trips through the event encoding.
+        let encoded = fvm_ipld_encoding::to_vec(evt)?;
+        let decoded: StampedEvent = fvm_ipld_encoding::from_slice(&encoded)?;
+        assert_eq!(&decoded, evt);
+
+        Ok(())
+    }
+
+    #[test]
+    fn emit_dedup() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+
+        let entry = |val_len| EventEntry {
+            flags: Flags::FLAG_INDEXED_ALL,
+            codec: IPLD_RAW,
+            key_len: 1,
+            val_len,
+        };
+        let (foo, bar) = ([entry(3)], [entry(3)]);
+        let a = RawEvent {
+            headers: &foo,
+            keys: b"k",
+            values: b"foo",
+        };
+        let b = RawEvent {
+            headers: &bar,
+            keys: b"k",
+            values: b"bar",
+        };
+        let batch = [a, a, a, b, b, a];
+
+        let before = kern.gas_used();
+        assert_eq!(kern.emit_events_dedup(&batch)?, 3);
+        let used = kern.gas_used() - before;
+
+        // Only the emitted events are charged for, plus a comparison per event after the first.
+        let prices = kern.price_list();
+        let emit = prices.on_actor_event(1, 1, 3).total();
+        let compare = prices.on_event_compare(a.size()).total();
+        assert_eq!(used, emit * 3u64 + compare * 5u64);
+
+        // Duplicates are only collapsed if they're adjacent.
+        let (call_manager, _) = kern.into_inner();
+        let values: Vec<_> = call_manager
+            .events
+            .iter()
+            .map(|e| e.event.entries[0].value.clone())
+            .collect();
+        assert_eq!(values, [b"foo".to_vec(), b"bar".to_vec(), b"foo".to_vec()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn gas_cost() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+
+        let entries = [
+            EventEntry {
+                flags: Flags::FLAG_INDEXED_ALL,
+                codec: IPLD_RAW,
+                key_len: 3,
+                val_len: 5,
+            },
+            EventEntry {
+                flags: Flags::FLAG_INDEXED_KEY,
+                codec: IPLD_RAW,
+                key_len: 1,
+                val_len: 0,
+            },
+        ];
+
+        let before = kern.gas_used();
+        let estimate = kern.event_gas_cost(&entries, b"fook", b"hello")?;
+        let estimated = kern.gas_used();
+        assert_eq!(
+            estimated - before,
+            kern.price_list().on_event_gas_cost().total()
+        );
+
+        // The estimate matches the actual charge.
+        kern.emit_event(&entries, b"fook", b"hello")?;
+        assert_eq!(kern.gas_used() - estimated, estimate);
+
+        // Estimating doesn't emit.
+        let (call_manager, _) = kern.into_inner();
+        assert_eq!(call_manager.events.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn events_remaining() -> anyhow::Result<()> {
+        // Unlimited by default.
+        let (mut kern, _) = build_inspecting_test()?;
+        assert_eq!(kern.events_remaining()?, u32::MAX);
+        emit(&mut kern)?;
+        assert_eq!(kern.events_remaining()?, u32::MAX);
+
+        let mut kern = rebuild_kernel(kern, |call_manager| {
+            call_manager.machine.ctx.max_events_per_message = Some(3);
+        });
+
+        // Events emitted earlier in the message count towards the limit.
+        assert_eq!(kern.events_remaining()?, 2);
+        emit(&mut kern)?;
+        assert_eq!(kern.events_remaining()?, 1);
+        emit(&mut kern)?;
+        assert_eq!(kern.events_remaining()?, 0);
+        expect_syscall_err!(LimitExceeded, emit(&mut kern));
+        assert_eq!(kern.events_remaining()?, 0);
+
+        let (call_manager, _) = kern.into_inner();
+        assert_eq!(call_manager.events.len(), 3);
+
+        Ok(())
+    }
+    #[test]
+    fn events_root_so_far() -> anyhow::Result<()> {
+        let (mut kern, _) = build_inspecting_test()?;
+        assert_eq!(kern.events_root_so_far()?, None);
+
+        emit(&mut kern)?;
+        emit(&mut kern)?;
+        emit(&mut kern)?;
+
+        let before = kern.gas_used();
+        let root = kern.events_root_so_far()?.expect("expected an events root");
+        assert_eq!(
+            kern.gas_used() - before,
+            kern.price_list().on_events_root(3, 3, 12).total()
+        );
+
+        let (mut call_manager, blocks) = kern.into_inner();
+        let expected = Amt::new_from_iter_with_bit_width(
+            MemoryBlockstore::default(),
+            5,
+            call_manager.events.iter(),
+        )?;
+        assert_eq!(root, expected);
+
+        // Drop the last event, as if the call that emitted it had been reverted.
+        call_manager.events.pop();
+        let expected = Amt::new_from_iter_with_bit_width(
+            MemoryBlockstore::default(),
+            5,
+            call_manager.events.iter(),
+        )?;
+        let kern = TestingKernel::new(call_manager, blocks, 0, 0, 0, Zero::zero(), false);
+        let root = kern.events_root_so_far()?.expect("expected an events root");
+        assert_eq!(root, expected);
+
+        // Nothing is written to the blockstore.
+        let (call_manager, _) = kern.into_inner();
+        assert!(!call_manager.machine.blockstore().has(&root)?);
 
         Ok(())
     }
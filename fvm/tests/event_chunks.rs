@@ -0,0 +1,53 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Checks that accumulating events never allocates a buffer larger than a single chunk. This
+//! lives in its own test binary because it installs a global allocator.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use fvm::executor::EventChunks;
+use fvm_shared::event::StampedEvent;
+
+/// Tracks the size of the largest allocation (or reallocation) made through it.
+struct MaxAlloc;
+
+static MAX_ALLOC: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for MaxAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        MAX_ALLOC.fetch_max(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        MAX_ALLOC.fetch_max(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: MaxAlloc = MaxAlloc;
+
+#[test]
+fn no_large_allocations() {
+    const EVENTS: usize = 10_000;
+    let chunk_bytes = EventChunks::CHUNK_SIZE * std::mem::size_of::<StampedEvent>();
+
+    MAX_ALLOC.store(0, Ordering::SeqCst);
+    let mut events = EventChunks::new();
+    for i in 0..EVENTS as u64 {
+        events.push(StampedEvent::new(i, vec![].into()));
+    }
+    let max = MAX_ALLOC.load(Ordering::SeqCst);
+
+    assert_eq!(events.len(), EVENTS);
+    assert!(
+        max <= chunk_bytes,
+        "largest allocation was {max} bytes, expected at most {chunk_bytes}"
+    );
+    assert!(events.iter().map(|e| e.emitter).eq(0..EVENTS as u64));
+}
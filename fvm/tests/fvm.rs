@@ -1,6 +1,11 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+mod actor_address;
 mod default_kernel;
-mod dummy;
+mod send_gas_limit;
+mod state_size;
+mod syscall_abi;
+mod tombstones;
+mod value_rejection;
 
-use dummy::*;
+use fvm::testing::*;
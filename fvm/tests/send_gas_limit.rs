@@ -0,0 +1,136 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use cid::Cid;
+use fvm::call_manager::{CallManager, DefaultCallManager, NO_DATA_BLOCK_ID};
+use fvm::engine::EnginePool;
+use fvm::gas::Gas;
+use fvm::kernel::{BlockRegistry, GasOps};
+use fvm::machine::Machine;
+use fvm::state_tree::ActorState;
+use fvm::{DefaultKernel, Kernel};
+use fvm_ipld_encoding::IPLD_RAW;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ExitCode;
+use fvm_shared::sys::SendFlags;
+use fvm_shared::version::NetworkVersion;
+use multihash::{Code, MultihashDigest};
+use pretty_assertions::assert_eq;
+
+use super::*;
+
+type TestCallManager = DefaultCallManager<MockMachine>;
+type TestKernel = DefaultKernel<TestCallManager>;
+
+const SENDER: fvm_shared::ActorID = 100;
+const RECIPIENT: fvm_shared::ActorID = 101;
+
+/// A minimal actor: exports a memory and an `invoke` method that returns no data.
+const NOOP_WASM: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+    0x01, 0x06, 0x01, 0x60, 0x01, 0x7f, 0x01, 0x7f, // type: (i32) -> i32
+    0x03, 0x02, 0x01, 0x00, // function: invoke
+    0x05, 0x03, 0x01, 0x00, 0x01, // memory: 1 page
+    0x07, 0x13, 0x02, // export: invoke, memory
+    0x06, 0x69, 0x6e, 0x76, 0x6f, 0x6b, 0x65, 0x00, 0x00, //
+    0x06, 0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02, 0x00, //
+    0x0a, 0x06, 0x01, 0x04, 0x00, 0x41, 0x00, 0x0b, // code: i32.const 0
+];
+
+/// Builds a kernel for `SENDER`, with a `RECIPIENT` actor running `NOOP_WASM` on a real engine.
+fn build(nv: NetworkVersion) -> anyhow::Result<TestKernel> {
+    let mut machine = MockMachine::new_stub()?;
+    machine.ctx.network_version = nv;
+
+    let account_code = *machine.builtin_actors().get_account_code();
+    let noop_code = Cid::new_v1(IPLD_RAW, Code::Blake2b256.digest(NOOP_WASM));
+    machine
+        .state_tree_mut()
+        .set_actor(SENDER, ActorState::new_empty(account_code, None));
+    machine
+        .state_tree_mut()
+        .set_actor(RECIPIENT, ActorState::new_empty(noop_code, None));
+
+    let engine = EnginePool::new_default((&machine.ctx.network).into())?.acquire();
+    engine.prepare_wasm_bytecode(&noop_code, NOOP_WASM)?;
+
+    let cm = TestCallManager::new(
+        machine,
+        engine,
+        1_000_000_000,
+        SENDER,
+        Address::new_id(SENDER),
+        None,
+        Some(SENDER),
+        Address::new_id(SENDER),
+        0,
+        TokenAmount::from_atto(0),
+        false,
+    );
+    Ok(TestKernel::new(
+        cm,
+        BlockRegistry::default(),
+        0,
+        SENDER,
+        0,
+        TokenAmount::from_atto(0),
+        false,
+    ))
+}
+
+fn send(kern: &mut TestKernel, gas_limit: Gas) -> fvm::kernel::Result<ExitCode> {
+    kern.send::<TestKernel>(
+        &Address::new_id(RECIPIENT),
+        2,
+        NO_DATA_BLOCK_ID,
+        &TokenAmount::from_atto(0),
+        Some(gas_limit),
+        SendFlags::empty(),
+    )
+    .map(|r| r.exit_code)
+}
+
+/// Returns the number of actor instantiations so far, along with the (rebuilt) kernel.
+fn instantiations(kern: TestKernel) -> (u64, TestKernel) {
+    let (cm, blocks) = kern.into_inner();
+    let count = cm.engine().instantiations();
+    let kern = TestKernel::new(cm, blocks, 0, SENDER, 0, TokenAmount::from_atto(0), false);
+    (count, kern)
+}
+
+#[test]
+fn below_min_gas_limit() -> anyhow::Result<()> {
+    let kern = build(NetworkVersion::V22)?;
+    let below = kern.price_list().min_send_gas_limit() - Gas::from_milligas(1);
+    let base_cost = kern.price_list().on_send_below_min_gas_limit().total();
+    let (before, mut kern) = instantiations(kern);
+
+    // The send fails locally, charging only its base cost and never instantiating the recipient.
+    let gas_before = kern.gas_used();
+    expect_syscall_err!(LimitExceeded, send(&mut kern, below));
+    assert_eq!(kern.gas_used() - gas_before, base_cost);
+    let (after, mut kern) = instantiations(kern);
+    assert_eq!(after, before);
+
+    // With enough gas, the recipient is instantiated and invoked.
+    assert_eq!(send(&mut kern, Gas::new(10_000_000))?, ExitCode::OK);
+    let (after, _) = instantiations(kern);
+    assert_eq!(after, before + 1);
+
+    Ok(())
+}
+
+#[test]
+fn below_min_gas_limit_before_nv22() -> anyhow::Result<()> {
+    let kern = build(NetworkVersion::V21)?;
+    let below = kern.price_list().min_send_gas_limit() - Gas::from_milligas(1);
+    let (before, mut kern) = instantiations(kern);
+
+    // Before nv22, small gas limits are passed through to the call manager, which runs out of gas
+    // charging for the invocation, before instantiating the recipient.
+    assert_eq!(send(&mut kern, below)?, ExitCode::SYS_OUT_OF_GAS);
+    let (after, _) = instantiations(kern);
+    assert_eq!(after, before);
+
+    Ok(())
+}
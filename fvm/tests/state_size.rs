@@ -0,0 +1,135 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use cid::Cid;
+use fvm::state_tree::{
+    measure_actor_state, measure_actor_state_with, ActorState, StateSizeOptions, StateTree,
+};
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_ipld_encoding::{to_vec, CborStore, IPLD_RAW};
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::state::StateTreeVersion;
+use multihash::{Code, MultihashDigest};
+use pretty_assertions::assert_eq;
+
+const ALICE: fvm_shared::ActorID = 100;
+const BOB: fvm_shared::ActorID = 101;
+
+struct Fixture {
+    store: MemoryBlockstore,
+    root: Cid,
+    shared: Cid,
+    /// The encoded sizes of Alice's root block, her own leaf, and the shared leaf.
+    sizes: [u64; 3],
+}
+
+/// Builds a state-tree where Alice and Bob both link to a shared block from their state roots.
+/// Alice links to it twice.
+fn build() -> anyhow::Result<Fixture> {
+    let mut tree = StateTree::new(MemoryBlockstore::default(), StateTreeVersion::V5)?;
+    let store = tree.store();
+
+    let shared_value = "shared substructure";
+    let shared = store.put_cbor(&shared_value, Code::Blake2b256)?;
+    let alice_leaf_value = vec![1u64, 2, 3];
+    let alice_leaf = store.put_cbor(&alice_leaf_value, Code::Blake2b256)?;
+    let bob_leaf = store.put_cbor(&"bob's own", Code::Blake2b256)?;
+
+    let alice_root_value = (alice_leaf, shared, shared);
+    let alice_root = store.put_cbor(&alice_root_value, Code::Blake2b256)?;
+    let bob_root = store.put_cbor(&(bob_leaf, shared), Code::Blake2b256)?;
+
+    let code = Cid::new_v1(IPLD_RAW, Code::Identity.digest(b"actor"));
+    for (id, state) in [(ALICE, alice_root), (BOB, bob_root)] {
+        tree.set_actor(
+            id,
+            ActorState::new(code, state, TokenAmount::from_atto(0), 0, None),
+        );
+    }
+    let root = tree.flush()?;
+
+    Ok(Fixture {
+        store: tree.into_store(),
+        root,
+        shared,
+        sizes: [
+            to_vec(&alice_root_value)?.len() as u64,
+            to_vec(&alice_leaf_value)?.len() as u64,
+            to_vec(&shared_value)?.len() as u64,
+        ],
+    })
+}
+
+#[test]
+fn unique_blocks() -> anyhow::Result<()> {
+    let f = build()?;
+
+    // The shared block is linked twice, but only counted once.
+    let report = measure_actor_state(&f.store, &f.root, ALICE, 10)?;
+    assert_eq!(report.blocks, 3);
+    assert_eq!(report.bytes, f.sizes.iter().sum::<u64>());
+    assert!(!report.truncated);
+    assert!(report.shared.is_empty());
+
+    // Only the root block is within depth 0.
+    let report = measure_actor_state(&f.store, &f.root, ALICE, 0)?;
+    assert_eq!(report.blocks, 1);
+    assert_eq!(report.bytes, f.sizes[0]);
+    assert!(report.truncated);
+
+    // The size limit cuts the walk short once reached.
+    let report = measure_actor_state_with(
+        &f.store,
+        &f.root,
+        ALICE,
+        &StateSizeOptions {
+            max_depth: 10,
+            max_bytes: Some(1),
+            ..Default::default()
+        },
+    )?;
+    assert_eq!(report.blocks, 1);
+    assert!(report.truncated);
+
+    assert!(measure_actor_state(&f.store, &f.root, 102, 10).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn shared_blocks() -> anyhow::Result<()> {
+    let f = build()?;
+
+    let report = measure_actor_state_with(
+        &f.store,
+        &f.root,
+        ALICE,
+        &StateSizeOptions {
+            max_depth: 10,
+            shared_with: vec![BOB],
+            ..Default::default()
+        },
+    )?;
+    assert_eq!(report.blocks, 3);
+    assert_eq!(report.bytes, f.sizes.iter().sum::<u64>());
+    assert_eq!(
+        report.shared.into_iter().collect::<Vec<_>>(),
+        vec![(f.shared, vec![BOB])]
+    );
+    assert_eq!(report.shared_bytes, f.sizes[2]);
+
+    // Blocks Bob can't reach within the depth limit aren't shared.
+    let report = measure_actor_state_with(
+        &f.store,
+        &f.root,
+        ALICE,
+        &StateSizeOptions {
+            max_depth: 0,
+            shared_with: vec![BOB],
+            ..Default::default()
+        },
+    )?;
+    assert!(report.shared.is_empty());
+    assert_eq!(report.shared_bytes, 0);
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::collections::HashSet;
+use std::path::Path;
+
+use fvm::call_manager::DefaultCallManager;
+use fvm::syscalls::{syscall_abi, syscall_abi_json, BASE_NETWORK_VERSION};
+use fvm::DefaultKernel;
+use fvm_shared::version::NetworkVersion;
+use pretty_assertions::assert_eq;
+
+use super::*;
+
+type TestKernel = DefaultKernel<DefaultCallManager<MockMachine>>;
+
+/// The expected syscall ABI. After intentionally adding a syscall, regenerate it by running this
+/// test with `FVM_BLESS_SYSCALL_ABI=1`.
+const GOLDEN: &str = "tests/syscall_abi.json";
+
+/// The latest network version that has been released. The declared ABI of this and earlier versions
+/// (i.e., the syscalls whose `since` is at most this version) must never change, and can't be
+/// re-blessed.
+///
+/// Note that `since` is informational: every syscall is linked at every network version, so this
+/// test only checks that new syscalls are *declared* for an unreleased version. Keeping them from
+/// affecting released versions is up to the kernel.
+const LATEST_RELEASED: NetworkVersion = NetworkVersion::V21;
+
+/// Returns the `since` field of a line of the golden file, if it describes a syscall.
+fn since(line: &str) -> Option<u32> {
+    let (_, rest) = line.split_once("\"since\": ")?;
+    rest.split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()
+}
+
+#[test]
+#[cfg(not(feature = "m2-native"))]
+fn unchanged() -> anyhow::Result<()> {
+    let abi = syscall_abi::<TestKernel>();
+
+    let mut seen = HashSet::new();
+    for syscall in &abi {
+        assert!(
+            seen.insert((syscall.module, syscall.name)),
+            "syscall {}::{} bound twice",
+            syscall.module,
+            syscall.name
+        );
+    }
+
+    let abi = syscall_abi_json(&abi);
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(GOLDEN);
+    let golden = std::fs::read_to_string(&path)?;
+
+    // Check released network versions first, as they can't be re-blessed.
+    for nv in u32::from(BASE_NETWORK_VERSION)..=u32::from(LATEST_RELEASED) {
+        let available = |abi: &str| -> Vec<String> {
+            abi.lines()
+                .filter(|l| since(l).map_or(false, |s| s <= nv))
+                .map(|l| l.trim_end_matches(',').to_owned())
+                .collect()
+        };
+        assert_eq!(
+            available(&golden),
+            available(&abi),
+            "the declared syscall ABI of released network version {nv} changed; new syscalls must \
+             declare a later network version with `since`"
+        );
+    }
+
+    if std::env::var_os("FVM_BLESS_SYSCALL_ABI").is_some() {
+        std::fs::write(&path, abi)?;
+        return Ok(());
+    }
+    assert_eq!(
+        golden, abi,
+        "the syscall ABI changed; if intended, rerun with FVM_BLESS_SYSCALL_ABI=1"
+    );
+    Ok(())
+}
@@ -0,0 +1,109 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use fvm::call_manager::{CallManager, DefaultCallManager};
+use fvm::engine::EnginePool;
+use fvm::machine::Machine;
+use fvm::state_tree::ActorState;
+use fvm::DefaultKernel;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ExitCode;
+use fvm_shared::version::NetworkVersion;
+use fvm_shared::METHOD_SEND;
+use pretty_assertions::assert_eq;
+
+use super::*;
+
+type TestCallManager = DefaultCallManager<MockMachine>;
+type TestKernel = DefaultKernel<TestCallManager>;
+
+const SENDER: fvm_shared::ActorID = 100;
+const DELETED: fvm_shared::ActorID = 101;
+
+/// Builds a call manager for a message sent by `SENDER`, with an existing `DELETED` actor.
+fn build(nv: NetworkVersion) -> anyhow::Result<TestCallManager> {
+    let mut machine = MockMachine::new_stub()?;
+    machine.ctx.network_version = nv;
+    let code = *machine.builtin_actors().get_account_code();
+    let mut sender = ActorState::new_empty(code, None);
+    sender.balance = TokenAmount::from_atto(100);
+    machine.state_tree_mut().set_actor(SENDER, sender);
+    machine
+        .state_tree_mut()
+        .set_actor(DELETED, ActorState::new_empty(code, None));
+
+    let engine = EnginePool::new_default((&machine.ctx.network).into())?.acquire();
+    Ok(TestCallManager::new(
+        machine,
+        engine,
+        1_000_000_000,
+        SENDER,
+        Address::new_id(SENDER),
+        None,
+        Some(DELETED),
+        Address::new_id(DELETED),
+        0,
+        TokenAmount::from_atto(0),
+        false,
+    ))
+}
+
+fn value_send(cm: &mut TestCallManager, value: u64) -> fvm::kernel::Result<ExitCode> {
+    cm.send::<TestKernel>(
+        SENDER,
+        Address::new_id(DELETED),
+        METHOD_SEND,
+        None,
+        &TokenAmount::from_atto(value),
+        None,
+        false,
+    )
+    .map(|r| r.exit_code)
+}
+
+#[test]
+fn send_then_delete() -> anyhow::Result<()> {
+    let mut cm = build(NetworkVersion::V22)?;
+    assert_eq!(value_send(&mut cm, 10)?, ExitCode::OK);
+    cm.delete_actor(DELETED)?;
+    assert_eq!(cm.get_actor(DELETED)?, None);
+    Ok(())
+}
+
+#[test]
+fn delete_then_send() -> anyhow::Result<()> {
+    let mut cm = build(NetworkVersion::V22)?;
+    cm.delete_actor(DELETED)?;
+
+    // Both valued and zero-value plain sends fail with the dedicated exit code, and nothing is
+    // transferred or recreated.
+    assert_eq!(value_send(&mut cm, 10)?, ExitCode::SYS_RECIPIENT_DELETED);
+    assert_eq!(value_send(&mut cm, 0)?, ExitCode::SYS_RECIPIENT_DELETED);
+    assert_eq!(cm.get_actor(DELETED)?, None);
+    assert_eq!(
+        cm.get_actor(SENDER)?.unwrap().balance,
+        TokenAmount::from_atto(100)
+    );
+    Ok(())
+}
+
+#[test]
+fn delete_then_recreate() -> anyhow::Result<()> {
+    let mut cm = build(NetworkVersion::V22)?;
+    let code = *cm.machine().builtin_actors().get_account_code();
+    cm.delete_actor(DELETED)?;
+    expect_syscall_err!(Forbidden, cm.create_actor(code, DELETED, None));
+    assert_eq!(cm.get_actor(DELETED)?, None);
+    Ok(())
+}
+
+#[test]
+fn before_tombstones() -> anyhow::Result<()> {
+    let mut cm = build(NetworkVersion::V21)?;
+    let code = *cm.machine().builtin_actors().get_account_code();
+    cm.delete_actor(DELETED)?;
+    expect_syscall_err!(NotFound, value_send(&mut cm, 10));
+    cm.create_actor(code, DELETED, None)?;
+    assert_eq!(value_send(&mut cm, 10)?, ExitCode::OK);
+    Ok(())
+}
@@ -0,0 +1,122 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use fvm::call_manager::{CallManager, DefaultCallManager, NO_DATA_BLOCK_ID};
+use fvm::engine::EnginePool;
+use fvm::kernel::BlockRegistry;
+use fvm::machine::{Machine, REWARD_ACTOR_ID};
+use fvm::state_tree::ActorState;
+use fvm::{DefaultKernel, Kernel};
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ExitCode;
+use fvm_shared::sys::SendFlags;
+use fvm_shared::version::NetworkVersion;
+use fvm_shared::{ActorID, METHOD_SEND};
+use pretty_assertions::assert_eq;
+
+use super::*;
+
+type TestCallManager = DefaultCallManager<MockMachine>;
+type TestKernel = DefaultKernel<TestCallManager>;
+
+const SENDER: ActorID = 100;
+const OTHER: ActorID = 1000;
+
+/// Builds a kernel for `SENDER` (holding 100 atto), with the reward actor and an `OTHER` actor.
+fn build(nv: NetworkVersion) -> anyhow::Result<TestKernel> {
+    let mut machine = MockMachine::new_stub()?;
+    machine.ctx.network_version = nv;
+    let code = *machine.builtin_actors().get_account_code();
+    let mut sender = ActorState::new_empty(code, None);
+    sender.balance = TokenAmount::from_atto(100);
+    machine.state_tree_mut().set_actor(SENDER, sender);
+    for id in [REWARD_ACTOR_ID, OTHER] {
+        machine
+            .state_tree_mut()
+            .set_actor(id, ActorState::new_empty(code, None));
+    }
+
+    let engine = EnginePool::new_default((&machine.ctx.network).into())?.acquire();
+    let cm = TestCallManager::new(
+        machine,
+        engine,
+        1_000_000_000,
+        SENDER,
+        Address::new_id(SENDER),
+        None,
+        Some(SENDER),
+        Address::new_id(SENDER),
+        0,
+        TokenAmount::from_atto(0),
+        false,
+    );
+    Ok(TestKernel::new(
+        cm,
+        BlockRegistry::default(),
+        0,
+        SENDER,
+        0,
+        TokenAmount::from_atto(0),
+        false,
+    ))
+}
+
+fn value_send(kern: &mut TestKernel, to: ActorID, value: u64) -> fvm::kernel::Result<ExitCode> {
+    kern.send::<TestKernel>(
+        &Address::new_id(to),
+        METHOD_SEND,
+        NO_DATA_BLOCK_ID,
+        &TokenAmount::from_atto(value),
+        None,
+        SendFlags::empty(),
+    )
+    .map(|r| r.exit_code)
+}
+
+/// Returns the balances (in atto) of `SENDER` and `to`, along with the (rebuilt) kernel.
+fn balances(kern: TestKernel, to: ActorID) -> anyhow::Result<((u64, u64), TestKernel)> {
+    let (cm, blocks) = kern.into_inner();
+    let balance = |id| -> anyhow::Result<u64> {
+        let balance = cm.get_actor(id)?.map(|a| a.balance).unwrap_or_default();
+        Ok(u64::try_from(balance.atto())?)
+    };
+    let res = (balance(SENDER)?, balance(to)?);
+    let kern = TestKernel::new(cm, blocks, 0, SENDER, 0, TokenAmount::from_atto(0), false);
+    Ok((res, kern))
+}
+
+#[test]
+fn rejects_value_sends_to_singletons() -> anyhow::Result<()> {
+    let mut kern = build(NetworkVersion::V22)?;
+
+    // The send fails with the dedicated exit code, and nothing is transferred.
+    assert_eq!(
+        value_send(&mut kern, REWARD_ACTOR_ID, 10)?,
+        ExitCode::SYS_VALUE_REJECTED
+    );
+    let (bals, mut kern) = balances(kern, REWARD_ACTOR_ID)?;
+    assert_eq!(bals, (100, 0));
+
+    // Zero-value sends are still allowed.
+    assert_eq!(value_send(&mut kern, REWARD_ACTOR_ID, 0)?, ExitCode::OK);
+
+    Ok(())
+}
+
+#[test]
+fn allows_value_sends_to_other_actors() -> anyhow::Result<()> {
+    let mut kern = build(NetworkVersion::V22)?;
+    assert_eq!(value_send(&mut kern, OTHER, 10)?, ExitCode::OK);
+    let (bals, _) = balances(kern, OTHER)?;
+    assert_eq!(bals, (90, 10));
+    Ok(())
+}
+
+#[test]
+fn accepts_value_sends_to_singletons_before_nv22() -> anyhow::Result<()> {
+    let mut kern = build(NetworkVersion::V21)?;
+    assert_eq!(value_send(&mut kern, REWARD_ACTOR_ID, 10)?, ExitCode::OK);
+    let (bals, _) = balances(kern, REWARD_ACTOR_ID)?;
+    assert_eq!(bals, (90, 10));
+    Ok(())
+}
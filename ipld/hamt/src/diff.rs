@@ -0,0 +1,185 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::cmp::Ordering;
+
+use fvm_ipld_blockstore::Blockstore;
+use fvm_ipld_encoding::de::DeserializeOwned;
+
+use crate::iter::IterImpl;
+use crate::node::Node;
+use crate::pointer::version::Version;
+use crate::pointer::Pointer;
+use crate::{Config, Error};
+
+/// A key that differs between two HAMTs. See [`HamtImpl::diff`](crate::hamt::HamtImpl::diff).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change<K, V> {
+    pub key: K,
+    /// The value in the old HAMT, or `None` if the key was added.
+    pub before: Option<V>,
+    /// The value in the new HAMT, or `None` if the key was removed.
+    pub after: Option<V>,
+}
+
+/// One of the two HAMTs being compared.
+pub(crate) struct Side<'a, BS> {
+    pub store: &'a BS,
+    pub conf: &'a Config,
+}
+
+impl<'a, BS: Blockstore> Side<'a, BS> {
+    /// Returns the node a pointer links to, or `None` if the pointer holds values.
+    fn child<'n, K, V, H, Ver>(
+        &self,
+        ptr: &'n Pointer<K, V, H, Ver>,
+        depth: u32,
+    ) -> Result<Option<&'n Node<K, V, H, Ver>>, Error>
+    where
+        K: PartialOrd + DeserializeOwned,
+        V: DeserializeOwned,
+        Ver: Version,
+    {
+        match ptr {
+            Pointer::Link { cid, cache } => cache
+                .get_or_try_init(|| Node::load(self.conf, self.store, cid, depth).map(Box::new))
+                .map(|n| Some(&**n)),
+            Pointer::Dirty(node) => Ok(Some(node)),
+            Pointer::Values(_) => Ok(None),
+        }
+    }
+
+    /// Returns all the entries under a node.
+    pub fn node_entries<'n, K, V, H, Ver>(
+        &'n self,
+        node: &'n Node<K, V, H, Ver>,
+    ) -> Result<Vec<(&'n K, &'n V)>, Error>
+    where
+        K: PartialOrd + DeserializeOwned,
+        V: DeserializeOwned,
+        Ver: Version,
+    {
+        IterImpl::new(self.store, node, self.conf).collect()
+    }
+
+    /// Returns all the entries under a pointer.
+    fn entries<'n, K, V, H, Ver>(
+        &'n self,
+        ptr: Option<&'n Pointer<K, V, H, Ver>>,
+        depth: u32,
+    ) -> Result<Vec<(&'n K, &'n V)>, Error>
+    where
+        K: PartialOrd + DeserializeOwned,
+        V: DeserializeOwned,
+        Ver: Version,
+    {
+        let Some(ptr) = ptr else { return Ok(Vec::new()) };
+        match self.child(ptr, depth)? {
+            Some(node) => self.node_entries(node),
+            None => match ptr {
+                Pointer::Values(kvs) => Ok(kvs.iter().map(|kv| (kv.key(), kv.value())).collect()),
+                _ => unreachable!("pointer without a node must hold values"),
+            },
+        }
+    }
+}
+
+/// Appends the changes between two sets of entries, in key order. Both sets are sorted and then
+/// merged, so this is linear in the number of entries (after sorting).
+pub(crate) fn diff_entries<K, V>(
+    mut old: Vec<(&K, &V)>,
+    mut new: Vec<(&K, &V)>,
+    changes: &mut Vec<Change<K, V>>,
+) where
+    K: PartialOrd + Clone,
+    V: PartialEq + Clone,
+{
+    let by_key = |a: &(&K, &V), b: &(&K, &V)| a.0.partial_cmp(b.0).unwrap_or(Ordering::Equal);
+    old.sort_unstable_by(by_key);
+    new.sort_unstable_by(by_key);
+
+    let mut old = old.into_iter().peekable();
+    let mut new = new.into_iter().peekable();
+    loop {
+        let ord = match (old.peek(), new.peek()) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some((o, _)), Some((n, _))) => o.partial_cmp(n).unwrap_or(Ordering::Equal),
+        };
+        let (key, before, after) = match ord {
+            Ordering::Less => {
+                let (k, v) = old.next().unwrap();
+                (k, Some(v), None)
+            }
+            Ordering::Greater => {
+                let (k, v) = new.next().unwrap();
+                (k, None, Some(v))
+            }
+            Ordering::Equal => {
+                let (k, before) = old.next().unwrap();
+                let (_, after) = new.next().unwrap();
+                (k, Some(before), Some(after))
+            }
+        };
+        if before != after {
+            changes.push(Change {
+                key: key.clone(),
+                before: before.cloned(),
+                after: after.cloned(),
+            });
+        }
+    }
+}
+
+/// Appends the changes between two nodes at the same position (and depth) in HAMTs with the same
+/// bit width. Subtrees with the same CID are skipped without being loaded.
+pub(crate) fn diff_nodes<K, V, H, Ver, BS1, BS2>(
+    old_side: &Side<BS1>,
+    old: &Node<K, V, H, Ver>,
+    new_side: &Side<BS2>,
+    new: &Node<K, V, H, Ver>,
+    depth: u32,
+    changes: &mut Vec<Change<K, V>>,
+) -> Result<(), Error>
+where
+    K: Eq + Clone + PartialOrd + DeserializeOwned,
+    V: PartialEq + Clone + DeserializeOwned,
+    Ver: Version,
+    BS1: Blockstore,
+    BS2: Blockstore,
+{
+    fn pointer_at<K, V, H, Ver>(
+        node: &Node<K, V, H, Ver>,
+        idx: u8,
+    ) -> Option<&Pointer<K, V, H, Ver>> {
+        node.bitfield
+            .test_bit(idx)
+            .then(|| &node.pointers[node.index_for_bit_pos(idx)])
+    }
+
+    for idx in 0..(1u32 << old_side.conf.bit_width) {
+        let idx = idx as u8;
+        let (old_ptr, new_ptr) = match (pointer_at(old, idx), pointer_at(new, idx)) {
+            (None, None) => continue,
+            (Some(Pointer::Link { cid: a, .. }), Some(Pointer::Link { cid: b, .. })) if a == b => {
+                continue
+            }
+            ptrs => ptrs,
+        };
+
+        if let (Some(o), Some(n)) = (old_ptr, new_ptr) {
+            if let (Some(o), Some(n)) =
+                (old_side.child(o, depth + 1)?, new_side.child(n, depth + 1)?)
+            {
+                diff_nodes(old_side, o, new_side, n, depth + 1, changes)?;
+                continue;
+            }
+        }
+        diff_entries(
+            old_side.entries(old_ptr, depth + 1)?,
+            new_side.entries(new_ptr, depth + 1)?,
+            changes,
+        );
+    }
+    Ok(())
+}
@@ -13,6 +13,7 @@ use multihash::Code;
 use serde::de::DeserializeOwned;
 use serde::{Serialize, Serializer};
 
+use crate::diff::{diff_entries, diff_nodes, Change, Side};
 use crate::iter::IterImpl;
 use crate::node::Node;
 use crate::pointer::version::Version;
@@ -518,6 +519,43 @@ where
     {
         IterImpl::new_from(&self.store, &self.root, key, &self.conf)
     }
+
+    /// Returns the keys whose values differ between this HAMT and `other`, as changes from this
+    /// HAMT to `other`. Subtrees with the same CID in both HAMTs are skipped without being loaded,
+    /// so this only visits the parts of the HAMTs that changed (unless they have different
+    /// bit-widths, in which case all entries are compared).
+    ///
+    /// The changes are ordered by key hash, except that HAMTs with different bit-widths are
+    /// compared (and their changes ordered) by key.
+    pub fn diff<BS2>(&self, other: &HamtImpl<BS2, V, K, H, Ver>) -> Result<Vec<Change<K, V>>, Error>
+    where
+        K: Clone,
+        V: PartialEq + Clone,
+        BS2: Blockstore,
+    {
+        let mut changes = Vec::new();
+        if self.flushed_cid.is_some() && self.flushed_cid == other.flushed_cid {
+            return Ok(changes);
+        }
+        let old = Side {
+            store: &self.store,
+            conf: &self.conf,
+        };
+        let new = Side {
+            store: &other.store,
+            conf: &other.conf,
+        };
+        if self.conf.bit_width == other.conf.bit_width {
+            diff_nodes(&old, &self.root, &new, &other.root, 0, &mut changes)?;
+        } else {
+            diff_entries(
+                old.node_entries(&self.root)?,
+                new.node_entries(&other.root)?,
+                &mut changes,
+            );
+        }
+        Ok(changes)
+    }
 }
 
 impl<'a, BS, V, K, H, Ver> IntoIterator for &'a HamtImpl<BS, V, K, H, Ver>
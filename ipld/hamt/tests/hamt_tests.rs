@@ -13,7 +13,7 @@ use fvm_ipld_encoding::strict_bytes::ByteBuf;
 use fvm_ipld_encoding::CborStore;
 #[cfg(feature = "identity")]
 use fvm_ipld_hamt::Identity;
-use fvm_ipld_hamt::{BytesKey, Config, Error, Hamt, Hash};
+use fvm_ipld_hamt::{BytesKey, Change, Config, Error, Hamt, Hash};
 use multihash::Code;
 use quickcheck::Arbitrary;
 use rand::seq::SliceRandom;
@@ -715,6 +715,99 @@ fn canonical_structure_alt_bit_width() {
     }
 }
 
+fn diff(size_factor: usize, factory: HamtFactory) {
+    let mem = MemoryBlockstore::default();
+    let mut old: Hamt<_, u64> = factory.new(&mem);
+    for i in 0..size_factor as u64 * 10 {
+        old.set(tstring(i), i).unwrap();
+    }
+    let old_cid = old.flush().unwrap();
+
+    let mut new: Hamt<_, u64> = factory.load(&old_cid, &mem).unwrap();
+    assert!(old.diff(&new).unwrap().is_empty());
+
+    let expected = vec![
+        Change {
+            key: tstring("added"),
+            before: None,
+            after: Some(1),
+        },
+        Change {
+            key: tstring(0),
+            before: Some(0),
+            after: None,
+        },
+        Change {
+            key: tstring(7),
+            before: Some(7),
+            after: Some(70),
+        },
+    ];
+    new.set(tstring("added"), 1).unwrap();
+    new.delete(&tstring(0)).unwrap();
+    new.set(tstring(7), 70).unwrap();
+    // Setting a key to the same value isn't a change.
+    new.set(tstring(8), 8).unwrap();
+
+    let sorted = |mut changes: Vec<Change<BytesKey, u64>>| {
+        changes.sort_by(|a, b| a.key.0.cmp(&b.key.0));
+        changes
+    };
+    let expected = sorted(expected);
+
+    // Unflushed changes are compared too.
+    assert_eq!(sorted(old.diff(&new).unwrap()), expected);
+
+    // Only the changed parts of the HAMTs are loaded.
+    let new_cid = new.flush().unwrap();
+    let store = TrackingBlockstore::new(&mem);
+    let old: Hamt<_, u64> = factory.load(&old_cid, &store).unwrap();
+    let new: Hamt<_, u64> = factory.load(&new_cid, &store).unwrap();
+    assert_eq!(sorted(old.diff(&new).unwrap()), expected);
+    let diff_reads = store.stats.borrow().r;
+
+    let store = TrackingBlockstore::new(&mem);
+    let full: Hamt<_, u64> = factory.load(&old_cid, &store).unwrap();
+    full.for_each(|_, _| Ok(())).unwrap();
+    let full_reads = store.stats.borrow().r;
+    if size_factor >= 100 {
+        assert!(diff_reads < full_reads, "{diff_reads} >= {full_reads}");
+    }
+
+    // The reverse diff swaps before and after.
+    let reversed: Vec<_> = expected
+        .into_iter()
+        .map(|c| Change {
+            key: c.key,
+            before: c.after,
+            after: c.before,
+        })
+        .collect();
+    assert_eq!(sorted(new.diff(&old).unwrap()), reversed);
+
+    // HAMTs with different bit-widths are compared entry by entry.
+    let bit_width = match factory.conf.bit_width {
+        1 => 2,
+        n => n - 1,
+    };
+    let mut resized: Hamt<_, u64> = factory.new_with_bit_width(&mem, bit_width);
+    new.for_each(|k, v| {
+        resized.set(k.clone(), *v)?;
+        Ok(())
+    })
+    .unwrap();
+    assert!(new.diff(&resized).unwrap().is_empty());
+    resized.delete(&tstring("added")).unwrap();
+    assert_eq!(
+        new.diff(&resized).unwrap(),
+        vec![Change {
+            key: tstring("added"),
+            before: Some(1),
+            after: None,
+        }]
+    );
+}
+
 fn clean_child_ordering(factory: HamtFactory, stats: Option<BSStats>, mut cids: CidChecker) {
     let make_key = |i: u64| -> BytesKey {
         let mut key = unsigned_varint::encode::u64_buffer();
@@ -1043,6 +1136,13 @@ mod test_default {
         super::for_each_ranged(200, HamtFactory::default(), Some(stats), cids);
     }
 
+    #[test]
+    fn diff() {
+        for s in super::SIZE_FACTORS {
+            super::diff(*s, HamtFactory::default())
+        }
+    }
+
     #[test]
     fn clean_child_ordering() {
         #[rustfmt::skip]
@@ -1164,6 +1264,13 @@ macro_rules! test_hamt_mod {
                 super::clean_child_ordering($factory, None, CidChecker::empty())
             }
 
+            #[test]
+            fn diff() {
+                for s in super::SIZE_FACTORS {
+                    super::diff(*s, $factory)
+                }
+            }
+
             #[quickcheck]
             fn prop_cid_indep_of_insert_order(
                 kvs: UniqueKeyValuePairs<u8, i64>,
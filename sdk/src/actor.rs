@@ -30,6 +30,16 @@ pub fn resolve_address(addr: &Address) -> Option<ActorID> {
     }
 }
 
+/// Looks up the namespace (the ID of the managing actor) of the specified actor's delegated (f4)
+/// address. Returns `None` if the actor doesn't exist or it doesn't have an f4 address.
+pub fn delegated_namespace(actor_id: ActorID) -> Option<ActorID> {
+    match unsafe { sys::actor::delegated_namespace(actor_id) } {
+        Ok(ns) if ns.delegated != 0 => Some(ns.namespace),
+        Ok(_) | Err(ErrorNumber::NotFound) => None,
+        Err(other) => panic!("unexpected delegated namespace lookup failure: {}", other),
+    }
+}
+
 /// Looks up the delegated (f4) address of the specified actor. Returns `None` if the actor doesn't
 /// exist or it doesn't have f4 address.
 pub fn lookup_delegated_address(addr: ActorID) -> Option<Address> {
@@ -90,6 +100,29 @@ pub fn next_actor_address() -> Address {
     }
 }
 
+/// Constructs the delegated (f4) address with the specified namespace (the ID of the managing
+/// actor) and subaddress.
+pub fn make_delegated_address(namespace: ActorID, subaddress: &[u8]) -> SyscallResult<Address> {
+    let mut buf = [0u8; MAX_ADDRESS_LEN];
+    unsafe {
+        let len = sys::actor::make_delegated_address(
+            namespace,
+            subaddress.as_ptr(),
+            subaddress.len() as u32,
+            buf.as_mut_ptr(),
+            MAX_ADDRESS_LEN as u32,
+        )?;
+        Ok(Address::from_bytes(&buf[..len as usize]).expect("syscall returned invalid address"))
+    }
+}
+
+/// Returns the number of actors created so far in the current message. Pass this to
+/// [`derive_actor_address`](fvm_shared::address::derive_actor_address), along with the message's
+/// origin and nonce, to compute the addresses of actors before they're created.
+pub fn actors_created() -> u64 {
+    unsafe { sys::actor::actors_created().expect("failed to get the number of actors created") }
+}
+
 /// Creates a new actor of the specified type in the state tree, under the provided address.
 pub fn create_actor(
     actor_id: ActorID,
@@ -132,6 +165,20 @@ pub fn get_builtin_actor_type(code_cid: &Cid) -> Option<i32> {
     }
 }
 
+/// Determines the built-in actor type of the actor at the given address, if any. Returns `None`
+/// if the address can't be resolved, the actor doesn't exist, or it isn't a built-in actor.
+pub fn address_builtin_type(addr: &Address) -> Option<i32> {
+    let bytes = addr.to_bytes();
+    unsafe {
+        match sys::actor::address_builtin_type(bytes.as_ptr(), bytes.len() as u32) {
+            Ok(0) => None,
+            Ok(typ) => Some(typ),
+            Err(ErrorNumber::NotFound) => None,
+            Err(other) => panic!("unexpected builtin actor type lookup failure: {}", other),
+        }
+    }
+}
+
 /// Returns the CodeCID for a built-in actor type. Aborts with IllegalArgument
 /// if the supplied type is invalid.
 pub fn get_code_cid_for_type(typ: i32) -> Cid {
@@ -143,6 +190,25 @@ pub fn get_code_cid_for_type(typ: i32) -> Cid {
     }
 }
 
+/// Returns true if no actor exists with the specified ID.
+pub fn actor_id_available(actor_id: ActorID) -> bool {
+    unsafe {
+        sys::actor::actor_id_available(actor_id)
+            .map(|v| v == 0)
+            .expect("failed to check actor ID availability")
+    }
+}
+
+/// Returns true if the specified actor is expected to accept a plain value transfer, judging by its
+/// builtin actor type. This is best-effort: the transfer may still fail.
+pub fn can_receive_funds(actor_id: ActorID) -> bool {
+    unsafe {
+        sys::actor::can_receive_funds(actor_id)
+            .map(|v| v == 0)
+            .expect("failed to check whether the actor can receive funds")
+    }
+}
+
 /// Retrieves the balance of the specified actor, or None if the actor doesn't exist.
 pub fn balance_of(actor_id: ActorID) -> Option<TokenAmount> {
     unsafe {
@@ -153,3 +219,19 @@ pub fn balance_of(actor_id: ActorID) -> Option<TokenAmount> {
         }
     }
 }
+
+/// Retrieves the balances of the specified actors (in order) in a single syscall. Non-existent
+/// actors have a balance of zero.
+pub fn balances_of(actor_ids: &[ActorID]) -> Vec<TokenAmount> {
+    let mut balances = Vec::<sys::TokenAmount>::with_capacity(actor_ids.len());
+    unsafe {
+        sys::actor::balances_of(
+            actor_ids.as_ptr(),
+            actor_ids.len() as u32,
+            balances.as_mut_ptr(),
+        )
+        .expect("failed to lookup balances");
+        balances.set_len(actor_ids.len());
+    }
+    balances.into_iter().map(Into::into).collect()
+}
@@ -51,6 +51,27 @@ pub fn recover_secp_public_key(
     unsafe { sys::crypto::recover_secp_public_key(hash.as_ptr(), signature.as_ptr()) }
 }
 
+/// Recovers the signer public keys from a batch of message hashes and signatures, in order. Fails
+/// if recovery fails for any item.
+pub fn recover_secp_public_keys(
+    items: &[([u8; SECP_SIG_MESSAGE_HASH_SIZE], [u8; SECP_SIG_LEN])],
+) -> SyscallResult<Vec<[u8; SECP_PUB_LEN]>> {
+    let buf: Vec<u8> = items
+        .iter()
+        .flat_map(|(hash, signature)| hash.iter().chain(signature.iter()).copied())
+        .collect();
+    let mut pubkeys = Vec::<[u8; SECP_PUB_LEN]>::with_capacity(items.len());
+    unsafe {
+        sys::crypto::recover_secp_public_keys(
+            buf.as_ptr(),
+            items.len() as u32,
+            pubkeys.as_mut_ptr(),
+        )?;
+        pubkeys.set_len(items.len());
+    }
+    Ok(pubkeys)
+}
+
 /// Hashes input data using blake2b with 256 bit output.
 pub fn hash_blake2b(data: &[u8]) -> [u8; 32] {
     const BLAKE2B_256: u64 = 0xb220;
@@ -106,6 +127,43 @@ pub fn hash_into(hasher: SupportedHashes, data: &[u8], digest: &mut [u8]) -> usi
     }
 }
 
+/// Hashes data in chunks using one of the supported functions, for data too large to hash at once.
+/// Digests longer than 64 bytes will be truncated.
+pub struct StreamingHash {
+    hasher: SupportedHashes,
+    handle: u32,
+}
+
+impl StreamingHash {
+    /// Starts a new hash computation.
+    pub fn new(hasher: SupportedHashes) -> Self {
+        let handle = unsafe { sys::crypto::hash_init(hasher as u64) }
+            .unwrap_or_else(|_| panic!("failed to start hash using {:?}", hasher));
+        StreamingHash { hasher, handle }
+    }
+
+    /// Feeds the next chunk of data into the hash.
+    pub fn update(&mut self, data: &[u8]) {
+        unsafe { sys::crypto::hash_update(self.handle, data.as_ptr(), data.len() as u32) }
+            .unwrap_or_else(|_| panic!("failed to update hash using {:?}", self.hasher))
+    }
+
+    /// Completes the hash computation, returning the digest.
+    pub fn finalize(self) -> Vec<u8> {
+        let mut ret = Vec::with_capacity(64);
+        unsafe {
+            let written = sys::crypto::hash_finalize(self.handle, ret.as_mut_ptr(), 64)
+                .unwrap_or_else(|_| panic!("failed to finalize hash using {:?}", self.hasher))
+                as usize;
+            assert!(written <= ret.capacity());
+            // SAFETY: hash_finalize should've written _exactly_ the number of bytes it wrote to
+            // the buffer
+            ret.set_len(written);
+        }
+        ret
+    }
+}
+
 /// Computes an unsealed sector CID (CommD) from its constituent piece CIDs (CommPs) and sizes.
 pub fn compute_unsealed_sector_cid(
     proof_type: RegisteredSealProof,
@@ -195,6 +253,22 @@ pub fn verify_replica_update(info: &ReplicaUpdateInfo) -> SyscallResult<bool> {
     }
 }
 
+/// Verifies a Groth16 proof over BLS12-381 given a serialized verifying key, a serialized proof,
+/// and the proof's public inputs (each a 32-byte little-endian scalar).
+pub fn groth16_verify(vk: &[u8], proof: &[u8], public_inputs: &[[u8; 32]]) -> SyscallResult<bool> {
+    unsafe {
+        sys::crypto::groth16_verify(
+            vk.as_ptr(),
+            vk.len() as u32,
+            proof.as_ptr(),
+            proof.len() as u32,
+            public_inputs.as_ptr() as *const u8,
+            (public_inputs.len() * 32) as u32,
+        )
+        .map(status_code_to_bool)
+    }
+}
+
 pub fn batch_verify_seals(batch: &[SealVerifyInfo]) -> SyscallResult<Vec<bool>> {
     let encoded = to_vec(batch).expect("failed to marshal batch seal verification input");
 
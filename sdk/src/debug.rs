@@ -1,5 +1,7 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use fvm_shared::error::ErrorNumber;
+use fvm_shared::{ActorID, MethodNum};
 use lazy_static::lazy_static;
 use log::LevelFilter;
 
@@ -26,7 +28,13 @@ pub fn init_logging() {
     }
 }
 
-/// Saves an artifact to the host env. New artifacts with the same name will overwrite old ones
+/// The maximum length of an artifact name, including any suffix added by the FVM to make it
+/// unique.
+const MAX_ARTIFACT_NAME_LEN: usize = 512;
+
+/// Saves an artifact to the host env. Storing an artifact with the same name twice in one
+/// invocation will store it under a new, unique name instead of overwriting the old one; use
+/// [`store_artifact_unique`] to learn that name.
 pub fn store_artifact(name: impl AsRef<str>, data: impl AsRef<[u8]>) {
     let name = name.as_ref();
     let data = data.as_ref();
@@ -41,6 +49,63 @@ pub fn store_artifact(name: impl AsRef<str>, data: impl AsRef<[u8]>) {
     }
 }
 
+/// Saves an artifact to the host env, returning the name under which it was stored. Storing an
+/// artifact with the same name twice in one invocation will store it under a new, unique name
+/// instead of overwriting the old one.
+pub fn store_artifact_unique(name: impl AsRef<str>, data: impl AsRef<[u8]>) -> String {
+    let name = name.as_ref();
+    let data = data.as_ref();
+    let mut out_buffer = [0u8; MAX_ARTIFACT_NAME_LEN];
+    unsafe {
+        let len = sys::debug::store_artifact_unique(
+            name.as_ptr(),
+            name.len() as u32,
+            data.as_ptr(),
+            data.len() as u32,
+            out_buffer.as_mut_ptr(),
+            out_buffer.len() as u32,
+        )
+        .unwrap();
+        if len == 0 {
+            name.to_owned()
+        } else {
+            String::from_utf8_lossy(&out_buffer[..len as usize]).into_owned()
+        }
+    }
+}
+
+/// Returns the actors currently being invoked along with the invoked methods, from the top-level
+/// message down to (and including) the current invocation. Returns `None` if debugging is
+/// disabled.
+pub fn call_stack() -> Option<Vec<(ActorID, MethodNum)>> {
+    const FRAME_SIZE: usize = 16;
+
+    let mut buf = vec![0u8; 16 * FRAME_SIZE];
+    loop {
+        let frames = match unsafe { sys::debug::call_stack(buf.as_mut_ptr(), buf.len() as u32) } {
+            Ok(frames) => frames as usize,
+            Err(ErrorNumber::Forbidden) => return None,
+            Err(e) => panic!("failed to get the call stack: {}", e),
+        };
+        if frames * FRAME_SIZE > buf.len() {
+            buf.resize(frames * FRAME_SIZE, 0);
+            continue;
+        }
+        return Some(
+            buf[..frames * FRAME_SIZE]
+                .chunks_exact(FRAME_SIZE)
+                .map(|frame| {
+                    let (actor, method) = frame.split_at(8);
+                    (
+                        ActorID::from_le_bytes(actor.try_into().unwrap()),
+                        MethodNum::from_le_bytes(method.try_into().unwrap()),
+                    )
+                })
+                .collect(),
+        );
+    }
+}
+
 /// Returns whether debug mode is enabled.
 #[inline(always)]
 pub fn enabled() -> bool {
@@ -1,32 +1,101 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 use crate::{sys, SyscallResult};
-use fvm_shared::event::ActorEvent;
+use cid::Cid;
+use fvm_shared::event::{ActorEvent, EventKey, Flags};
+use fvm_shared::MAX_CID_LEN;
 
 pub fn emit_event(evt: &ActorEvent) -> SyscallResult<()> {
+    let (entries, keys, values) = serialize(evt);
+    unsafe {
+        sys::event::emit_event(
+            entries.as_ptr(),
+            entries.len() as u32,
+            keys.as_ptr(),
+            keys.len() as u32,
+            values.as_ptr(),
+            values.len() as u32,
+        )
+    }
+}
+
+/// Returns the gas [`emit_event`] would charge for the event (in whole gas units, rounded up),
+/// without emitting it.
+pub fn gas_cost(evt: &ActorEvent) -> u64 {
+    let (entries, keys, values) = serialize(evt);
+    unsafe {
+        sys::event::gas_cost(
+            entries.as_ptr(),
+            entries.len() as u32,
+            keys.as_ptr(),
+            keys.len() as u32,
+            values.as_ptr(),
+            values.len() as u32,
+        )
+        .expect("failed to compute the event's gas cost")
+    }
+}
+
+/// Emits the events in order, skipping events identical to the previously emitted one, so runs of
+/// duplicates collapse into a single event. Returns the number of events actually emitted.
+pub fn emit_events_dedup(evts: &[ActorEvent]) -> SyscallResult<u32> {
+    let mut sizes = Vec::with_capacity(evts.len());
+    let (mut entries, mut keys, mut values) = (Vec::new(), Vec::new(), Vec::new());
+    for evt in evts {
+        let (evt_entries, evt_keys, evt_values) = serialize(evt);
+        sizes.push(evt_entries.len() as u32);
+        entries.extend(evt_entries);
+        keys.extend(evt_keys);
+        values.extend(evt_values);
+    }
+    unsafe {
+        sys::event::emit_events_dedup(
+            sizes.as_ptr(),
+            sizes.len() as u32,
+            entries.as_ptr(),
+            entries.len() as u32,
+            keys.as_ptr(),
+            keys.len() as u32,
+            values.as_ptr(),
+            values.len() as u32,
+        )
+    }
+}
+
+/// Serializes the event into its entry headers, keys, and values.
+fn serialize(evt: &ActorEvent) -> (Vec<fvm_shared::sys::EventEntry>, Vec<u8>, Vec<u8>) {
     // we manually serialize the ActorEvent (not using CBOR) into three byte arrays so
     // we can accurately charge gas without needing to parse anything inside the FVM
     let mut total_key_len: usize = 0;
     let mut total_val_len: usize = 0;
 
     let mut fixed_entries = Vec::with_capacity(evt.entries.len());
+    let mut entry_keys = Vec::with_capacity(evt.entries.len());
     for i in 0..evt.entries.len() {
         let e = &evt.entries[i];
+        let key = e.key.to_bytes();
+
+        // CID keys must be flagged so the FVM knows how to validate them.
+        let mut flags = e.flags;
+        if let EventKey::Cid(_) = e.key {
+            flags |= Flags::FLAG_CID_KEY;
+        }
 
         fixed_entries.push(fvm_shared::sys::EventEntry {
-            flags: e.flags,
+            flags,
             codec: e.codec,
-            key_len: e.key.len() as u32,
+            key_len: key.len() as u32,
             val_len: e.value.len() as u32,
         });
 
-        total_key_len += e.key.len();
+        total_key_len += key.len();
         total_val_len += e.value.len();
+        entry_keys.push(key);
     }
 
     let mut keys = Vec::with_capacity(total_key_len);
-    for i in 0..evt.entries.len() {
-        keys.extend_from_slice(evt.entries[i].key.as_bytes());
+    for key in entry_keys {
+        keys.extend_from_slice(&key);
     }
 
     let mut values = Vec::with_capacity(total_val_len);
@@ -34,14 +103,25 @@ pub fn emit_event(evt: &ActorEvent) -> SyscallResult<()> {
         values.extend_from_slice(evt.entries[i].value.as_slice());
     }
 
+    (fixed_entries, keys, values)
+}
+
+/// Returns the root of the events AMT over the events emitted so far in the current message, or
+/// `None` if no events have been emitted. Events emitted by calls that have since been reverted are
+/// excluded.
+pub fn root_so_far() -> Option<Cid> {
+    let mut buf = [0u8; MAX_CID_LEN];
     unsafe {
-        sys::event::emit_event(
-            fixed_entries.as_ptr(),
-            fixed_entries.len() as u32,
-            keys.as_ptr(),
-            keys.len() as u32,
-            values.as_ptr(),
-            values.len() as u32,
-        )
+        match sys::event::root_so_far(buf.as_mut_ptr(), MAX_CID_LEN as u32)
+            .expect("failed to compute the events root")
+        {
+            0 => None,
+            len => Some(Cid::read_bytes(&buf[..len as usize]).expect("invalid cid returned")),
+        }
     }
 }
+
+/// Returns how many more events the current message may emit, or `u32::MAX` if there's no limit.
+pub fn events_remaining() -> u32 {
+    unsafe { sys::event::events_remaining() }.expect("failed to get the remaining event count")
+}
@@ -1,6 +1,9 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
-use crate::sys;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::error::ErrorNumber;
+
+use crate::{sys, SyscallResult};
 
 /// Charge gas for the operation identified by name.
 pub fn charge(name: &str, compute: u64) {
@@ -12,3 +15,27 @@ pub fn charge(name: &str, compute: u64) {
 pub fn available() -> u64 {
     unsafe { sys::gas::available() }.expect("failed to check available gas")
 }
+
+/// Estimates the total fee (base fee plus premium) of a message with the given gas limit and
+/// premium, assuming it uses all of its gas, at the current base fee.
+pub fn estimate_fee(gas_limit: u64, premium: &TokenAmount) -> SyscallResult<TokenAmount> {
+    let premium: sys::TokenAmount = premium.try_into().map_err(|_| ErrorNumber::LimitExceeded)?;
+    unsafe { sys::gas::estimate_fee(gas_limit, premium.hi, premium.lo) }.map(Into::into)
+}
+
+/// Creates a sub-meter with the given budget, returning a handle that's valid for the rest of this
+/// invocation. Charges to the sub-meter also draw from the global gas budget.
+pub fn create_submeter(budget: u64) -> SyscallResult<u32> {
+    unsafe { sys::gas::create_submeter(budget) }
+}
+
+/// Charges gas to a sub-meter. Exhausting the sub-meter fails with
+/// [`ErrorNumber::LimitExceeded`], in which case nothing is charged.
+pub fn charge_to(meter: u32, amount: u64) -> SyscallResult<()> {
+    unsafe { sys::gas::charge_to(meter, amount) }
+}
+
+/// Returns the gas remaining in a sub-meter.
+pub fn submeter_remaining(meter: u32) -> SyscallResult<u64> {
+    unsafe { sys::gas::submeter_remaining(meter) }
+}
@@ -40,6 +40,25 @@ pub fn put(mh_code: u64, mh_size: u32, codec: u64, data: &[u8]) -> SyscallResult
     }
 }
 
+/// Compute the CID [`put`] would return for a block, without storing it.
+pub fn compute_cid(mh_code: u64, mh_size: u32, codec: u64, data: &[u8]) -> SyscallResult<Cid> {
+    // Identity-hashed CIDs are computed locally, exactly like `put` does.
+    if mh_code == fvm_shared::IDENTITY_HASH {
+        return put(mh_code, mh_size, codec, data);
+    }
+
+    unsafe {
+        let id = sys::ipld::block_create(codec, data.as_ptr(), data.len() as u32)?;
+
+        let mut buf = [0u8; MAX_CID_LEN];
+        let res = sys::ipld::block_cid(id, mh_code, mh_size, buf.as_mut_ptr(), buf.len() as u32);
+        // Free the handle, as the block is never used again.
+        sys::ipld::block_close(id).expect("failed to close a newly created block");
+        let len = res?;
+        Ok(Cid::read_bytes(&buf[..len as usize]).expect("runtime returned an invalid CID"))
+    }
+}
+
 /// Get a block. It's valid to call this on:
 ///
 /// 1. All CIDs returned by prior calls to `get_root`...
@@ -68,6 +87,28 @@ pub fn get(cid: &Cid) -> SyscallResult<Vec<u8>> {
     }
 }
 
+/// Opens the block identified by `cid` if it's reachable, otherwise creates it from `codec` and
+/// `data`. Returns the block's ID, and whether it was created.
+pub fn open_or_create(
+    cid: &Cid,
+    codec: u64,
+    data: &[u8],
+) -> SyscallResult<(fvm_shared::sys::BlockId, bool)> {
+    let mut cid_buf = [0u8; MAX_CID_LEN];
+    cid.write_bytes(&mut cid_buf[..])
+        .expect("CID encoding should not fail");
+    unsafe {
+        let fvm_shared::sys::out::ipld::IpldOpenOrCreate { id, created } =
+            sys::ipld::block_open_or_create(
+                cid_buf.as_ptr(),
+                codec,
+                data.as_ptr(),
+                data.len() as u32,
+            )?;
+        Ok((id, created != 0))
+    }
+}
+
 /// Gets the data of the block referenced by BlockId. If the caller knows the size, this function
 /// will read the block in a single syscall. Otherwise, any block over 1KiB will take two syscalls.
 pub fn get_block(id: fvm_shared::sys::BlockId, size_hint: Option<u32>) -> SyscallResult<Vec<u8>> {
@@ -101,6 +142,12 @@ pub fn get_block(id: fvm_shared::sys::BlockId, size_hint: Option<u32>) -> Syscal
     Ok(buf)
 }
 
+/// Reads a CBOR block containing a single unsigned integer (e.g., a counter or an epoch) without
+/// copying the block into actor memory.
+pub fn get_block_u64(id: fvm_shared::sys::BlockId) -> SyscallResult<u64> {
+    unsafe { sys::ipld::block_read_u64(id) }
+}
+
 /// Writes the supplied block and returns the BlockId.
 pub fn put_block(
     codec: fvm_shared::sys::Codec,
@@ -108,3 +155,89 @@ pub fn put_block(
 ) -> SyscallResult<fvm_shared::sys::BlockId> {
     unsafe { sys::ipld::block_create(codec, data.as_ptr(), data.len() as u32) }
 }
+
+/// Marks the block referenced by BlockId as retained until it's linked, exempting it from eviction.
+pub fn retain_block(id: fvm_shared::sys::BlockId) -> SyscallResult<()> {
+    unsafe { sys::ipld::block_retain(id) }
+}
+
+/// Returns true if the two blocks have the same codec and contents.
+pub fn block_eq(a: fvm_shared::sys::BlockId, b: fvm_shared::sys::BlockId) -> SyscallResult<bool> {
+    unsafe { sys::ipld::block_eq(a, b).map(|v| v == 0) }
+}
+
+/// Returns true if the block's codec and hash match the expected CID. Only 32-byte blake2b CIDs
+/// are supported.
+pub fn block_hash_eq(id: fvm_shared::sys::BlockId, expected: &Cid) -> SyscallResult<bool> {
+    let mut cid_buf = [0u8; MAX_CID_LEN];
+    expected
+        .write_bytes(&mut cid_buf[..])
+        .expect("CID encoding should not fail");
+    unsafe { sys::ipld::block_hash_eq(id, cid_buf.as_ptr()).map(|v| v == 0) }
+}
+
+/// Returns true if the block links to the target CID. Inline (identity-hashed) CIDs never match
+/// themselves, but are searched for links.
+pub fn block_contains_link(id: fvm_shared::sys::BlockId, target: &Cid) -> SyscallResult<bool> {
+    let mut cid_buf = [0u8; MAX_CID_LEN];
+    target
+        .write_bytes(&mut cid_buf[..])
+        .expect("CID encoding should not fail");
+    unsafe { sys::ipld::block_contains_link(id, cid_buf.as_ptr()).map(|v| v == 0) }
+}
+
+/// Returns true if `root` can be reached by following links from `child` within `max_depth` links
+/// (or is `child`), i.e., if making `root` link to `child` would form a cycle. The child must be
+/// reachable by the calling actor.
+pub fn would_create_cycle(root: &Cid, child: &Cid, max_depth: u32) -> SyscallResult<bool> {
+    let mut root_buf = [0u8; MAX_CID_LEN];
+    root.write_bytes(&mut root_buf[..])
+        .expect("CID encoding should not fail");
+    let mut child_buf = [0u8; MAX_CID_LEN];
+    child
+        .write_bytes(&mut child_buf[..])
+        .expect("CID encoding should not fail");
+    unsafe {
+        sys::ipld::would_create_cycle(root_buf.as_ptr(), child_buf.as_ptr(), max_depth)
+            .map(|v| v == 0)
+    }
+}
+
+/// Returns true if the block satisfies the constraints on return values (maximum block size and
+/// allowed codecs), so actors can restructure large return values before returning them.
+pub fn would_fit_as_return(id: fvm_shared::sys::BlockId) -> SyscallResult<bool> {
+    unsafe { sys::ipld::would_fit_as_return(id).map(|v| v == 0) }
+}
+
+/// Returns true if blocks with the given codec may contain links (e.g., DAG_CBOR), and false if
+/// the codec is a leaf codec (e.g., raw) or unknown.
+pub fn is_dag_codec(codec: u64) -> SyscallResult<bool> {
+    unsafe { sys::ipld::is_dag_codec(codec).map(|v| v == 0) }
+}
+
+/// Decompresses zlib-compressed data, failing if the result would exceed `max_output` bytes.
+pub fn inflate(compressed: &[u8], max_output: u32) -> SyscallResult<Vec<u8>> {
+    let mut buf = Vec::with_capacity(max_output as usize);
+    unsafe {
+        let len = sys::ipld::inflate(
+            compressed.as_ptr(),
+            compressed.len() as u32,
+            buf.as_mut_ptr(),
+            max_output,
+        )?;
+        buf.set_len(len as usize);
+    }
+    Ok(buf)
+}
+
+/// Runs `f` with the maximum number of blocks this invocation may hold open temporarily raised by
+/// `extra`, paying gas proportional to the increase. Blocks created by `f` remain valid
+/// afterwards, but still count towards the (restored) limit.
+pub fn with_expanded_block_handles<R>(extra: u32, f: impl FnOnce() -> R) -> R {
+    let limit = unsafe { sys::ipld::expand_block_handles(extra) }
+        .expect("failed to expand the block handle limit");
+    let ret = f();
+    unsafe { sys::ipld::restore_block_handles(limit) }
+        .expect("failed to restore the block handle limit");
+    ret
+}
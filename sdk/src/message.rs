@@ -3,10 +3,12 @@
 use std::convert::TryInto;
 
 use fvm_ipld_encoding::ipld_block::IpldBlock;
+use fvm_shared::address::{Address, MAX_ADDRESS_LEN};
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::sys::out::vm::MessageContext;
-use fvm_shared::sys::BlockId;
+use fvm_shared::sys::{BlockId, ReadOnlyReason};
 use fvm_shared::{ActorID, MethodNum};
+use num_traits::FromPrimitive;
 
 use crate::{sys, SyscallResult, NO_DATA_BLOCK_ID};
 
@@ -24,12 +26,53 @@ pub fn nonce() -> u64 {
     MESSAGE_CONTEXT.nonce
 }
 
+/// Returns the origin account's current sequence number. This is usually one more than [`nonce`],
+/// as the origin's sequence is incremented before the message is executed. Meta-transaction
+/// relayers can use this for replay protection.
+pub fn origin_account_nonce() -> u64 {
+    unsafe { sys::vm::origin_account_nonce().expect("failed to lookup origin nonce") }
+}
+
 /// Returns the ID address of the caller.
 #[inline(always)]
 pub fn caller() -> ActorID {
     MESSAGE_CONTEXT.caller
 }
 
+/// Returns the delegated (f4) address of the caller, if any. This is equivalent to (but cheaper
+/// than) calling [`crate::actor::lookup_delegated_address`] on [`caller`].
+pub fn caller_delegated_address() -> Option<Address> {
+    let mut out_buffer = [0u8; MAX_ADDRESS_LEN];
+    unsafe {
+        match sys::vm::caller_delegated_address(out_buffer.as_mut_ptr(), out_buffer.len() as u32)
+            .expect("failed to lookup caller's delegated address")
+        {
+            0 => None,
+            length => Address::from_bytes(&out_buffer[..length as usize]).ok(),
+        }
+    }
+}
+
+/// Returns why the current invocation is (or isn't) read-only. Only available when actor debugging
+/// is enabled.
+pub fn read_only_reason() -> SyscallResult<ReadOnlyReason> {
+    let reason = unsafe { sys::vm::read_only_reason()? };
+    Ok(ReadOnlyReason::from_u32(reason).expect("unknown read-only reason"))
+}
+
+/// Returns the delegated (f4) address of the origin, if any, as of the start of the message.
+///
+/// Only available from network version 22.
+pub fn origin_delegated_address() -> Option<Address> {
+    let ctx = unsafe {
+        sys::vm::message_context_v2().expect("failed to lookup extended message context")
+    };
+    match ctx.origin_delegated_address_len {
+        0 => None,
+        length => Address::from_bytes(&ctx.origin_delegated_address[..length as usize]).ok(),
+    }
+}
+
 /// Returns the ID address of the origin
 #[inline(always)]
 pub fn origin() -> ActorID {
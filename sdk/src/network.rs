@@ -49,6 +49,23 @@ pub fn tipset_timestamp() -> u64 {
     NETWORK_CONTEXT.timestamp
 }
 
+/// Returns the expected delay between epochs, in seconds. Use this to convert between epochs and
+/// wall-clock time instead of hardcoding the block time.
+///
+/// Before network version 22, this returns 0.
+pub fn block_delay_seconds() -> u32 {
+    NETWORK_CONTEXT.block_delay_seconds
+}
+
+/// Returns true if the current epoch is at or past the `target` epoch.
+pub fn epoch_elapsed(target: ChainEpoch) -> bool {
+    unsafe {
+        sys::network::epoch_elapsed(target)
+            .map(|v| v == 0)
+            .expect("failed to check the current epoch")
+    }
+}
+
 /// Returns the tipset CID of the specified epoch, if available. Allows querying from now up to
 /// finality (900 epochs).
 pub fn tipset_cid(epoch: ChainEpoch) -> Result<Cid, EpochBoundsError> {
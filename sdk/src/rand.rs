@@ -1,7 +1,7 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 use fvm_shared::clock::ChainEpoch;
-use fvm_shared::randomness::RANDOMNESS_LENGTH;
+use fvm_shared::randomness::{RandSource, RANDOMNESS_LENGTH};
 
 use crate::{sys, SyscallResult};
 
@@ -20,3 +20,23 @@ pub fn get_chain_randomness(round: ChainEpoch) -> SyscallResult<[u8; RANDOMNESS_
 pub fn get_beacon_randomness(round: ChainEpoch) -> SyscallResult<[u8; RANDOMNESS_LENGTH]> {
     unsafe { sys::rand::get_beacon_randomness(round) }
 }
+
+/// Draws randomness from the given source, mixes in the supplied entropy, and maps it to a
+/// uniformly distributed integer in `[0, bound)`. The sampling is performed by the FVM and is
+/// free of modulo bias.
+pub fn random_in_range(
+    source: RandSource,
+    round: ChainEpoch,
+    entropy: &[u8],
+    bound: u64,
+) -> SyscallResult<u64> {
+    unsafe {
+        sys::rand::random_in_range(
+            source as u32,
+            round,
+            entropy.as_ptr(),
+            entropy.len() as u32,
+            bound,
+        )
+    }
+}
@@ -1,6 +1,7 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 use cid::Cid;
+use fvm_shared::address::{Address, MAX_ADDRESS_LEN};
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::error::ErrorNumber;
 use fvm_shared::MAX_CID_LEN;
@@ -52,6 +53,16 @@ pub fn current_balance() -> TokenAmount {
     }
 }
 
+/// Gets the balance of the calling actor before the value received by this invocation was credited.
+#[inline(always)]
+pub fn balance_before_receipt() -> TokenAmount {
+    unsafe {
+        sys::sself::balance_before_receipt()
+            .expect("failed to get balance before receipt")
+            .into()
+    }
+}
+
 /// Destroys the calling actor, burning any remaining balance.
 pub fn self_destruct(burn_funds: bool) -> Result<(), ActorDeleteError> {
     unsafe {
@@ -62,3 +73,19 @@ pub fn self_destruct(burn_funds: bool) -> Result<(), ActorDeleteError> {
         })
     }
 }
+
+/// Returns the calling actor's "delegated" (f4) address, if any.
+pub fn delegated_address() -> Option<Address> {
+    let mut out_buffer = [0u8; MAX_ADDRESS_LEN];
+    unsafe {
+        match sys::sself::delegated_address(out_buffer.as_mut_ptr(), out_buffer.len() as u32)
+            .expect("failed to lookup own delegated address")
+        {
+            0 => None,
+            length => Some(
+                Address::from_bytes(&out_buffer[..length as usize])
+                    .expect("invalid delegated address"),
+            ),
+        }
+    }
+}
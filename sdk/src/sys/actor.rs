@@ -2,6 +2,8 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 //! Syscalls for creating and resolving actors.
 
+pub use fvm_shared::sys::out::actor::DelegatedNamespace;
+
 // for documentation links
 #[cfg(doc)]
 use crate::sys::ErrorNumber::*;
@@ -51,6 +53,48 @@ super::fvm_syscalls! {
         addr_buf_len: u32,
     ) -> Result<u32>;
 
+    /// Looks up the namespace (the ID of the managing actor) of the target actor's delegated (f4)
+    /// address, without reading the address itself.
+    ///
+    /// # Returns
+    ///
+    /// The namespace, with `delegated` set to 0 if the target actor has no delegated address.
+    ///
+    /// # Errors
+    ///
+    /// | Error        | Reason                             |
+    /// |--------------|------------------------------------|
+    /// | [`NotFound`] | if the target actor does not exist |
+    pub fn delegated_namespace(actor_id: u64) -> Result<DelegatedNamespace>;
+
+
+    /// Constructs the delegated (f4) address with the specified namespace (the ID of the managing
+    /// actor) and subaddress.
+    ///
+    /// # Arguments
+    ///
+    /// - `namespace` is the ID of the managing actor.
+    /// - `subaddr_off` and `subaddr_len` specify the location and length of the subaddress.
+    /// - `obuf_off` and `obuf_len` specify the location and length of the output buffer in which
+    ///   to store the address.
+    ///
+    /// # Returns
+    ///
+    /// The length of the address written to the output buffer.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                                     |
+    /// |---------------------|------------------------------------------------------------|
+    /// | [`BufferTooSmall`]  | if the output buffer isn't large enough to fit the address |
+    /// | [`IllegalArgument`] | if the subaddress is too long, or a buffer is invalid      |
+    pub fn make_delegated_address(
+        namespace: u64,
+        subaddr_off: *const u8,
+        subaddr_len: u32,
+        obuf_off: *mut u8,
+        obuf_len: u32,
+    ) -> Result<u32>;
 
     /// Gets the CodeCID of an actor by address.
     ///
@@ -91,6 +135,21 @@ super::fvm_syscalls! {
     /// | [`IllegalArgument`] | if the passed CID isn't valid                             |
     pub fn get_builtin_actor_type(cid_off: *const u8) -> Result<i32>;
 
+    /// Resolves the given address and returns the builtin-actor type ID of the target actor, or 0
+    /// if the actor doesn't exist or isn't a builtin actor.
+    ///
+    /// # Arguments
+    ///
+    /// `addr_off` and `addr_len` specify the location and length of the address.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                                    |
+    /// |---------------------|-----------------------------------------------------------|
+    /// | [`NotFound`]        | if the address can't be resolved                          |
+    /// | [`IllegalArgument`] | if the passed address buffer isn't valid, in memory, etc. |
+    pub fn address_builtin_type(addr_off: *const u8, addr_len: u32) -> Result<i32>;
+
     /// Returns the CodeCID for the given built-in actor type.
     ///
     /// # Arguments
@@ -116,6 +175,14 @@ super::fvm_syscalls! {
     #[doc(hidden)]
     pub fn next_actor_address(obuf_off: *mut u8, obuf_len: u32) -> Result<u32>;
 
+    /// Returns the number of actors created so far in the current message. Together with the
+    /// message's origin and nonce, this determines the addresses of the next actors created.
+    ///
+    /// # Errors
+    ///
+    /// None
+    pub fn actors_created() -> Result<u64>;
+
     /// Creates a new actor in the state-tree with the specified actor ID, recording the specified
     /// "delegated" address in the actor root if non-empty, and returning a new stable address.
     ///
@@ -147,4 +214,52 @@ super::fvm_syscalls! {
     pub fn balance_of(
         actor_id: u64
     )  -> Result<super::TokenAmount>;
+
+    /// Gets the balances of the specified actors, in order. Non-existent actors have a balance of
+    /// zero.
+    ///
+    /// # Arguments
+    ///
+    /// - `ids_off` and `ids_len` specify the location and number of the actor IDs.
+    /// - `obuf_off` is the location of the output buffer, which must have room for `ids_len`
+    ///   balances.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                  |
+    /// |---------------------|-----------------------------------------|
+    /// | [`IllegalArgument`] | if the input or output buffer is invalid |
+    pub fn balances_of(
+        ids_off: *const u64,
+        ids_len: u32,
+        obuf_off: *mut super::TokenAmount,
+    ) -> Result<()>;
+
+    /// Checks whether an actor ID is free, i.e., whether an actor could be created with it.
+    ///
+    /// Returns 0 if no actor exists with the specified ID, or -1 if the ID is in use.
+    ///
+    /// # Arguments
+    ///
+    /// - `actor_id` is the ID to check.
+    ///
+    /// # Errors
+    ///
+    /// None.
+    pub fn actor_id_available(actor_id: u64) -> Result<i32>;
+
+    /// Checks whether an actor is expected to accept a plain value transfer (method 0), based on
+    /// its builtin type: i.e., whether it exists, isn't a builtin actor without a fund-receiving
+    /// entrypoint, and isn't a singleton that rejects value transfers. This is best-effort.
+    ///
+    /// Returns 0 if the actor can receive funds, or -1 otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// - `actor_id` is the ID of the actor.
+    ///
+    /// # Errors
+    ///
+    /// None.
+    pub fn can_receive_funds(actor_id: u64) -> Result<i32>;
 }
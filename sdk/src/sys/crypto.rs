@@ -57,6 +57,27 @@ super::fvm_syscalls! {
         sig_off: *const u8,
     ) -> Result<[u8; SECP_PUB_LEN]>;
 
+    /// Recovers the signer public keys from a batch of signed message hashes and their
+    /// signatures, in order. Fails if recovery fails for any item.
+    ///
+    /// # Arguments
+    ///
+    /// - `items_off` and `items_len` specify the location and number of the items, each a 32-byte
+    ///   message hash immediately followed by its 65-byte signature.
+    /// - `obuf_off` is the location of the output buffer, which must have room for `items_len`
+    ///   65-byte public keys.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                                     |
+    /// |---------------------|------------------------------------------------------------|
+    /// | [`IllegalArgument`] | any signature is invalid, or the buffers are invalid       |
+    pub fn recover_secp_public_keys(
+        items_off: *const u8,
+        items_len: u32,
+        obuf_off: *mut [u8; SECP_PUB_LEN],
+    ) -> Result<()>;
+
 
     /// Hashes input data using the specified hash function. The digest is written to the passed
     /// digest buffer and truncated to `digest_len`.
@@ -83,6 +104,44 @@ super::fvm_syscalls! {
         digest_len: u32,
     ) -> Result<u32>;
 
+    /// Starts hashing data in chunks using the specified hash function, for data too large to
+    /// pass to [`hash`] at once. Returns a handle to the in-progress computation.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                              |
+    /// |---------------------|-------------------------------------|
+    /// | [`IllegalArgument`] | the hash function isn't supported   |
+    pub fn hash_init(hash_code: u64) -> Result<u32>;
+
+    /// Feeds the next chunk of data into an in-progress hash computation.
+    ///
+    /// # Arguments
+    ///
+    /// - `handle` is the handle returned by [`hash_init`].
+    /// - `data_off` and `data_len` specify location and length of the chunk to be hashed.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                          |
+    /// |---------------------|-------------------------------------------------|
+    /// | [`InvalidHandle`]   | the handle isn't known                          |
+    /// | [`IllegalArgument`] | the input buffer does not point to valid memory |
+    pub fn hash_update(handle: u32, data_off: *const u8, data_len: u32) -> Result<()>;
+
+    /// Completes an in-progress hash computation. The digest is written to the passed digest
+    /// buffer and truncated to `digest_len`. The handle is invalid afterwards.
+    ///
+    /// Returns the length of the digest written to the digest buffer.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                           |
+    /// |---------------------|--------------------------------------------------|
+    /// | [`InvalidHandle`]   | the handle isn't known                           |
+    /// | [`IllegalArgument`] | the digest buffer does not point to valid memory |
+    pub fn hash_finalize(handle: u32, digest_off: *mut u8, digest_len: u32) -> Result<u32>;
+
     /// Computes an unsealed sector CID (CommD) from its constituent piece CIDs
     /// (CommPs) and sizes.
     ///
@@ -190,6 +249,31 @@ super::fvm_syscalls! {
     /// | [`IllegalArgument`] | an argument is malformed      |
     pub fn verify_replica_update(rep_off: *const u8, rep_len: u32) -> Result<i32>;
 
+    /// Verifies a Groth16 proof over BLS12-381.
+    ///
+    /// Returns 0 on success, or -1 if the proof fails to validate.
+    ///
+    /// # Arguments
+    ///
+    /// - `vk_off` and `vk_len` specify the location and length of the serialized verifying key.
+    /// - `proof_off` and `proof_len` specify the location and length of the serialized proof.
+    /// - `inputs_off` and `inputs_len` specify the location and length of the public inputs, each
+    ///   encoded as a 32-byte little-endian scalar.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                                      |
+    /// |---------------------|-------------------------------------------------------------|
+    /// | [`IllegalArgument`] | the key, proof, or inputs are malformed or don't match      |
+    pub fn groth16_verify(
+        vk_off: *const u8,
+        vk_len: u32,
+        proof_off: *const u8,
+        proof_len: u32,
+        inputs_off: *const u8,
+        inputs_len: u32,
+    ) -> Result<i32>;
+
     /// Verifies a batch of sector seal proofs.
     ///
     /// # Arguments
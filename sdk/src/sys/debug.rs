@@ -2,6 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 //! Syscalls for debugging.
 
+// for documentation links
+#[cfg(doc)]
+use crate::sys::ErrorNumber::*;
+
 super::fvm_syscalls! {
     module = "debug";
 
@@ -13,5 +17,39 @@ super::fvm_syscalls! {
     pub fn log(message: *const u8, message_len: u32) -> Result<()>;
 
     /// Save data as a debug artifact on the node.
-    pub fn store_artifact(name_off: *const u8, name_len: u32, data_off: *const u8, data_len: u32) -> Result<()>;
+    pub fn store_artifact(
+        name_off: *const u8,
+        name_len: u32,
+        data_off: *const u8,
+        data_len: u32,
+    ) -> Result<()>;
+
+    /// Save data as a debug artifact on the node, reporting the name under which it was stored.
+    ///
+    /// The name under which the artifact was stored is written to `obuf_off` (at most `obuf_len`
+    /// bytes), and its length is returned. This name differs from the requested name if an artifact
+    /// of the same name was already stored by this invocation. Returns 0 if debugging is disabled.
+    pub fn store_artifact_unique(
+        name_off: *const u8,
+        name_len: u32,
+        data_off: *const u8,
+        data_len: u32,
+        obuf_off: *mut u8,
+        obuf_len: u32,
+    ) -> Result<u32>;
+
+    /// Writes the actors currently being invoked along with the invoked methods, outermost first,
+    /// into the output buffer. Each frame is written as two little-endian `u64`s: the actor ID,
+    /// then the method number.
+    ///
+    /// Returns the total number of frames, which may exceed the number of frames that fit in the
+    /// output buffer (`obuf_len / 16`).
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                            |
+    /// |---------------------|---------------------------------------------------|
+    /// | [`Forbidden`]       | debugging is disabled                             |
+    /// | [`IllegalArgument`] | if the output buffer isn't valid, in memory, etc. |
+    pub fn call_stack(obuf_off: *mut u8, obuf_len: u32) -> Result<u32>;
 }
@@ -21,6 +21,7 @@ super::fvm_syscalls! {
     /// |---------------------|---------------------------------------------------------------------|
     /// | [`IllegalArgument`] | entries failed to validate due to improper encoding or invalid data |
     /// | [`ReadOnly`]        | cannot send events while read-only                                  |
+    /// | [`LimitExceeded`]   | the event is too large or the call stack is too deep                |
     pub fn emit_event(
         evt_off: *const EventEntry,
         evt_len: u32,
@@ -29,4 +30,82 @@ super::fvm_syscalls! {
         value_off: *const u8,
         value_len: u32,
     ) -> Result<()>;
+
+    /// Computes the root of the events AMT over the events emitted so far in the current message,
+    /// excluding events emitted by reverted calls.
+    ///
+    /// # Arguments
+    ///
+    /// - `obuf_off` and `obuf_len` specify the location and length of a byte buffer into which the
+    ///   FVM will write the root CID, if any events have been emitted.
+    ///
+    /// # Returns
+    ///
+    /// The length of the CID, or 0 if no events have been emitted.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                                    |
+    /// |---------------------|-----------------------------------------------------------|
+    /// | [`BufferTooSmall`]  | if the output buffer isn't large enough to fit the CID    |
+    /// | [`IllegalArgument`] | if the output buffer isn't valid, in memory, etc.         |
+    pub fn root_so_far(
+        obuf_off: *mut u8,
+        obuf_len: u32,
+    ) -> Result<u32>;
+
+    /// Returns the gas (in whole gas units, rounded up) that [`emit_event`] would charge for the
+    /// given event, without emitting it. The arguments are the same as for [`emit_event`], but the
+    /// event isn't validated.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                               |
+    /// |---------------------|------------------------------------------------------|
+    /// | [`IllegalArgument`] | if the passed buffers aren't valid, in memory, etc.  |
+    pub fn gas_cost(
+        evt_off: *const EventEntry,
+        evt_len: u32,
+        key_off: *const u8,
+        key_len: u32,
+        value_off: *const u8,
+        value_len: u32,
+    ) -> Result<u64>;
+
+    /// Returns how many more events the current message may emit, or `u32::MAX` if there's no
+    /// limit.
+    ///
+    /// # Errors
+    ///
+    /// None
+    pub fn events_remaining() -> Result<u32>;
+
+    /// Emits a batch of actor events, skipping events identical to the previously emitted one.
+    ///
+    /// The entries, keys, and values of all events are concatenated as for [`emit_event`], and
+    /// `sizes_off`/`sizes_len` specify the number of entries in each event. Only emitted events
+    /// are charged for, plus the cost of comparing each event to the previous one. Events emitted
+    /// before an error remain emitted.
+    ///
+    /// # Returns
+    ///
+    /// The number of events actually emitted.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                                           |
+    /// |---------------------|------------------------------------------------------------------|
+    /// | [`IllegalArgument`] | entries failed to validate, or the sizes don't match the buffers |
+    /// | [`ReadOnly`]        | cannot send events while read-only                               |
+    /// | [`LimitExceeded`]   | an event is too large, or too many events, or too deep           |
+    pub fn emit_events_dedup(
+        sizes_off: *const u32,
+        sizes_len: u32,
+        evt_off: *const EventEntry,
+        evt_len: u32,
+        key_off: *const u8,
+        key_len: u32,
+        value_off: *const u8,
+        value_len: u32,
+    ) -> Result<u32>;
 }
@@ -26,4 +26,63 @@ super::fvm_syscalls! {
 
     /// Returns the amount of gas remaining.
     pub fn available() -> Result<u64>;
+
+    /// Estimates the total fee (base fee plus premium) of a message that uses all of its gas
+    /// limit, at the current base fee.
+    ///
+    /// # Arguments
+    ///
+    /// - `gas_limit` is the message's gas limit.
+    /// - `premium_hi` and `premium_lo` are the high and low 64 bits of the gas premium.
+    ///
+    /// # Errors
+    ///
+    /// | Error             | Reason                               |
+    /// |-------------------|--------------------------------------|
+    /// | [`LimitExceeded`] | the estimated fee doesn't fit a u128 |
+    pub fn estimate_fee(gas_limit: u64, premium_hi: u64, premium_lo: u64) -> Result<super::TokenAmount>;
+
+    /// Creates a sub-meter with the given budget, returning a handle to it. Charges to the
+    /// sub-meter (see [`charge_to`]) also draw from the global gas budget.
+    ///
+    /// Handles are only valid within the invocation that created them.
+    ///
+    /// # Arguments
+    ///
+    /// - `budget` is the sub-meter's budget, in gas.
+    ///
+    /// # Errors
+    ///
+    /// | Error                | Reason                                  |
+    /// |----------------------|-----------------------------------------|
+    /// | [`IllegalOperation`] | sub-meters aren't supported before nv22 |
+    pub fn create_submeter(budget: u64) -> Result<u32>;
+
+    /// Charges gas to a sub-meter, and to the global gas budget.
+    ///
+    /// # Arguments
+    ///
+    /// - `meter` is a sub-meter handle returned by [`create_submeter`].
+    /// - `amount` is the amount of gas to charge.
+    ///
+    /// # Errors
+    ///
+    /// | Error             | Reason                                                     |
+    /// |-------------------|------------------------------------------------------------|
+    /// | [`NotFound`]      | the sub-meter wasn't created by this invocation            |
+    /// | [`LimitExceeded`] | the sub-meter's remaining budget is less than the amount   |
+    pub fn charge_to(meter: u32, amount: u64) -> Result<()>;
+
+    /// Returns the gas remaining in a sub-meter.
+    ///
+    /// # Arguments
+    ///
+    /// - `meter` is a sub-meter handle returned by [`create_submeter`].
+    ///
+    /// # Errors
+    ///
+    /// | Error        | Reason                                          |
+    /// |--------------|-------------------------------------------------|
+    /// | [`NotFound`] | the sub-meter wasn't created by this invocation |
+    pub fn submeter_remaining(meter: u32) -> Result<u64>;
 }
@@ -53,6 +53,55 @@ super::fvm_syscalls! {
     /// | [`IllegalArgument`] | the block isn't in memory, etc.                         |
     pub fn block_create(codec: u64, data: *const u8, len: u32) -> Result<u32>;
 
+    /// Creates a new block equal to an existing block, but with the passed data overwritten at
+    /// `offset`, returning the new block's ID. The source block is unchanged, and the same rules
+    /// apply to the new block as to [`block_create`].
+    ///
+    /// # Arguments
+    ///
+    /// - `id` is ID of the source block.
+    /// - `offset` is the offset in the source block at which to write the data.
+    /// - `data` and `len` specify the location and length of the data to write.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                                  |
+    /// |---------------------|---------------------------------------------------------|
+    /// | [`InvalidHandle`]   | the source block handle isn't known                     |
+    /// | [`IllegalArgument`] | the data extends past the end of the source block, etc. |
+    /// | [`LimitExceeded`]   | the block is too big                                    |
+    /// | [`NotFound`]        | one of the blocks's children isn't in the reachable set |
+    /// | [`Serialization`]   | the patched block doesn't match the block's codec       |
+    pub fn block_patch(id: u32, offset: u32, data: *const u8, len: u32) -> Result<u32>;
+
+    /// Opens the block identified by `cid` if it's in the reachable set and present in the
+    /// blockstore. Otherwise, creates a new block from `codec` and `data` (like [`block_create`])
+    /// after checking that it matches `cid`. Returns the block's ID, and whether it was created
+    /// (1) or opened (0).
+    ///
+    /// # Arguments
+    ///
+    /// - `cid` the location of the CID (in wasm memory). It must be a 32-byte blake2b CID.
+    /// - `codec` is the codec of the block, which must match the CID's codec.
+    /// - `data` and `len` specify the location and length of the block data.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                                  |
+    /// |---------------------|---------------------------------------------------------|
+    /// | [`LimitExceeded`]   | the block is too big                                    |
+    /// | [`NotFound`]        | one of the blocks's children isn't in the reachable set |
+    /// | [`IllegalCodec`]    | the passed codec isn't supported                        |
+    /// | [`IllegalCid`]      | the CID isn't a 32-byte blake2b CID                     |
+    /// | [`Serialization`]   | the passed block doesn't match the passed codec         |
+    /// | [`IllegalArgument`] | the codec or data don't match the CID, etc.             |
+    pub fn block_open_or_create(
+        cid: *const u8,
+        codec: u64,
+        data: *const u8,
+        len: u32,
+    ) -> Result<IpldOpenOrCreate>;
+
     /// Reads the block identified by `id` into `obuf`, starting at `offset`, reading _at most_
     /// `max_len` bytes.
     ///
@@ -82,6 +131,17 @@ super::fvm_syscalls! {
     /// | [`IllegalArgument`] | if the passed buffer isn't valid, in memory, etc. |
     pub fn block_read(id: u32, offset: u32, obuf: *mut u8, max_len: u32) -> Result<i32>;
 
+    /// Reads a CBOR block containing a single unsigned integer (e.g., a counter or an epoch),
+    /// without copying the block into actor memory.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                                  |
+    /// |---------------------|---------------------------------------------------------|
+    /// | [`InvalidHandle`]   | if the handle isn't known.                              |
+    /// | [`IllegalArgument`] | if the block isn't a CBOR block containing just a u64.  |
+    pub fn block_read_u64(id: u32) -> Result<u64>;
+
     /// Returns the codec and size of the specified block.
     ///
     /// # Errors
@@ -122,4 +182,189 @@ super::fvm_syscalls! {
         cid: *mut u8,
         cid_max_len: u32,
     ) -> Result<u32>;
+
+    /// Computes the CID [`block_link`] would return for the given block, writing it into `cid`.
+    ///
+    /// Unlike [`block_link`], the block is neither persisted nor added to the reachable set, and
+    /// the block handle remains valid.
+    ///
+    /// # Arguments
+    ///
+    /// - `id` is ID of the block.
+    /// - `hash_fun` is the multicodec of the hash function to use.
+    /// - `hash_len` is the desired length of the hash digest.
+    /// - `cid` is the output buffer (in wasm memory) where the FVM will write the resulting cid.
+    /// - `cid_max_length` is the length of the output CID buffer.
+    ///
+    /// # Returns
+    ///
+    /// The length of the CID.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                            |
+    /// |---------------------|---------------------------------------------------|
+    /// | [`InvalidHandle`]   | if the handle isn't known.                        |
+    /// | [`IllegalCid`]      | hash code and/or hash length aren't supported.    |
+    /// | [`BufferTooSmall`]  | if the passed buffer is too small                 |
+    /// | [`IllegalArgument`] | if the passed buffer isn't valid, in memory, etc. |
+    pub fn block_cid(
+        id: u32,
+        hash_fun: u64,
+        hash_len: u32,
+        cid: *mut u8,
+        cid_max_len: u32,
+    ) -> Result<u32>;
+
+    /// Marks the specified block as retained, exempting it from eviction until it's linked with
+    /// [`block_link`]. Retained blocks still count towards the gas budget.
+    ///
+    /// # Errors
+    ///
+    /// | Error             | Reason                     |
+    /// |-------------------|----------------------------|
+    /// | [`InvalidHandle`] | if the handle isn't known. |
+    pub fn block_retain(id: u32) -> Result<()>;
+
+    /// Closes the specified block, freeing its handle for reuse. Only the most recently created or
+    /// opened block may be closed.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                       |
+    /// |---------------------|----------------------------------------------|
+    /// | [`InvalidHandle`]   | if the handle isn't known.                   |
+    /// | [`IllegalArgument`] | if the block isn't the most recently opened. |
+    pub fn block_close(id: u32) -> Result<()>;
+
+    /// Compares two blocks by codec and contents, without reading them into actor memory.
+    ///
+    /// Returns 0 if the blocks are equal, or -1 otherwise.
+    ///
+    /// # Errors
+    ///
+    /// | Error             | Reason                         |
+    /// |-------------------|--------------------------------|
+    /// | [`InvalidHandle`] | if either handle isn't known.  |
+    pub fn block_eq(a: u32, b: u32) -> Result<i32>;
+
+    /// Checks whether the block hashes to the expected CID, without reading it into actor memory.
+    ///
+    /// Returns 0 if the block's codec and hash match the CID, or -1 otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// - `id` is the block handle.
+    /// - `cid` is the location of the expected CID (in wasm memory).
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                      |
+    /// |---------------------|---------------------------------------------|
+    /// | [`InvalidHandle`]   | if the handle isn't known.                  |
+    /// | [`IllegalCid`]      | the CID isn't a 32-byte blake2b CID.        |
+    /// | [`IllegalArgument`] | there's something wrong with the CID        |
+    pub fn block_hash_eq(id: u32, cid: *const u8) -> Result<i32>;
+
+    /// Checks whether the block links to the target CID, without reading it into actor memory.
+    /// The block is only scanned up to the first matching link.
+    ///
+    /// Returns 0 if the block contains the link, or -1 otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// - `id` is the block handle.
+    /// - `cid` is the location of the target CID (in wasm memory).
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                               |
+    /// |---------------------|--------------------------------------|
+    /// | [`InvalidHandle`]   | if the handle isn't known.           |
+    /// | [`IllegalArgument`] | there's something wrong with the CID |
+    pub fn block_contains_link(id: u32, cid: *const u8) -> Result<i32>;
+
+    /// Checks whether `root` can be reached by following links from `child` within `max_depth`
+    /// links (or is `child`), i.e., whether making `root` link to `child` would form a cycle. Gas
+    /// is charged for every block visited.
+    ///
+    /// Returns 0 if linking would create a cycle, or -1 otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// - `root` is the location of the root CID (in wasm memory).
+    /// - `child` is the location of the candidate child CID (in wasm memory), which must be
+    ///   reachable by the calling actor.
+    /// - `max_depth` is the maximum number of links to follow.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                    |
+    /// |---------------------|-------------------------------------------|
+    /// | [`NotFound`]        | the child CID isn't reachable             |
+    /// | [`IllegalArgument`] | there's something wrong with either CID   |
+    pub fn would_create_cycle(root: *const u8, child: *const u8, max_depth: u32) -> Result<i32>;
+
+    /// Checks whether the block satisfies the constraints on return values (maximum block size and
+    /// allowed codecs).
+    ///
+    /// Returns 0 if the block may be returned, or -1 otherwise.
+    ///
+    /// # Errors
+    ///
+    /// | Error             | Reason                     |
+    /// |-------------------|----------------------------|
+    /// | [`InvalidHandle`] | if the handle isn't known. |
+    pub fn would_fit_as_return(id: u32) -> Result<i32>;
+
+    /// Checks whether the codec is a DAG codec, i.e., whether blocks with this codec may contain
+    /// links.
+    ///
+    /// Returns 0 if the codec is a DAG codec, or -1 if it's a leaf codec (e.g., raw) or unknown.
+    ///
+    /// # Errors
+    ///
+    /// None.
+    pub fn is_dag_codec(codec: u64) -> Result<i32>;
+
+    /// Decompresses zlib-compressed data into the output buffer.
+    ///
+    /// Returns the number of bytes written to the output buffer.
+    ///
+    /// # Arguments
+    ///
+    /// - `data_off` and `data_len` specify the location and length of the compressed data.
+    /// - `obuf_off` and `obuf_len` specify the location and length of the output buffer. The
+    ///   output buffer's length is the maximum size of the decompressed data.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                               |
+    /// |---------------------|------------------------------------------------------|
+    /// | [`LimitExceeded`]   | the decompressed data doesn't fit in the buffer      |
+    /// | [`IllegalArgument`] | the compressed data is malformed or buffers invalid  |
+    pub fn inflate(
+        data_off: *const u8,
+        data_len: u32,
+        obuf_off: *mut u8,
+        obuf_len: u32,
+    ) -> Result<u32>;
+
+    /// Raises the maximum number of blocks the current invocation may hold open by `extra`,
+    /// charging gas proportional to the increase.
+    ///
+    /// Returns the previous limit, to be passed to [`restore_block_handles`].
+    ///
+    /// # Errors
+    ///
+    /// None.
+    pub fn expand_block_handles(extra: u32) -> Result<u32>;
+
+    /// Restores a block limit returned by [`expand_block_handles`]. This can only lower the limit.
+    /// Blocks created while the limit was raised remain valid.
+    ///
+    /// # Errors
+    ///
+    /// None.
+    pub fn restore_block_handles(limit: u32) -> Result<()>;
 }
@@ -135,7 +135,9 @@ macro_rules! fvm_syscalls {
                 fn syscall(ret: *mut $ret $(, $args : $args_ty)*) -> u32;
             }
 
-            let mut ret = std::mem::MaybeUninit::<$ret>::uninit();
+            // Zeroed, so that fields missing from an older layout (see
+            // `fvm_shared::sys::layout`) read as zero.
+            let mut ret = std::mem::MaybeUninit::<$ret>::zeroed();
             let code = syscall(ret.as_mut_ptr(), $($args),*);
 
             if code == 0 {
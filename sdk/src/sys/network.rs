@@ -49,4 +49,13 @@ super::fvm_syscalls! {
     ///
     /// None
     pub fn context() -> Result<NetworkContext>;
+
+    /// Checks whether the current epoch is at or past the `target` epoch.
+    ///
+    /// Returns 0 if the target epoch has elapsed, or -1 otherwise.
+    ///
+    /// # Errors
+    ///
+    /// None
+    pub fn epoch_elapsed(target: i64) -> Result<i32>;
 }
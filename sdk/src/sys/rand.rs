@@ -42,4 +42,28 @@ super::fvm_syscalls! {
     pub fn get_beacon_randomness(
         epoch: i64,
     ) -> Result<[u8; RANDOMNESS_LENGTH]>;
+
+    /// Draws randomness from the given source, mixes in the supplied entropy, and maps it to a
+    /// uniformly distributed integer in `[0, bound)`.
+    ///
+    /// # Arguments
+    ///
+    /// - `source` is the randomness source (0 for the ticket chain, 1 for the beacon).
+    /// - `epoch` is the epoch to pull the randomness from.
+    /// - `entropy_off` and `entropy_len` specify the location and length of the entropy.
+    /// - `bound` is the exclusive upper bound of the result.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                              |
+    /// |---------------------|-----------------------------------------------------|
+    /// | [`LimitExceeded`]   | lookback exceeds limit.                             |
+    /// | [`IllegalArgument`] | invalid source, zero bound, invalid buffer, etc.    |
+    pub fn random_in_range(
+        source: u32,
+        epoch: i64,
+        entropy_off: *const u8,
+        entropy_len: u32,
+        bound: u64,
+    ) -> Result<u64>;
 }
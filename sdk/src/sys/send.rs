@@ -43,7 +43,7 @@ super::fvm_syscalls! {
     /// | [`InsufficientFunds`] | tried to send more FIL than available.               |
     /// | [`InvalidHandle`]     | parameters block not found.                          |
     /// | [`LimitExceeded`]     | recursion limit reached.                             |
-    /// | [`IllegalArgument`]   | invalid recipient address buffer.                    |
+    /// | [`IllegalArgument`]   | invalid recipient address buffer, or unknown flags.  |
     /// | [`ReadOnly`]          | the send would mutate state in read-only mode.       |
     pub fn send(
         recipient_off: *const u8,
@@ -49,6 +49,14 @@ super::fvm_syscalls! {
     /// None.
     pub fn current_balance() -> Result<super::TokenAmount>;
 
+    /// Gets the balance of the calling actor before the value received by this invocation was
+    /// credited, saturating at zero.
+    ///
+    /// # Errors
+    ///
+    /// None.
+    pub fn balance_before_receipt() -> Result<super::TokenAmount>;
+
     /// Destroys the calling actor. If `burn_funds` is true, any unspent balance will be burnt
     /// (destroyed). Otherwise, if `burnt_funds` is false and there are unspent funds, this syscall
     /// will fail.
@@ -64,4 +72,24 @@ super::fvm_syscalls! {
     /// | [`IllegalOperation`]  | the actor has unspent funds               |
     /// | [`ReadOnly`]          | the actor is executing in read-only mode  |
     pub fn self_destruct(burn_funds: bool) -> Result<()>;
+
+    /// Gets the calling actor's "delegated" (f4) address, if any.
+    ///
+    /// # Arguments
+    ///
+    /// `addr_buf_off` and `addr_buf_len` specify the location and length of the output buffer in
+    /// which to store the address.
+    ///
+    /// # Returns
+    ///
+    /// The length of the address written to the output buffer, or 0 if the calling actor has no
+    /// delegated (f4) address.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                                           |
+    /// |---------------------|------------------------------------------------------------------|
+    /// | [`BufferTooSmall`]  | if the output buffer isn't large enough to fit the address       |
+    /// | [`IllegalArgument`] | if the output buffer isn't valid, in memory, etc.                |
+    pub fn delegated_address(addr_buf_off: *mut u8, addr_buf_len: u32) -> Result<u32>;
 }
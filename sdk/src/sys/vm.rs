@@ -3,7 +3,11 @@
 //! Syscalls for interacting with the VM.
 
 #[doc(inline)]
-pub use fvm_shared::sys::out::vm::MessageContext;
+pub use fvm_shared::sys::out::vm::{MemoryUsage, MessageContext, MessageContextV2};
+
+// for documentation links
+#[cfg(doc)]
+use crate::sys::ErrorNumber::*;
 
 super::fvm_syscalls! {
     module = "vm";
@@ -36,4 +40,76 @@ super::fvm_syscalls! {
     ///
     /// None
     pub fn message_context() -> Result<MessageContext>;
+
+    /// Returns the extended details about the message causing this invocation, including the
+    /// origin's delegated address.
+    ///
+    /// # Errors
+    ///
+    /// | Error                | Reason                                   |
+    /// |----------------------|------------------------------------------|
+    /// | [`IllegalOperation`] | if the network version is lower than 22. |
+    pub fn message_context_v2() -> Result<MessageContextV2>;
+
+    /// Looks up the "delegated" (f4) address of the immediate caller (if any).
+    ///
+    /// # Arguments
+    ///
+    /// `addr_buf_off` and `addr_buf_len` specify the location and length of the output buffer in
+    /// which to store the address.
+    ///
+    /// # Returns
+    ///
+    /// The length of the address written to the output buffer, or 0 if the caller has no
+    /// delegated (f4) address.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                                     |
+    /// |---------------------|------------------------------------------------------------|
+    /// | [`BufferTooSmall`]  | if the output buffer isn't large enough to fit the address |
+    /// | [`IllegalArgument`] | if the output buffer isn't valid, in memory, etc.          |
+    pub fn caller_delegated_address(addr_buf_off: *mut u8, addr_buf_len: u32) -> Result<u32>;
+
+    /// Returns why the current invocation is (or isn't) read-only, as a
+    /// [`ReadOnlyReason`](fvm_shared::sys::ReadOnlyReason).
+    ///
+    /// # Errors
+    ///
+    /// | Error                | Reason                            |
+    /// |----------------------|-----------------------------------|
+    /// | [`IllegalOperation`] | if actor debugging isn't enabled. |
+    pub fn read_only_reason() -> Result<u32>;
+
+    /// Returns the memory currently used by the call stack, and the maximum it may use.
+    ///
+    /// # Errors
+    ///
+    /// | Error                | Reason                            |
+    /// |----------------------|-----------------------------------|
+    /// | [`IllegalOperation`] | before network version 22.        |
+    pub fn memory_usage() -> Result<MemoryUsage>;
+
+    /// Returns the origin account's current sequence number, as of execution time. This differs
+    /// from the message context's `nonce` (the sequence the message was sent with), as the origin's
+    /// sequence is incremented before the message is executed.
+    ///
+    /// # Errors
+    ///
+    /// None
+    pub fn origin_account_nonce() -> Result<u64>;
+
+    /// Checks whether the given buffer is valid UTF-8. Charged proportionally to the length of the
+    /// buffer.
+    ///
+    /// # Returns
+    ///
+    /// 0 if the buffer is valid UTF-8, -1 otherwise.
+    ///
+    /// # Errors
+    ///
+    /// | Error               | Reason                                      |
+    /// |---------------------|---------------------------------------------|
+    /// | [`IllegalArgument`] | if the input buffer isn't in memory.        |
+    pub fn is_valid_utf8(data_off: *const u8, data_len: u32) -> Result<i32>;
 }
@@ -3,9 +3,9 @@
 use std::ptr;
 
 use fvm_ipld_encoding::ipld_block::IpldBlock;
-use fvm_shared::error::ExitCode;
+use fvm_shared::error::{ErrorNumber, ExitCode};
 
-use crate::sys;
+use crate::{status_code_to_bool, sys};
 
 /// BlockID representing nil parameters or return data.
 pub const NO_DATA_BLOCK_ID: u32 = 0;
@@ -20,6 +20,18 @@ pub fn read_only() -> bool {
     super::message::MESSAGE_CONTEXT.flags.read_only()
 }
 
+/// Returns the memory (in bytes) currently used by the call stack, and the maximum it may use.
+/// Actors processing variable-size inputs can use this to avoid exceeding the limit.
+///
+/// Returns `None` before network version 22.
+pub fn memory_usage() -> Option<(u64, u64)> {
+    match unsafe { sys::vm::memory_usage() } {
+        Ok(usage) => Some((usage.used, usage.limit)),
+        Err(ErrorNumber::IllegalOperation) => None,
+        Err(e) => panic!("failed to get memory usage: {e}"),
+    }
+}
+
 /// Abort execution; exit code must be non zero.
 pub fn abort(code: u32, message: Option<&str>) -> ! {
     if code == 0 {
@@ -48,6 +60,15 @@ pub fn exit(code: u32, data: Option<IpldBlock>, message: Option<&str>) -> ! {
     }
 }
 
+/// Returns whether the given bytes are valid UTF-8, validated by the FVM.
+pub fn is_valid_utf8(data: &[u8]) -> bool {
+    unsafe {
+        sys::vm::is_valid_utf8(data.as_ptr(), data.len() as u32)
+            .map(status_code_to_bool)
+            .expect("failed to validate utf8")
+    }
+}
+
 /// Sets a panic handler to turn all panics into aborts with `USR_ASSERTION_FAILED`. This should be
 /// called early in the actor to improve debuggability.
 ///
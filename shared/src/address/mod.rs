@@ -41,6 +41,25 @@ pub const BLS_PUB_LEN: usize = 48;
 /// Max length of f4 sub addresses.
 pub const MAX_SUBADDRESS_LEN: usize = 54;
 
+/// Max length of an ID (f0) address payload: a LEB128-encoded u64.
+pub const MAX_ID_PAYLOAD_LEN: usize = 10;
+
+/// Length of a secp256k1 (f1) address payload.
+pub const SECP256K1_PAYLOAD_LEN: usize = PAYLOAD_HASH_LEN;
+
+/// Length of an actor (f2) address payload.
+pub const ACTOR_PAYLOAD_LEN: usize = PAYLOAD_HASH_LEN;
+
+/// Length of a BLS (f3) address payload.
+pub const BLS_PAYLOAD_LEN: usize = BLS_PUB_LEN;
+
+/// Max length of a delegated (f4) address payload: a LEB128-encoded namespace followed by the
+/// subaddress.
+pub const MAX_DELEGATED_PAYLOAD_LEN: usize = MAX_ID_PAYLOAD_LEN + MAX_SUBADDRESS_LEN;
+
+/// Max length of any address payload (i.e., an encoded address without the protocol byte).
+pub const MAX_PAYLOAD_LEN: usize = MAX_DELEGATED_PAYLOAD_LEN;
+
 /// Defines first available ID address after builtin actors
 pub const FIRST_NON_SINGLETON_ADDR: ActorID = 100;
 
@@ -164,6 +183,21 @@ impl Address {
         self.payload.to_raw_bytes()
     }
 
+    /// Writes the raw bytes data payload of the Address into `buf` without allocating, returning
+    /// the number of bytes written. See [`Payload::write_raw_bytes`].
+    pub fn payload_bytes_into(&self, buf: &mut [u8]) -> usize {
+        self.payload.write_raw_bytes(buf)
+    }
+
+    /// Writes the encoded bytes of the Address (including the protocol byte) into `buf` without
+    /// allocating, returning the number of bytes written.
+    ///
+    /// Panics if `buf` is too small. A buffer of [`MAX_ADDRESS_LEN`] bytes always suffices.
+    pub fn write_bytes(&self, buf: &mut [u8]) -> usize {
+        buf[0] = self.protocol() as u8;
+        1 + self.payload.write_raw_bytes(&mut buf[1..])
+    }
+
     /// Returns encoded bytes of Address
     pub fn to_bytes(self) -> Vec<u8> {
         self.payload.to_bytes()
@@ -366,6 +400,19 @@ impl<'de> Deserialize<'de> for Address {
     }
 }
 
+/// Derives the actor (f2) address assigned to the `count`th actor created (counting from 0) by the
+/// message with the given origin address and nonce. This is the address
+/// `next_actor_address` returns, so clients can compute it off-chain.
+///
+/// The address is based on the address the message was sent from, as specified (e.g., it may be an
+/// ID address), not the origin's resolved or "stable" address.
+pub fn derive_actor_address(origin: &Address, nonce: u64, count: u64) -> Address {
+    let mut b = fvm_ipld_encoding::to_vec(origin).expect("failed to serialize address");
+    b.extend_from_slice(&nonce.to_be_bytes());
+    b.extend_from_slice(&count.to_be_bytes());
+    Address::new_actor(&b)
+}
+
 pub(crate) fn to_leb_bytes(id: u64) -> Vec<u8> {
     // write id to buffer in leb128 format
     unsigned_varint::encode::u64(id, &mut unsigned_varint::encode::u64_buffer()).into()
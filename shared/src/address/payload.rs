@@ -127,6 +127,37 @@ impl Payload {
         }
     }
 
+    /// Writes the encoded bytes of Address without the protocol byte into `buf`, returning the
+    /// number of bytes written. Unlike [`Payload::to_raw_bytes`], this doesn't allocate.
+    ///
+    /// Panics if `buf` is too small. A buffer of [`MAX_PAYLOAD_LEN`](super::MAX_PAYLOAD_LEN)
+    /// bytes always suffices.
+    pub fn write_raw_bytes(&self, buf: &mut [u8]) -> usize {
+        use Payload::*;
+        let mut write = |bytes: &[u8]| {
+            buf[..bytes.len()].copy_from_slice(bytes);
+            bytes.len()
+        };
+        match self {
+            ID(i) => write(unsigned_varint::encode::u64(
+                *i,
+                &mut unsigned_varint::encode::u64_buffer(),
+            )),
+            Secp256k1(arr) => write(arr),
+            Actor(arr) => write(arr),
+            BLS(arr) => write(arr),
+            Delegated(addr) => {
+                let n = write(unsigned_varint::encode::u64(
+                    addr.namespace(),
+                    &mut unsigned_varint::encode::u64_buffer(),
+                ));
+                let sub = addr.subaddress();
+                buf[n..n + sub.len()].copy_from_slice(sub);
+                n + sub.len()
+            }
+        }
+    }
+
     /// Returns encoded bytes of Address including the protocol byte.
     pub fn to_bytes(self) -> Vec<u8> {
         let mut bz = self.to_raw_bytes();
@@ -77,8 +77,10 @@ impl ExitCode {
     pub const SYS_ASSERTION_FAILED: ExitCode = ExitCode::new(10);
     /// The actor returned a block handle that doesn't exist
     pub const SYS_MISSING_RETURN: ExitCode = ExitCode::new(11);
-    // pub const SYS_RESERVED_12: ExitCode = ExitCode::new(12);
-    // pub const SYS_RESERVED_13: ExitCode = ExitCode::new(13);
+    /// The message attempted to transfer value to a system singleton that cannot use it.
+    pub const SYS_VALUE_REJECTED: ExitCode = ExitCode::new(12);
+    /// The message attempted to transfer value to an actor deleted earlier in the same message.
+    pub const SYS_RECIPIENT_DELETED: ExitCode = ExitCode::new(13);
     // pub const SYS_RESERVED_14: ExitCode = ExitCode::new(14);
     // pub const SYS_RESERVED_15: ExitCode = ExitCode::new(15);
 
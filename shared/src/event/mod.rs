@@ -1,11 +1,22 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
+use std::borrow::Cow;
+use std::fmt;
+
+use anyhow::anyhow;
 use bitflags::bitflags;
+use cid::Cid;
 use fvm_ipld_encoding::strict_bytes;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use serde_tuple::*;
 
-use crate::ActorID;
+use crate::{ActorID, MAX_CID_LEN};
+
+/// The maximum number of entries in a single event.
+pub const MAX_NR_ENTRIES: usize = 255;
+
+/// The maximum length (in bytes) of a string event key.
+pub const MAX_KEY_LEN: usize = 31;
 
 /// Event with extra information stamped by the FVM. This is the structure that gets committed
 /// on-chain via the receipt.
@@ -30,6 +41,37 @@ pub struct ActorEvent {
     pub entries: Vec<Entry>,
 }
 
+impl ActorEvent {
+    /// Decodes a CBOR-encoded event (e.g., relayed from another chain), validating it against the
+    /// same entry count and key length limits the FVM enforces when events are emitted.
+    pub fn from_cbor(bytes: &[u8]) -> anyhow::Result<ActorEvent> {
+        let evt: ActorEvent = fvm_ipld_encoding::from_slice(bytes)?;
+        if evt.entries.len() > MAX_NR_ENTRIES {
+            return Err(anyhow!(
+                "event exceeded max entries: {} > {MAX_NR_ENTRIES}",
+                evt.entries.len()
+            ));
+        }
+        for entry in &evt.entries {
+            let (key_len, max_key_len) = match &entry.key {
+                EventKey::String(k) => (k.len(), MAX_KEY_LEN),
+                EventKey::Cid(c) => (c.encoded_len(), MAX_CID_LEN),
+            };
+            if key_len > max_key_len {
+                return Err(anyhow!(
+                    "event key exceeded max size: {key_len} > {max_key_len}"
+                ));
+            }
+        }
+        Ok(evt)
+    }
+
+    /// Encodes this event as CBOR.
+    pub fn to_cbor(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(fvm_ipld_encoding::to_vec(self)?)
+    }
+}
+
 impl From<Vec<Entry>> for ActorEvent {
     fn from(entries: Vec<Entry>) -> Self {
         Self { entries }
@@ -45,6 +87,8 @@ bitflags! {
         const FLAG_INDEXED_KEY      = 0b00000001;
         const FLAG_INDEXED_VALUE    = 0b00000010;
         const FLAG_INDEXED_ALL      = Self::FLAG_INDEXED_KEY.bits() | Self::FLAG_INDEXED_VALUE.bits();
+        /// The entry's key is a binary CID instead of a UTF-8 string.
+        const FLAG_CID_KEY          = 0b00000100;
     }
 }
 
@@ -54,10 +98,140 @@ pub struct Entry {
     /// A bitmap conveying metadata or hints about this entry.
     pub flags: Flags,
     /// The key of this event.
-    pub key: String,
+    pub key: EventKey,
     /// The value's codec. Must be IPLD_RAW (0x55) for now according to FIP-0049.
     pub codec: u64,
     /// The event's value.
     #[serde(with = "strict_bytes")]
     pub value: Vec<u8>,
 }
+
+/// The key of an event [`Entry`]. Keys are usually UTF-8 strings, but may also be CIDs (flagged
+/// with [`Flags::FLAG_CID_KEY`]) so that indexers keying on content hashes get a canonical binary
+/// key.
+///
+/// String keys are encoded as CBOR text strings and CID keys as CBOR byte strings (not as links,
+/// so they're never traversed).
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum EventKey {
+    String(String),
+    Cid(Cid),
+}
+
+impl EventKey {
+    /// Returns the raw bytes of the key, as passed to the FVM when emitting an event.
+    pub fn to_bytes(&self) -> Cow<'_, [u8]> {
+        match self {
+            EventKey::String(k) => Cow::Borrowed(k.as_bytes()),
+            EventKey::Cid(c) => Cow::Owned(c.to_bytes()),
+        }
+    }
+}
+
+impl From<String> for EventKey {
+    fn from(k: String) -> Self {
+        EventKey::String(k)
+    }
+}
+
+impl From<&str> for EventKey {
+    fn from(k: &str) -> Self {
+        EventKey::String(k.to_owned())
+    }
+}
+
+impl From<Cid> for EventKey {
+    fn from(k: Cid) -> Self {
+        EventKey::Cid(k)
+    }
+}
+
+impl Serialize for EventKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            EventKey::String(k) => serializer.serialize_str(k),
+            EventKey::Cid(c) => serializer.serialize_bytes(&c.to_bytes()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for EventKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct KeyVisitor;
+
+        impl<'de> de::Visitor<'de> for KeyVisitor {
+            type Value = EventKey;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string or a binary CID")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(EventKey::String(v.to_owned()))
+            }
+
+            fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+                Ok(EventKey::String(v))
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+                Cid::try_from(v).map(EventKey::Cid).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(KeyVisitor)
+    }
+}
+
+#[cfg(feature = "arb")]
+impl quickcheck::Arbitrary for ActorEvent {
+    fn arbitrary(g: &mut quickcheck::Gen) -> Self {
+        let nr_entries = usize::arbitrary(g) % (MAX_NR_ENTRIES + 1);
+        (0..nr_entries)
+            .map(|_| Entry::arbitrary(g))
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+#[cfg(feature = "arb")]
+impl quickcheck::Arbitrary for Entry {
+    fn arbitrary(g: &mut quickcheck::Gen) -> Self {
+        let key = EventKey::arbitrary(g);
+        let mut flags = Flags::from_bits_truncate(u64::arbitrary(g)) - Flags::FLAG_CID_KEY;
+        if let EventKey::Cid(_) = key {
+            flags |= Flags::FLAG_CID_KEY;
+        }
+        Self {
+            flags,
+            key,
+            codec: fvm_ipld_encoding::IPLD_RAW,
+            value: Vec::arbitrary(g),
+        }
+    }
+}
+
+#[cfg(feature = "arb")]
+impl quickcheck::Arbitrary for EventKey {
+    fn arbitrary(g: &mut quickcheck::Gen) -> Self {
+        if bool::arbitrary(g) {
+            let digest: [u8; 32] = std::array::from_fn(|_| u8::arbitrary(g));
+            // Identity multihash, so we don't need a hasher.
+            let mh = cid::multihash::Multihash::wrap(0, &digest).unwrap();
+            EventKey::Cid(Cid::new_v1(fvm_ipld_encoding::IPLD_RAW, mh))
+        } else {
+            // Truncate to the max key length on a char boundary.
+            let mut key = String::arbitrary(g);
+            while key.len() > MAX_KEY_LEN {
+                key.pop();
+            }
+            EventKey::String(key)
+        }
+    }
+}
@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use fvm_ipld_encoding::{BytesDe, BytesSer};
+use num_derive::FromPrimitive;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 // TODO: turn this back into a 32byte array once we no longer need go compat. It's a vec so that the
@@ -13,6 +14,16 @@ pub struct Randomness(pub Vec<u8>);
 
 pub const RANDOMNESS_LENGTH: usize = 32;
 
+/// The source from which to draw randomness.
+#[derive(FromPrimitive, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum RandSource {
+    /// The ticket chain (fork dependent, biasable).
+    Chain = 0,
+    /// The randomness beacon (currently Drand).
+    Beacon = 1,
+}
+
 impl Serialize for Randomness {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
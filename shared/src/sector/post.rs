@@ -2,12 +2,17 @@
 // Copyright 2019-2022 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
+use std::collections::HashSet;
+
 use cid::Cid;
+use fvm_ipld_bitfield::BitField;
 use fvm_ipld_encoding::strict_bytes;
 use serde_tuple::*;
+use thiserror::Error;
 
 use super::*;
-use crate::randomness::Randomness;
+use crate::clock::ChainEpoch;
+use crate::randomness::{Randomness, RANDOMNESS_LENGTH};
 use crate::ActorID;
 
 /// Randomness type used for generating PoSt proof randomness.
@@ -79,3 +84,125 @@ pub struct WindowPoStVerifyInfo {
 pub struct OnChainWindowPoStVerifyInfo {
     pub proofs: Vec<PoStProof>,
 }
+
+impl From<&SubmitWindowedPoStParams> for OnChainWindowPoStVerifyInfo {
+    fn from(params: &SubmitWindowedPoStParams) -> Self {
+        OnChainWindowPoStVerifyInfo {
+            proofs: params.proofs.clone(),
+        }
+    }
+}
+
+/// A partition proven by a Window PoSt, along with the sectors the miner skipped (declared faulty).
+#[derive(Debug, PartialEq, Default, Clone, Serialize_tuple, Deserialize_tuple)]
+pub struct PoStPartition {
+    /// The partition's index within the deadline.
+    pub index: u64,
+    /// Sectors in the partition that are skipped (not proven).
+    pub skipped: BitField,
+}
+
+/// The parameters of a Window PoSt submission (the miner actor's `SubmitWindowedPoSt` method).
+#[derive(Debug, PartialEq, Default, Clone, Serialize_tuple, Deserialize_tuple)]
+pub struct SubmitWindowedPoStParams {
+    /// The deadline (within the proving period) being proven.
+    pub deadline: u64,
+    /// The partitions being proven.
+    pub partitions: Vec<PoStPartition>,
+    /// The proofs, one per proof "batch" of partitions.
+    pub proofs: Vec<PoStProof>,
+    /// The epoch at which the chain commitment was drawn.
+    pub chain_commit_epoch: ChainEpoch,
+    /// The ticket randomness drawn at `chain_commit_epoch`, committing the proof to a chain.
+    pub chain_commit_rand: Randomness,
+}
+
+/// The limits a Window PoSt submission must respect. See
+/// [`SubmitWindowedPoStParams::validate_submission`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoStPolicy {
+    /// The number of deadlines in a proving period.
+    pub deadlines: u64,
+    /// The maximum number of partitions a single submission may prove.
+    pub max_partitions: u64,
+    /// The maximum number of proofs a single submission may carry (at least one is required).
+    pub max_proofs: usize,
+    /// The earliest allowed chain commitment epoch, usually the deadline's challenge epoch.
+    pub min_chain_commit_epoch: ChainEpoch,
+    /// The latest allowed chain commitment epoch, usually the epoch before the current one.
+    pub max_chain_commit_epoch: ChainEpoch,
+}
+
+/// Why a Window PoSt submission is invalid.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PoStValidationError {
+    #[error("invalid deadline {deadline}, must be less than {deadlines}")]
+    InvalidDeadline { deadline: u64, deadlines: u64 },
+    #[error("expected at least one proof")]
+    NoProofs,
+    #[error("too many proofs: {count} > {max}")]
+    TooManyProofs { count: usize, max: usize },
+    #[error("too many partitions: {count} > {max}")]
+    TooManyPartitions { count: u64, max: u64 },
+    #[error("partition {0} submitted more than once")]
+    DuplicatePartition(u64),
+    #[error("chain commit epoch {epoch} outside of allowed range [{min}, {max}]")]
+    ChainCommitEpochOutOfRange {
+        epoch: ChainEpoch,
+        min: ChainEpoch,
+        max: ChainEpoch,
+    },
+    #[error("chain commit randomness must be {RANDOMNESS_LENGTH} bytes, got {0}")]
+    InvalidRandomnessLength(usize),
+}
+
+impl SubmitWindowedPoStParams {
+    /// Checks the submission against the policy, without verifying the proofs themselves. This
+    /// doesn't depend on the miner's state, so it can be checked before accepting the message.
+    pub fn validate_submission(&self, policy: &PoStPolicy) -> Result<(), PoStValidationError> {
+        if self.deadline >= policy.deadlines {
+            return Err(PoStValidationError::InvalidDeadline {
+                deadline: self.deadline,
+                deadlines: policy.deadlines,
+            });
+        }
+
+        if self.proofs.is_empty() {
+            return Err(PoStValidationError::NoProofs);
+        }
+        if self.proofs.len() > policy.max_proofs {
+            return Err(PoStValidationError::TooManyProofs {
+                count: self.proofs.len(),
+                max: policy.max_proofs,
+            });
+        }
+
+        if self.partitions.len() as u64 > policy.max_partitions {
+            return Err(PoStValidationError::TooManyPartitions {
+                count: self.partitions.len() as u64,
+                max: policy.max_partitions,
+            });
+        }
+        let mut seen = HashSet::with_capacity(self.partitions.len());
+        if let Some(p) = self.partitions.iter().find(|p| !seen.insert(p.index)) {
+            return Err(PoStValidationError::DuplicatePartition(p.index));
+        }
+
+        if self.chain_commit_epoch < policy.min_chain_commit_epoch
+            || self.chain_commit_epoch > policy.max_chain_commit_epoch
+        {
+            return Err(PoStValidationError::ChainCommitEpochOutOfRange {
+                epoch: self.chain_commit_epoch,
+                min: policy.min_chain_commit_epoch,
+                max: policy.max_chain_commit_epoch,
+            });
+        }
+        if self.chain_commit_rand.0.len() != RANDOMNESS_LENGTH {
+            return Err(PoStValidationError::InvalidRandomnessLength(
+                self.chain_commit_rand.0.len(),
+            ));
+        }
+
+        Ok(())
+    }
+}
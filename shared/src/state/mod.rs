@@ -7,6 +7,8 @@ use fvm_ipld_encoding::repr::*;
 use fvm_ipld_encoding::tuple::*;
 use serde::{Deserialize, Serialize};
 
+use crate::version::NetworkVersion;
+
 /// Specifies the version of the state tree
 #[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Serialize_repr, Deserialize_repr)]
 #[repr(u64)]
@@ -25,6 +27,21 @@ pub enum StateTreeVersion {
     V5,
 }
 
+impl StateTreeVersion {
+    /// Returns the first network version that produces state trees of this version. State trees
+    /// of this version can't be correctly interpreted under earlier network versions.
+    pub fn min_network_version(self) -> NetworkVersion {
+        match self {
+            StateTreeVersion::V0 => NetworkVersion::V0,
+            StateTreeVersion::V1 => NetworkVersion::V4,
+            StateTreeVersion::V2 => NetworkVersion::V10,
+            StateTreeVersion::V3 => NetworkVersion::V12,
+            StateTreeVersion::V4 => NetworkVersion::V13,
+            StateTreeVersion::V5 => NetworkVersion::V18,
+        }
+    }
+}
+
 /// State root information. Contains information about the version of the state tree,
 /// the root of the tree, and a link to the information about the tree.
 #[derive(Deserialize_tuple, Serialize_tuple)]
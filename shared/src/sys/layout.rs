@@ -0,0 +1,125 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+//! Versioned layouts of the structs returned by syscalls.
+//!
+//! Actors are compiled against a fixed version of the structs in [`out`](super::out), so a struct
+//! can't simply grow: an actor compiled against the old layout would only have space for the old
+//! fields. Instead, fields may only be appended, and the new fields are only returned from the
+//! network version that introduces them. Earlier network versions keep returning the old layout,
+//! which is a prefix of the new one.
+//!
+//! To append a field to a returned struct:
+//!
+//! 1. Copy the current struct into the `legacy` module and check the new struct against it with
+//!    `assert_appended!`.
+//! 2. Record the old layout in the struct's [`SyscallSafe::LAYOUTS`].
+//! 3. Add the encoding under the new network version to the golden fixtures in
+//!    `shared/tests/sys_layouts.txt`.
+
+use std::mem;
+
+use self::legacy::NetworkContextV21;
+use super::out::network::NetworkContext;
+use super::{SyscallSafe, TokenAmount};
+use crate::version::NetworkVersion;
+
+/// A superseded layout of a struct returned by a syscall. See [`SyscallSafe::LAYOUTS`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SysStructVersion {
+    /// The first network version that uses the next layout.
+    pub superseded_in: NetworkVersion,
+    /// The size of the layout, in bytes.
+    pub size: usize,
+}
+
+/// The layouts of [`NetworkContext`](super::out::network::NetworkContext), before
+/// `block_delay_seconds` was appended.
+pub(super) const NETWORK_CONTEXT: &[SysStructVersion] = &[SysStructVersion {
+    superseded_in: NetworkVersion::V22,
+    size: mem::size_of::<NetworkContextV21>(),
+}];
+
+/// Returns the size (in bytes) of `T`'s layout at the given network version.
+pub fn abi_size<T: SyscallSafe>(nv: NetworkVersion) -> usize {
+    T::LAYOUTS
+        .iter()
+        .find(|l| nv < l.superseded_in)
+        .map_or(mem::size_of::<T>(), |l| l.size)
+}
+
+/// Encodes `value` in the layout used at the given network version.
+pub fn encode<T: SyscallSafe>(value: &T, nv: NetworkVersion) -> &[u8] {
+    // SAFETY: syscall-safe types have no padding and may be read as bytes.
+    let bytes =
+        unsafe { std::slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) };
+    &bytes[..abi_size::<T>(nv)]
+}
+
+/// Decodes a `T` encoded in any of its layouts, zero-filling the fields appended by later layouts.
+/// Returns `None` if the length doesn't match any layout.
+///
+/// # Safety
+///
+/// The all-zero bit pattern must be a valid `T`.
+pub unsafe fn decode<T: SyscallSafe>(bytes: &[u8]) -> Option<T> {
+    if bytes.len() != mem::size_of::<T>() && !T::LAYOUTS.iter().any(|l| l.size == bytes.len()) {
+        return None;
+    }
+    let mut value = mem::MaybeUninit::<T>::zeroed();
+    std::ptr::copy_nonoverlapping(bytes.as_ptr(), value.as_mut_ptr() as *mut u8, bytes.len());
+    Some(value.assume_init())
+}
+
+/// Checks at compile time that `$new` only appends fields to `$old`: both are built from the same
+/// (distinct) field values, and the encoding of `$old` must be a strict prefix of the encoding of
+/// `$new`.
+macro_rules! assert_appended {
+    ($old:ident => $new:ident { $($field:ident: $value:expr,)* } + { $($added:ident: $added_value:expr,)* }) => {
+        const _: () = {
+            // SAFETY: both are packed syscall structs.
+            let old: [u8; mem::size_of::<$old>()] =
+                unsafe { mem::transmute($old { $($field: $value,)* }) };
+            let new: [u8; mem::size_of::<$new>()] =
+                unsafe { mem::transmute($new { $($field: $value,)* $($added: $added_value,)* }) };
+            assert!(
+                old.len() < new.len(),
+                concat!(stringify!($new), " must be larger than ", stringify!($old))
+            );
+            let mut i = 0;
+            while i < old.len() {
+                assert!(
+                    old[i] == new[i],
+                    concat!(stringify!($old), " must be a prefix of ", stringify!($new))
+                );
+                i += 1;
+            }
+        };
+    };
+}
+
+/// Superseded struct layouts, kept to check that their successors only append fields.
+mod legacy {
+    use crate::clock::ChainEpoch;
+    use crate::sys::TokenAmount;
+    use crate::version::NetworkVersion;
+
+    /// [`NetworkContext`](crate::sys::out::network::NetworkContext) before network version 22.
+    #[repr(packed, C)]
+    pub struct NetworkContextV21 {
+        pub epoch: ChainEpoch,
+        pub timestamp: u64,
+        pub base_fee: TokenAmount,
+        pub chain_id: u64,
+        pub network_version: NetworkVersion,
+    }
+}
+
+assert_appended!(NetworkContextV21 => NetworkContext {
+    epoch: 0x0807060504030201,
+    timestamp: 0x100f0e0d0c0b0a09,
+    base_fee: TokenAmount { lo: 0x1817161514131211, hi: 0x201f1e1d1c1b1a19 },
+    chain_id: 0x2827262524232221,
+    network_version: NetworkVersion::new(0x2c2b2a29),
+} + {
+    block_delay_seconds: 0x302f2e2d,
+});
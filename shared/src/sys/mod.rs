@@ -5,7 +5,9 @@
 
 use bitflags::bitflags;
 use num_bigint::TryFromBigIntError;
+use num_derive::FromPrimitive;
 
+pub mod layout;
 pub mod out;
 
 pub type BlockId = u32;
@@ -50,7 +52,8 @@ impl<'a> TryFrom<&'a crate::econ::TokenAmount> for TokenAmount {
 }
 
 bitflags! {
-    /// Flags passed to the send syscall.
+    /// Flags passed to the send syscall. The FVM rejects any bits not defined here with
+    /// `IllegalArgument`, so new flags can be assigned without breaking existing actors.
     #[derive(Default, Copy, Clone, Eq, PartialEq, Debug)]
     #[repr(transparent)]
     // note: this is 64 bits because I don't want to hate my past self, not because we need them
@@ -67,6 +70,19 @@ impl SendFlags {
     }
 }
 
+/// Why the current invocation is (or isn't) executing in read-only mode. Only available when actor
+/// debugging is enabled.
+#[derive(FromPrimitive, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum ReadOnlyReason {
+    /// The current invocation isn't read-only.
+    NotReadOnly = 0,
+    /// The top-level message is being executed in read-only mode.
+    MessageLevel = 1,
+    /// The caller is read-only, or sent to this actor with [`SendFlags::READ_ONLY`].
+    InheritedFromCaller = 2,
+}
+
 /// A fixed sized struct for serializing an [event `Entry`](crate::event::Entry) separately from the
 /// key/value bytes.
 #[repr(C, packed)]
@@ -88,7 +104,11 @@ pub struct EventEntry {
 ///
 /// Incorrectly implementing this could lead to undefined behavior in types passed between wasm and
 /// rust.
-pub unsafe trait SyscallSafe: Copy + Sized + 'static {}
+pub unsafe trait SyscallSafe: Copy + Sized + 'static {
+    /// The superseded layouts of this type when returned from a syscall, oldest first. Empty for
+    /// types whose layout never changed. See [`layout`].
+    const LAYOUTS: &'static [layout::SysStructVersion] = &[];
+}
 
 macro_rules! assert_syscall_safe {
     ($($t:ty,)*) => {
@@ -105,10 +125,17 @@ assert_syscall_safe! {
     TokenAmount,
     out::ipld::IpldOpen,
     out::ipld::IpldStat,
+    out::ipld::IpldOpenOrCreate,
     out::send::Send,
+    out::actor::DelegatedNamespace,
     out::crypto::VerifyConsensusFault,
-    out::network::NetworkContext,
     out::vm::MessageContext,
+    out::vm::MemoryUsage,
+    out::vm::MessageContextV2,
+}
+
+unsafe impl SyscallSafe for out::network::NetworkContext {
+    const LAYOUTS: &'static [layout::SysStructVersion] = layout::NETWORK_CONTEXT;
 }
 
 unsafe impl<T, const N: usize> SyscallSafe for [T; N] where T: SyscallSafe {}
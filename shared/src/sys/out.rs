@@ -31,6 +31,13 @@ pub mod ipld {
         pub codec: u64,
         pub size: u32,
     }
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    #[repr(packed, C)]
+    pub struct IpldOpenOrCreate {
+        pub id: u32,
+        pub created: u32,
+    }
 }
 
 pub mod send {
@@ -46,6 +53,20 @@ pub mod send {
     }
 }
 
+pub mod actor {
+    use crate::ActorID;
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    #[repr(packed, C)]
+    pub struct DelegatedNamespace {
+        /// The namespace (the ID of the managing actor) of the actor's delegated address. Only
+        /// meaningful if `delegated` is non-zero.
+        pub namespace: ActorID,
+        /// 1 if the actor has a delegated (f4) address, 0 otherwise.
+        pub delegated: u32,
+    }
+}
+
 pub mod crypto {
     use crate::{ActorID, ChainEpoch};
 
@@ -61,6 +82,7 @@ pub mod crypto {
 pub mod vm {
     use bitflags::bitflags;
 
+    use crate::address::MAX_ADDRESS_LEN;
     use crate::sys::TokenAmount;
     use crate::{ActorID, MethodNum};
 
@@ -72,6 +94,10 @@ pub mod vm {
             /// Invocation is in "read-only" mode. Any balance transfers, sends that would create
             /// actors, and calls to `sself::set_root` and `sself::self_destruct` will be rejected.
             const READ_ONLY = 0b00000001;
+            /// The current message is an implicit (system) message, e.g., a cron tick, rather
+            /// than a user message. Inherited by all calls made while executing the message. Only
+            /// set from network version 22 onwards.
+            const IMPLICIT = 0b00000010;
         }
     }
 
@@ -79,6 +105,10 @@ pub mod vm {
         pub fn read_only(self) -> bool {
             self.intersects(Self::READ_ONLY)
         }
+
+        pub fn implicit(self) -> bool {
+            self.intersects(Self::IMPLICIT)
+        }
     }
 
     #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -86,7 +116,9 @@ pub mod vm {
     pub struct MessageContext {
         /// The current call's origin actor ID.
         pub origin: ActorID,
-        /// The nonce from the explicit message.
+        /// The nonce from the explicit message (i.e., the origin's sequence number when the message
+        /// was sent). This is constant for all invocations within a message. For the origin
+        /// account's current sequence, see the `vm::origin_account_nonce` syscall.
         pub nonce: u64,
         /// The caller's actor ID.
         pub caller: ActorID,
@@ -103,6 +135,27 @@ pub mod vm {
         /// Flags pertaining to the currently executing actor's invocation context.
         pub flags: ContextFlags,
     }
+
+    /// The extended message context, available from network version 22.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    #[repr(packed, C)]
+    pub struct MessageContextV2 {
+        /// The basic message context.
+        pub context: MessageContext,
+        /// The length of the origin's delegated (f4) address, or 0 if it has none.
+        pub origin_delegated_address_len: u32,
+        /// The origin's delegated (f4) address as of the start of the message, zero-padded.
+        pub origin_delegated_address: [u8; MAX_ADDRESS_LEN],
+    }
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    #[repr(packed, C)]
+    pub struct MemoryUsage {
+        /// The memory currently used by the call stack, in bytes.
+        pub used: u64,
+        /// The maximum memory the call stack may use, in bytes.
+        pub limit: u64,
+    }
 }
 
 pub mod network {
@@ -123,5 +176,7 @@ pub mod network {
         pub chain_id: u64,
         /// The network version.
         pub network_version: NetworkVersion,
+        /// The expected delay between epochs, in seconds.
+        pub block_delay_seconds: u32,
     }
 }
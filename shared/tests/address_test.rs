@@ -8,7 +8,8 @@ use std::str::FromStr;
 use data_encoding::{DecodeError, DecodeKind};
 use fvm_ipld_encoding::{from_slice, to_vec};
 use fvm_shared::address::{
-    Address, Error, Protocol, BLS_PUB_LEN, MAX_SUBADDRESS_LEN, PAYLOAD_HASH_LEN, SECP_PUB_LEN,
+    derive_actor_address, Address, Error, Protocol, BLS_PUB_LEN, MAX_ADDRESS_LEN, MAX_PAYLOAD_LEN,
+    MAX_SUBADDRESS_LEN, PAYLOAD_HASH_LEN, SECP_PUB_LEN,
 };
 use quickcheck_macros::quickcheck;
 
@@ -620,3 +621,101 @@ fn prop_address_roundtrip(addr0: Address) -> Result<(), String> {
     }
     Ok(())
 }
+
+#[quickcheck]
+fn prop_write_bytes_matches_to_bytes(addr: Address) -> Result<(), String> {
+    let mut buf = [0u8; MAX_PAYLOAD_LEN];
+    let len = addr.payload_bytes_into(&mut buf);
+    if buf[..len] != addr.payload_bytes() {
+        return Err("written payload differs from to_raw_bytes".to_owned());
+    }
+
+    let mut buf = [0u8; MAX_ADDRESS_LEN];
+    let len = addr.write_bytes(&mut buf);
+    if buf[..len] != addr.to_bytes() {
+        return Err("written address differs from to_bytes".to_owned());
+    }
+    Ok(())
+}
+
+#[test]
+fn write_bytes_all_payload_lengths() {
+    let mut addrs = vec![
+        Address::new_secp256k1(&[1; SECP_PUB_LEN]).unwrap(),
+        Address::new_actor(b"actor"),
+        Address::new_bls(&[2; BLS_PUB_LEN]).unwrap(),
+    ];
+    // Every LEB128 length of an ID, and every subaddress length of a delegated address.
+    addrs.extend((0..64).map(|shift| Address::new_id(u64::MAX >> shift)));
+    addrs.extend(
+        (0..=MAX_SUBADDRESS_LEN)
+            .map(|len| Address::new_delegated(u64::MAX, &vec![3; len]).unwrap()),
+    );
+
+    for addr in addrs {
+        let mut buf = [0u8; MAX_PAYLOAD_LEN];
+        let len = addr.payload_bytes_into(&mut buf);
+        assert_eq!(buf[..len], addr.payload_bytes(), "{addr}");
+
+        let mut buf = [0u8; MAX_ADDRESS_LEN];
+        let len = addr.write_bytes(&mut buf);
+        assert_eq!(buf[..len], addr.to_bytes(), "{addr}");
+    }
+}
+
+#[test]
+fn derive_actor_address_vectors() {
+    let id = Address::new_id(100);
+    let secp = Address::new_secp256k1(&[1; SECP_PUB_LEN]).unwrap();
+    let delegated = Address::new_delegated(10, &[2; 20]).unwrap();
+
+    // Pinned so that the derivation (and thus every f2 address assigned on chain) can't silently
+    // change. These were computed outside this crate (blake2b-160 over the preimage checked below),
+    // not taken from chain: they should be replaced with addresses observed on mainnet for known
+    // messages.
+    for (origin, nonce, count, expected) in [
+        (&id, 0, 0, "f2cnmel6no4rlbzpir4zzk3rbw3wbipyrczsbm5si"),
+        (&id, 7, 3, "f2plqhqdp7tw5xlspanyy25k7qkl6uafpbm2zqona"),
+        (&secp, 0, 1, "f2sbjflv7ybtq3iwtspirpkgv3picfol5schq3lha"),
+        (
+            &delegated,
+            7,
+            0,
+            "f27dctdlm2voaciwrnkkznvisrndy5jt66zypegcq",
+        ),
+    ] {
+        let addr = derive_actor_address(origin, nonce, count);
+        assert_eq!(addr.protocol(), Protocol::Actor);
+        assert_eq!(addr.to_string(), expected, "{origin}, {nonce}, {count}");
+    }
+
+    // Every input matters.
+    let base = derive_actor_address(&id, 7, 3);
+    assert_ne!(base, derive_actor_address(&secp, 7, 3));
+    assert_ne!(base, derive_actor_address(&id, 8, 3));
+    assert_ne!(base, derive_actor_address(&id, 7, 4));
+}
+
+/// The derivation matches Lotus' `NewActorAddress`: the hash of the CBOR-encoded origin address,
+/// followed by the big-endian nonce and creation count.
+#[quickcheck]
+fn prop_derive_actor_address_preimage(payload: Vec<u8>, nonce: u64, count: u64) {
+    let origin =
+        Address::new_delegated(10, &payload[..payload.len().min(MAX_SUBADDRESS_LEN)]).unwrap();
+
+    let bytes = origin.to_bytes();
+    let mut preimage = match bytes.len() {
+        len @ 0..=23 => vec![0x40 | len as u8],
+        len => vec![0x58, len as u8],
+    };
+    preimage.extend_from_slice(&bytes);
+    preimage.extend_from_slice(&nonce.to_be_bytes());
+    preimage.extend_from_slice(&count.to_be_bytes());
+    let hash = blake2b_simd::Params::new()
+        .hash_length(PAYLOAD_HASH_LEN)
+        .hash(&preimage);
+
+    let expected =
+        Address::from_bytes(&[&[Protocol::Actor as u8], hash.as_bytes()].concat()).unwrap();
+    assert_eq!(derive_actor_address(&origin, nonce, count), expected);
+}
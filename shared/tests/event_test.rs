@@ -0,0 +1,38 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use fvm_ipld_encoding::IPLD_RAW;
+use fvm_shared::event::{ActorEvent, Entry, Flags, MAX_KEY_LEN, MAX_NR_ENTRIES};
+use quickcheck_macros::quickcheck;
+
+fn entry(key: &str) -> Entry {
+    Entry {
+        flags: Flags::all() - Flags::FLAG_CID_KEY,
+        key: key.into(),
+        codec: IPLD_RAW,
+        value: vec![1, 2, 3],
+    }
+}
+
+#[test]
+fn from_cbor_rejects_invalid_events() {
+    let long_key = ActorEvent::from(vec![entry(&"a".repeat(MAX_KEY_LEN + 1))]);
+    assert!(ActorEvent::from_cbor(&long_key.to_cbor().unwrap()).is_err());
+
+    let too_many = ActorEvent::from(vec![entry("a"); MAX_NR_ENTRIES + 1]);
+    assert!(ActorEvent::from_cbor(&too_many.to_cbor().unwrap()).is_err());
+
+    assert!(ActorEvent::from_cbor(&[0xff]).is_err());
+}
+
+#[quickcheck]
+fn prop_event_cbor_roundtrip(evt0: ActorEvent) -> Result<(), String> {
+    let bz = evt0
+        .to_cbor()
+        .map_err(|e| format!("error serializing event: {e}"))?;
+    let evt1 = ActorEvent::from_cbor(&bz).map_err(|e| format!("error deserializing event: {e}"))?;
+    if evt1 != evt0 {
+        return Err("event differs after roundtrip".to_owned());
+    }
+    Ok(())
+}
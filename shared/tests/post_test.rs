@@ -0,0 +1,114 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use fvm_ipld_bitfield::BitField;
+use fvm_shared::randomness::Randomness;
+use fvm_shared::sector::{
+    OnChainWindowPoStVerifyInfo, PoStPartition, PoStPolicy, PoStProof, PoStValidationError,
+    RegisteredPoStProof, SubmitWindowedPoStParams,
+};
+
+fn policy() -> PoStPolicy {
+    PoStPolicy {
+        deadlines: 48,
+        max_partitions: 3,
+        max_proofs: 1,
+        min_chain_commit_epoch: 100,
+        max_chain_commit_epoch: 110,
+    }
+}
+
+fn params() -> SubmitWindowedPoStParams {
+    SubmitWindowedPoStParams {
+        deadline: 2,
+        partitions: vec![
+            PoStPartition {
+                index: 0,
+                skipped: BitField::new(),
+            },
+            PoStPartition {
+                index: 1,
+                skipped: BitField::try_from_bits([3, 5]).unwrap(),
+            },
+        ],
+        proofs: vec![PoStProof {
+            post_proof: RegisteredPoStProof::StackedDRGWindow32GiBV1P1,
+            proof_bytes: vec![1, 2, 3],
+        }],
+        chain_commit_epoch: 105,
+        chain_commit_rand: Randomness(vec![7; 32]),
+    }
+}
+
+#[test]
+fn submit_params_roundtrip() {
+    let params = params();
+    let bytes = fvm_ipld_encoding::to_vec(&params).unwrap();
+    let decoded: SubmitWindowedPoStParams = fvm_ipld_encoding::from_slice(&bytes).unwrap();
+    assert_eq!(decoded, params);
+
+    assert_eq!(
+        OnChainWindowPoStVerifyInfo::from(&decoded).proofs,
+        params.proofs
+    );
+}
+
+#[test]
+fn valid_submission() {
+    params().validate_submission(&policy()).unwrap();
+}
+
+#[test]
+fn invalid_submissions() {
+    let check = |f: fn(&mut SubmitWindowedPoStParams), expected: PoStValidationError| {
+        let mut params = params();
+        f(&mut params);
+        assert_eq!(params.validate_submission(&policy()), Err(expected));
+    };
+
+    check(
+        |p| p.deadline = 48,
+        PoStValidationError::InvalidDeadline {
+            deadline: 48,
+            deadlines: 48,
+        },
+    );
+    check(|p| p.proofs.clear(), PoStValidationError::NoProofs);
+    check(
+        |p| p.proofs.push(p.proofs[0].clone()),
+        PoStValidationError::TooManyProofs { count: 2, max: 1 },
+    );
+    check(
+        |p| {
+            p.partitions.extend((2..4).map(|index| PoStPartition {
+                index,
+                skipped: BitField::new(),
+            }))
+        },
+        PoStValidationError::TooManyPartitions { count: 4, max: 3 },
+    );
+    check(
+        |p| p.partitions.push(p.partitions[1].clone()),
+        PoStValidationError::DuplicatePartition(1),
+    );
+    check(
+        |p| p.chain_commit_epoch = 99,
+        PoStValidationError::ChainCommitEpochOutOfRange {
+            epoch: 99,
+            min: 100,
+            max: 110,
+        },
+    );
+    check(
+        |p| p.chain_commit_epoch = 111,
+        PoStValidationError::ChainCommitEpochOutOfRange {
+            epoch: 111,
+            min: 100,
+            max: 110,
+        },
+    );
+    check(
+        |p| p.chain_commit_rand = Randomness(vec![7; 31]),
+        PoStValidationError::InvalidRandomnessLength(31),
+    );
+}
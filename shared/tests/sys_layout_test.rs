@@ -0,0 +1,112 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use std::fmt::Write;
+use std::path::Path;
+
+use fvm_shared::address::MAX_ADDRESS_LEN;
+use fvm_shared::sys::layout::{abi_size, decode, encode};
+use fvm_shared::sys::out::network::NetworkContext;
+use fvm_shared::sys::out::vm::{ContextFlags, MessageContext, MessageContextV2};
+use fvm_shared::sys::{SyscallSafe, TokenAmount};
+use fvm_shared::version::NetworkVersion;
+
+/// The encodings of each struct returned by a syscall, under every network version the FVM
+/// supports. After intentionally changing a layout, regenerate them by running this test with
+/// `FVM_BLESS_SYS_LAYOUTS=1`.
+const GOLDEN: &str = "tests/sys_layouts.txt";
+
+const VERSIONS: [NetworkVersion; 2] = [NetworkVersion::V21, NetworkVersion::V22];
+
+fn message_context() -> MessageContext {
+    MessageContext {
+        origin: 100,
+        nonce: 7,
+        caller: 101,
+        receiver: 102,
+        method_number: 3,
+        value_received: TokenAmount { lo: 5, hi: 0 },
+        gas_premium: TokenAmount { lo: 1, hi: 0 },
+        flags: ContextFlags::READ_ONLY,
+    }
+}
+
+fn message_context_v2() -> MessageContextV2 {
+    let mut origin_delegated_address = [0u8; MAX_ADDRESS_LEN];
+    origin_delegated_address[..2].copy_from_slice(&[4, 10]);
+    origin_delegated_address[2..22].fill(0xab);
+    MessageContextV2 {
+        context: message_context(),
+        origin_delegated_address_len: 22,
+        origin_delegated_address,
+    }
+}
+
+fn network_context() -> NetworkContext {
+    NetworkContext {
+        epoch: 1000,
+        timestamp: 1_700_000_000,
+        base_fee: TokenAmount { lo: 100, hi: 0 },
+        chain_id: 314,
+        network_version: NetworkVersion::V22,
+        block_delay_seconds: 30,
+    }
+}
+
+fn encode_all<T: SyscallSafe>(out: &mut String, name: &str, value: &T) {
+    for nv in VERSIONS {
+        let hex: String = encode(value, nv)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        writeln!(out, "{name} {nv} {hex}").unwrap();
+    }
+}
+
+#[test]
+fn golden_encodings() -> anyhow::Result<()> {
+    let mut encodings = String::new();
+    encode_all(&mut encodings, "MessageContext", &message_context());
+    encode_all(&mut encodings, "MessageContextV2", &message_context_v2());
+    encode_all(&mut encodings, "NetworkContext", &network_context());
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(GOLDEN);
+    if std::env::var_os("FVM_BLESS_SYS_LAYOUTS").is_some() {
+        std::fs::write(&path, encodings)?;
+        return Ok(());
+    }
+    assert_eq!(
+        std::fs::read_to_string(&path)?,
+        encodings,
+        "a syscall struct layout changed; if intended, rerun with FVM_BLESS_SYS_LAYOUTS=1"
+    );
+    Ok(())
+}
+
+#[test]
+fn network_context_layouts() {
+    let ctx = network_context();
+    let full = std::mem::size_of::<NetworkContext>();
+    assert_eq!(abi_size::<NetworkContext>(NetworkVersion::V21), full - 4);
+    assert_eq!(abi_size::<NetworkContext>(NetworkVersion::V22), full);
+
+    // Fields appended after the layout was encoded are zero-filled.
+    let old = unsafe { decode::<NetworkContext>(encode(&ctx, NetworkVersion::V21)) }.unwrap();
+    assert_eq!(
+        old,
+        NetworkContext {
+            block_delay_seconds: 0,
+            ..ctx
+        }
+    );
+    let new = unsafe { decode::<NetworkContext>(encode(&ctx, NetworkVersion::V22)) }.unwrap();
+    assert_eq!(new, ctx);
+
+    // Lengths that don't match a layout are rejected.
+    assert!(unsafe { decode::<NetworkContext>(&[0; 3]) }.is_none());
+
+    // Structs that never changed always use their only layout.
+    assert_eq!(
+        abi_size::<MessageContext>(NetworkVersion::V0),
+        std::mem::size_of::<MessageContext>()
+    );
+}
@@ -1,7 +1,7 @@
 // Copyright 2021-2023 Protocol Labs
 // SPDX-License-Identifier: Apache-2.0, MIT
 use std::fmt;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Result};
 use cid::Cid;
@@ -267,7 +267,13 @@ pub fn run_variant(
             }
         }
 
-        rets.push((start.elapsed(), ret));
+        // Don't leak wall-clock time into the traces of a deterministic run.
+        let elapsed = if exec.context().deterministic_only {
+            Duration::ZERO
+        } else {
+            start.elapsed()
+        };
+        rets.push((elapsed, ret));
     }
 
     // Flush the machine, obtain the blockstore, and compare the
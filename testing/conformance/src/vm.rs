@@ -11,7 +11,7 @@ use fvm::call_manager::{CallManager, DefaultCallManager};
 use fvm::gas::{price_list_by_network_version, Gas, GasTimer, PriceList};
 use fvm::kernel::*;
 use fvm::machine::limiter::MemoryLimiter;
-use fvm::machine::{DefaultMachine, Machine, MachineContext, Manifest, NetworkConfig};
+use fvm::machine::{DefaultMachine, FlushStats, Machine, MachineContext, Manifest, NetworkConfig};
 use fvm::state_tree::StateTree;
 use fvm::DefaultKernel;
 use fvm_ipld_blockstore::MemoryBlockstore;
@@ -23,12 +23,12 @@ use fvm_shared::crypto::signature::{
 };
 use fvm_shared::econ::TokenAmount;
 use fvm_shared::piece::PieceInfo;
-use fvm_shared::randomness::RANDOMNESS_LENGTH;
+use fvm_shared::randomness::{RandSource, RANDOMNESS_LENGTH};
 use fvm_shared::sector::{
     AggregateSealVerifyProofAndInfos, RegisteredSealProof, ReplicaUpdateInfo, SealVerifyInfo,
     WindowPoStVerifyInfo,
 };
-use fvm_shared::sys::{EventEntry, SendFlags};
+use fvm_shared::sys::{EventEntry, ReadOnlyReason, SendFlags};
 use fvm_shared::version::NetworkVersion;
 use fvm_shared::{ActorID, MethodNum, TOTAL_FILECOIN};
 
@@ -103,7 +103,7 @@ impl TestMachine<Box<DefaultMachine<MemoryBlockstore, TestExterns>>> {
         if let Some(nv) = price_network_version {
             nc.price_list = price_list_by_network_version(nv);
         }
-        mc.set_base_fee(base_fee);
+        mc.set_base_fee(base_fee).enable_deterministic_only();
         mc.tracing = tracing;
 
         let machine = DefaultMachine::new(&mc, blockstore, externs).unwrap();
@@ -163,7 +163,7 @@ where
         self.machine.into_store()
     }
 
-    fn flush(&mut self) -> Result<Cid> {
+    fn flush(&mut self) -> Result<FlushStats> {
         self.machine.flush()
     }
 
@@ -171,6 +171,10 @@ where
         self.machine.machine_id()
     }
 
+    fn advance_epoch(&mut self, epoch: ChainEpoch, timestamp: u64) -> Result<()> {
+        self.machine.advance_epoch(epoch, timestamp)
+    }
+
     fn new_limiter(&self) -> Self::Limiter {
         TestLimiter {
             inner: self.machine.new_limiter(),
@@ -269,6 +273,10 @@ where
         self.0.next_actor_address()
     }
 
+    fn actors_created(&self) -> Result<u64> {
+        self.0.actors_created()
+    }
+
     fn create_actor(
         &mut self,
         code_id: Cid,
@@ -278,10 +286,22 @@ where
         self.0.create_actor(code_id, actor_id, delegated_address)
     }
 
+    fn can_receive_funds(&self, id: ActorID) -> Result<bool> {
+        self.0.can_receive_funds(id)
+    }
+
+    fn enumerate_deployed_code_cids(&self) -> Result<Vec<(Cid, u64)>> {
+        self.0.enumerate_deployed_code_cids()
+    }
+
     fn get_builtin_actor_type(&self, code_cid: &Cid) -> Result<u32> {
         self.0.get_builtin_actor_type(code_cid)
     }
 
+    fn address_builtin_type(&self, address: &Address) -> Result<Option<u32>> {
+        self.0.address_builtin_type(address)
+    }
+
     fn get_code_cid_for_type(&self, typ: u32) -> Result<Cid> {
         self.0.get_code_cid_for_type(typ)
     }
@@ -295,9 +315,25 @@ where
         self.0.balance_of(actor_id)
     }
 
+    fn balances_of(&self, actor_ids: &[ActorID]) -> Result<Vec<TokenAmount>> {
+        self.0.balances_of(actor_ids)
+    }
+
+    fn actor_id_available(&self, actor_id: ActorID) -> Result<bool> {
+        self.0.actor_id_available(actor_id)
+    }
+
     fn lookup_delegated_address(&self, actor_id: ActorID) -> Result<Option<Address>> {
         self.0.lookup_delegated_address(actor_id)
     }
+
+    fn delegated_namespace(&self, id: ActorID) -> Result<Option<ActorID>> {
+        self.0.delegated_namespace(id)
+    }
+
+    fn make_delegated_address(&self, namespace: ActorID, subaddress: &[u8]) -> Result<Address> {
+        self.0.make_delegated_address(namespace, subaddress)
+    }
 }
 
 impl<M, C, K> IpldBlockOps for TestKernel<K>
@@ -314,17 +350,82 @@ where
         self.0.block_create(codec, data)
     }
 
+    fn block_open_or_create(
+        &mut self,
+        cid: &Cid,
+        codec: u64,
+        data: &[u8],
+    ) -> Result<(BlockId, bool)> {
+        self.0.block_open_or_create(cid, codec, data)
+    }
+
+    fn block_patch(&mut self, id: BlockId, offset: u32, data: &[u8]) -> Result<BlockId> {
+        self.0.block_patch(id, offset, data)
+    }
+
     fn block_link(&mut self, id: BlockId, hash_fun: u64, hash_len: u32) -> Result<Cid> {
         self.0.block_link(id, hash_fun, hash_len)
     }
 
+    fn block_cid(&mut self, id: BlockId, hash_fun: u64, hash_len: u32) -> Result<Cid> {
+        self.0.block_cid(id, hash_fun, hash_len)
+    }
+
     fn block_read(&self, id: BlockId, offset: u32, buf: &mut [u8]) -> Result<i32> {
         self.0.block_read(id, offset, buf)
     }
 
+    fn block_read_u64(&self, id: BlockId) -> Result<u64> {
+        self.0.block_read_u64(id)
+    }
+
     fn block_stat(&self, id: BlockId) -> Result<BlockStat> {
         self.0.block_stat(id)
     }
+
+    fn block_retain(&mut self, id: BlockId) -> Result<()> {
+        self.0.block_retain(id)
+    }
+
+    fn block_close(&mut self, id: BlockId) -> Result<()> {
+        self.0.block_close(id)
+    }
+
+    fn inflate(&self, compressed: &[u8], max_output: u32) -> Result<Vec<u8>> {
+        self.0.inflate(compressed, max_output)
+    }
+
+    fn is_dag_codec(&self, codec: u64) -> Result<bool> {
+        self.0.is_dag_codec(codec)
+    }
+
+    fn block_eq(&self, a: BlockId, b: BlockId) -> Result<bool> {
+        self.0.block_eq(a, b)
+    }
+
+    fn block_hash_eq(&self, id: BlockId, expected: &Cid) -> Result<bool> {
+        self.0.block_hash_eq(id, expected)
+    }
+
+    fn block_contains_link(&self, id: BlockId, target: &Cid) -> Result<bool> {
+        self.0.block_contains_link(id, target)
+    }
+
+    fn would_create_cycle(&self, root: Cid, candidate_child: Cid, max_depth: u32) -> Result<bool> {
+        self.0.would_create_cycle(root, candidate_child, max_depth)
+    }
+
+    fn expand_block_handles(&mut self, extra: u32) -> Result<u32> {
+        self.0.expand_block_handles(extra)
+    }
+
+    fn restore_block_handles(&mut self, limit: u32) {
+        self.0.restore_block_handles(limit)
+    }
+
+    fn would_fit_as_return(&self, id: BlockId) -> Result<bool> {
+        self.0.would_fit_as_return(id)
+    }
 }
 
 impl<M, C, K> CircSupplyOps for TestKernel<K>
@@ -350,6 +451,18 @@ where
         self.0.hash(code, data)
     }
 
+    fn hash_init(&mut self, code: u64) -> Result<HashHandle> {
+        self.0.hash_init(code)
+    }
+
+    fn hash_update(&mut self, handle: &HashHandle, data: &[u8]) -> Result<()> {
+        self.0.hash_update(handle, data)
+    }
+
+    fn hash_finalize(&mut self, handle: HashHandle) -> Result<MultihashGeneric<64>> {
+        self.0.hash_finalize(handle)
+    }
+
     // forwarded
     fn compute_unsealed_sector_cid(
         &self,
@@ -380,6 +493,14 @@ where
         self.0.recover_secp_public_key(hash, signature)
     }
 
+    // forwarded
+    fn recover_secp_public_keys(
+        &self,
+        items: &[([u8; SECP_SIG_MESSAGE_HASH_SIZE], [u8; SECP_SIG_LEN])],
+    ) -> Result<Vec<[u8; SECP_PUB_LEN]>> {
+        self.0.recover_secp_public_keys(items)
+    }
+
     // NOT forwarded
     fn batch_verify_seals(&self, vis: &[SealVerifyInfo]) -> Result<Vec<bool>> {
         Ok(vec![true; vis.len()])
@@ -420,6 +541,11 @@ where
         let _ = self.0.charge_gas(&charge.name, charge.total())?;
         Ok(true)
     }
+
+    // forwarded
+    fn groth16_verify(&self, vk: &[u8], proof: &[u8], public_inputs: &[u8]) -> Result<bool> {
+        self.0.groth16_verify(vk, proof, public_inputs)
+    }
 }
 
 impl<M, C, K> DebugOps for TestKernel<K>
@@ -436,9 +562,24 @@ where
         self.0.debug_enabled()
     }
 
-    fn store_artifact(&self, name: &str, data: &[u8]) -> Result<()> {
+    fn store_artifact(&mut self, name: &str, data: &[u8]) -> Result<String> {
         self.0.store_artifact(name, data)
     }
+
+    fn call_stack(&self) -> Result<Vec<(ActorID, MethodNum)>> {
+        self.0.call_stack()
+    }
+}
+
+impl<M, C, K> EncodingOps for TestKernel<K>
+where
+    M: Machine,
+    C: CallManager<Machine = TestMachine<M>>,
+    K: Kernel<CallManager = C>,
+{
+    fn is_valid_utf8(&self, bytes: &[u8]) -> Result<bool> {
+        self.0.is_valid_utf8(bytes)
+    }
 }
 
 impl<M, C, K> GasOps for TestKernel<K>
@@ -455,10 +596,39 @@ where
         self.0.charge_gas(name, compute)
     }
 
+    fn charge_gas_per_epoch(
+        &self,
+        name: &str,
+        cost_per_epoch: Gas,
+        epochs: i64,
+    ) -> Result<GasTimer> {
+        self.0.charge_gas_per_epoch(name, cost_per_epoch, epochs)
+    }
+
     fn price_list(&self) -> &PriceList {
         self.0.price_list()
     }
 
+    fn estimate_fee(&self, gas_limit: Gas, gas_premium: &TokenAmount) -> Result<TokenAmount> {
+        self.0.estimate_fee(gas_limit, gas_premium)
+    }
+
+    fn gas_tracing_enabled(&self) -> bool {
+        self.0.gas_tracing_enabled()
+    }
+
+    fn create_submeter(&mut self, budget: Gas) -> Result<u32> {
+        self.0.create_submeter(budget)
+    }
+
+    fn charge_to(&self, meter: u32, compute: Gas) -> Result<GasTimer> {
+        self.0.charge_to(meter, compute)
+    }
+
+    fn submeter_remaining(&self, meter: u32) -> Result<Gas> {
+        self.0.submeter_remaining(meter)
+    }
+
     fn gas_available(&self) -> Gas {
         self.0.gas_available()
     }
@@ -473,6 +643,22 @@ where
     fn msg_context(&self) -> Result<fvm_shared::sys::out::vm::MessageContext> {
         self.0.msg_context()
     }
+
+    fn msg_context_v2(&self) -> Result<fvm_shared::sys::out::vm::MessageContextV2> {
+        self.0.msg_context_v2()
+    }
+
+    fn caller_delegated_address(&self) -> Result<Option<Address>> {
+        self.0.caller_delegated_address()
+    }
+
+    fn read_only_reason(&self) -> Result<ReadOnlyReason> {
+        self.0.read_only_reason()
+    }
+
+    fn origin_account_nonce(&self) -> Result<u64> {
+        self.0.origin_account_nonce()
+    }
 }
 
 impl<M, C, K> NetworkOps for TestKernel<K>
@@ -488,6 +674,14 @@ where
     fn tipset_cid(&self, epoch: ChainEpoch) -> Result<Cid> {
         self.0.tipset_cid(epoch)
     }
+
+    fn block_delay_seconds(&self) -> Result<u32> {
+        self.0.block_delay_seconds()
+    }
+
+    fn epoch_elapsed(&self, target: ChainEpoch) -> Result<bool> {
+        self.0.epoch_elapsed(target)
+    }
 }
 
 impl<M, C, K> RandomnessOps for TestKernel<K>
@@ -509,6 +703,16 @@ where
     ) -> Result<[u8; RANDOMNESS_LENGTH]> {
         self.0.get_randomness_from_beacon(rand_epoch)
     }
+
+    fn random_in_range(
+        &self,
+        source: RandSource,
+        rand_epoch: ChainEpoch,
+        entropy: &[u8],
+        bound: u64,
+    ) -> Result<u64> {
+        self.0.random_in_range(source, rand_epoch, entropy, bound)
+    }
 }
 
 impl<M, C, K> SelfOps for TestKernel<K>
@@ -529,9 +733,21 @@ where
         self.0.current_balance()
     }
 
+    fn balance_before_receipt(&self) -> Result<TokenAmount> {
+        self.0.balance_before_receipt()
+    }
+
+    fn is_first_invocation(&self) -> Result<bool> {
+        self.0.is_first_invocation()
+    }
+
     fn self_destruct(&mut self, burn_unspent: bool) -> Result<()> {
         self.0.self_destruct(burn_unspent)
     }
+
+    fn self_delegated_address(&self) -> Result<Option<Address>> {
+        self.0.self_delegated_address()
+    }
 }
 
 impl<K> LimiterOps for TestKernel<K>
@@ -543,6 +759,10 @@ where
     fn limiter_mut(&mut self) -> &mut Self::Limiter {
         self.0.limiter_mut()
     }
+
+    fn memory_usage(&self) -> Result<(u64, u64)> {
+        self.0.memory_usage()
+    }
 }
 
 impl<M, C, K> EventOps for TestKernel<K>
@@ -559,6 +779,27 @@ where
     ) -> Result<()> {
         self.0.emit_event(event_headers, key_evt, val_evt)
     }
+
+    fn event_gas_cost(
+        &self,
+        event_headers: &[EventEntry],
+        key_evt: &[u8],
+        val_evt: &[u8],
+    ) -> Result<Gas> {
+        self.0.event_gas_cost(event_headers, key_evt, val_evt)
+    }
+
+    fn events_remaining(&self) -> Result<u32> {
+        self.0.events_remaining()
+    }
+
+    fn events_root_so_far(&self) -> Result<Option<Cid>> {
+        self.0.events_root_so_far()
+    }
+
+    fn emit_events_dedup(&mut self, events: &[RawEvent<'_>]) -> Result<u32> {
+        self.0.emit_events_dedup(events)
+    }
 }
 
 /// Wrap a `ResourceLimiter` and collect statistics.
@@ -629,6 +870,10 @@ where
         self.inner.memory_used()
     }
 
+    fn memory_limit(&self) -> usize {
+        self.inner.memory_limit()
+    }
+
     fn with_stack_frame<T, G, F, R>(t: &mut T, g: G, f: F) -> R
     where
         G: Fn(&mut T) -> &mut Self,
@@ -4,7 +4,7 @@ use anyhow::{anyhow, Context, Result};
 use cid::Cid;
 use fvm::call_manager::DefaultCallManager;
 use fvm::engine::EnginePool;
-use fvm::executor::DefaultExecutor;
+use fvm::executor::{CancellationToken, DefaultExecutor, EventFilter};
 use fvm::externs::Externs;
 use fvm::machine::{DefaultMachine, Machine, MachineContext, NetworkConfig};
 use fvm::state_tree::{ActorState, StateTree};
@@ -48,6 +48,16 @@ pub struct ExecutionOptions {
     pub trace: bool,
     /// Enabls events
     pub events: bool,
+    /// Enables token conservation checks after every message
+    pub check_invariants: bool,
+    /// Never consults the wall clock, so that traces are identical between runs
+    pub deterministic_only: bool,
+    /// Filters the events returned when applying messages
+    pub event_filter: Option<EventFilter>,
+    /// Pays miner tips of simulated messages to this actor instead of the reward actor
+    pub miner_tip_recipient: Option<ActorID>,
+    /// Allows cancelling message execution from another thread
+    pub cancellation: Option<CancellationToken>,
 }
 
 pub struct Tester<B: Blockstore + 'static, E: Externs + 'static> {
@@ -410,8 +420,18 @@ impl BasicTester {
             if let Some(options) = self.options.clone() {
                 self.instantiate_machine_with_config(
                     DummyExterns,
-                    |cfg| cfg.actor_debugging = options.debug,
-                    |mc| mc.tracing = options.trace,
+                    |cfg| {
+                        cfg.actor_debugging = options.debug;
+                        cfg.interruptible = options.cancellation.is_some();
+                    },
+                    |mc| {
+                        mc.tracing = options.trace;
+                        mc.check_invariants = options.check_invariants;
+                        mc.deterministic_only = options.deterministic_only;
+                        mc.event_filter = options.event_filter;
+                        mc.miner_tip_recipient = options.miner_tip_recipient;
+                        mc.cancellation = options.cancellation;
+                    },
                 )?;
             } else {
                 self.instantiate_machine(DummyExterns)?;
@@ -7,7 +7,9 @@ use bundles::*;
 use fvm::executor::{ApplyKind, Executor};
 use fvm::gas::GasCharge;
 use fvm::machine::Machine;
+use fvm::trace::ExecutionEvent;
 use fvm_integration_tests::dummy::DummyExterns;
+use fvm_integration_tests::tester::{BasicTester, ExecutionOptions};
 use fvm_ipld_blockstore::MemoryBlockstore;
 use fvm_shared::address::Address;
 use fvm_shared::econ::TokenAmount;
@@ -120,3 +122,58 @@ fn basic_send() {
         assert_eq!(charges, case.trace);
     }
 }
+
+#[test]
+fn miner_tip_recipient() {
+    for kind in [ApplyKind::Simulated, ApplyKind::Explicit] {
+        let mut tester = new_basic_tester(ExecutionOptions::default()).unwrap();
+        let [(_, sender), (recipient, _)] = tester.create_accounts().unwrap();
+        tester.options = Some(ExecutionOptions {
+            trace: true,
+            miner_tip_recipient: Some(recipient),
+            ..Default::default()
+        });
+
+        let balance = |tester: &mut BasicTester| {
+            tester
+                .with_executor(|e| {
+                    Ok(e.state_tree()
+                        .get_actor(recipient)?
+                        .expect("recipient exists")
+                        .balance)
+                })
+                .unwrap()
+        };
+        let before = balance(&mut tester);
+
+        let message = Message {
+            from: sender,
+            to: Address::new_id(10),
+            gas_limit: 1000000000,
+            gas_fee_cap: TokenAmount::from_atto(1),
+            gas_premium: TokenAmount::from_atto(1),
+            method_num: METHOD_SEND,
+            value: TokenAmount::from_atto(1),
+            ..Message::default()
+        };
+        let res = tester
+            .with_executor(|e| e.execute_message(message, kind, 100))
+            .unwrap();
+        assert!(res.msg_receipt.exit_code.is_success());
+        assert!(res.miner_tip.is_positive());
+
+        let payout = res.exec_trace.iter().any(|ev| {
+            matches!(ev, ExecutionEvent::Call { to, value, .. }
+                if *to == Address::new_id(recipient) && *value == res.miner_tip)
+        });
+        let delta = balance(&mut tester) - before;
+        if kind == ApplyKind::Simulated {
+            assert_eq!(delta, res.miner_tip);
+            assert!(payout, "tip payout missing from the trace");
+        } else {
+            // Consensus messages always pay the reward actor.
+            assert!(delta.is_zero());
+            assert!(!payout);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+mod bundles;
+use bundles::*;
+use fvm::executor::{ApplyKind, Executor};
+use fvm_integration_tests::dummy::DummyExterns;
+use fvm_ipld_blockstore::MemoryBlockstore;
+use fvm_shared::address::Address;
+use fvm_shared::econ::TokenAmount;
+use fvm_shared::message::Message;
+use fvm_shared::state::StateTreeVersion;
+use fvm_shared::version::NetworkVersion;
+use fvm_test_actors::wasm_bin::CALL_STACK_ACTOR_BINARY;
+use num_traits::Zero;
+
+fn call_stack_tester(
+    debug: bool,
+) -> (
+    fvm_integration_tests::tester::Tester<MemoryBlockstore, DummyExterns>,
+    Address,
+    Address,
+) {
+    let mut tester = new_tester(
+        NetworkVersion::V21,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(_sender_id, sender_address)] = tester.create_accounts().unwrap();
+
+    let state_cid = tester.set_state(&[(); 0]).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(
+            CALL_STACK_ACTOR_BINARY,
+            state_cid,
+            actor_address,
+            TokenAmount::zero(),
+        )
+        .unwrap();
+
+    tester
+        .instantiate_machine_with_config(DummyExterns, |nc| nc.actor_debugging = debug, |_| ())
+        .unwrap();
+
+    (tester, sender_address, actor_address)
+}
+
+#[test]
+fn nested_call_stack() {
+    let (mut tester, sender_address, actor_address) = call_stack_tester(true);
+
+    // The actor calls itself twice (methods 2 -> 3 -> 4), and checks the call stack at the bottom.
+    let message = Message {
+        from: sender_address,
+        to: actor_address,
+        gas_limit: 1000000000,
+        method_num: 2,
+        ..Message::default()
+    };
+
+    let res = tester
+        .executor
+        .as_mut()
+        .unwrap()
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+    assert!(
+        res.msg_receipt.exit_code.is_success(),
+        "{:?}",
+        res.failure_info
+    );
+}
+
+#[test]
+fn call_stack_requires_debugging() {
+    let (mut tester, sender_address, actor_address) = call_stack_tester(false);
+
+    let message = Message {
+        from: sender_address,
+        to: actor_address,
+        gas_limit: 1000000000,
+        method_num: 2,
+        ..Message::default()
+    };
+
+    let res = tester
+        .executor
+        .as_mut()
+        .unwrap()
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+    assert!(
+        !res.msg_receipt.exit_code.is_success(),
+        "the call stack should only be available when debugging"
+    );
+}
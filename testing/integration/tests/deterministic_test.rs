@@ -0,0 +1,66 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+mod bundles;
+
+use std::fs;
+
+use anyhow::Context;
+use fvm::trace::ExecutionEvent;
+use fvm_integration_tests::{tester, testkit};
+use fvm_shared::address::Address;
+
+const CONTRACT_PATH: &str = "../../tools/contracts/gas-stress/recursive.bin";
+
+/// Creates and invokes a contract on a fresh machine, returning the serialized gas trace of the
+/// invocation, including timings.
+fn serialized_trace() -> String {
+    let options = tester::ExecutionOptions {
+        trace: true,
+        deterministic_only: true,
+        ..Default::default()
+    };
+
+    let mut tester = bundles::new_basic_tester(options).unwrap();
+    let mut account = tester.create_basic_account().unwrap();
+    let contract = hex::decode(fs::read_to_string(CONTRACT_PATH).unwrap())
+        .context("error decoding contract")
+        .unwrap();
+
+    let create_res = testkit::fevm::create_contract(&mut tester, &mut account, &contract).unwrap();
+    assert!(
+        create_res.msg_receipt.exit_code.is_success(),
+        "{:?}",
+        create_res.failure_info
+    );
+    let create_return: testkit::fevm::CreateReturn =
+        create_res.msg_receipt.return_data.deserialize().unwrap();
+    let actor = Address::new_id(create_return.actor_id);
+
+    let invoke_res =
+        testkit::fevm::invoke_contract(&mut tester, &mut account, actor, &[], 12_000_000).unwrap();
+
+    let charges = invoke_res
+        .exec_trace
+        .into_iter()
+        .filter_map(|event| match event {
+            ExecutionEvent::GasCharge(charge) => Some(serde_json::json!({
+                "name": charge.name,
+                "compute_gas": charge.compute_gas.as_milligas(),
+                "other_gas": charge.other_gas.as_milligas(),
+                "elapsed_nanos": charge.elapsed.get().map(|e| e.as_nanos() as u64),
+            })),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    // Make sure we're actually comparing timings.
+    assert!(charges.iter().any(|c| !c["elapsed_nanos"].is_null()));
+
+    serde_json::to_string(&charges).unwrap()
+}
+
+#[test]
+fn deterministic_traces_are_identical() {
+    assert_eq!(serialized_trace(), serialized_trace());
+}
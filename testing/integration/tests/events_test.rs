@@ -2,10 +2,12 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 mod bundles;
 use bundles::*;
-use fvm::executor::{ApplyKind, Executor};
+use cid::Cid;
+use fvm::executor::{ApplyKind, EventFilter, Executor};
 use fvm::machine::Machine;
 use fvm_integration_tests::dummy::DummyExterns;
 use fvm_integration_tests::tester::IntegrationExecutor;
+use fvm_ipld_amt::Amt;
 use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
 use fvm_ipld_encoding::{to_vec, IPLD_RAW};
 use fvm_shared::address::Address;
@@ -20,7 +22,7 @@ use num_traits::Zero;
 
 #[test]
 fn events_test() {
-    let (mut executor, sender_address, actor_address) = setup();
+    let (mut executor, sender_address, actor_address) = setup(None);
 
     // === Emits two events ===
 
@@ -48,13 +50,13 @@ fn events_test() {
     // Assert that we got the correct events.
     let actor_id = actor_address.id().unwrap();
     assert_eq!(
-        &res.events,
+        &res.events.to_vec(),
         &[
             StampedEvent {
                 emitter: actor_id,
                 event: vec![Entry {
                     flags: Flags::all(),
-                    key: "foo".to_owned(),
+                    key: "foo".into(),
                     codec: IPLD_RAW,
                     value: "abc".into(),
                 },]
@@ -65,13 +67,13 @@ fn events_test() {
                 event: vec![
                     Entry {
                         flags: Flags::all(),
-                        key: "bar".to_owned(),
+                        key: "bar".into(),
                         codec: IPLD_RAW,
                         value: "def".into(),
                     },
                     Entry {
                         flags: Flags::FLAG_INDEXED_KEY | Flags::FLAG_INDEXED_VALUE,
-                        key: "👱".to_string(),
+                        key: "👱".into(),
                         codec: IPLD_RAW,
                         value: "123456789 abcdefg 123456789".into(),
                     },
@@ -157,7 +159,105 @@ fn events_test() {
     assert_eq!(0, res.events.len());
 }
 
-fn setup() -> (
+#[test]
+fn events_root_so_far() {
+    let (mut executor, sender_address, actor_address) = setup(None);
+
+    let message = Message {
+        from: sender_address,
+        to: actor_address,
+        gas_limit: 1000000000,
+        method_num: 6,
+        sequence: 0,
+        ..Message::default()
+    };
+
+    let res = executor
+        .execute_message(message, ApplyKind::Explicit, 100)
+        .unwrap();
+
+    assert!(
+        res.msg_receipt.exit_code.is_success(),
+        "{:?}",
+        res.failure_info
+    );
+
+    // One event before and after the subcalls, plus ten events from the subcalls that didn't
+    // revert.
+    assert_eq!(12, res.events.len());
+
+    // The root computed by the actor commits to exactly the surviving events.
+    let root: Cid = res.msg_receipt.return_data.deserialize().unwrap();
+    let expected =
+        Amt::new_from_iter_with_bit_width(MemoryBlockstore::default(), 5, res.events.iter())
+            .unwrap();
+    assert_eq!(root, expected);
+    assert_eq!(res.msg_receipt.events_root, Some(root));
+
+    // Nothing was written to the blockstore.
+    assert!(!executor.blockstore().has(&root).unwrap());
+}
+
+#[test]
+fn filtered_events() {
+    let run = |filter| {
+        let (mut executor, sender_address, actor_address) = setup(filter);
+        let message = Message {
+            from: sender_address,
+            to: actor_address,
+            gas_limit: 1000000000,
+            method_num: 2,
+            ..Message::default()
+        };
+        let res = executor
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap();
+        assert!(
+            res.msg_receipt.exit_code.is_success(),
+            "{:?}",
+            res.failure_info
+        );
+        res
+    };
+
+    let unfiltered = run(None);
+    assert_eq!(unfiltered.events.len(), 2);
+    assert_eq!(unfiltered.suppressed_events, 0);
+
+    // Only the first event has an entry keyed "foo".
+    let filtered = run(Some(EventFilter {
+        key_prefixes: vec![b"fo".to_vec()],
+        ..Default::default()
+    }));
+    assert_eq!(filtered.events, unfiltered.events.to_vec()[..1]);
+    assert_eq!(filtered.suppressed_events, 1);
+
+    // The events root still commits to all events.
+    assert_eq!(
+        filtered.msg_receipt.events_root,
+        unfiltered.msg_receipt.events_root
+    );
+    assert_eq!(
+        filtered.msg_receipt.gas_used,
+        unfiltered.msg_receipt.gas_used
+    );
+
+    // Filtering on a different emitter suppresses everything.
+    let filtered = run(Some(EventFilter {
+        emitters: [1].into(),
+        ..Default::default()
+    }));
+    assert!(filtered.events.is_empty());
+    assert_eq!(filtered.suppressed_events, 2);
+    assert_eq!(
+        filtered.msg_receipt.events_root,
+        unfiltered.msg_receipt.events_root
+    );
+}
+
+fn setup(
+    event_filter: Option<EventFilter>,
+) -> (
     IntegrationExecutor<MemoryBlockstore, DummyExterns>,
     Address,
     Address,
@@ -186,7 +286,9 @@ fn setup() -> (
         .unwrap();
 
     // Instantiate machine
-    tester.instantiate_machine(DummyExterns).unwrap();
+    tester
+        .instantiate_machine_with_config(DummyExterns, |_| (), |mc| mc.event_filter = event_filter)
+        .unwrap();
 
     let executor = tester.executor.unwrap();
     (executor, sender, actor)
@@ -37,6 +37,7 @@ fn gasfuzz_fuzz(charge_points_milligas: Vec<u64>) {
         debug: false,
         trace: false,
         events: false,
+        ..Default::default()
     };
 
     let mut tester = bundles::new_basic_tester(options).unwrap();
@@ -89,6 +90,7 @@ fn gasfuzz_get_exec_trace() -> ExecutionTrace {
         debug: false,
         trace: true,
         events: false,
+        ..Default::default()
     };
 
     let mut tester = bundles::new_basic_tester(options).unwrap();
@@ -6,9 +6,11 @@ use std::rc::Rc;
 
 use anyhow::anyhow;
 use cid::Cid;
-use fvm::executor::{ApplyKind, Executor, ThreadedExecutor};
+use fvm::executor::{ApplyKind, CancellationToken, Executor, PreApplyError, ThreadedExecutor};
+use fvm::machine::Machine;
 use fvm_integration_tests::dummy::DummyExterns;
-use fvm_integration_tests::tester::{Account, IntegrationExecutor};
+use fvm_integration_tests::tester::{Account, BasicTester, ExecutionOptions, IntegrationExecutor};
+use fvm_ipld_blockstore::tracking::{BSStats, TrackingBlockstore};
 use fvm_ipld_blockstore::{Blockstore, MemoryBlockstore};
 use fvm_ipld_encoding::tuple::*;
 use fvm_ipld_encoding::RawBytes;
@@ -135,6 +137,217 @@ fn ipld() {
     }
 }
 
+#[test]
+fn flush_stats() {
+    let blockstore = Rc::new(TrackingBlockstore::new(MemoryBlockstore::default()));
+
+    // Instantiate tester
+    let mut tester = new_tester(NV_FOR_TEST, StateTreeVersion::V5, blockstore.clone()).unwrap();
+
+    let sender: [Account; 1] = tester.create_accounts().unwrap();
+
+    // Instantiate machine
+    tester.instantiate_machine(DummyExterns).unwrap();
+
+    // Only count the blocks written by the machine's flush.
+    *blockstore.stats.borrow_mut() = BSStats::default();
+
+    let executor = tester.executor.as_mut().unwrap();
+
+    // Send to a few new addresses, creating account actors (and their state).
+    for i in 0..3u8 {
+        let message = Message {
+            from: sender[0].1,
+            to: Address::new_secp256k1(&[i; 65]).unwrap(),
+            gas_limit: 1000000000,
+            value: TokenAmount::from_atto(1),
+            sequence: i as u64,
+            ..Message::default()
+        };
+        let res = executor
+            .execute_message(message, ApplyKind::Explicit, 100)
+            .unwrap();
+        assert!(
+            res.msg_receipt.exit_code.is_success(),
+            "message failed: {:?}",
+            res.failure_info
+        );
+    }
+
+    // Nothing should be written to the underlying blockstore until we flush.
+    assert_eq!(blockstore.stats.borrow().w, 0);
+
+    let stats = Machine::flush(&mut **executor).unwrap();
+    let written = *blockstore.stats.borrow();
+
+    assert!(stats.state_tree_blocks > 0);
+    assert!(stats.user_blocks > 0);
+    assert_eq!(stats.total_blocks(), written.w as u64);
+    assert_eq!(stats.total_bytes(), written.bw as u64);
+    assert!(blockstore.has(&stats.root).unwrap());
+}
+
+#[test]
+fn validate_for_application() {
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let sender: [Account; 1] = tester.create_accounts().unwrap();
+    tester.instantiate_machine(DummyExterns).unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    let message = Message {
+        from: sender[0].1,
+        to: sender[0].1,
+        gas_limit: 1000000000,
+        ..Message::default()
+    };
+
+    // A valid message.
+    let validated = executor
+        .validate_for_application(&message, ApplyKind::Explicit, 100)
+        .unwrap()
+        .unwrap();
+    assert_eq!(validated.sender_id, sender[0].0);
+    assert_eq!(validated.receiver_id, Some(sender[0].0));
+    assert_eq!(validated.new_sender_state.unwrap().sequence, 1);
+
+    // Validation doesn't touch the state.
+    let sender_state = executor
+        .state_tree()
+        .get_actor(sender[0].0)
+        .unwrap()
+        .unwrap();
+    assert_eq!(sender_state.sequence, 0);
+
+    // The wrong sequence.
+    let failure = executor
+        .validate_for_application(
+            &Message {
+                sequence: 5,
+                ..message.clone()
+            },
+            ApplyKind::Explicit,
+            100,
+        )
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(
+        failure.error,
+        PreApplyError::SequenceMismatch {
+            expected: 0,
+            actual: 5
+        }
+    );
+    assert_eq!(
+        failure.error.exit_code(),
+        ExitCode::SYS_SENDER_STATE_INVALID
+    );
+
+    // The sender can't afford the gas.
+    let failure = executor
+        .validate_for_application(
+            &Message {
+                gas_fee_cap: TokenAmount::from_atto(1),
+                ..message.clone()
+            },
+            ApplyKind::Explicit,
+            100,
+        )
+        .unwrap()
+        .unwrap_err();
+    assert!(matches!(
+        failure.error,
+        PreApplyError::InsufficientBalance { .. }
+    ));
+
+    // An unknown sender.
+    let failure = executor
+        .validate_for_application(
+            &Message {
+                from: Address::new_id(9999),
+                ..message.clone()
+            },
+            ApplyKind::Explicit,
+            100,
+        )
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(failure.error, PreApplyError::SenderNotFound);
+    assert_eq!(failure.error.exit_code(), ExitCode::SYS_SENDER_INVALID);
+
+    // Executing the message gives the same result as validating it.
+    let res = executor
+        .execute_message(
+            Message {
+                sequence: 5,
+                ..message
+            },
+            ApplyKind::Explicit,
+            100,
+        )
+        .unwrap();
+    assert_eq!(
+        res.msg_receipt.exit_code,
+        ExitCode::SYS_SENDER_STATE_INVALID
+    );
+}
+
+#[test]
+fn invariant_checks() {
+    let mut tester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let accounts: [Account; 2] = tester.create_accounts().unwrap();
+    tester
+        .instantiate_machine_with_config(
+            DummyExterns,
+            |_| (),
+            |mc| {
+                mc.enable_invariant_checks();
+            },
+        )
+        .unwrap();
+    let executor = tester.executor.as_mut().unwrap();
+
+    let message = |sequence| Message {
+        from: accounts[0].1,
+        to: accounts[1].1,
+        gas_limit: 1000000000,
+        value: TokenAmount::from_atto(1),
+        sequence,
+        ..Message::default()
+    };
+
+    // A well-behaved transfer passes the checks.
+    let res = executor
+        .execute_message(message(0), ApplyKind::Explicit, 100)
+        .unwrap();
+    assert!(
+        res.msg_receipt.exit_code.is_success(),
+        "message failed: {:?}",
+        res.failure_info
+    );
+
+    // Minting tokens in the refund is detected.
+    executor.skew_refunds(TokenAmount::from_atto(1));
+    let err = executor
+        .execute_message(message(1), ApplyKind::Explicit, 100)
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("token conservation"),
+        "unexpected error: {err}"
+    );
+}
+
 #[test]
 fn syscalls() {
     // Instantiate tester
@@ -659,6 +872,89 @@ fn no_memory() {
     );
 }
 
+#[test]
+fn cancellation() {
+    let mut tester: BasicTester = new_tester(
+        NV_FOR_TEST,
+        StateTreeVersion::V5,
+        MemoryBlockstore::default(),
+    )
+    .unwrap();
+
+    let [(sender_id, sender), (_, receiver)] = tester.create_accounts().unwrap();
+
+    // An actor that loops until it runs out of gas.
+    let wasm_bin = wat::parse_str(
+        r#"(module
+             (memory (export "memory") 1)
+             (func (export "invoke") (param $x i32) (result i32)
+               (loop (br 0))
+               (i32.const 1)))"#,
+    )
+    .unwrap();
+    let state_cid = tester.set_state(&State { count: 0 }).unwrap();
+    let actor_address = Address::new_id(10000);
+    tester
+        .set_actor_from_bin(&wasm_bin, state_cid, actor_address, TokenAmount::zero())
+        .unwrap();
+
+    let token = CancellationToken::new();
+    tester.options = Some(ExecutionOptions {
+        cancellation: Some(token.clone()),
+        ..Default::default()
+    });
+
+    // Cancel once the actor is running, so we interrupt the loop itself rather than catching
+    // the cancellation up-front.
+    let canceller = {
+        let token = token.clone();
+        std::thread::spawn(move || {
+            while !token.is_running() {
+                std::thread::yield_now();
+            }
+            token.cancel();
+        })
+    };
+    let message = Message {
+        from: sender,
+        to: actor_address,
+        gas_limit: fvm_shared::BLOCK_GAS_LIMIT,
+        method_num: 1,
+        ..Message::default()
+    };
+    let res = tester
+        .with_executor(|e| e.execute_message(message, ApplyKind::Explicit, 100))
+        .unwrap();
+    canceller.join().unwrap();
+    assert!(res.is_cancelled(), "{:?}", res.failure_info);
+    // The token no longer references the engine once the actor has stopped.
+    assert!(!token.is_running());
+
+    // The cancelled message had no effect, so the sender's next message reuses its sequence.
+    token.reset();
+    let message = Message {
+        from: sender,
+        to: receiver,
+        gas_limit: 10_000_000,
+        method_num: fvm_shared::METHOD_SEND,
+        value: TokenAmount::from_atto(1),
+        ..Message::default()
+    };
+    let res = tester
+        .with_executor(|e| e.execute_message(message, ApplyKind::Explicit, 100))
+        .unwrap();
+    assert!(
+        res.msg_receipt.exit_code.is_success(),
+        "{:?}",
+        res.failure_info
+    );
+
+    let sequence = tester
+        .with_executor(|e| Ok(e.state_tree().get_actor(sender_id)?.unwrap().sequence))
+        .unwrap();
+    assert_eq!(sequence, 1);
+}
+
 #[test]
 fn backtraces() {
     // Note: this test **does not actually assert anything**, but it's useful to
@@ -0,0 +1,35 @@
+// Copyright 2021-2023 Protocol Labs
+// SPDX-License-Identifier: Apache-2.0, MIT
+use fvm_sdk as sdk;
+use fvm_shared::address::Address;
+
+/// Method 2 calls method 3, which calls method 4, which checks the call stack.
+#[no_mangle]
+pub fn invoke(_: u32) -> u32 {
+    sdk::initialize();
+
+    let receiver = sdk::message::receiver();
+    let method = sdk::message::method_number();
+    match method {
+        2 | 3 => {
+            let resp = sdk::send::send(
+                &Address::new_id(receiver),
+                method + 1,
+                None,
+                Default::default(),
+                None,
+                Default::default(),
+            )
+            .unwrap();
+            assert!(resp.exit_code.is_success(), "{}", resp.exit_code);
+        }
+        4 => {
+            assert_eq!(
+                sdk::debug::call_stack().expect("debugging should be enabled"),
+                vec![(receiver, 2), (receiver, 3), (receiver, 4)]
+            );
+        }
+        _ => panic!("unexpected method"),
+    }
+    0
+}
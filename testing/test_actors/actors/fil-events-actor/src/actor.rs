@@ -3,7 +3,7 @@
 use std::ptr;
 
 use fvm_ipld_encoding::ipld_block::IpldBlock;
-use fvm_ipld_encoding::IPLD_RAW;
+use fvm_ipld_encoding::{CBOR, IPLD_RAW};
 use fvm_sdk as sdk;
 use fvm_shared::address::Address;
 use fvm_shared::bigint::Zero;
@@ -19,6 +19,7 @@ pub fn invoke(params: u32) -> u32 {
     const EMIT_MALFORMED: u64 = 3;
     const EMIT_SUBCALLS: u64 = 4;
     const EMIT_SUBCALLS_REVERT: u64 = 5;
+    const EMIT_ROOT_SO_FAR: u64 = 6;
 
     let payload1 = "abc".as_bytes();
     let payload2 = "def".as_bytes();
@@ -27,7 +28,7 @@ pub fn invoke(params: u32) -> u32 {
     // Emit a single-entry event.
     let single_entry_evt = vec![Entry {
         flags: Flags::all(),
-        key: "foo".to_owned(),
+        key: "foo".into(),
         codec: IPLD_RAW,
         value: payload1.to_owned(),
     }];
@@ -35,13 +36,13 @@ pub fn invoke(params: u32) -> u32 {
     let multi_entry = vec![
         Entry {
             flags: Flags::all(),
-            key: "bar".to_owned(),
+            key: "bar".into(),
             codec: IPLD_RAW,
             value: payload2.to_owned(),
         },
         Entry {
             flags: Flags::FLAG_INDEXED_KEY | Flags::FLAG_INDEXED_VALUE,
-            key: "👱".to_string(),
+            key: "👱".into(),
             codec: IPLD_RAW,
             value: payload3.to_owned(),
         },
@@ -298,6 +299,29 @@ pub fn invoke(params: u32) -> u32 {
                 sdk::vm::abort(ExitCode::USR_ASSERTION_FAILED.value(), None);
             }
         }
+        EMIT_ROOT_SO_FAR => {
+            assert_eq!(sdk::event::root_so_far(), None);
+
+            sdk::event::emit_event(&single_entry_evt.into()).unwrap();
+
+            // Performs a chain of subcalls in which the deepest calls revert (see
+            // EMIT_SUBCALLS_REVERT), discarding their events.
+            let our_addr = Address::new_id(sdk::message::receiver());
+            sdk::send::send(
+                &our_addr,
+                EMIT_SUBCALLS_REVERT,
+                IpldBlock::serialize_cbor(&10u64).unwrap(),
+                Zero::zero(),
+                None,
+                Default::default(),
+            )
+            .unwrap();
+
+            sdk::event::emit_event(&multi_entry.into()).unwrap();
+
+            let root = sdk::event::root_so_far().expect("expected an events root");
+            return sdk::ipld::put_block(CBOR, &fvm_ipld_encoding::to_vec(&root).unwrap()).unwrap();
+        }
         _ => panic!("invalid method number"),
     }
     0
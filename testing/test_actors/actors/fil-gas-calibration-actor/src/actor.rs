@@ -174,7 +174,7 @@ fn on_event(p: OnEventParams) -> Result<()> {
                 // 1. Benchmark utf8 validation separately.
                 // 2. Assume that all other "key" related costs will behave the same as "value"
                 //    costs.
-                key: char::MAX.to_string(),
+                key: char::MAX.to_string().into(),
                 codec: IPLD_RAW,
                 value: d.into(),
             })
@@ -55,7 +55,7 @@ pub fn invoke(params_id: u32) -> u32 {
             let payload: u64 = 400;
             vec![Entry {
                 flags: Flags::all(),
-                key: "foo".to_owned(),
+                key: "foo".into(),
                 codec: IPLD_RAW,
                 value: fvm_ipld_encoding::to_vec(&payload).unwrap(),
             }]
@@ -151,7 +151,7 @@ fn invoke_method(blk: u32, method: u64) -> u32 {
             // Should fail to emit events.
             let evt = vec![Entry {
                 flags: Flags::all(),
-                key: "foo".to_owned(),
+                key: "foo".into(),
                 codec: IPLD_RAW,
                 value: vec![0, 1, 2],
             }];
@@ -40,6 +40,7 @@ pub fn invoke(_: u32) -> u32 {
     test_message_context();
     test_balance();
     test_unaligned();
+    test_send_flags();
 
     #[cfg(coverage)]
     sdk::debug::store_artifact("syscall_actor.profraw", minicov::capture_coverage());
@@ -330,6 +331,8 @@ fn test_network_context() {
     assert_eq!(sdk::network::curr_epoch(), 0);
     assert_eq!(sdk::network::version(), NetworkVersion::V21);
     assert_eq!(sdk::network::tipset_timestamp(), 0);
+    // The block delay is only returned from network version 22.
+    assert_eq!(sdk::network::block_delay_seconds(), 0);
     assert_eq!(sdk::network::base_fee(), TokenAmount::from_atto(100));
 }
 
@@ -377,3 +380,23 @@ fn test_unaligned() {
         assert_eq!(expected, actual);
     }
 }
+
+fn test_send_flags() {
+    use fvm_shared::sys::SendFlags;
+
+    // Undefined flags are rejected, rather than being silently ignored.
+    let recipient = Address::new_id(sdk::message::receiver()).to_bytes();
+    let res = unsafe {
+        sdk::sys::send::send(
+            recipient.as_ptr(),
+            recipient.len() as u32,
+            0,
+            sdk::NO_DATA_BLOCK_ID,
+            0,
+            0,
+            u64::MAX,
+            SendFlags::from_bits_retain(1 << 63),
+        )
+    };
+    assert_eq!(res.err(), Some(ErrorNumber::IllegalArgument));
+}